@@ -0,0 +1,56 @@
+//! ASCII caseless comparison.
+//!
+//! Header names, transfer codings and such are compared without regard
+//! to ASCII case. Non-ASCII characters are compared as is, no Unicode
+//! case folding is done.
+
+/// Compare two strings ignoring ASCII case.
+///
+/// Unlike comparing against a lowercased constant, this is symmetric; neither
+/// side needs to be lowercase.
+///
+/// ```
+/// use hoot::ascii::eq_ignore_ascii;
+///
+/// assert!(eq_ignore_ascii("Content-Length", "content-LENGTH"));
+/// assert!(!eq_ignore_ascii("Ä", "ä"));
+/// ```
+pub fn eq_ignore_ascii(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
+
+/// Check if `s` starts with `prefix` ignoring ASCII case.
+///
+/// ```
+/// use hoot::ascii::starts_with_ignore_ascii;
+///
+/// assert!(starts_with_ignore_ascii("Content-Type", "content-"));
+/// assert!(!starts_with_ignore_ascii("Content", "content-"));
+/// ```
+pub fn starts_with_ignore_ascii(s: &str, prefix: &str) -> bool {
+    let s = s.as_bytes();
+    let prefix = prefix.as_bytes();
+    s.len() >= prefix.len() && s[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_eq_ignore_ascii() {
+        assert!(eq_ignore_ascii("", ""));
+        assert!(eq_ignore_ascii("Host", "HOST"));
+        assert!(eq_ignore_ascii("HOST", "host"));
+        assert!(!eq_ignore_ascii("host", "hosts"));
+        assert!(!eq_ignore_ascii("hostö", "hostÖ"));
+    }
+
+    #[test]
+    fn test_starts_with_ignore_ascii() {
+        assert!(starts_with_ignore_ascii("Sec-WebSocket-Key", "sec-"));
+        assert!(starts_with_ignore_ascii("x", ""));
+        // Prefix ending in the middle of a multi byte char.
+        assert!(!starts_with_ignore_ascii("ö", "\u{c3}"));
+    }
+}
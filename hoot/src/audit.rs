@@ -0,0 +1,203 @@
+//! Audit of specification deviations
+//!
+//! Parsing tolerates some things that are not quite right, and some peers send
+//! messages that are valid, but unusual. [`audit()`] looks over a parsed message head
+//! and reports such deviations, without failing, which lets proxies and security tools
+//! log or block noncompliant peers.
+//!
+//! ```
+//! use hoot::audit::{audit, Deviation};
+//!
+//! let head = b"GET / HTTP/1.1\r\nHOST: a.test\r\nHost: b.test\r\n\r\n";
+//!
+//! let deviations = audit(head);
+//! assert!(deviations.contains(Deviation::DuplicateHeader));
+//! assert!(deviations.contains(Deviation::UnusualCasing));
+//! assert!(!deviations.contains(Deviation::BareLf));
+//! ```
+
+use crate::ascii::eq_ignore_ascii;
+use crate::util::flag_set;
+
+/// A deviation from the specification, or from common practice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Deviation {
+    /// A line ended with `\n` instead of `\r\n`.
+    BareLf,
+    /// A header value continued on the next line, which is obsolete.
+    ObsFold,
+    /// A header value with bytes 0x80-0xff, which is obsolete.
+    ObsText,
+    /// A header that can only be sent once appeared more than once.
+    DuplicateHeader,
+    /// A header name that is neither lowercase nor capitalized like `Content-Type`.
+    UnusualCasing,
+    /// A header value with trailing whitespace.
+    TrailingWhitespace,
+}
+
+flag_set! {
+    /// Set of deviations found by [`audit()`].
+    Deviations(Deviation):
+        BareLf,
+        ObsFold,
+        ObsText,
+        DuplicateHeader,
+        UnusualCasing,
+        TrailingWhitespace,
+}
+
+// Headers that are not lists, and therefore can't be repeated.
+// Compared caseless, but kept lowercase for readability.
+const SINGLETON_HEADERS: &[&str] = &[
+    "authorization",
+    "content-length",
+    "content-location",
+    "content-range",
+    "content-type",
+    "date",
+    "etag",
+    "expires",
+    "host",
+    "if-modified-since",
+    "if-unmodified-since",
+    "last-modified",
+    "location",
+    "max-forwards",
+    "proxy-authorization",
+    "range",
+    "referer",
+    "retry-after",
+    "user-agent",
+];
+
+/// Audit a message head, the request or status line followed by headers.
+///
+/// The head is typically `&input[..attempt.input_used()]` after a successful read.
+/// Anything after the empty line ending the headers is ignored.
+pub fn audit(head: &[u8]) -> Deviations {
+    let mut found = Deviations::default();
+
+    let mut lines = head.split_inclusive(|c| *c == b'\n');
+
+    // Request or status line.
+    if let Some(line) = lines.next() {
+        trim_line(line, &mut found);
+    }
+
+    let headers_start = head.len() - lines.clone().map(|l| l.len()).sum::<usize>();
+
+    for line in lines {
+        let line = trim_line(line, &mut found);
+
+        if line.is_empty() {
+            break;
+        }
+
+        if line[0] == b' ' || line[0] == b'\t' {
+            found.insert(Deviation::ObsFold);
+            continue;
+        }
+
+        let colon = match line.iter().position(|c| *c == b':') {
+            Some(v) => v,
+            None => continue,
+        };
+
+        let (name, value) = (&line[..colon], &line[(colon + 1)..]);
+
+        if value.iter().any(|c| *c >= 0x80) {
+            found.insert(Deviation::ObsText);
+        }
+
+        if value.ends_with(b" ") || value.ends_with(b"\t") {
+            found.insert(Deviation::TrailingWhitespace);
+        }
+
+        let name = match core::str::from_utf8(name) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+
+        if !is_usual_casing(name) {
+            found.insert(Deviation::UnusualCasing);
+        }
+
+        let singleton = SINGLETON_HEADERS.iter().any(|h| eq_ignore_ascii(h, name));
+
+        if singleton && count_header(&head[headers_start..], name) > 1 {
+            found.insert(Deviation::DuplicateHeader);
+        }
+    }
+
+    found
+}
+
+/// Remove the line ending, noting a bare LF.
+fn trim_line<'a>(line: &'a [u8], found: &mut Deviations) -> &'a [u8] {
+    match line {
+        [rest @ .., b'\r', b'\n'] => rest,
+        [rest @ .., b'\n'] => {
+            found.insert(Deviation::BareLf);
+            rest
+        }
+        _ => line,
+    }
+}
+
+fn count_header(headers: &[u8], name: &str) -> usize {
+    headers
+        .split(|c| *c == b'\n')
+        .take_while(|l| !l.is_empty() && *l != b"\r")
+        .filter(|l| {
+            l.len() > name.len()
+                && l[name.len()] == b':'
+                && l[..name.len()].eq_ignore_ascii_case(name.as_bytes())
+        })
+        .count()
+}
+
+/// Either all lowercase, or each dash separated part capitalized, like `Content-Type`,
+/// `ETag` or `WWW-Authenticate`, but not shouting like `CONTENT-TYPE`.
+fn is_usual_casing(name: &str) -> bool {
+    let has_upper = name.bytes().any(|c| c.is_ascii_uppercase());
+    let has_lower = name.bytes().any(|c| c.is_ascii_lowercase());
+
+    if !has_upper || !has_lower {
+        return !has_upper;
+    }
+
+    name.split('-').all(|part| {
+        part.bytes()
+            .next()
+            .map(|c| !c.is_ascii_lowercase())
+            .unwrap_or(true)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_audit_clean() {
+        let head = b"HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\n\
+            Set-Cookie: a=1\r\nSet-Cookie: b=2\r\nWWW-Authenticate: Basic\r\n\r\nbody";
+        let d = audit(head);
+        assert!(d.is_empty(), "{:?}", d.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_audit_deviations() {
+        let head = b"HTTP/1.1 200 OK\n\
+            X-A: caf\xc3\xa9 \r\n\
+            \tfolded\r\n\
+            content-Type: a\r\n\
+            Content-Type: b\r\n\r\n";
+        let d = audit(head);
+
+        let all: Vec<_> = d.iter().collect();
+        assert_eq!(all, Deviations::ALL);
+    }
+}
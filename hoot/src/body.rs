@@ -2,14 +2,14 @@ use core::fmt;
 use core::ops::Deref;
 use core::str;
 
-use crate::chunk::Dechunker;
+use crate::ascii::eq_ignore_ascii;
+use crate::chunked::{ChunkedDecoder, TrailerAttempt};
 use crate::error::Result;
-use crate::util::compare_lowercase_ascii;
-use crate::{CallState, HootError, Method};
+use crate::{CallState, Header, HootError, Method};
 
-pub(crate) fn do_read_body<'a, 'b>(
+pub(crate) fn do_read_body<'b>(
     state: &mut CallState,
-    src: &'a [u8],
+    src: &[u8],
     dst: &'b mut [u8],
 ) -> Result<BodyPart<'b>> {
     trace!("Read body");
@@ -21,21 +21,157 @@ pub(crate) fn do_read_body<'a, 'b>(
 
     // unwrap is ok because we can't be in state RECV_BODY without setting it.
     let part = match state.recv_body_mode.unwrap() {
+        // There is no length checker for empty bodies.
+        RecvBodyMode::LengthDelimited(0) => Ok(BodyPart {
+            finished: true,
+            ..BodyPart::empty()
+        }),
         RecvBodyMode::LengthDelimited(_) => read_limit(state, src, dst, true),
         RecvBodyMode::Chunked => read_chunked(state, src, dst),
         RecvBodyMode::CloseDelimited => read_limit(state, src, dst, false),
     }?;
 
+    Ok(body_part_read(state, part))
+}
+
+/// Like `do_read_body`, but the body data is borrowed from the input.
+///
+/// The status/headers must be read already, otherwise no input is used.
+pub(crate) fn do_read_body_borrowed<'a>(
+    state: &mut CallState,
+    src: &'a [u8],
+) -> Result<BodyPart<'a>> {
+    trace!("Read body borrowed");
+
+    let mode = match state.recv_body_mode {
+        Some(v) if !state.did_read_to_end => v,
+        _ => return Ok(BodyPart::empty()),
+    };
+
+    let part = match mode {
+        RecvBodyMode::LengthDelimited(_) => {
+            let (input_used, finished) = match state.recv_checker.as_mut() {
+                Some(checker) => {
+                    let input_used = checker.left_to_read().min(src.len());
+                    checker.append(input_used, HootError::RecvMoreThanContentLength)?;
+                    (input_used, checker.complete())
+                }
+                // No checker means a 0 length body.
+                None => (0, true),
+            };
+            BodyPart {
+                input_used,
+                data: &src[..input_used],
+                finished,
+                trailers_pending: false,
+            }
+        }
+        RecvBodyMode::Chunked => {
+            let decoder = state.dechunker.get_or_insert_with(ChunkedDecoder::new);
+            let (input_used, data, finished) = decoder.decode_borrowed(src)?;
+            BodyPart {
+                input_used,
+                data,
+                finished,
+                trailers_pending: decoder.is_trailers_pending(),
+            }
+        }
+        RecvBodyMode::CloseDelimited => BodyPart {
+            input_used: src.len(),
+            data: src,
+            finished: false,
+            trailers_pending: false,
+        },
+    };
+
+    Ok(body_part_read(state, part))
+}
+
+/// Take note of a body part read.
+fn body_part_read<'b>(state: &mut CallState, part: BodyPart<'b>) -> BodyPart<'b> {
     if part.finished {
         state.did_read_to_end = true;
     }
 
-    Ok(part)
+    let payload = part.data.len();
+    state.recv_bytes.payload += payload as u64;
+    state.recv_bytes.framing += (part.input_used - payload) as u64;
+
+    part
+}
+
+/// Bounds of the body left to read, like `Iterator::size_hint()`.
+pub(crate) fn do_body_size_hint(state: &CallState) -> (u64, Option<u64>) {
+    if state.did_read_to_end {
+        return (0, Some(0));
+    }
+
+    let mode = match state.recv_body_mode {
+        Some(v) => v,
+        None => return (0, None),
+    };
+
+    match mode {
+        RecvBodyMode::LengthDelimited(_) => {
+            // No checker means a 0 length body.
+            let left = state.recv_checker.as_ref().map(|c| c.left()).unwrap_or(0);
+            (left, Some(left))
+        }
+        RecvBodyMode::Chunked => {
+            // At least the rest of the current chunk.
+            let left = state.dechunker.map(|d| d.chunk_left()).unwrap_or(0);
+            (left as u64, None)
+        }
+        RecvBodyMode::CloseDelimited => (0, None),
+    }
+}
+
+/// Skip body input without copying it anywhere. Returns the amount of input used.
+pub(crate) fn do_drain_body(state: &mut CallState, src: &[u8]) -> Result<usize> {
+    trace!("Drain body");
+
+    if state.did_read_to_end {
+        return Ok(0);
+    }
+
+    // Draining requires the status/headers to be read already.
+    let mode = match state.recv_body_mode {
+        Some(v) => v,
+        None => return Ok(0),
+    };
+
+    let (input_used, payload, finished) = match mode {
+        RecvBodyMode::LengthDelimited(_) => match state.recv_checker.as_mut() {
+            Some(checker) => {
+                let input_used = checker.left_to_read().min(src.len());
+                checker.append(input_used, HootError::RecvMoreThanContentLength)?;
+                (input_used, input_used, checker.complete())
+            }
+            // No checker means a 0 length body.
+            None => (0, 0, true),
+        },
+        RecvBodyMode::Chunked => {
+            let decoder = state.dechunker.get_or_insert_with(ChunkedDecoder::new);
+            decoder.skip_counted(src)?
+        }
+        RecvBodyMode::CloseDelimited => (src.len(), src.len(), false),
+    };
+
+    trace!("Drained: {}", input_used);
+
+    state.recv_bytes.payload += payload as u64;
+    state.recv_bytes.framing += (input_used - payload) as u64;
+
+    if finished {
+        state.did_read_to_end = true;
+    }
+
+    Ok(input_used)
 }
 
-fn read_limit<'a, 'b>(
+fn read_limit<'b>(
     state: &mut CallState,
-    src: &'a [u8],
+    src: &[u8],
     dst: &'b mut [u8],
     use_checker: bool,
 ) -> Result<BodyPart<'b>> {
@@ -66,18 +202,16 @@ fn read_limit<'a, 'b>(
         input_used,
         data,
         finished,
+        trailers_pending: false,
     })
 }
 
 fn read_chunked<'a>(state: &mut CallState, src: &[u8], dst: &'a mut [u8]) -> Result<BodyPart<'a>> {
-    if state.dechunker.is_none() {
-        state.dechunker = Some(Dechunker::new());
-    }
-    let dechunker = state.dechunker.as_mut().unwrap();
-    let (input_used, produced_output) = dechunker.parse_input(src, dst)?;
+    let decoder = state.dechunker.get_or_insert_with(ChunkedDecoder::new);
+
+    let (input_used, produced_output, finished) = decoder.decode(src, dst)?;
 
     let data = &mut dst[..produced_output];
-    let finished = dechunker.is_ended();
 
     trace!("Read chunked: {}", input_used);
 
@@ -85,13 +219,35 @@ fn read_chunked<'a>(state: &mut CallState, src: &[u8], dst: &'a mut [u8]) -> Res
         input_used,
         data,
         finished,
+        trailers_pending: decoder.is_trailers_pending(),
     })
 }
 
+pub(crate) fn do_try_read_trailers<'a, 'b>(
+    state: &mut CallState,
+    input: &'a [u8],
+    buf: &'b mut [u8],
+) -> Result<TrailerAttempt<'a, 'b>> {
+    let decoder = match state.dechunker.as_mut() {
+        Some(v) if !state.did_read_to_end => v,
+        _ => return Ok(TrailerAttempt::empty()),
+    };
+
+    let attempt = decoder.read_trailers(input, buf)?;
+
+    if attempt.is_success() {
+        state.did_read_to_end = true;
+        state.recv_bytes.framing += attempt.input_used() as u64;
+    }
+
+    Ok(attempt)
+}
+
 pub struct BodyPart<'b> {
     pub(crate) input_used: usize,
     pub(crate) data: &'b [u8],
     pub(crate) finished: bool,
+    pub(crate) trailers_pending: bool,
 }
 
 impl BodyPart<'_> {
@@ -106,6 +262,13 @@ impl BodyPart<'_> {
     pub fn is_finished(&self) -> bool {
         self.finished
     }
+
+    /// The last chunk of a chunked body is read, and the trailers follow next.
+    ///
+    /// Read them with `try_read_trailers()`. Reading more body instead skips the trailers.
+    pub fn is_trailers_pending(&self) -> bool {
+        self.trailers_pending
+    }
 }
 
 impl BodyPart<'_> {
@@ -114,6 +277,7 @@ impl BodyPart<'_> {
             input_used: 0,
             data: &[],
             finished: false,
+            trailers_pending: false,
         }
     }
 }
@@ -122,7 +286,7 @@ impl Deref for BodyPart<'_> {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        &self.data
+        self.data
     }
 }
 
@@ -143,19 +307,23 @@ impl RecvBodyMode {
         method: Method,
         header_lookup: &'a dyn Fn(&str) -> Option<&'a str>,
     ) -> Result<Self> {
+        let framing = Framing::from_lookup(header_lookup)?;
+        framing.check_request(http10)?;
+        Ok(Self::for_request_framing(http10, method, &framing))
+    }
+
+    pub(crate) fn for_request_framing(http10: bool, method: Method, framing: &Framing) -> Self {
         let has_no_body = !method.has_request_body();
 
         if has_no_body {
-            return Ok(Self::LengthDelimited(0));
+            return Self::LengthDelimited(0);
         }
 
-        let ret = match Self::header_defined(http10, header_lookup)? {
+        match framing.body_mode(http10) {
             // Request bodies cannot be close delimited (even under http10).
             Self::CloseDelimited => Self::LengthDelimited(0),
-            r @ _ => r,
-        };
-
-        Ok(ret)
+            r => r,
+        }
     }
 
     pub fn for_response<'a>(
@@ -164,73 +332,232 @@ impl RecvBodyMode {
         status_code: u16,
         header_lookup: &'a dyn Fn(&str) -> Option<&'a str>,
     ) -> Result<Self> {
-        let is_success = status_code >= 200 && status_code <= 299;
-        let is_informational = status_code >= 100 && status_code <= 199;
-
-        let has_no_body =
-            // https://datatracker.ietf.org/doc/html/rfc2616#section-4.3
-            // All responses to the HEAD request method
-            // MUST NOT include a message-body, even though the presence of entity-
-            // header fields might lead one to believe they do.
-            method == Method::HEAD ||
-            // A client MUST ignore any Content-Length or Transfer-Encoding
-            // header fields received in a successful response to CONNECT.
-            is_success && method == Method::CONNECT ||
-            // All 1xx (informational), 204 (no content), and 304 (not modified) responses
-            // MUST NOT include a message-body.
-            is_informational ||
-            matches!(status_code, 204 | 304);
+        let framing = Framing::from_lookup(header_lookup)?;
+        Ok(Self::for_response_framing(
+            http10,
+            method,
+            status_code,
+            &framing,
+        ))
+    }
 
-        if has_no_body {
+    pub(crate) fn for_response_framing(
+        http10: bool,
+        method: Method,
+        status_code: u16,
+        framing: &Framing,
+    ) -> Self {
+        if response_has_no_body(method, status_code) {
             if http10 {
-                return Ok(Self::CloseDelimited);
+                return Self::CloseDelimited;
             } else {
-                return Ok(Self::LengthDelimited(0));
+                return Self::LengthDelimited(0);
             }
         }
 
         // https://datatracker.ietf.org/doc/html/rfc2616#section-4.3
         // All other responses do include a message-body, although it MAY be of zero length.
-        Self::header_defined(http10, header_lookup)
+        framing.body_mode(http10)
     }
+}
 
-    fn header_defined<'a>(
-        http10: bool,
+/// Whether a response has no body, regardless of its headers.
+pub(crate) fn response_has_no_body(method: Method, status_code: u16) -> bool {
+    let is_success = (200..=299).contains(&status_code);
+    let is_informational = (100..=199).contains(&status_code);
+
+    // https://datatracker.ietf.org/doc/html/rfc2616#section-4.3
+    // All responses to the HEAD request method
+    // MUST NOT include a message-body, even though the presence of entity-
+    // header fields might lead one to believe they do.
+    method == Method::HEAD ||
+    // A client MUST ignore any Content-Length or Transfer-Encoding
+    // header fields received in a successful response to CONNECT.
+    is_success && method == Method::CONNECT ||
+    // All 1xx (informational), 204 (no content), and 304 (not modified) responses
+    // MUST NOT include a message-body.
+    is_informational ||
+    matches!(status_code, 204 | 304)
+}
+
+/// How the body of a received message is delimited.
+///
+/// Unlike [`RecvBodyMode`], this tells apart messages that can't have a body, such as
+/// responses to `HEAD`, or 204 and 304 responses, even when they have a `Content-Length`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyMode {
+    /// There is no body, whatever the headers say.
+    NoBody,
+    /// Delimited by content-length, which can be 0.
+    LengthDelimited(u64),
+    /// Chunked transfer encoding.
+    Chunked,
+    /// The body ends when the remote closes the connection.
+    CloseDelimited,
+}
+
+/// The body framing headers seen in a message.
+///
+/// This can be built either from a lookup over all headers, or incrementally
+/// when headers are seen one by one.
+#[derive(Default)]
+pub(crate) struct Framing {
+    content_length: Option<u64>,
+    transfer_encoding: bool,
+    chunked: bool,
+    coding: Option<TransferCoding>,
+}
+
+/// A transfer coding, other than chunked, applied to a received body.
+///
+/// hoot only undoes the chunked coding. The body read is still encoded with
+/// this coding, and must be decoded by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferCoding {
+    /// `gzip` or `x-gzip`
+    Gzip,
+    /// `deflate`
+    Deflate,
+    /// `compress` or `x-compress`
+    Compress,
+}
+
+impl TransferCoding {
+    fn from_name(name: &str) -> Option<Self> {
+        let c = if eq_ignore_ascii(name, "gzip") || eq_ignore_ascii(name, "x-gzip") {
+            TransferCoding::Gzip
+        } else if eq_ignore_ascii(name, "deflate") {
+            TransferCoding::Deflate
+        } else if eq_ignore_ascii(name, "compress") || eq_ignore_ascii(name, "x-compress") {
+            TransferCoding::Compress
+        } else {
+            return None;
+        };
+        Some(c)
+    }
+}
+
+impl Framing {
+    pub(crate) fn from_lookup<'a>(
         header_lookup: &'a dyn Fn(&str) -> Option<&'a str>,
     ) -> Result<Self> {
-        let mut content_length: Option<u64> = None;
-        let mut chunked = false;
+        let mut framing = Framing::default();
 
-        // for head in headers {
         if let Some(value) = header_lookup("content-length") {
+            framing.header("content-length", value)?;
+        }
+
+        if let Some(value) = header_lookup("transfer-encoding") {
+            framing.header("transfer-encoding", value)?;
+        }
+
+        Ok(framing)
+    }
+
+    /// Framing from all headers, to catch repeated framing headers.
+    pub(crate) fn from_headers(headers: &[Header<'_>]) -> Result<Self> {
+        let mut framing = Framing::default();
+
+        for h in headers {
+            framing.header(h.name(), h.try_value().unwrap_or(""))?;
+        }
+
+        Ok(framing)
+    }
+
+    /// Take note of a header. Headers not concerning the framing are ignored.
+    pub fn header(&mut self, name: &str, value: &str) -> Result<()> {
+        if eq_ignore_ascii(name, "content-length") {
             let v = value.parse::<u64>()?;
-            if content_length.is_some() {
+            if self.content_length.is_some() {
                 return Err(HootError::DuplicateContentLength);
             }
-            content_length = Some(v);
+            self.content_length = Some(v);
+        } else if eq_ignore_ascii(name, "transfer-encoding") {
+            self.transfer_encoding = true;
+            // Header can repeat, the codings are in the order they were applied.
+            for coding in value.split(',') {
+                // Drop any transfer-parameter.
+                let coding = coding.split(';').next().unwrap_or("").trim();
+                self.transfer_coding(coding)?;
+            }
         }
 
-        if let Some(value) = header_lookup("transfer-encoding") {
-            // Header can repeat, stop looking if we found "chunked"
-            chunked = value
-                .split(",")
-                .map(|v| v.trim())
-                .any(|v| compare_lowercase_ascii(v, "chunked"));
+        // https://www.rfc-editor.org/rfc/rfc9112#section-6.1
+        // A server MAY reject a request that contains both Content-Length and
+        // Transfer-Encoding [...] Such a message might indicate an attempt to perform
+        // request smuggling.
+        if self.transfer_encoding && self.content_length.is_some() {
+            return Err(HootError::ContentLengthAndTransferEncoding);
         }
 
-        if chunked && !http10 {
+        Ok(())
+    }
+
+    fn transfer_coding(&mut self, coding: &str) -> Result<()> {
+        if coding.is_empty() || eq_ignore_ascii(coding, "identity") {
+            return Ok(());
+        }
+
+        // https://www.rfc-editor.org/rfc/rfc9112#section-6.1
+        // A sender MUST NOT apply the chunked transfer coding more than once to a
+        // message body. If any transfer coding other than chunked is applied to a
+        // request's content, the sender MUST apply chunked as the final transfer coding.
+        if self.chunked {
+            return Err(HootError::ChunkedNotLast);
+        }
+
+        if eq_ignore_ascii(coding, "chunked") {
+            self.chunked = true;
+            return Ok(());
+        }
+
+        let c = TransferCoding::from_name(coding).ok_or(HootError::UnknownTransferCoding)?;
+
+        if self.coding.is_some() {
+            return Err(HootError::StackedTransferCoding);
+        }
+
+        self.coding = Some(c);
+
+        Ok(())
+    }
+
+    /// Check the framing of a request.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc9112#section-6.3
+    /// If a Transfer-Encoding header field is present in a request and the chunked
+    /// transfer coding is not the final encoding, the message body length cannot be
+    /// determined reliably; the server MUST respond with the 400 (Bad Request) status
+    /// code and then close the connection.
+    ///
+    /// HTTP/1.0 has no chunked coding, so any Transfer-Encoding is the same.
+    pub(crate) fn check_request(&self, http10: bool) -> Result<()> {
+        if self.transfer_encoding && (!self.chunked || http10) {
+            return Err(HootError::UnframedTransferEncoding);
+        }
+        Ok(())
+    }
+
+    /// The transfer coding left after removing chunked.
+    pub fn coding(&self) -> Option<TransferCoding> {
+        self.coding
+    }
+
+    fn body_mode(&self, http10: bool) -> RecvBodyMode {
+        if self.chunked && !http10 {
             // https://datatracker.ietf.org/doc/html/rfc2616#section-4.4
             // Messages MUST NOT include both a Content-Length header field and a
             // non-identity transfer-coding. If the message does include a non-
             // identity transfer-coding, the Content-Length MUST be ignored.
-            return Ok(Self::Chunked);
+            return RecvBodyMode::Chunked;
         }
 
-        if let Some(len) = content_length {
-            return Ok(Self::LengthDelimited(len));
+        if let Some(len) = self.content_length {
+            return RecvBodyMode::LengthDelimited(len);
         }
 
-        Ok(Self::CloseDelimited)
+        RecvBodyMode::CloseDelimited
     }
 }
 
@@ -243,3 +570,46 @@ impl fmt::Debug for RecvBodyMode {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn framing(te: &str) -> Result<Framing> {
+        let mut f = Framing::default();
+        f.header("Transfer-Encoding", te)?;
+        Ok(f)
+    }
+
+    #[test]
+    fn test_transfer_coding_stack() -> Result<()> {
+        let f = framing("gzip, chunked")?;
+        assert_eq!(f.body_mode(false), RecvBodyMode::Chunked);
+        assert_eq!(f.coding(), Some(TransferCoding::Gzip));
+
+        let f = framing("chunked")?;
+        assert_eq!(f.coding(), None);
+
+        let f = framing("X-Compress;q=1 , Chunked")?;
+        assert_eq!(f.coding(), Some(TransferCoding::Compress));
+
+        // Without chunked, a response is delimited by close.
+        let f = framing("deflate")?;
+        assert_eq!(f.body_mode(false), RecvBodyMode::CloseDelimited);
+        assert_eq!(f.coding(), Some(TransferCoding::Deflate));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transfer_coding_errors() {
+        let err = |te| framing(te).err();
+        assert_eq!(err("chunked, gzip"), Some(HootError::ChunkedNotLast));
+        assert_eq!(err("chunked, chunked"), Some(HootError::ChunkedNotLast));
+        assert_eq!(err("br, chunked"), Some(HootError::UnknownTransferCoding));
+        assert_eq!(
+            err("gzip, deflate, chunked"),
+            Some(HootError::StackedTransferCoding)
+        );
+    }
+}
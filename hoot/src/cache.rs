@@ -0,0 +1,678 @@
+//! Caching information of responses
+//!
+//! HTTP caches need a handful of response headers to decide whether, and for how
+//! long, a response can be stored and how to revalidate it. [`CacheInfo`] picks
+//! those out of the response headers in one pass. It also does the age and
+//! freshness calculations of RFC 9111, using times from the caller's clock.
+//!
+//! ```
+//! use hoot::cache::CacheInfo;
+//! use hoot::client::Response;
+//! # use hoot::client::Request;
+//! # let mut buf = [0; 1024];
+//! # let output = Request::new(&mut buf).http_11().get("a.test", "/")?.send()?.flush();
+//! # let mut response = output.into_response();
+//!
+//! const INPUT: &[u8] = b"HTTP/1.1 200 OK\r\n\
+//!     Cache-Control: public, max-age=60\r\n\
+//!     ETag: \"abc\"\r\n\
+//!     Vary: Accept-Encoding, Accept-Language\r\n\
+//!     Content-Length: 0\r\n\r\n";
+//!
+//! let attempt = response.try_read_response(INPUT, &mut buf)?;
+//! let info = CacheInfo::from_headers(attempt.headers().unwrap());
+//!
+//! assert_eq!(info.cache_control().max_age, Some(60));
+//! assert!(info.cache_control().public);
+//! assert_eq!(info.etag(), Some("\"abc\""));
+//!
+//! let vary: Vec<_> = info.vary().collect();
+//! assert_eq!(vary, ["Accept-Encoding", "Accept-Language"]);
+//! # Ok::<(), hoot::HootError>(())
+//! ```
+
+use core::cmp::Ordering;
+use core::fmt;
+
+use crate::ascii::eq_ignore_ascii;
+use crate::error::Result;
+use crate::out::{Out, Writer};
+use crate::Header;
+
+/// The caching related headers of a response.
+///
+/// Dates are kept as the header value, since parsing them is up to the cache.
+#[derive(Clone, Copy)]
+pub struct CacheInfo<'a, 'b> {
+    headers: &'b [Header<'a>],
+    cache_control: CacheControl,
+    etag: Option<&'a str>,
+    last_modified: Option<&'a str>,
+    date: Option<&'a str>,
+    expires: Option<&'a str>,
+    age: Option<u64>,
+}
+
+/// Directives of the `Cache-Control` header.
+///
+/// Directives that are absent, or have an invalid value, are `false` or `None`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CacheControl {
+    pub max_age: Option<u64>,
+    pub s_maxage: Option<u64>,
+    pub stale_while_revalidate: Option<u64>,
+    pub stale_if_error: Option<u64>,
+    pub no_cache: bool,
+    pub no_store: bool,
+    pub no_transform: bool,
+    pub private: bool,
+    pub public: bool,
+    pub must_revalidate: bool,
+    pub proxy_revalidate: bool,
+    pub immutable: bool,
+}
+
+impl<'a, 'b> CacheInfo<'a, 'b> {
+    /// Extract the caching information from response headers.
+    ///
+    /// Several `Cache-Control` headers are combined. For other headers, the first one is used.
+    pub fn from_headers(headers: &'b [Header<'a>]) -> Self {
+        let mut info = CacheInfo {
+            headers,
+            cache_control: CacheControl::default(),
+            etag: None,
+            last_modified: None,
+            date: None,
+            expires: None,
+            age: None,
+        };
+
+        for h in headers {
+            let name = h.name();
+            let value = match h.try_value() {
+                Some(v) => v.trim(),
+                None => continue,
+            };
+
+            if eq_ignore_ascii(name, "cache-control") {
+                info.cache_control.parse(value);
+            } else if eq_ignore_ascii(name, "etag") {
+                info.etag = info.etag.or(Some(value));
+            } else if eq_ignore_ascii(name, "last-modified") {
+                info.last_modified = info.last_modified.or(Some(value));
+            } else if eq_ignore_ascii(name, "date") {
+                info.date = info.date.or(Some(value));
+            } else if eq_ignore_ascii(name, "expires") {
+                info.expires = info.expires.or(Some(value));
+            } else if eq_ignore_ascii(name, "age") {
+                info.age = info.age.or_else(|| value.parse().ok());
+            }
+        }
+
+        info
+    }
+
+    pub fn cache_control(&self) -> &CacheControl {
+        &self.cache_control
+    }
+
+    pub fn etag(&self) -> Option<&'a str> {
+        self.etag
+    }
+
+    pub fn last_modified(&self) -> Option<&'a str> {
+        self.last_modified
+    }
+
+    pub fn date(&self) -> Option<&'a str> {
+        self.date
+    }
+
+    pub fn expires(&self) -> Option<&'a str> {
+        self.expires
+    }
+
+    /// The `Age` header in seconds.
+    pub fn age(&self) -> Option<u64> {
+        self.age
+    }
+
+    /// The header names in `Vary`, from all `Vary` headers.
+    ///
+    /// A `*` means the response varies on more than the request headers.
+    pub fn vary(&self) -> impl Iterator<Item = &'a str> + 'b {
+        self.headers
+            .iter()
+            .filter(|h| eq_ignore_ascii(h.name(), "vary"))
+            .filter_map(|h| h.try_value())
+            .flat_map(|v| v.split(','))
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Compute the secondary cache key of a request, from the `Vary` names of this response.
+    ///
+    /// A cached response can only be used for a request that has the same key as
+    /// the request that caused the response. The key is written to `buf` and consists
+    /// of one line per `Vary` name. The names are lowercased and sorted, and duplicates
+    /// are removed. The values are the request header values, trimmed and with inner
+    /// whitespace collapsed. Repeated request headers are joined by `,`. A header absent
+    /// from the request is written without `:`, to not be equal to an empty header.
+    ///
+    /// Returns `None` for `Vary: *`, which means no other request can use the response.
+    /// Errors with [`HootError::OutputOverflow`][crate::HootError::OutputOverflow] if `buf` is too small.
+    pub fn vary_key<'o>(
+        &self,
+        request: &[Header<'_>],
+        buf: &'o mut [u8],
+    ) -> Result<Option<&'o [u8]>> {
+        if self.vary().any(|n| n == "*") {
+            return Ok(None);
+        }
+
+        let mut out = Out::wrap(buf);
+        let mut w = out.writer();
+
+        // Sorting without allocation by picking the next larger name each round.
+        let mut prev: Option<&str> = None;
+
+        loop {
+            let next = self
+                .vary()
+                .filter(|n| prev.map(|p| cmp_ignore_ascii(n, p).is_gt()).unwrap_or(true))
+                .min_by(|a, b| cmp_ignore_ascii(a, b));
+
+            let name = match next {
+                Some(v) => v,
+                None => break,
+            };
+
+            for c in name.bytes() {
+                w.write_bytes(&[c.to_ascii_lowercase()])?;
+            }
+
+            let mut found = false;
+
+            for h in request.iter().filter(|h| eq_ignore_ascii(h.name(), name)) {
+                w.write_bytes(if found { b"," } else { b":" })?;
+                write_collapsed(&mut w, h.value_raw())?;
+                found = true;
+            }
+
+            w.write_bytes(b"\n")?;
+
+            prev = Some(name);
+        }
+
+        w.commit();
+
+        let len = out.as_bytes().len();
+        let buf = out.into_buf();
+
+        Ok(Some(&buf[..len]))
+    }
+}
+
+/// Times used for calculating the age of a stored response.
+///
+/// All times are seconds since the Unix epoch, from a clock supplied by the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheTimes {
+    /// When the request was sent.
+    pub request_time: u64,
+    /// When the response was received.
+    pub response_time: u64,
+    /// The time now.
+    pub now: u64,
+}
+
+/// Whether a stored response can be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    /// The response can be used without asking the origin server.
+    Fresh,
+    /// The response is stale, but is allowed to be used in some situations, such
+    /// as when the origin server can't be reached.
+    Stale,
+    /// The response must be validated with the origin server before it can be used.
+    Revalidate,
+}
+
+impl<'a, 'b> CacheInfo<'a, 'b> {
+    /// The current age of the response in seconds, as defined by RFC 9111 4.2.3.
+    pub fn current_age(&self, times: &CacheTimes) -> u64 {
+        let date = self.date.and_then(parse_http_date);
+
+        let apparent_age = date
+            .map(|d| times.response_time.saturating_sub(d))
+            .unwrap_or(0);
+
+        let response_delay = times.response_time.saturating_sub(times.request_time);
+        let corrected_age_value = self.age.unwrap_or(0).saturating_add(response_delay);
+
+        let corrected_initial_age = apparent_age.max(corrected_age_value);
+        let resident_time = times.now.saturating_sub(times.response_time);
+
+        corrected_initial_age.saturating_add(resident_time)
+    }
+
+    /// How long the response is fresh in seconds, as defined by RFC 9111 4.2.1.
+    ///
+    /// `shared` is true for caches used by more than one user, which use `s-maxage`.
+    /// Without explicit expiration, the heuristic is 10% of the time since `Last-Modified`.
+    /// Heuristic freshness is only allowed for some status codes, and responses with
+    /// `public`, which is up to the caller to check.
+    ///
+    /// A response without a valid `Date` is dated by `times.response_time`.
+    pub fn freshness_lifetime(&self, times: &CacheTimes, shared: bool) -> u64 {
+        let cc = &self.cache_control;
+
+        if shared {
+            if let Some(v) = cc.s_maxage {
+                return v;
+            }
+        }
+
+        if let Some(v) = cc.max_age {
+            return v;
+        }
+
+        let date = self
+            .date
+            .and_then(parse_http_date)
+            .unwrap_or(times.response_time);
+
+        if let Some(expires) = self.expires {
+            // An invalid Expires means already expired.
+            return match parse_http_date(expires) {
+                Some(v) => v.saturating_sub(date),
+                None => 0,
+            };
+        }
+
+        match self.last_modified.and_then(parse_http_date) {
+            Some(lm) => date.saturating_sub(lm) / 10,
+            None => 0,
+        }
+    }
+
+    /// Whether the stored response is fresh, stale, or must be revalidated.
+    pub fn freshness(&self, times: &CacheTimes, shared: bool) -> Freshness {
+        let cc = &self.cache_control;
+
+        if cc.no_cache || cc.no_store {
+            return Freshness::Revalidate;
+        }
+
+        if self.freshness_lifetime(times, shared) > self.current_age(times) {
+            return Freshness::Fresh;
+        }
+
+        let must_revalidate =
+            cc.must_revalidate || shared && (cc.proxy_revalidate || cc.s_maxage.is_some());
+
+        if must_revalidate {
+            Freshness::Revalidate
+        } else {
+            Freshness::Stale
+        }
+    }
+}
+
+/// Parse an HTTP date to seconds since the Unix epoch.
+///
+/// Handles the preferred IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`), and the obsolete
+/// RFC 850 (`Sunday, 06-Nov-94 08:49:37 GMT`) and asctime (`Sun Nov  6 08:49:37 1994`)
+/// formats. The day name is not checked.
+pub fn parse_http_date(s: &str) -> Option<u64> {
+    let mut parts = s.split([' ', '-']).filter(|p| !p.is_empty());
+
+    let first = parts.next()?;
+
+    let (day, month, year, time) = if first.ends_with(',') {
+        // IMF-fixdate or RFC 850
+        let day = parts.next()?;
+        let month = parts.next()?;
+        let year = parts.next()?;
+        let time = parts.next()?;
+        if parts.next()? != "GMT" {
+            return None;
+        }
+        (day, month, year, time)
+    } else {
+        // asctime
+        let month = parts.next()?;
+        let day = parts.next()?;
+        let time = parts.next()?;
+        let year = parts.next()?;
+        (day, month, year, time)
+    };
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let day: u64 = parse_digits(day, 1, 2)?;
+    let month = MONTHS.iter().position(|m| *m == month)? as u64 + 1;
+
+    let year: u64 = match year.len() {
+        // RFC 850 two digit years.
+        2 => match parse_digits(year, 2, 2)? {
+            y if y < 70 => 2000 + y,
+            y => 1900 + y,
+        },
+        _ => parse_digits(year, 4, 4)?,
+    };
+
+    let mut hms = time.split(':');
+    let hour: u64 = parse_digits(hms.next()?, 2, 2)?;
+    let min: u64 = parse_digits(hms.next()?, 2, 2)?;
+    let sec: u64 = parse_digits(hms.next()?, 2, 2)?;
+
+    if hms.next().is_some() || year < 1970 || day == 0 || day > 31 || hour > 23 || min > 59 {
+        return None;
+    }
+
+    // Leap seconds are allowed.
+    if sec > 60 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+
+    Some(days * 86400 + hour * 3600 + min * 60 + sec)
+}
+
+/// Seconds since the Unix epoch, displayed as an IMF-fixdate, `Sun, 06 Nov 1994 08:49:37 GMT`.
+///
+/// The preferred format of `Date`, `Last-Modified` and `Expires`.
+///
+/// ```
+/// use hoot::cache::HttpDate;
+///
+/// assert_eq!(HttpDate(784111777).to_string(), "Sun, 06 Nov 1994 08:49:37 GMT");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpDate(pub u64);
+
+impl fmt::Display for HttpDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 1970-01-01 was a Thursday.
+        const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+
+        let days = self.0 / 86400;
+        let rem = self.0 % 86400;
+        let (year, month, day) = civil_from_days(days);
+
+        write!(
+            f,
+            "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+            DAYS[(days % 7) as usize],
+            day,
+            MONTHS[(month - 1) as usize],
+            year,
+            rem / 3600,
+            rem % 3600 / 60,
+            rem % 60
+        )
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+fn parse_digits(s: &str, min: usize, max: usize) -> Option<u64> {
+    if s.len() < min || s.len() > max || !s.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Days since 1970-01-01 for a date from 1970 and onwards.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    // Years starting in March puts the leap day last.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Year, month and day of days since 1970-01-01.
+pub(crate) fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Years starting in March puts the leap day last.
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Write a value trimmed, with runs of inner whitespace as one space.
+fn write_collapsed(w: &mut Writer<'_, '_>, value: &[u8]) -> Result<()> {
+    let mut space = false;
+
+    for c in value.split(|c| *c == b' ' || *c == b'\t') {
+        if c.is_empty() {
+            continue;
+        }
+        if space {
+            w.write_bytes(b" ")?;
+        }
+        w.write_bytes(c)?;
+        space = true;
+    }
+
+    Ok(())
+}
+
+fn cmp_ignore_ascii(a: &str, b: &str) -> Ordering {
+    let a = a.bytes().map(|c| c.to_ascii_lowercase());
+    let b = b.bytes().map(|c| c.to_ascii_lowercase());
+    a.cmp(b)
+}
+
+impl CacheControl {
+    fn parse(&mut self, value: &str) {
+        for directive in value.split(',') {
+            let (name, arg) = match directive.split_once('=') {
+                Some((n, a)) => (n.trim(), Some(a.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+
+            let seconds = arg.and_then(|a| a.parse().ok());
+
+            if eq_ignore_ascii(name, "max-age") {
+                self.max_age = seconds;
+            } else if eq_ignore_ascii(name, "s-maxage") {
+                self.s_maxage = seconds;
+            } else if eq_ignore_ascii(name, "stale-while-revalidate") {
+                self.stale_while_revalidate = seconds;
+            } else if eq_ignore_ascii(name, "stale-if-error") {
+                self.stale_if_error = seconds;
+            } else if eq_ignore_ascii(name, "no-cache") {
+                self.no_cache = true;
+            } else if eq_ignore_ascii(name, "no-store") {
+                self.no_store = true;
+            } else if eq_ignore_ascii(name, "no-transform") {
+                self.no_transform = true;
+            } else if eq_ignore_ascii(name, "private") {
+                self.private = true;
+            } else if eq_ignore_ascii(name, "public") {
+                self.public = true;
+            } else if eq_ignore_ascii(name, "must-revalidate") {
+                self.must_revalidate = true;
+            } else if eq_ignore_ascii(name, "proxy-revalidate") {
+                self.proxy_revalidate = true;
+            } else if eq_ignore_ascii(name, "immutable") {
+                self.immutable = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_headers;
+
+    #[test]
+    fn test_cache_info() {
+        let input = b"Cache-Control: no-cache\r\n\
+            cache-control: MAX-AGE=\"30\", s-maxage=x\r\n\
+            Date: Mon, 01 Jan 2024 00:00:00 GMT\r\n\
+            Expires: Mon, 01 Jan 2024 00:01:00 GMT\r\n\
+            Last-Modified: Sun, 31 Dec 2023 00:00:00 GMT\r\n\
+            Age: 12\r\n\
+            Vary: accept\r\n\
+            Vary: cookie,,\r\n\r\n";
+        let mut buf = [0; 1024];
+        let headers = parse_headers(input, &mut buf).unwrap();
+
+        let info = CacheInfo::from_headers(headers);
+        let cc = info.cache_control();
+
+        assert!(cc.no_cache);
+        assert_eq!(cc.max_age, Some(30));
+        assert_eq!(cc.s_maxage, None);
+        assert!(!cc.no_store);
+
+        assert_eq!(info.etag(), None);
+        assert_eq!(info.date(), Some("Mon, 01 Jan 2024 00:00:00 GMT"));
+        assert_eq!(info.expires(), Some("Mon, 01 Jan 2024 00:01:00 GMT"));
+        assert_eq!(info.last_modified(), Some("Sun, 31 Dec 2023 00:00:00 GMT"));
+        assert_eq!(info.age(), Some(12));
+
+        let vary: Vec<_> = info.vary().collect();
+        assert_eq!(vary, ["accept", "cookie"]);
+    }
+
+    #[test]
+    fn test_vary_key() {
+        let input = b"Vary: Accept-Encoding, accept\r\nVary: X-Absent, ACCEPT\r\n\r\n";
+        let mut buf = [0; 1024];
+        let headers = parse_headers(input, &mut buf).unwrap();
+        let info = CacheInfo::from_headers(headers);
+
+        let req = b"accept:  text/html,   text/plain \r\nAccept-Encoding: gzip\r\n\
+            Accept-Encoding: br\r\n\r\n";
+        let mut buf2 = [0; 1024];
+        let req = parse_headers(req, &mut buf2).unwrap();
+
+        let mut out = [0; 1024];
+        let key = info.vary_key(req, &mut out).unwrap().unwrap();
+        assert_eq!(
+            key,
+            b"accept:text/html, text/plain\naccept-encoding:gzip,br\nx-absent\n"
+        );
+
+        let mut small = [0; 10];
+        assert_eq!(
+            info.vary_key(req, &mut small),
+            Err(crate::HootError::OutputOverflow)
+        );
+
+        let input = b"Vary: accept, *\r\n\r\n";
+        let headers = parse_headers(input, &mut buf).unwrap();
+        let info = CacheInfo::from_headers(headers);
+        assert_eq!(info.vary_key(req, &mut out).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        const T: Option<u64> = Some(784111777);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), T);
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), T);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), T);
+        assert_eq!(parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 12:00:00 GMT"),
+            Some(1709208000)
+        );
+
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 CET"), None);
+        assert_eq!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("0"), None);
+        assert_eq!(parse_http_date(""), None);
+    }
+
+    #[test]
+    fn test_format_http_date() {
+        assert_eq!(HttpDate(0).to_string(), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(
+            HttpDate(784111777).to_string(),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        assert_eq!(
+            HttpDate(1709164800).to_string(),
+            "Thu, 29 Feb 2024 00:00:00 GMT"
+        );
+
+        let date = HttpDate(1709164800).to_string();
+        assert_eq!(parse_http_date(&date), Some(1709164800));
+    }
+
+    #[test]
+    fn test_freshness() {
+        let input = b"Date: Thu, 01 Jan 1970 00:01:40 GMT\r\n\
+            Cache-Control: max-age=60\r\n\
+            Age: 10\r\n\r\n";
+        let mut buf = [0; 1024];
+        let headers = parse_headers(input, &mut buf).unwrap();
+        let info = CacheInfo::from_headers(headers);
+
+        let mut times = CacheTimes {
+            request_time: 100,
+            response_time: 102,
+            now: 102,
+        };
+
+        // corrected age is 10 + 2 delay.
+        assert_eq!(info.current_age(&times), 12);
+        assert_eq!(info.freshness_lifetime(&times, false), 60);
+        assert_eq!(info.freshness(&times, false), Freshness::Fresh);
+
+        times.now = 150;
+        assert_eq!(info.current_age(&times), 60);
+        assert_eq!(info.freshness(&times, false), Freshness::Stale);
+
+        let input = b"Date: Thu, 01 Jan 1970 00:01:40 GMT\r\n\
+            Expires: Thu, 01 Jan 1970 00:02:00 GMT\r\n\
+            Cache-Control: s-maxage=5\r\n\r\n";
+        let headers = parse_headers(input, &mut buf).unwrap();
+        let info = CacheInfo::from_headers(headers);
+
+        assert_eq!(info.freshness_lifetime(&times, false), 20);
+        assert_eq!(info.freshness_lifetime(&times, true), 5);
+
+        times.now = 110;
+        assert_eq!(info.freshness(&times, false), Freshness::Fresh);
+        assert_eq!(info.freshness(&times, true), Freshness::Revalidate);
+
+        let input = b"Date: Thu, 01 Jan 1970 00:01:40 GMT\r\n\
+            Last-Modified: Thu, 01 Jan 1970 00:00:00 GMT\r\n\r\n";
+        let headers = parse_headers(input, &mut buf).unwrap();
+        let info = CacheInfo::from_headers(headers);
+        assert_eq!(info.freshness_lifetime(&times, false), 10);
+
+        // Without Date, the response time is the base.
+        let input = b"Expires: Thu, 01 Jan 1970 00:02:00 GMT\r\n\r\n";
+        let headers = parse_headers(input, &mut buf).unwrap();
+        let info = CacheInfo::from_headers(headers);
+        assert_eq!(info.freshness_lifetime(&times, false), 18);
+
+        times.now = 110;
+        assert_eq!(info.freshness(&times, false), Freshness::Fresh);
+        times.now = 125;
+        assert_eq!(info.freshness(&times, false), Freshness::Stale);
+    }
+}
@@ -1,6 +1,7 @@
 use core::str;
 
 use crate::error::Result;
+use crate::out::Writer;
 use crate::parser::find_crlf;
 use crate::HootError;
 
@@ -44,8 +45,46 @@ impl Dechunker {
         Ok((pos.index_in, pos.index_out))
     }
 
-    #[cfg(test)]
-    fn left(&self) -> usize {
+    /// Like `parse_input`, but instead of copying the chunk data to an output
+    /// buffer, this returns a slice of the input for the current chunk.
+    ///
+    /// Returns the amount of input used and the chunk data, which might be empty
+    /// if the input only held chunk framing.
+    pub fn parse_input_borrowed<'a>(&mut self, src: &'a [u8]) -> Result<(usize, &'a [u8])> {
+        let mut pos = Pos {
+            index_in: 0,
+            index_out: 0,
+        };
+
+        loop {
+            let more = match self {
+                Dechunker::Size => self.read_size(src, &mut pos)?,
+                Dechunker::Chunk(left) => {
+                    let start = pos.index_in;
+                    let to_read = (src.len() - start).min(*left);
+
+                    *left -= to_read;
+                    if *left == 0 {
+                        *self = Self::CrLf;
+                    }
+
+                    let end = start + to_read;
+                    return Ok((end, &src[start..end]));
+                }
+                Dechunker::CrLf => self.read_crlf(src, &mut pos)?,
+                Dechunker::Ended => false,
+            };
+
+            if !more {
+                break;
+            }
+        }
+
+        Ok((pos.index_in, &[]))
+    }
+
+    /// Data left in the current chunk.
+    pub fn left(&self) -> usize {
         if let Self::Chunk(l) = self {
             *l
         } else {
@@ -65,7 +104,8 @@ impl Dechunker {
             None => return Ok(false),
         };
 
-        let len_end = src.iter().position(|c| *c == b';').unwrap_or(i);
+        // Chunk extensions after ; are ignored.
+        let len_end = src[..i].iter().position(|c| *c == b';').unwrap_or(i);
         let len_str = str::from_utf8(&src[..len_end])?;
         let len = usize::from_str_radix(len_str, 16)?;
 
@@ -91,7 +131,7 @@ impl Dechunker {
         // Read the smallest amount of input/output or length left of chunk.
         let to_read = src.len().min(dst.len()).min(*left);
 
-        (&mut dst[..to_read]).copy_from_slice(&src[..to_read]);
+        dst[..to_read].copy_from_slice(&src[..to_read]);
         pos.index_in += to_read;
         pos.index_out += to_read;
         *left -= to_read;
@@ -122,6 +162,32 @@ impl Dechunker {
     }
 }
 
+/// Write the chunk size line, `<hex>\r\n`.
+///
+/// This avoids `core::fmt` in the body writing path.
+pub(crate) fn write_chunk_size(w: &mut Writer<'_, '_>, size: usize) -> Result<usize> {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    // Enough for a u64 in hex + \r\n
+    let mut buf = [0_u8; 18];
+    let mut pos = 16;
+
+    buf[16] = b'\r';
+    buf[17] = b'\n';
+
+    let mut n = size;
+    loop {
+        pos -= 1;
+        buf[pos] = HEX[n & 0xf];
+        n >>= 4;
+        if n == 0 {
+            break;
+        }
+    }
+
+    w.write_bytes(&buf[pos..])
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -162,4 +228,33 @@ mod test {
         assert!(d.is_ended());
         Ok(())
     }
+
+    #[test]
+    fn test_write_chunk_size() -> Result<()> {
+        use crate::out::Out;
+
+        let mut buf = [0; 1024];
+        let mut out = Out::wrap(&mut buf);
+
+        let mut w = out.writer();
+        for n in [0, 1, 15, 16, 0xabc, usize::MAX] {
+            write_chunk_size(&mut w, n)?;
+        }
+        w.commit();
+
+        let expected = format!("0\r\n1\r\nf\r\n10\r\nabc\r\n{:x}\r\n", usize::MAX);
+        assert_eq!(out.as_bytes(), expected.as_bytes());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dechunk_borrowed() -> Result<()> {
+        let mut d = Dechunker::new();
+        assert_eq!(d.parse_input_borrowed(b"3\r\nOK")?, (5, &b"OK"[..]));
+        assert_eq!(d.left(), 1);
+        assert_eq!(d.parse_input_borrowed(b"!\r\n0\r\n")?, (1, &b"!"[..]));
+        assert_eq!(d.parse_input_borrowed(b"\r\n0\r\n")?, (5, &b""[..]));
+        assert!(d.is_ended());
+        Ok(())
+    }
 }
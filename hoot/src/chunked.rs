@@ -0,0 +1,523 @@
+//! Chunked transfer coding
+//!
+//! The decoder and encoder used for `Transfer-Encoding: chunked` bodies, as standalone
+//! types. They are useful for chunked framing outside of a request or response.
+//!
+//! ```
+//! use hoot::chunked::ChunkedDecoder;
+//!
+//! let mut decoder = ChunkedDecoder::new();
+//! let mut dst = [0; 1024];
+//!
+//! let input = b"5;ext=1\r\nhello\r\n0\r\nx-sum: 1\r\n\r\n";
+//!
+//! let (input_used, output_len, done) = decoder.decode(input, &mut dst)?;
+//! assert_eq!(&dst[..output_len], b"hello");
+//! assert!(!done);
+//!
+//! // The last chunk is read, and the trailers follow.
+//! assert!(decoder.is_trailers_pending());
+//!
+//! let mut buf = [0; 1024];
+//! let attempt = decoder.read_trailers(&input[input_used..], &mut buf)?;
+//! assert_eq!(attempt.trailers().unwrap()[0].name(), "x-sum");
+//! assert!(decoder.is_done());
+//! # Ok::<(), hoot::HootError>(())
+//! ```
+
+use crate::chunk::{write_chunk_size, Dechunker};
+use crate::error::Result;
+use crate::header::transmute_headers;
+use crate::out::{Out, Writer};
+use crate::parse_impl;
+use crate::parser::{find_crlf, parse_header_line};
+use crate::util::cast_buf_for_headers;
+use crate::Header;
+
+/// Push-style decoder of chunked transfer coding.
+///
+/// Chunk extensions are ignored. After the last chunk, the decoder stops before
+/// the trailers, which are either read with [`read_trailers()`][Self::read_trailers],
+/// or skipped by decoding further.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ChunkedDecoder {
+    dechunker: Dechunker,
+    done: bool,
+}
+
+impl ChunkedDecoder {
+    pub fn new() -> Self {
+        ChunkedDecoder {
+            dechunker: Dechunker::new(),
+            done: false,
+        }
+    }
+
+    /// Decode input to output.
+    ///
+    /// Returns the amount of input used, the amount of output produced, and whether
+    /// the chunked data is done, including the trailers. Decoding never uses input after
+    /// the end, which means the input can hold other data after the chunked data.
+    pub fn decode(&mut self, src: &[u8], dst: &mut [u8]) -> Result<(usize, usize, bool)> {
+        if self.done {
+            return Ok((0, 0, true));
+        }
+
+        // The last chunk is read, and the trailers are skipped since we are decoding
+        // instead of reading them.
+        if self.dechunker.is_ended() {
+            return Ok(match skip_trailers(src)? {
+                Some(input_used) => {
+                    self.done = true;
+                    (input_used, 0, true)
+                }
+                None => (0, 0, false),
+            });
+        }
+
+        let (input_used, output_len) = self.dechunker.parse_input(src, dst)?;
+
+        trace!("Decoded chunked: {} -> {}", input_used, output_len);
+
+        Ok((input_used, output_len, false))
+    }
+
+    /// Like [`decode()`][Self::decode], but instead of copying to output, the data is
+    /// borrowed from the input.
+    ///
+    /// Returns the amount of input used, the data of the current chunk, and whether the
+    /// chunked data is done. The data is only ever from one chunk, which means it can
+    /// be empty when there is input left.
+    pub fn decode_borrowed<'a>(&mut self, src: &'a [u8]) -> Result<(usize, &'a [u8], bool)> {
+        if self.done || self.dechunker.is_ended() {
+            let (input_used, _, done) = self.decode(src, &mut [])?;
+            return Ok((input_used, &[], done));
+        }
+
+        let (input_used, data) = self.dechunker.parse_input_borrowed(src)?;
+
+        trace!("Decoded chunked borrowed: {} -> {}", input_used, data.len());
+
+        Ok((input_used, data, false))
+    }
+
+    /// Skip input without producing output, including the trailers.
+    ///
+    /// Returns the amount of input used, and whether the chunked data is done.
+    pub fn skip(&mut self, src: &[u8]) -> Result<(usize, bool)> {
+        let (input_used, _, done) = self.skip_counted(src)?;
+        Ok((input_used, done))
+    }
+
+    /// Like `skip`, but also returns the amount of chunk data skipped.
+    pub(crate) fn skip_counted(&mut self, src: &[u8]) -> Result<(usize, usize, bool)> {
+        let mut input_used = 0;
+        let mut data_len = 0;
+
+        while !self.dechunker.is_ended() {
+            let (used, data) = self.dechunker.parse_input_borrowed(&src[input_used..])?;
+            input_used += used;
+            data_len += data.len();
+            if used == 0 {
+                break;
+            }
+        }
+
+        if self.dechunker.is_ended() && !self.done {
+            if let Some(n) = skip_trailers(&src[input_used..])? {
+                input_used += n;
+                self.done = true;
+            }
+        }
+
+        Ok((input_used, data_len, self.done))
+    }
+
+    /// Read the trailers following the last chunk.
+    ///
+    /// Like for headers, the buffer is used to parse the trailers into. The attempt
+    /// only succeeds when [`is_trailers_pending()`][Self::is_trailers_pending] and the
+    /// input holds all the trailers.
+    pub fn read_trailers<'a, 'b>(
+        &mut self,
+        src: &'a [u8],
+        buf: &'b mut [u8],
+    ) -> Result<TrailerAttempt<'a, 'b>> {
+        if !self.is_trailers_pending() {
+            return Ok(TrailerAttempt::empty());
+        }
+
+        let hbuf = cast_buf_for_headers(buf);
+
+        let (input_used, trailers) = match parse_impl::parse_headers(src, hbuf)? {
+            parse_impl::Status::Complete(v) => v,
+            parse_impl::Status::Partial => return Ok(TrailerAttempt::empty()),
+        };
+
+        let trailers = transmute_headers(trailers);
+        trace!("Trailers: {:?}", trailers);
+
+        self.done = true;
+
+        Ok(TrailerAttempt {
+            input_used,
+            trailers: Some(trailers),
+        })
+    }
+
+    /// Data left to decode in the current chunk.
+    ///
+    /// This is 0 between chunks, and the size of the next chunk is unknown until its
+    /// size line is decoded.
+    pub fn chunk_left(&self) -> usize {
+        self.dechunker.left()
+    }
+
+    /// The last chunk is read, and the trailers are next.
+    pub fn is_trailers_pending(&self) -> bool {
+        self.dechunker.is_ended() && !self.done
+    }
+
+    /// The chunked data is decoded to the end, including the trailers.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+impl Default for ChunkedDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encoder of chunked transfer coding.
+///
+/// Each call to [`encode()`][Self::encode] writes the given data as chunks, which
+/// means a partial chunk is flushed by encoding the data at hand. Chunks are never
+/// larger than the max chunk size, if set.
+///
+/// ```
+/// use hoot::chunked::ChunkedEncoder;
+///
+/// let mut encoder = ChunkedEncoder::new().max_chunk_size(4);
+/// let mut dst = [0; 1024];
+///
+/// let (input_used, n) = encoder.encode(b"hello", &mut dst)?;
+/// assert_eq!(input_used, 5);
+///
+/// let m = encoder.finish(&mut dst[n..])?;
+/// assert_eq!(&dst[..(n + m)], b"4\r\nhell\r\n1\r\no\r\n0\r\n\r\n");
+/// # Ok::<(), hoot::HootError>(())
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkedEncoder {
+    max_chunk: Option<usize>,
+    align: Option<usize>,
+    /// Position in the current aligned block.
+    pos: usize,
+}
+
+impl ChunkedEncoder {
+    pub fn new() -> Self {
+        ChunkedEncoder {
+            max_chunk: None,
+            align: None,
+            pos: 0,
+        }
+    }
+
+    /// Never write chunks with more than `max` bytes of data.
+    ///
+    /// This is for receivers with fixed size buffers. Panics if `max` is 0.
+    pub fn max_chunk_size(mut self, max: usize) -> Self {
+        assert!(max > 0, "max chunk size must be greater than 0");
+        self.max_chunk = Some(max);
+        self
+    }
+
+    /// Split chunks so that chunk boundaries fall on multiples of `size` in the data.
+    ///
+    /// Panics if `size` is 0.
+    pub fn align_chunks(mut self, size: usize) -> Self {
+        assert!(size > 0, "chunk alignment must be greater than 0");
+        self.align = Some(size);
+        self
+    }
+
+    /// Encode input as chunks to output.
+    ///
+    /// Returns the amount of input used and the amount of output written. Chunks are
+    /// made smaller to fit the output, and input that doesn't fit is left for the next call.
+    /// Empty input writes nothing, since an empty chunk ends the body.
+    pub fn encode(&mut self, src: &[u8], dst: &mut [u8]) -> Result<(usize, usize)> {
+        let mut out = Out::wrap(dst);
+
+        let input_used = self.fit(out.capacity()).min(src.len());
+
+        let mut w = out.writer();
+        self.write(&mut w, &src[..input_used])?;
+        w.commit();
+
+        trace!(
+            "Encoded chunked: {} -> {}",
+            input_used,
+            out.as_bytes().len()
+        );
+
+        Ok((input_used, out.as_bytes().len()))
+    }
+
+    /// Write the last chunk, ending the chunked data without trailers.
+    ///
+    /// Returns the amount of output written.
+    pub fn finish(&mut self, dst: &mut [u8]) -> Result<usize> {
+        let mut out = Out::wrap(dst);
+        let mut w = out.writer();
+        w.write_bytes(b"0\r\n\r\n")?;
+        w.commit();
+        Ok(out.as_bytes().len())
+    }
+
+    /// Write all of `bytes` as chunks. Nothing is written for empty input.
+    pub(crate) fn write(&mut self, w: &mut Writer<'_, '_>, bytes: &[u8]) -> Result<()> {
+        let mut rest = bytes;
+
+        while !rest.is_empty() {
+            let max = self.next_max().unwrap_or(rest.len());
+            let (chunk, next) = rest.split_at(max.min(rest.len()));
+
+            write_chunk_size(w, chunk.len())?;
+            w.write_bytes(chunk)?;
+            w.write_bytes(b"\r\n")?;
+
+            self.advance(chunk.len());
+            rest = next;
+        }
+
+        Ok(())
+    }
+
+    /// The most data [`write()`][Self::write] can write in `capacity` bytes of output.
+    pub(crate) fn fit(&self, mut capacity: usize) -> usize {
+        let mut encoder = *self;
+        let mut fit = 0;
+
+        loop {
+            // Size line and chunk end, with the size line as long as it can be.
+            let room = capacity.saturating_sub(hex_len(capacity) + 4);
+            let max = encoder.next_max();
+            let len = room.min(max.unwrap_or(room));
+
+            if len == 0 {
+                break;
+            }
+
+            fit += len;
+            capacity -= hex_len(len) + 4 + len;
+
+            // Without a cap, the data is written as one chunk.
+            match max {
+                Some(m) if len == m => encoder.advance(len),
+                _ => break,
+            }
+        }
+
+        fit
+    }
+
+    /// The most data in the next chunk, from the max chunk size and alignment.
+    fn next_max(&self) -> Option<usize> {
+        let to_align = self.align.map(|a| a - self.pos);
+        match (self.max_chunk, to_align) {
+            (Some(m), Some(a)) => Some(m.min(a)),
+            (m, a) => m.or(a),
+        }
+    }
+
+    fn advance(&mut self, len: usize) {
+        if let Some(a) = self.align {
+            self.pos = (self.pos + len) % a;
+        }
+    }
+}
+
+fn hex_len(n: usize) -> usize {
+    let bits = usize::BITS - n.leading_zeros();
+    ((bits as usize + 3) / 4).max(1)
+}
+
+/// Skip the trailer section. Returns `None` if the input doesn't hold all of it.
+fn skip_trailers(src: &[u8]) -> Result<Option<usize>> {
+    let mut pos = 0;
+
+    loop {
+        let i = match find_crlf(&src[pos..]) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        let line = &src[pos..(pos + i + 2)];
+        pos += line.len();
+
+        if i == 0 {
+            return Ok(Some(pos));
+        }
+
+        // Still check that the trailers are correct.
+        parse_header_line(line)?;
+    }
+}
+
+/// Attempt to read the trailers of a chunked body.
+pub struct TrailerAttempt<'a, 'b> {
+    input_used: usize,
+    trailers: Option<&'b [Header<'a>]>,
+}
+
+impl<'a, 'b> TrailerAttempt<'a, 'b> {
+    pub(crate) const fn empty() -> Self {
+        TrailerAttempt {
+            input_used: 0,
+            trailers: None,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.trailers.is_some()
+    }
+
+    pub fn input_used(&self) -> usize {
+        self.input_used
+    }
+
+    /// The trailers. Often empty, since most bodies don't have trailers.
+    pub fn trailers(&self) -> Option<&'b [Header<'a>]> {
+        self.trailers
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_in_pieces() -> Result<()> {
+        let input = b"3;a=b\r\nabc\r\n2\r\nde\r\n0\r\nx: 1\r\n\r\nrest";
+
+        let mut d = ChunkedDecoder::new();
+        let mut out = vec![];
+        let mut pos = 0;
+        let mut end = 0;
+        let mut dst = [0; 2];
+
+        loop {
+            let (used, n, done) = d.decode(&input[pos..end], &mut dst)?;
+            pos += used;
+            out.extend_from_slice(&dst[..n]);
+            if done {
+                break;
+            }
+            end = (end + 1).min(input.len());
+        }
+
+        assert_eq!(out, b"abcde");
+        assert_eq!(&input[pos..], b"rest");
+        assert!(d.is_done());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip() -> Result<()> {
+        let input = b"3\r\nabc\r\n0\r\n\r\nrest";
+
+        let mut d = ChunkedDecoder::new();
+        assert_eq!(d.skip(&input[..10])?, (8, false));
+        assert_eq!(d.skip(&input[8..])?, (5, true));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_fit_output() -> Result<()> {
+        let mut e = ChunkedEncoder::new();
+        let src = [b'x'; 20];
+
+        // 16 bytes of data needs two hex digits, "10\r\n" + data + "\r\n"
+        let mut dst = [0; 21];
+        let (used, n) = e.encode(&src, &mut dst)?;
+        assert_eq!((used, n), (15, 20));
+        assert_eq!(&dst[..3], b"f\r\n");
+
+        // Not even room for one byte.
+        let mut dst = [0; 5];
+        assert_eq!(e.encode(&src, &mut dst)?, (0, 0));
+
+        let mut dst = [0; 6];
+        assert_eq!(e.encode(&src, &mut dst)?, (1, 6));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_max_chunk_roundtrip() -> Result<()> {
+        let src: Vec<u8> = (0..100_u8).collect();
+
+        let mut e = ChunkedEncoder::new().max_chunk_size(16);
+        let mut dst = [0; 1024];
+        let (used, n) = e.encode(&src, &mut dst)?;
+        assert_eq!(used, 100);
+        let m = e.finish(&mut dst[n..])?;
+
+        // 6 chunks of 16 and one of 4
+        assert_eq!(n, 6 * (2 + 2 + 16 + 2) + (1 + 2 + 4 + 2));
+
+        let mut d = ChunkedDecoder::new();
+        let mut out = [0; 1024];
+        let (used, len, _) = d.decode(&dst[..(n + m)], &mut out)?;
+        assert_eq!(&out[..len], &src[..]);
+        assert_eq!(d.skip(&dst[used..(n + m)])?, (2, true));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_aligned() -> Result<()> {
+        let mut e = ChunkedEncoder::new().align_chunks(4);
+        let mut dst = [0; 1024];
+
+        let (_, n) = e.encode(b"abcdef", &mut dst)?;
+        let (_, m) = e.encode(b"ghij", &mut dst[n..])?;
+
+        assert_eq!(
+            &dst[..(n + m)],
+            b"4\r\nabcd\r\n2\r\nef\r\n2\r\ngh\r\n2\r\nij\r\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fit() -> Result<()> {
+        let encoders = [
+            ChunkedEncoder::new(),
+            ChunkedEncoder::new().max_chunk_size(3),
+            ChunkedEncoder::new().align_chunks(4),
+            ChunkedEncoder::new().align_chunks(16).max_chunk_size(5),
+        ];
+
+        for cap in 0..64 {
+            for mut e in encoders {
+                // Start off the aligned block.
+                e.encode(b"x", &mut [0; 8])?;
+
+                let fit = e.fit(cap);
+
+                let mut buf = [0; 64];
+                let mut out = Out::wrap(&mut buf[..cap]);
+                let mut w = out.writer();
+                e.write(&mut w, &[b'x'; 64][..fit])?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use core::time::Duration;
+
+/// Candidate origins for a request, tried in order of preference.
+///
+/// The origins are typically the results of an SRV lookup or `Alt-Svc`, and can be
+/// anything identifying where to connect, such as a `(&str, u16)` host and port. Holds
+/// `N` origins.
+///
+/// An origin failing to connect, or answering with a 5xx, is put in a cooldown where
+/// it isn't tried. There is no clock here. The caller passes the time, as a duration
+/// since any fixed point, such as the start of the program.
+///
+/// ```
+/// use core::time::Duration;
+/// use hoot::client::Failover;
+///
+/// let cooldown = Duration::from_secs(30);
+/// let mut failover = Failover::new(["a.test", "b.test"], cooldown);
+///
+/// let now = Duration::from_secs(100);
+///
+/// // First try the most preferred origin.
+/// let (idx, origin) = failover.next_origin(now).unwrap();
+/// assert_eq!(origin, "a.test");
+///
+/// // Connecting failed, try the next.
+/// failover.connect_failed(idx, now);
+/// let (idx, origin) = failover.next_origin(now).unwrap();
+/// assert_eq!(origin, "b.test");
+///
+/// // A 503 puts that one in cooldown too.
+/// assert!(failover.response(idx, 503, now));
+/// assert!(failover.next_origin(now).is_none());
+///
+/// // Later, the cooldown is over.
+/// let later = now + cooldown;
+/// assert_eq!(failover.next_origin(later), Some((0, "a.test")));
+/// ```
+pub struct Failover<T, const N: usize> {
+    origins: [T; N],
+    cooldown_until: [Option<Duration>; N],
+    cooldown: Duration,
+}
+
+impl<T: Copy, const N: usize> Failover<T, N> {
+    /// Origins, most preferred first, and how long a failed origin is left alone.
+    pub fn new(origins: [T; N], cooldown: Duration) -> Self {
+        Failover {
+            origins,
+            cooldown_until: [None; N],
+            cooldown,
+        }
+    }
+
+    /// The most preferred origin not in cooldown, and its index.
+    ///
+    /// `None` if all origins are in cooldown.
+    pub fn next_origin(&self, now: Duration) -> Option<(usize, T)> {
+        (0..N)
+            .find(|i| !self.is_cooling_down(*i, now))
+            .map(|i| (i, self.origins[i]))
+    }
+
+    /// Whether the origin at `index` is in cooldown.
+    ///
+    /// Panics if `index` is not less than `N`.
+    pub fn is_cooling_down(&self, index: usize, now: Duration) -> bool {
+        match self.cooldown_until[index] {
+            Some(until) => now < until,
+            None => false,
+        }
+    }
+
+    /// Connecting to the origin at `index` failed.
+    ///
+    /// Panics if `index` is not less than `N`.
+    pub fn connect_failed(&mut self, index: usize, now: Duration) {
+        trace!("Failover connect failed: {}", index);
+        self.cooldown_until[index] = Some(now.saturating_add(self.cooldown));
+    }
+
+    /// The origin at `index` answered with `status`.
+    ///
+    /// A 5xx puts the origin in cooldown, and returns true, meaning the request should
+    /// be tried with the next origin. Other statuses end any cooldown.
+    ///
+    /// Panics if `index` is not less than `N`.
+    pub fn response(&mut self, index: usize, status: u16, now: Duration) -> bool {
+        let failed = (500..600).contains(&status);
+
+        if failed {
+            trace!("Failover status {}: {}", status, index);
+            self.cooldown_until[index] = Some(now.saturating_add(self.cooldown));
+        } else {
+            self.cooldown_until[index] = None;
+        }
+
+        failed
+    }
+
+    /// The origins, most preferred first.
+    pub fn origins(&self) -> &[T; N] {
+        &self.origins
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_failover_recovers() {
+        let secs = Duration::from_secs;
+        let mut f = Failover::new([("a.test", 443), ("b.test", 8443)], secs(10));
+
+        f.connect_failed(0, secs(0));
+        assert_eq!(f.next_origin(secs(5)), Some((1, ("b.test", 8443))));
+
+        // b recovers from a 502 by answering with a 404.
+        assert!(f.response(1, 502, secs(5)));
+        assert!(f.next_origin(secs(9)).is_none());
+        assert!(!f.response(1, 404, secs(9)));
+        assert_eq!(f.next_origin(secs(9)), Some((1, ("b.test", 8443))));
+
+        assert_eq!(f.next_origin(secs(10)), Some((0, ("a.test", 443))));
+    }
+
+    #[test]
+    fn test_failover_cooldown_saturates() {
+        let mut f = Failover::new(["a.test"], Duration::MAX);
+
+        f.connect_failed(0, Duration::from_secs(1));
+        assert!(f.is_cooling_down(0, Duration::MAX - Duration::from_secs(1)));
+
+        let late = Duration::MAX - Duration::from_secs(1);
+        assert!(f.response(0, 500, late));
+        assert!(f.next_origin(late).is_none());
+    }
+}
@@ -136,10 +136,33 @@
 //! ```
 
 mod req;
-pub use req::{Output, Request, ResumeToken};
+pub use req::{Output, Request, ResumeToken, VectoredOutput, VersionedRequest};
 
 mod res;
-pub use res::{Response, Status};
+pub use res::{Response, Status, Tunnel, Upgraded};
+
+mod pipeline;
+pub use pipeline::Pipeline;
+
+mod failover;
+pub use failover::Failover;
+
+mod retry;
+pub use retry::Retry;
+
+mod resolve;
+pub use resolve::{Lookup, Resolve};
+
+mod normalize;
+pub use normalize::{normalize_uri, Normalization, Normalizations};
+
+mod redirect;
+pub use redirect::{Hop, RedirectPolicy, Rewrite};
+
+#[cfg(feature = "socks")]
+mod socks;
+#[cfg(feature = "socks")]
+pub use socks::{Socks5, SocksTarget};
 
 #[cfg(feature = "http_crate")]
 pub use res::StatusText;
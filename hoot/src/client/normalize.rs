@@ -0,0 +1,250 @@
+use crate::error::Result;
+use crate::util::flag_set;
+use crate::HootError;
+
+/// A change made by [`normalize_uri()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Normalization {
+    /// A `#fragment` was removed. Fragments are for the client, and never sent.
+    Fragment,
+    /// An empty path became `/`.
+    EmptyPath,
+    /// The scheme was lowercased.
+    SchemeCase,
+    /// The host was lowercased.
+    HostCase,
+    /// The port was removed, being the default of the scheme.
+    DefaultPort,
+}
+
+flag_set! {
+    /// Set of changes made by [`normalize_uri()`].
+    Normalizations(Normalization):
+        Fragment,
+        EmptyPath,
+        SchemeCase,
+        HostCase,
+        DefaultPort,
+}
+
+/// Normalize a request URI before sending it, into `buf`.
+///
+/// The URI is either absolute, like `http://host/path`, or a path like `/path?query`.
+/// The fragment is removed, an empty path becomes `/`, and the scheme and host are
+/// lowercased. With `remove_default_port`, the port is removed when it is the default
+/// of the scheme, `80` for `http` and `443` for `https`. Everything else, such as
+/// percent-encoding, is left as it is.
+///
+/// Returns the normalized URI and the changes made, which are empty if the URI was
+/// already normal. Errors with [`HootError::OutputOverflow`] if `buf` is too small.
+///
+/// ```
+/// use hoot::client::{normalize_uri, Normalization};
+///
+/// let mut buf = [0; 256];
+/// let (uri, changes) = normalize_uri("HTTP://Example.TEST:80#top", true, &mut buf)?;
+///
+/// assert_eq!(uri, "http://example.test/");
+/// assert!(changes.contains(Normalization::Fragment));
+/// assert!(changes.contains(Normalization::DefaultPort));
+///
+/// let (uri, changes) = normalize_uri("/search?q=Hoot", true, &mut buf)?;
+/// assert_eq!(uri, "/search?q=Hoot");
+/// assert!(changes.is_empty());
+/// # Ok::<(), hoot::HootError>(())
+/// ```
+pub fn normalize_uri<'b>(
+    uri: &str,
+    remove_default_port: bool,
+    buf: &'b mut [u8],
+) -> Result<(&'b str, Normalizations)> {
+    let mut changes = Normalizations::default();
+    let mut w = Writer { buf, pos: 0 };
+
+    let uri = match uri.split_once('#') {
+        Some((uri, _)) => {
+            changes.insert(Normalization::Fragment);
+            uri
+        }
+        None => uri,
+    };
+
+    let rest = match split_scheme(uri) {
+        Some((scheme, rest)) => {
+            if scheme.bytes().any(|c| c.is_ascii_uppercase()) {
+                changes.insert(Normalization::SchemeCase);
+            }
+            w.lower(scheme)?;
+            w.push("://")?;
+
+            let end = rest.find(['/', '?']).unwrap_or(rest.len());
+            let (authority, rest) = rest.split_at(end);
+
+            let (userinfo, host_port) = match authority.rsplit_once('@') {
+                Some((userinfo, host_port)) => (Some(userinfo), host_port),
+                None => (None, authority),
+            };
+            if let Some(userinfo) = userinfo {
+                w.push(userinfo)?;
+                w.push("@")?;
+            }
+
+            let (host, port) = split_port(host_port);
+            if host.bytes().any(|c| c.is_ascii_uppercase()) {
+                changes.insert(Normalization::HostCase);
+            }
+            w.lower(host)?;
+
+            if let Some(port) = port {
+                let default = if scheme.eq_ignore_ascii_case("https") {
+                    "443"
+                } else if scheme.eq_ignore_ascii_case("http") {
+                    "80"
+                } else {
+                    ""
+                };
+
+                if remove_default_port && port == default {
+                    changes.insert(Normalization::DefaultPort);
+                } else {
+                    w.push(":")?;
+                    w.push(port)?;
+                }
+            }
+
+            rest
+        }
+        None => uri,
+    };
+
+    if !rest.starts_with('/') && rest != "*" {
+        changes.insert(Normalization::EmptyPath);
+        w.push("/")?;
+    }
+    w.push(rest)?;
+
+    if !changes.is_empty() {
+        trace!("Normalized URI: {:?}", changes);
+    }
+
+    let len = w.pos;
+    let uri = core::str::from_utf8(&w.buf[..len])?;
+
+    Ok((uri, changes))
+}
+
+/// The scheme and the rest of an absolute URI, `None` for a path.
+pub(crate) fn split_scheme(uri: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = uri.split_once("://")?;
+
+    // A scheme is letters, digits and a few symbols. Anything else is a path.
+    let is_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+
+    if is_scheme {
+        Some((scheme, rest))
+    } else {
+        None
+    }
+}
+
+/// The host, with brackets for IPv6, and the port if there is one.
+fn split_port(host_port: &str) -> (&str, Option<&str>) {
+    let after_host = match host_port.find(']') {
+        Some(i) => i + 1,
+        None => 0,
+    };
+
+    match host_port[after_host..].rfind(':') {
+        Some(i) => {
+            let i = after_host + i;
+            (&host_port[..i], Some(&host_port[i + 1..]))
+        }
+        None => (host_port, None),
+    }
+}
+
+struct Writer<'b> {
+    buf: &'b mut [u8],
+    pos: usize,
+}
+
+impl<'b> Writer<'b> {
+    fn push(&mut self, s: &str) -> Result<()> {
+        let to = self
+            .buf
+            .get_mut(self.pos..self.pos + s.len())
+            .ok_or(HootError::OutputOverflow)?;
+        to.copy_from_slice(s.as_bytes());
+        self.pos += s.len();
+        Ok(())
+    }
+
+    fn lower(&mut self, s: &str) -> Result<()> {
+        let start = self.pos;
+        self.push(s)?;
+        self.buf[start..self.pos].make_ascii_lowercase();
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    fn normalize(uri: &str, remove_default_port: bool) -> (String, Vec<Normalization>) {
+        let mut buf = [0; 256];
+        let (uri, changes) = normalize_uri(uri, remove_default_port, &mut buf).unwrap();
+        (uri.to_string(), changes.iter().collect())
+    }
+
+    #[test]
+    fn test_normalize_uri() {
+        use Normalization::*;
+
+        let cases: &[(&str, &str, &[Normalization])] = &[
+            ("http://a.test/p", "http://a.test/p", &[]),
+            ("/p?q#f", "/p?q", &[Fragment]),
+            ("", "/", &[EmptyPath]),
+            ("*", "*", &[]),
+            ("?q", "/?q", &[EmptyPath]),
+            ("http://a.test", "http://a.test/", &[EmptyPath]),
+            ("http://a.test?q", "http://a.test/?q", &[EmptyPath]),
+            (
+                "HTTPS://A.Test/P",
+                "https://a.test/P",
+                &[SchemeCase, HostCase],
+            ),
+            ("http://User@A.test/", "http://User@a.test/", &[HostCase]),
+            ("http://a.test:80/", "http://a.test/", &[DefaultPort]),
+            ("https://a.test:443/", "https://a.test/", &[DefaultPort]),
+            ("https://a.test:80/", "https://a.test:80/", &[]),
+            ("http://[::1]:80/", "http://[::1]/", &[DefaultPort]),
+            ("http://[::1]/", "http://[::1]/", &[]),
+            ("ws://a.test:80/", "ws://a.test:80/", &[]),
+            (
+                "/p?next=http://A.test#f",
+                "/p?next=http://A.test",
+                &[Fragment],
+            ),
+        ];
+
+        for (uri, expected, changes) in cases {
+            let (normal, found) = normalize(uri, true);
+            assert_eq!(&normal, expected, "{}", uri);
+            assert_eq!(&found, changes, "{}", uri);
+        }
+
+        // Default ports are only removed when asked.
+        let (normal, found) = normalize("http://a.test:80/", false);
+        assert_eq!(normal, "http://a.test:80/");
+        assert!(found.is_empty());
+
+        let mut buf = [0; 8];
+        let r = normalize_uri("http://a.test/", true, &mut buf);
+        assert_eq!(r.err(), Some(HootError::OutputOverflow));
+    }
+}
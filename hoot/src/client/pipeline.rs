@@ -0,0 +1,180 @@
+use crate::types::state::*;
+use crate::{HootError, Result};
+
+use super::{Response, ResumeToken};
+
+/// Requests sent back-to-back on one connection, awaiting responses in order.
+///
+/// HTTP/1.1 responses carry no reference to the request they answer. They come in the
+/// same order as the requests were sent, and the pipeline keeps track of that order.
+/// Holds at most `N` requests.
+///
+/// Each request is written as usual, and the finished request is pushed to the pipeline
+/// after its output is sent. The output of several requests can be written to the
+/// transport before reading any response. Responses are then taken from the pipeline one
+/// at a time. The next response must not be read before the previous is finished, since
+/// any input after the end of one response belongs to the next.
+///
+/// If a finished response is not [reusable][Response::is_reusable], the server will not
+/// answer the remaining requests. [`clear()`][Self::clear] them, and send them again on
+/// another connection.
+///
+/// Only requests with idempotent methods, such as `GET` and `HEAD`, should be pipelined.
+///
+/// ```
+/// use hoot::client::{Pipeline, Request};
+///
+/// let mut pipeline: Pipeline<4> = Pipeline::new();
+/// let mut buf = [0; 1024];
+///
+/// for path in ["/a", "/b"] {
+///     let output = Request::new(&mut buf)
+///         .http_11()
+///         .get("a.test", path)?
+///         .send()?
+///         .flush();
+///
+///     // Write output to transport here.
+///
+///     pipeline.push(output.ready())?;
+/// }
+///
+/// const INPUT: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\na\
+///     HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\nb";
+///
+/// let mut input = INPUT;
+/// let mut bodies = vec![];
+///
+/// while let Some(mut response) = pipeline.next_response() {
+///     let attempt = response.try_read_response(input, &mut buf)?;
+///     input = &input[attempt.input_used()..];
+///
+///     let mut response = response.proceed();
+///     let part = response.read_body(input, &mut buf)?;
+///     input = &input[part.input_used()..];
+///     bodies.push(part.to_vec());
+///
+///     let response = response.finish()?;
+///     assert!(response.is_reusable());
+/// }
+///
+/// assert_eq!(bodies, [b"a", b"b"]);
+/// # Ok::<(), hoot::HootError>(())
+/// ```
+pub struct Pipeline<const N: usize> {
+    queue: [Option<ResumeToken<ENDED, (), (), ()>>; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> Pipeline<N> {
+    /// An empty pipeline, with room for `N` requests in flight.
+    pub fn new() -> Self {
+        Pipeline {
+            queue: [(); N].map(|_| None),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Add a sent request to await a response for.
+    ///
+    /// Errors with [`HootError::PipelineFull`] if there are already `N` requests
+    /// awaiting responses.
+    pub fn push(&mut self, request: ResumeToken<ENDED, (), (), ()>) -> Result<()> {
+        if self.is_full() {
+            return Err(HootError::PipelineFull);
+        }
+
+        let idx = (self.head + self.len) % N;
+        self.queue[idx] = Some(request);
+        self.len += 1;
+
+        trace!("Pipeline push: {}", self.len);
+
+        Ok(())
+    }
+
+    /// The response to the oldest request, or `None` if no request awaits a response.
+    pub fn next_response(&mut self) -> Option<Response<RECV_RESPONSE>> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let request = self.queue[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+
+        trace!("Pipeline next response: {} left", self.len);
+
+        request.map(|r| r.into_response())
+    }
+
+    /// Drop all requests awaiting responses. Returns how many they were.
+    pub fn clear(&mut self) -> usize {
+        let len = self.len;
+
+        for slot in &mut self.queue {
+            *slot = None;
+        }
+        self.head = 0;
+        self.len = 0;
+
+        len
+    }
+
+    /// Number of requests awaiting responses.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+}
+
+impl<const N: usize> Default for Pipeline<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::client::Request;
+
+    fn sent(path: &str) -> Result<ResumeToken<ENDED, (), (), ()>> {
+        let mut buf = [0; 1024];
+        let output = Request::new(&mut buf)
+            .http_11()
+            .get("a.test", path)?
+            .send()?
+            .flush();
+        Ok(output.ready())
+    }
+
+    #[test]
+    fn test_pipeline_full_and_clear() -> Result<()> {
+        let mut p: Pipeline<2> = Pipeline::new();
+
+        p.push(sent("/a")?)?;
+        p.push(sent("/b")?)?;
+        assert!(p.is_full());
+        assert_eq!(p.push(sent("/c")?).err(), Some(HootError::PipelineFull));
+
+        // Wraps around after taking one.
+        assert!(p.next_response().is_some());
+        p.push(sent("/c")?)?;
+        assert_eq!(p.len(), 2);
+
+        assert_eq!(p.clear(), 2);
+        assert!(p.next_response().is_none());
+
+        Ok(())
+    }
+}
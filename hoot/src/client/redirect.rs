@@ -0,0 +1,394 @@
+use crate::error::Result;
+use crate::{HootError, Method};
+
+use super::normalize::split_scheme;
+use super::Resolve;
+
+/// Headers with credentials, dropped on redirects to another origin.
+const SENSITIVE: &[&str] = &["authorization", "cookie", "proxy-authorization"];
+
+/// Which request headers to send again when following a redirect.
+///
+/// Credentials meant for one origin must not leak to another. On a redirect to another
+/// origin, `Authorization`, `Cookie`, `Proxy-Authorization` and the headers [denied] are
+/// dropped, unless [allowed]. On a downgrade from `https` to `http`, they are dropped
+/// even when allowed, since they would be sent in the clear.
+///
+/// ```
+/// use hoot::client::RedirectPolicy;
+///
+/// let policy = RedirectPolicy::new().deny(&["x-api-key"]);
+///
+/// let hop = policy.hop("https://a.test/login", "https://b.test/home")?;
+/// assert!(hop.is_cross_origin());
+/// assert!(!hop.keep("Authorization"));
+/// assert!(!hop.keep("x-api-key"));
+/// assert!(hop.keep("accept"));
+///
+/// // A relative location is the same origin.
+/// let hop = policy.hop("https://a.test/login", "/home")?;
+/// assert!(hop.keep("Authorization"));
+/// # Ok::<(), hoot::HootError>(())
+/// ```
+///
+/// [denied]: Self::deny
+/// [allowed]: Self::allow
+#[derive(Debug, Clone, Copy)]
+pub struct RedirectPolicy<'a> {
+    deny: &'a [&'a str],
+    allow: &'a [&'a str],
+    post_to_get: bool,
+}
+
+impl<'a> Default for RedirectPolicy<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> RedirectPolicy<'a> {
+    pub fn new() -> Self {
+        RedirectPolicy {
+            deny: &[],
+            allow: &[],
+            post_to_get: true,
+        }
+    }
+
+    /// More headers to drop on redirects to another origin, such as `x-api-key`.
+    pub fn deny(mut self, headers: &'a [&'a str]) -> Self {
+        self.deny = headers;
+        self
+    }
+
+    /// Headers to keep on redirects to another origin, even if sensitive.
+    ///
+    /// For example `authorization`, when the redirects are known to stay within
+    /// trusted hosts. Still dropped on a downgrade to `http`.
+    pub fn allow(mut self, headers: &'a [&'a str]) -> Self {
+        self.allow = headers;
+        self
+    }
+
+    /// Whether `POST` becomes `GET` without body on `301` and `302`, `true` by default.
+    ///
+    /// This is what browsers do, and what most servers expect, though the status codes
+    /// don't ask for it. Without it, `POST` is sent again with its body.
+    pub fn post_to_get(mut self, enabled: bool) -> Self {
+        self.post_to_get = enabled;
+        self
+    }
+
+    /// The request to send after a redirect with `status`, for a request with `method`.
+    ///
+    /// `None` if the status isn't a redirect to follow. `303` is always followed with
+    /// `GET`, or `HEAD` for `HEAD`. `307` and `308` keep the method and body, for which
+    /// the body must be possible to send again.
+    ///
+    /// ```
+    /// use hoot::client::RedirectPolicy;
+    /// use hoot::Method;
+    ///
+    /// let policy = RedirectPolicy::new();
+    ///
+    /// let rewrite = policy.rewrite(303, Method::PUT).unwrap();
+    /// assert_eq!(rewrite.method(), Method::GET);
+    /// assert!(!rewrite.resend_body());
+    /// assert!(!rewrite.keep("content-type"));
+    ///
+    /// let rewrite = policy.rewrite(307, Method::POST).unwrap();
+    /// assert_eq!(rewrite.method(), Method::POST);
+    /// assert!(rewrite.resend_body());
+    ///
+    /// assert!(policy.rewrite(304, Method::GET).is_none());
+    /// ```
+    pub fn rewrite(&self, status: u16, method: Method) -> Option<Rewrite> {
+        let to_get = match status {
+            301 | 302 => self.post_to_get && method == Method::POST,
+            303 => method != Method::HEAD,
+            307 | 308 => false,
+            _ => return None,
+        };
+
+        let method = if to_get { Method::GET } else { method };
+
+        trace!("Redirect {} with {:?}", status, method);
+
+        Some(Rewrite {
+            method,
+            resend_body: method.has_request_body(),
+        })
+    }
+
+    /// A redirect from the absolute URL `from` to `location`, as in the `Location`
+    /// header, which can be relative.
+    ///
+    /// Errors with [`HootError::InvalidAuthority`] if `from` isn't an absolute URL.
+    pub fn hop(&self, from: &str, location: &str) -> Result<Hop<'a>> {
+        let from = Origin::parse(from)?.ok_or(HootError::InvalidAuthority)?;
+        let to = match Origin::parse(location)? {
+            Some(to) => to,
+            // Relative, or without scheme.
+            None => match location.strip_prefix("//") {
+                Some(rest) => Origin::with_scheme(from.scheme, rest)?,
+                None => from,
+            },
+        };
+
+        let downgrade =
+            from.scheme.eq_ignore_ascii_case("https") && to.scheme.eq_ignore_ascii_case("http");
+        let cross_origin = !from.same(&to);
+
+        if cross_origin || downgrade {
+            trace!(
+                "Redirect to other origin, cross: {} downgrade: {}",
+                cross_origin,
+                downgrade
+            );
+        }
+
+        Ok(Hop {
+            policy: *self,
+            cross_origin,
+            downgrade,
+        })
+    }
+}
+
+/// One redirect, to tell which request headers to send again. See [`RedirectPolicy`].
+#[derive(Debug, Clone, Copy)]
+pub struct Hop<'a> {
+    policy: RedirectPolicy<'a>,
+    cross_origin: bool,
+    downgrade: bool,
+}
+
+impl<'a> Hop<'a> {
+    /// Whether the redirect goes to another scheme, host or port.
+    pub fn is_cross_origin(&self) -> bool {
+        self.cross_origin
+    }
+
+    /// Whether the redirect goes from `https` to `http`.
+    pub fn is_downgrade(&self) -> bool {
+        self.downgrade
+    }
+
+    /// Whether to send the header `name` again after the redirect.
+    pub fn keep(&self, name: &str) -> bool {
+        let any = |names: &[&str]| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+
+        let sensitive = any(SENSITIVE) || any(self.policy.deny);
+
+        if !sensitive {
+            true
+        } else if self.downgrade {
+            false
+        } else {
+            !self.cross_origin || any(self.policy.allow)
+        }
+    }
+}
+
+/// The method and body of the request after a redirect. See
+/// [`RedirectPolicy::rewrite()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rewrite {
+    method: Method,
+    resend_body: bool,
+}
+
+impl Rewrite {
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Whether to send the body of the request again, in which case it must be replayable.
+    pub fn resend_body(&self) -> bool {
+        self.resend_body
+    }
+
+    /// Whether to send the header `name` again. Headers describing the body are dropped
+    /// along with it.
+    pub fn keep(&self, name: &str) -> bool {
+        if self.resend_body {
+            return true;
+        }
+
+        let content = name.len() > 8 && name.as_bytes()[..8].eq_ignore_ascii_case(b"content-");
+        !content && !name.eq_ignore_ascii_case("transfer-encoding")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Origin<'a> {
+    scheme: &'a str,
+    host: &'a str,
+    port: u16,
+}
+
+impl<'a> Origin<'a> {
+    /// The origin of an absolute URL, `None` for anything else.
+    fn parse(url: &'a str) -> Result<Option<Self>> {
+        match split_scheme(url) {
+            Some((scheme, rest)) => Origin::with_scheme(scheme, rest).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The origin of `rest`, which starts with the authority.
+    fn with_scheme(scheme: &'a str, rest: &'a str) -> Result<Self> {
+        let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        let authority = &rest[..end];
+        // Drop any userinfo.
+        let authority = authority.rsplit_once('@').map_or(authority, |(_, a)| a);
+
+        let default_port = if scheme.eq_ignore_ascii_case("https") {
+            443
+        } else {
+            80
+        };
+        let resolve = Resolve::new(authority, default_port)?;
+
+        Ok(Origin {
+            scheme,
+            host: resolve.host(),
+            port: resolve.port(),
+        })
+    }
+
+    fn same(&self, other: &Origin) -> bool {
+        self.scheme.eq_ignore_ascii_case(other.scheme)
+            && self.host.eq_ignore_ascii_case(other.host)
+            && self.port == other.port
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_redirect_same_origin() -> Result<()> {
+        let policy = RedirectPolicy::new();
+
+        for to in [
+            "/other",
+            "other?a=b",
+            "//a.test/other",
+            "https://A.test:443/other",
+            "https://user@a.test/other",
+        ] {
+            let hop = policy.hop("https://a.test/path", to)?;
+            assert!(!hop.is_cross_origin(), "{}", to);
+            assert!(hop.keep("authorization"));
+            assert!(hop.keep("cookie"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_redirect_cross_origin() -> Result<()> {
+        let policy = RedirectPolicy::new()
+            .deny(&["X-Api-Key"])
+            .allow(&["authorization"]);
+
+        for to in [
+            "https://b.test/",
+            "https://a.test:8443/",
+            "//b.test",
+            "https://a.test@b.test/",
+        ] {
+            let hop = policy.hop("https://a.test/path", to)?;
+            assert!(hop.is_cross_origin(), "{}", to);
+            assert!(!hop.is_downgrade());
+            assert!(!hop.keep("Cookie"));
+            assert!(!hop.keep("proxy-authorization"));
+            assert!(!hop.keep("x-api-key"));
+            // Allowed.
+            assert!(hop.keep("Authorization"));
+            assert!(hop.keep("accept"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_redirect_rewrite() {
+        use Method::*;
+
+        let policy = RedirectPolicy::new();
+        let legacy = RedirectPolicy::new().post_to_get(false);
+
+        let rewrite = |policy: &RedirectPolicy, status, method| {
+            let r = policy.rewrite(status, method).unwrap();
+            (r.method(), r.resend_body())
+        };
+
+        for status in [301, 302] {
+            assert_eq!(rewrite(&policy, status, POST), (GET, false));
+            assert_eq!(rewrite(&legacy, status, POST), (POST, true));
+            assert_eq!(rewrite(&policy, status, PUT), (PUT, true));
+            assert_eq!(rewrite(&policy, status, HEAD), (HEAD, false));
+        }
+
+        for method in [GET, POST, PUT, PATCH, DELETE] {
+            assert_eq!(rewrite(&legacy, 303, method), (GET, false));
+        }
+        assert_eq!(rewrite(&policy, 303, HEAD), (HEAD, false));
+
+        for status in [307, 308] {
+            for method in [POST, PUT, PATCH] {
+                assert_eq!(rewrite(&policy, status, method), (method, true));
+            }
+            assert_eq!(rewrite(&policy, status, DELETE), (DELETE, false));
+        }
+
+        for status in [200, 300, 304, 305, 306, 309] {
+            assert!(policy.rewrite(status, GET).is_none());
+        }
+
+        let r = policy.rewrite(303, POST).unwrap();
+        assert!(!r.keep("Content-Length"));
+        assert!(!r.keep("content-type"));
+        assert!(!r.keep("Transfer-Encoding"));
+        assert!(r.keep("accept"));
+        assert!(r.keep("content"));
+
+        let r = policy.rewrite(307, POST).unwrap();
+        assert!(r.keep("content-type"));
+    }
+
+    #[test]
+    fn test_redirect_downgrade() -> Result<()> {
+        let policy = RedirectPolicy::new().allow(&["authorization", "cookie"]);
+
+        // Same host, but in the clear.
+        let hop = policy.hop("https://a.test/", "http://a.test/")?;
+        assert!(hop.is_downgrade());
+        assert!(hop.is_cross_origin());
+        assert!(!hop.keep("authorization"));
+        assert!(!hop.keep("cookie"));
+        assert!(hop.keep("accept"));
+
+        let hop = policy.hop("HTTPS://a.test/", "http://b.test/")?;
+        assert!(hop.is_downgrade());
+        assert!(!hop.keep("authorization"));
+
+        // Upgrades are fine, with the headers allowed.
+        let hop = policy.hop("http://a.test/", "https://a.test/")?;
+        assert!(!hop.is_downgrade());
+        assert!(hop.keep("authorization"));
+        assert!(!RedirectPolicy::new()
+            .hop("http://a.test/", "https://a.test/")?
+            .keep("cookie"));
+
+        assert_eq!(
+            policy.hop("/relative", "/other").err(),
+            Some(HootError::InvalidAuthority)
+        );
+
+        Ok(())
+    }
+}
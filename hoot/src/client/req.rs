@@ -1,18 +1,23 @@
 use core::fmt::Write;
 use core::marker::PhantomData;
 use core::ops::Deref;
+use core::str;
 
+use crate::ascii::eq_ignore_ascii;
 use crate::error::OVERFLOW;
-use crate::header::check_and_output_header;
-use crate::out::{Out, Writer};
+use crate::header::{check_and_output_header, check_and_output_header_streamed};
+use crate::header::{check_trailer_name, connection_has, output_header_continue};
+use crate::header::{write_generated, write_name};
+use crate::out::{header_line_len, Out, Writer};
+use crate::out::{END_HEADERS_LEN, MAX_BODY_HEADER_LEN, MIN_CHUNK_LEN};
 use crate::types::body::*;
 use crate::types::method::*;
 use crate::types::state::*;
 use crate::types::version::*;
-use crate::util::LengthChecker;
+use crate::util::{is_authority_form, LengthChecker};
 use crate::Method as M;
 use crate::{types::*, BodyWriter};
-use crate::{CallState, HttpVersion};
+use crate::{ByteCount, CallState, HeaderCasing, HttpVersion};
 use crate::{HootError, Result};
 
 use super::Response;
@@ -56,7 +61,7 @@ impl<'a> Request<'a, (), (), (), ()> {
 
 impl<'a, S: State, V: Version, M: Method, B: BodyType> Request<'a, S, V, M, B> {
     fn transition<S2: State, V2: Version, M2: Method, B2: BodyType>(
-        self,
+        mut self,
     ) -> Request<'a, S2, V2, M2, B2> {
         trace!(
             "Transition: {}/{}/{} -> {}/{}/{}",
@@ -68,6 +73,10 @@ impl<'a, S: State, V: Version, M: Method, B: BodyType> Request<'a, S, V, M, B> {
             B2::state_name(),
         );
 
+        if S::is_sending_head() && !S2::is_sending_head() {
+            self.state.mark_head_sent(self.out.as_bytes().len());
+        }
+
         Request {
             typ: Typ(PhantomData, PhantomData, PhantomData, PhantomData),
             state: self.state,
@@ -76,18 +85,66 @@ impl<'a, S: State, V: Version, M: Method, B: BodyType> Request<'a, S, V, M, B> {
     }
 
     fn header_raw(mut self, name: &str, bytes: &[u8], trailer: bool) -> Result<Self> {
+        self.state.check_header_done()?;
+        let casing = self.state.header_casing;
         // Attempt writing the header
         let w = self.out.writer();
-        check_and_output_header(w, V::version(), name, bytes, trailer)?;
+        check_and_output_header(w, V::version(), name, bytes, trailer, casing)?;
+
+        // Remember what we said about the connection, to know whether it can be reused.
+        if !trailer && eq_ignore_ascii(name, "connection") {
+            let value = str::from_utf8(bytes).unwrap_or("");
+            self.state.conn_close |= connection_has(value, "close");
+            self.state.conn_keep_alive |= connection_has(value, "keep-alive");
+        }
+
         Ok(self)
     }
 
+    fn header_streamed_raw(
+        mut self,
+        name: &str,
+        bytes: &[u8],
+        trailer: bool,
+    ) -> Result<(Self, usize)> {
+        self.state.check_header_done()?;
+        let ver = V::version();
+        let casing = self.state.header_casing;
+        let w = self.out.writer();
+        let (amount, left) =
+            check_and_output_header_streamed(w, ver, name, bytes, trailer, casing)?;
+        self.state.header_left = left;
+        Ok((self, amount))
+    }
+
+    fn header_continue_raw(mut self, bytes: &[u8]) -> Result<(Self, usize)> {
+        let left = match self.state.header_left {
+            Some(v) => v,
+            None => return Err(HootError::StreamedHeader),
+        };
+        let w = self.out.writer();
+        let (amount, left) = output_header_continue(w, bytes, left)?;
+        self.state.header_left = left;
+        Ok((self, amount))
+    }
+
+    /// Value bytes left to write for a streamed header. `None` if no streamed header is in progress.
+    pub fn header_left(&self) -> Option<usize> {
+        self.state.header_left
+    }
+
     pub fn capacity(&self) -> usize {
         self.out.capacity()
     }
 
-    pub fn flush(self) -> Output<'a, S, V, M, B> {
+    /// Bytes sent so far, including output not yet flushed.
+    pub fn bytes_sent(&self) -> ByteCount {
+        self.state.bytes_sent(self.out.as_bytes().len())
+    }
+
+    pub fn flush(mut self) -> Output<'a, S, V, M, B> {
         trace!("Flush");
+        self.state.sent_total += self.out.as_bytes().len() as u64;
         Output {
             token: ResumeToken {
                 typ: self.typ,
@@ -109,11 +166,70 @@ impl<'a, S: State, V: Version, M: Method, B: BodyType> Request<'a, S, V, M, B> {
             state: token.state,
             out: Out::wrap(buf),
         }
+        .with_limit()
+    }
+
+    fn with_limit(mut self) -> Self {
+        self.out.set_limit(self.state.max_output);
+        self
+    }
+
+    /// Never use more than `max` bytes of the output buffer.
+    ///
+    /// This caps the size of each flushed output, for transports that require a
+    /// maximum frame size. The limit is kept when resuming with another buffer.
+    pub fn max_output(mut self, max: usize) -> Self {
+        trace!("Max output: {}", max);
+        self.state.max_output = Some(max);
+        self.with_limit()
+    }
+
+    /// Split chunked body data so that chunk boundaries fall on multiples of `size`
+    /// in the body.
+    ///
+    /// Panics if `size` is 0.
+    pub fn align_chunks(mut self, size: usize) -> Self {
+        assert!(size > 0, "chunk alignment must be greater than 0");
+        trace!("Align chunks: {}", size);
+        self.state.chunked = self.state.chunked.align_chunks(size);
+        self
+    }
+
+    /// Never write chunks with more than `max` bytes of body data.
+    ///
+    /// Panics if `max` is 0.
+    pub fn max_chunk_size(mut self, max: usize) -> Self {
+        assert!(max > 0, "max chunk size must be greater than 0");
+        trace!("Max chunk size: {}", max);
+        self.state.chunked = self.state.chunked.max_chunk_size(max);
+        self
+    }
+
+    /// Case header names when writing them, for peers that are picky about casing.
+    ///
+    /// Applies to headers and trailers written after this, including the ones hoot adds,
+    /// such as `Host`, `Content-Length` and `Transfer-Encoding`.
+    pub fn header_casing(mut self, casing: HeaderCasing) -> Self {
+        trace!("Header casing: {:?}", casing);
+        self.state.header_casing = casing;
+        self
+    }
+
+    /// Hand the output to `output`, and start over with an empty buffer.
+    fn emit<E>(
+        &mut self,
+        output: &mut impl FnMut(&[u8]) -> core::result::Result<(), E>,
+    ) -> core::result::Result<(), E> {
+        output(self.out.as_bytes())?;
+        self.state.sent_total += self.out.as_bytes().len() as u64;
+        self.out.reset_position();
+        Ok(())
     }
 
     #[cfg(feature = "std")]
     pub fn write_to(mut self, write: &mut dyn std::io::Write) -> std::io::Result<Self> {
         write.write_all(self.out.as_bytes())?;
+        self.state.sent_total += self.out.as_bytes().len() as u64;
         self.out.reset_position();
         Ok(self)
     }
@@ -141,6 +257,43 @@ impl<'a, S: State, V: Version, M: Method, B: BodyType> Deref for Output<'a, S, V
     }
 }
 
+/// Output where the body is kept apart from the buffer.
+///
+/// Write [`as_bytes()`][Self::as_bytes] followed by [`body()`][Self::body] to the transport.
+pub struct VectoredOutput<'a, 'b, S: State, V: Version, M: Method, B: BodyType> {
+    output: Output<'a, S, V, M, B>,
+    body: &'b [u8],
+}
+
+impl<'a, 'b, S: State, V: Version, M: Method, B: BodyType> VectoredOutput<'a, 'b, S, V, M, B> {
+    pub fn ready(self) -> ResumeToken<S, V, M, B> {
+        self.output.ready()
+    }
+
+    pub fn ready_and_buf(self) -> (ResumeToken<S, V, M, B>, &'a mut [u8]) {
+        self.output.ready_and_buf()
+    }
+
+    /// The buffered output, which goes before the body.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.output.as_bytes()
+    }
+
+    /// The body data, which goes after the buffered output.
+    pub fn body(&self) -> &'b [u8] {
+        self.body
+    }
+
+    /// Both parts as slices for [`std::io::Write::write_vectored()`].
+    #[cfg(feature = "std")]
+    pub fn io_slices(&self) -> [std::io::IoSlice<'_>; 2] {
+        [
+            std::io::IoSlice::new(self.as_bytes()),
+            std::io::IoSlice::new(self.body),
+        ]
+    }
+}
+
 impl<S: State, V: Version, M: Method, B: BodyType> ResumeToken<S, V, M, B> {
     pub(crate) fn into_state(self) -> CallState {
         self.state
@@ -159,6 +312,39 @@ impl<'a> Request<'a, INIT, (), (), ()> {
         self.state.version = Some(HttpVersion::Http11);
         self.transition()
     }
+
+    /// Select the HTTP version at runtime, such as from `http::Request::version()`.
+    ///
+    /// The version decides what is possible from here. HTTP/1.0 has no `Host`
+    /// requirement or chunked bodies, and a response without `Content-Length` is
+    /// delimited by closing the connection.
+    ///
+    /// ```
+    /// use hoot::client::{Request, VersionedRequest};
+    /// use hoot::HttpVersion;
+    ///
+    /// let mut buf = [0; 1024];
+    ///
+    /// let output = match Request::new(&mut buf).http_version(HttpVersion::Http10) {
+    ///     VersionedRequest::Http10(r) => r.get("/")?.send()?.flush(),
+    ///     VersionedRequest::Http11(r) => r.get("device.test", "/")?.send()?.flush(),
+    /// };
+    ///
+    /// assert_eq!(&*output, b"GET / HTTP/1.0\r\n\r\n");
+    /// # Ok::<(), hoot::HootError>(())
+    /// ```
+    pub fn http_version(self, version: HttpVersion) -> VersionedRequest<'a> {
+        match version {
+            HttpVersion::Http10 => VersionedRequest::Http10(self.http_10()),
+            HttpVersion::Http11 => VersionedRequest::Http11(self.http_11()),
+        }
+    }
+}
+
+/// A request with the HTTP version selected by [`Request::http_version()`].
+pub enum VersionedRequest<'a> {
+    Http10(Request<'a, SEND_LINE, HTTP_10, (), ()>),
+    Http11(Request<'a, SEND_LINE, HTTP_11, (), ()>),
 }
 
 macro_rules! write_line_10 {
@@ -188,16 +374,28 @@ macro_rules! write_line_11 {
             host: &str,
             path: &str,
         ) -> Result<Request<'a, SEND_HEADERS, HTTP_11, $meth_up, ()>> {
-            write_line_11(self.out.writer(), stringify!($meth_up), host, path)?;
+            let host = self.host_value(host);
+            let casing = self.state.header_casing;
+            let w = self.out.writer();
+            write_line_11(w, stringify!($meth_up), host, path, casing)?;
             self.state.method = Some(M::$meth_up);
             Ok(self.transition())
         }
     };
 }
 
-fn write_line_11(mut w: Writer<'_, '_>, method: &str, host: &str, path: &str) -> Result<()> {
+fn write_line_11(
+    mut w: Writer<'_, '_>,
+    method: &str,
+    host: Option<&str>,
+    path: &str,
+    casing: HeaderCasing,
+) -> Result<()> {
     trace!("{} {} HTTP/1.1", method, path);
-    write!(w, "{} {} HTTP/1.1\r\nHost: {}\r\n", method, path, host).or(OVERFLOW)?;
+    write!(w, "{} {} HTTP/1.1\r\n", method, path).or(OVERFLOW)?;
+    if let Some(host) = host {
+        write_generated(&mut w, "Host", format_args!("{}", host), casing)?;
+    }
     w.commit();
     Ok(())
 }
@@ -208,19 +406,116 @@ impl<'a> Request<'a, SEND_LINE, HTTP_10, (), ()> {
     write_line_10!(post, POST);
 }
 
+impl<'a> Request<'a, SEND_LINE, HTTP_11, (), ()> {
+    /// Leave out the port from the `Host` header when it is `port`.
+    ///
+    /// The `Host` header is the `host` given when choosing the method, as is. Setting
+    /// this to the default port of the scheme, such as 443 for https, normalizes
+    /// `a.test:443` to `a.test`. This matters when the exact `Host` value is signed,
+    /// such as for AWS SigV4. It doesn't apply to `CONNECT`, which always has a port.
+    pub fn default_port(mut self, port: u16) -> Self {
+        trace!("Default port: {}", port);
+        self.state.default_port = Some(port);
+        self
+    }
+
+    /// Don't send the `Host` header.
+    ///
+    /// HTTP/1.1 requires the `Host` header, and servers answer requests without it
+    /// with 400. This is for testing such servers.
+    pub fn without_host(mut self) -> Self {
+        trace!("Without host");
+        self.state.omit_host = true;
+        self
+    }
+
+    fn host_value<'h>(&self, host: &'h str) -> Option<&'h str> {
+        if self.state.omit_host {
+            return None;
+        }
+
+        let port = match self.state.default_port {
+            Some(v) => v,
+            None => return Some(host),
+        };
+
+        let stripped = match host.rsplit_once(':') {
+            // A colon before the port must be in a bracketed IPv6 address.
+            Some((h, p)) if !h.is_empty() && (!h.contains(':') || h.ends_with(']')) => {
+                if p.parse::<u16>() == Ok(port) {
+                    h
+                } else {
+                    host
+                }
+            }
+            _ => host,
+        };
+
+        Some(stripped)
+    }
+}
+
 impl<'a> Request<'a, SEND_LINE, HTTP_11, (), ()> {
     write_line_11!(get, GET);
     write_line_11!(head, HEAD);
     write_line_11!(post, POST);
     write_line_11!(put, PUT);
     write_line_11!(delete, DELETE);
-    write_line_11!(connect, CONNECT);
     write_line_11!(options, OPTIONS);
     write_line_11!(trace, TRACE);
     write_line_11!(patch, PATCH);
 }
 
+impl<'a> Request<'a, SEND_LINE, HTTP_11, (), ()> {
+    /// Ask for a tunnel to `authority`, which is `host:port`.
+    ///
+    /// The request target of `CONNECT` is the authority, and not a path. A successful
+    /// response turns the connection into a tunnel, see
+    /// [`Response::into_tunnel()`][crate::client::Response::into_tunnel].
+    pub fn connect(
+        mut self,
+        authority: &str,
+    ) -> Result<Request<'a, SEND_HEADERS, HTTP_11, CONNECT, ()>> {
+        if !is_authority_form(authority) {
+            return Err(HootError::ConnectTarget);
+        }
+
+        trace!("CONNECT {} HTTP/1.1", authority);
+
+        let casing = self.state.header_casing;
+        let mut w = self.out.writer();
+        write!(w, "CONNECT {} HTTP/1.1\r\n", authority).or(OVERFLOW)?;
+        write_generated(&mut w, "Host", format_args!("{}", authority), casing)?;
+        w.commit();
+
+        self.state.method = Some(M::CONNECT);
+        Ok(self.transition())
+    }
+}
+
 impl<'a, M: Method, V: Version> Request<'a, SEND_HEADERS, V, M, ()> {
+    /// The smallest output buffer size that guarantees forward progress in this state.
+    ///
+    /// For headers this is the size needed to end the headers, including the
+    /// body framing header. Compare with the buffer size (or [`max_output()`][Self::max_output])
+    /// to detect configurations that would never progress.
+    pub fn min_output_required(&self) -> usize {
+        // method is always set in SEND_HEADERS
+        let has_body = self.state.method.unwrap().has_request_body();
+
+        if has_body {
+            MAX_BODY_HEADER_LEN
+        } else {
+            END_HEADERS_LEN
+        }
+    }
+
+    /// Like [`min_output_required()`][Self::min_output_required], but also considering
+    /// a pending header to be written.
+    pub fn min_output_required_for_header(&self, name: &str, value: &[u8]) -> usize {
+        header_line_len(name, value).max(self.min_output_required())
+    }
+
     pub fn header(self, name: &str, value: &str) -> Result<Self> {
         trace!("Set header {}: {}", name, value);
         self.header_raw(name, value.as_bytes(), false)
@@ -230,9 +525,45 @@ impl<'a, M: Method, V: Version> Request<'a, SEND_HEADERS, V, M, ()> {
         trace!("Set header bytes {}: {:?}", name, bytes);
         self.header_raw(name, bytes, false)
     }
+
+    /// Write a header whose value might not fit in the output buffer.
+    ///
+    /// The header is validated in full before anything is written. The name must fit
+    /// the output, while the value is written as far as there is room. Returns the number
+    /// of value bytes written. If not all of the value was written, `flush()` the output and
+    /// continue the header with [`header_continue()`][Self::header_continue] using the rest.
+    pub fn header_streamed(self, name: &str, bytes: &[u8]) -> Result<(Self, usize)> {
+        trace!("Set header streamed {}: {} bytes", name, bytes.len());
+        self.header_streamed_raw(name, bytes, false)
+    }
+
+    /// Continue a header started with [`header_streamed()`][Self::header_streamed].
+    ///
+    /// `bytes` must be the remaining part of the value not yet written. Returns the number
+    /// of value bytes written. Repeat until [`header_left()`][Self::header_left] is `None`.
+    pub fn header_continue(self, bytes: &[u8]) -> Result<(Self, usize)> {
+        self.header_continue_raw(bytes)
+    }
 }
 
 impl<'a, M: MethodWithRequestBody> Request<'a, SEND_HEADERS, HTTP_10, M, ()> {
+    /// Send a `Content-Length: 0` body.
+    ///
+    /// The request ends right after the headers, without passing through
+    /// a body state that only allows `finish()`.
+    pub fn with_empty_body(mut self) -> Result<Request<'a, ENDED, (), (), ()>> {
+        trace!("Empty body");
+        self.state.check_header_done()?;
+
+        let casing = self.state.header_casing;
+        let mut w = self.out.writer();
+        write_generated(&mut w, "Content-Length", format_args!("0"), casing)?;
+        w.write_bytes(b"\r\n")?;
+        w.commit();
+
+        Ok(self.transition())
+    }
+
     pub fn with_body(
         mut self,
         length: impl TryInto<u64>,
@@ -240,9 +571,12 @@ impl<'a, M: MethodWithRequestBody> Request<'a, SEND_HEADERS, HTTP_10, M, ()> {
         let length: u64 = length.try_into().map_err(|_| HootError::BodyNotFinished)?;
 
         trace!("Length delimited body: {}", length);
+        self.state.check_header_done()?;
 
+        let casing = self.state.header_casing;
         let mut w = self.out.writer();
-        write!(w, "Content-Length: {}\r\n\r\n", length).or(OVERFLOW)?;
+        write_generated(&mut w, "Content-Length", format_args!("{}", length), casing)?;
+        w.write_bytes(b"\r\n")?;
         w.commit();
 
         self.state.send_checker = Some(LengthChecker::new(length));
@@ -252,6 +586,7 @@ impl<'a, M: MethodWithRequestBody> Request<'a, SEND_HEADERS, HTTP_10, M, ()> {
 
     pub fn without_body(mut self) -> Result<Request<'a, RECV_RESPONSE, HTTP_11, M, ()>> {
         trace!("Without body");
+        self.state.check_header_done()?;
 
         let mut w = self.out.writer();
         write!(w, "\r\n").or(OVERFLOW)?;
@@ -262,6 +597,23 @@ impl<'a, M: MethodWithRequestBody> Request<'a, SEND_HEADERS, HTTP_10, M, ()> {
 }
 
 impl<'a, M: MethodWithRequestBody> Request<'a, SEND_HEADERS, HTTP_11, M, ()> {
+    /// Send a `Content-Length: 0` body.
+    ///
+    /// The request ends right after the headers, without passing through
+    /// a body state that only allows `finish()`.
+    pub fn with_empty_body(mut self) -> Result<Request<'a, ENDED, (), (), ()>> {
+        trace!("Empty body");
+        self.state.check_header_done()?;
+
+        let casing = self.state.header_casing;
+        let mut w = self.out.writer();
+        write_generated(&mut w, "Content-Length", format_args!("0"), casing)?;
+        w.write_bytes(b"\r\n")?;
+        w.commit();
+
+        Ok(self.transition())
+    }
+
     pub fn with_body(
         mut self,
         length: impl TryInto<u64>,
@@ -269,9 +621,12 @@ impl<'a, M: MethodWithRequestBody> Request<'a, SEND_HEADERS, HTTP_11, M, ()> {
         let length: u64 = length.try_into().map_err(|_| HootError::BodyNotFinished)?;
 
         trace!("Length delimited body: {}", length);
+        self.state.check_header_done()?;
 
+        let casing = self.state.header_casing;
         let mut w = self.out.writer();
-        write!(w, "Content-Length: {}\r\n\r\n", length).or(OVERFLOW)?;
+        write_generated(&mut w, "Content-Length", format_args!("{}", length), casing)?;
+        w.write_bytes(b"\r\n")?;
         w.commit();
 
         self.state.send_checker = Some(LengthChecker::new(length));
@@ -281,16 +636,83 @@ impl<'a, M: MethodWithRequestBody> Request<'a, SEND_HEADERS, HTTP_11, M, ()> {
 
     pub fn with_chunked(mut self) -> Result<Request<'a, SEND_BODY, HTTP_11, M, BODY_CHUNKED>> {
         trace!("Chunked body");
+        self.state.check_header_done()?;
+
+        let casing = self.state.header_casing;
+        let mut w = self.out.writer();
+        write_generated(&mut w, "Transfer-Encoding", format_args!("chunked"), casing)?;
+        w.write_bytes(b"\r\n")?;
+        w.commit();
+
+        Ok(self.transition())
+    }
+
+    /// Chunked body followed by the declared trailers.
+    ///
+    /// This writes a `Trailer` header with the names, which tells the receiver what
+    /// trailers to expect. The names are checked against the trailers that are not allowed.
+    /// The trailers are written after the body using `with_trailer()`.
+    pub fn with_chunked_trailers(
+        mut self,
+        names: &[&str],
+    ) -> Result<Request<'a, SEND_BODY, HTTP_11, M, BODY_CHUNKED>> {
+        trace!("Chunked body with trailers: {:?}", names);
+        self.state.check_header_done()?;
+
+        for name in names {
+            check_trailer_name(name)?;
+        }
 
+        let casing = self.state.header_casing;
         let mut w = self.out.writer();
-        write!(w, "Transfer-Encoding: chunked\r\n\r\n").or(OVERFLOW)?;
+
+        if !names.is_empty() {
+            write_name(&mut w, "Trailer", casing)?;
+            w.write_bytes(b": ")?;
+            for (i, name) in names.iter().enumerate() {
+                if i > 0 {
+                    w.write_bytes(b", ")?;
+                }
+                write_name(&mut w, name, casing)?;
+            }
+            w.write_bytes(b"\r\n")?;
+        }
+
+        write_generated(&mut w, "Transfer-Encoding", format_args!("chunked"), casing)?;
+        w.write_bytes(b"\r\n")?;
         w.commit();
 
         Ok(self.transition())
     }
 
+    /// Chunked body where the total length is known in advance.
+    ///
+    /// The body is sent chunked, but the amount written is checked against `length`
+    /// like with [`with_body()`][Self::with_body]. This is useful when the length is
+    /// known, but chunked framing is wanted anyway, such as to send trailers.
+    pub fn with_chunked_length(
+        mut self,
+        length: impl TryInto<u64>,
+    ) -> Result<Request<'a, SEND_BODY, HTTP_11, M, BODY_CHUNKED>> {
+        let length: u64 = length.try_into().map_err(|_| HootError::NotU64)?;
+
+        trace!("Chunked body with length: {}", length);
+        self.state.check_header_done()?;
+
+        let casing = self.state.header_casing;
+        let mut w = self.out.writer();
+        write_generated(&mut w, "Transfer-Encoding", format_args!("chunked"), casing)?;
+        w.write_bytes(b"\r\n")?;
+        w.commit();
+
+        self.state.send_checker = Some(LengthChecker::new(length));
+
+        Ok(self.transition())
+    }
+
     pub fn without_body(mut self) -> Result<Request<'a, RECV_RESPONSE, HTTP_11, M, ()>> {
         trace!("Without body");
+        self.state.check_header_done()?;
 
         let mut w = self.out.writer();
         write!(w, "\r\n").or(OVERFLOW)?;
@@ -303,6 +725,8 @@ impl<'a, M: MethodWithRequestBody> Request<'a, SEND_HEADERS, HTTP_11, M, ()> {
 impl<'a, V: Version, M: MethodWithoutRequestBody> Request<'a, SEND_HEADERS, V, M, ()> {
     // TODO: Can we find a trait bound that allows us to call this without_body()?
     pub fn send(mut self) -> Result<Request<'a, ENDED, (), (), ()>> {
+        self.state.check_header_done()?;
+
         let mut w = self.out.writer();
         write!(w, "\r\n").or(OVERFLOW)?;
         w.commit();
@@ -311,6 +735,54 @@ impl<'a, V: Version, M: MethodWithoutRequestBody> Request<'a, SEND_HEADERS, V, M
     }
 }
 
+macro_rules! write_body_iter {
+    () => {
+        /// Write a body held as an iterator of chunks, and finish the request.
+        ///
+        /// Saves looping over `write_bytes()` and `flush()`. The output is handed to
+        /// `output` every time the buffer is full, and a last time when the request is
+        /// finished. Chunks larger than the buffer are split.
+        ///
+        /// Errors from `output` are passed on, which is why the error type `E` only needs
+        /// to convert from [`HootError`].
+        pub fn write_body_iter<'b, E: From<HootError>>(
+            mut self,
+            body: impl IntoIterator<Item = &'b [u8]>,
+            mut output: impl FnMut(&[u8]) -> core::result::Result<(), E>,
+        ) -> core::result::Result<ResumeToken<ENDED, (), (), ()>, E> {
+            for mut rest in body {
+                while !rest.is_empty() {
+                    let fit = self.body_fit().min(rest.len());
+
+                    if fit == 0 {
+                        // Not even an empty buffer fits any body.
+                        if self.out.as_bytes().is_empty() {
+                            return Err(HootError::OutputOverflow.into());
+                        }
+                        self.emit(&mut output)?;
+                        continue;
+                    }
+
+                    let (now, next) = rest.split_at(fit);
+                    self = self.write_bytes(now)?;
+                    rest = next;
+                }
+            }
+
+            if self.out.capacity() < Self::FINISH_LEN {
+                self.emit(&mut output)?;
+            }
+
+            let out = self.finish()?.flush();
+            if !out.is_empty() {
+                output(&out)?;
+            }
+
+            Ok(out.ready())
+        }
+    };
+}
+
 impl<'a, V: Version, M: MethodWithRequestBody> Request<'a, SEND_BODY, V, M, BODY_LENGTH> {
     #[inline(always)]
     fn checker(&mut self) -> &mut LengthChecker {
@@ -321,6 +793,15 @@ impl<'a, V: Version, M: MethodWithRequestBody> Request<'a, SEND_BODY, V, M, BODY
             .expect("SendByteCheck when SEND_BODY")
     }
 
+    /// The smallest output buffer size that guarantees forward progress in this state.
+    ///
+    /// This is 1 byte while there is body left to write.
+    pub fn min_output_required(&self) -> usize {
+        let checker = self.state.send_checker.as_ref();
+        let left = checker.map(|c| c.left_to_read()).unwrap_or(0);
+        left.min(1)
+    }
+
     pub fn finish(mut self) -> Result<Request<'a, ENDED, (), (), ()>> {
         trace!("Body finished");
 
@@ -330,6 +811,38 @@ impl<'a, V: Version, M: MethodWithRequestBody> Request<'a, SEND_BODY, V, M, BODY
 
         Ok(self.transition())
     }
+
+    /// Flush the output, with the body data kept apart instead of copied to the buffer.
+    ///
+    /// This is for vectored writes, such as `writev`, where the buffered output and the
+    /// body are written to the transport without copying the body. The body counts as
+    /// sent, and like other body writes, it must not exceed the content-length.
+    pub fn write_vectored<'b>(
+        mut self,
+        body: &'b [u8],
+    ) -> Result<VectoredOutput<'a, 'b, SEND_BODY, V, M, BODY_LENGTH>> {
+        trace!("Write vectored len: {}", body.len());
+
+        self.checker()
+            .append(body.len(), HootError::SentMoreThanContentLength)?;
+
+        self.state.sent_total += body.len() as u64;
+        self.state.sent_payload += body.len() as u64;
+
+        Ok(VectoredOutput {
+            output: self.flush(),
+            body,
+        })
+    }
+
+    // Output written by finish().
+    const FINISH_LEN: usize = 0;
+
+    fn body_fit(&self) -> usize {
+        self.out.capacity()
+    }
+
+    write_body_iter!();
 }
 
 impl<'a, V: Version, M: MethodWithRequestBody> BodyWriter
@@ -346,13 +859,31 @@ impl<'a, V: Version, M: MethodWithRequestBody> BodyWriter
         w.write_bytes(bytes)?;
         w.commit();
 
+        self.state.sent_payload += bytes.len() as u64;
+
         Ok(self)
     }
 }
 
 impl<'a, V: Version, M: MethodWithRequestBody> Request<'a, SEND_BODY, V, M, BODY_CHUNKED> {
+    /// The smallest output buffer size that guarantees forward progress in this state.
+    ///
+    /// This is the size of a chunk with a single byte of data.
+    pub fn min_output_required(&self) -> usize {
+        MIN_CHUNK_LEN
+    }
+
+    fn check_chunked_length(&self) -> Result<()> {
+        // Only set when using with_chunked_length()
+        if let Some(checker) = &self.state.send_checker {
+            checker.assert_expected(HootError::SentLessThanContentLength)?;
+        }
+        Ok(())
+    }
+
     pub fn with_trailer(mut self) -> Result<Request<'a, SEND_TRAILER, V, M, BODY_CHUNKED>> {
         trace!("With trailer");
+        self.check_chunked_length()?;
 
         let mut w = self.out.writer();
         write!(w, "0\r\n").or(OVERFLOW)?;
@@ -363,6 +894,7 @@ impl<'a, V: Version, M: MethodWithRequestBody> Request<'a, SEND_BODY, V, M, BODY
 
     pub fn finish(mut self) -> Result<Request<'a, ENDED, (), (), ()>> {
         trace!("Body chunks finished");
+        self.check_chunked_length()?;
 
         let mut w = self.out.writer();
         write!(w, "0\r\n\r\n").or(OVERFLOW)?;
@@ -370,6 +902,15 @@ impl<'a, V: Version, M: MethodWithRequestBody> Request<'a, SEND_BODY, V, M, BODY
 
         Ok(self.transition())
     }
+
+    // Output written by finish(), the last chunk.
+    const FINISH_LEN: usize = 5;
+
+    fn body_fit(&self) -> usize {
+        self.state.chunked.fit(self.out.capacity())
+    }
+
+    write_body_iter!();
 }
 
 impl<'a, V: Version, M: MethodWithRequestBody> BodyWriter
@@ -383,18 +924,19 @@ impl<'a, V: Version, M: MethodWithRequestBody> BodyWriter
             return Ok(self);
         }
 
-        let mut w = self.out.writer();
+        if let Some(checker) = &self.state.send_checker {
+            checker.check_append(bytes.len(), HootError::SentMoreThanContentLength)?;
+        }
 
-        // chunk length
-        write!(w, "{:0x?}\r\n", bytes.len()).or(OVERFLOW)?;
+        let mut w = self.out.writer();
+        self.state.chunked.write(&mut w, bytes)?;
+        w.commit();
 
-        // chunk
-        w.write_bytes(bytes)?;
+        self.state.sent_payload += bytes.len() as u64;
 
-        // chunk end
-        write!(w, "\r\n").or(OVERFLOW)?;
-
-        w.commit();
+        if let Some(checker) = &mut self.state.send_checker {
+            checker.append(bytes.len(), HootError::SentMoreThanContentLength)?;
+        }
 
         Ok(self)
     }
@@ -402,6 +944,19 @@ impl<'a, V: Version, M: MethodWithRequestBody> BodyWriter
 
 // TODO: ensure trailers are declared in a `Trailer: xxx` header.
 impl<'a, V: Version, M: MethodWithRequestBody> Request<'a, SEND_TRAILER, V, M, BODY_CHUNKED> {
+    /// The smallest output buffer size that guarantees forward progress in this state.
+    ///
+    /// This is the size needed to end the trailers.
+    pub fn min_output_required(&self) -> usize {
+        END_HEADERS_LEN
+    }
+
+    /// Like [`min_output_required()`][Self::min_output_required], but also considering
+    /// a pending trailer to be written.
+    pub fn min_output_required_for_trailer(&self, name: &str, value: &[u8]) -> usize {
+        header_line_len(name, value).max(self.min_output_required())
+    }
+
     pub fn trailer(self, name: &str, value: &str) -> Result<Self> {
         trace!("Set trailer {}: {}", name, value);
 
@@ -414,8 +969,28 @@ impl<'a, V: Version, M: MethodWithRequestBody> Request<'a, SEND_TRAILER, V, M, B
         self.header_raw(name, bytes, true)
     }
 
+    /// Write a trailer whose value might not fit in the output buffer.
+    ///
+    /// The trailer is validated in full before anything is written. The name must fit
+    /// the output, while the value is written as far as there is room. Returns the number
+    /// of value bytes written. If not all of the value was written, `flush()` the output and
+    /// continue the trailer with [`header_continue()`][Self::header_continue] using the rest.
+    pub fn trailer_streamed(self, name: &str, bytes: &[u8]) -> Result<(Self, usize)> {
+        trace!("Set trailer streamed {}: {} bytes", name, bytes.len());
+        self.header_streamed_raw(name, bytes, true)
+    }
+
+    /// Continue a trailer started with [`trailer_streamed()`][Self::trailer_streamed].
+    ///
+    /// `bytes` must be the remaining part of the value not yet written. Returns the number
+    /// of value bytes written. Repeat until [`header_left()`][Self::header_left] is `None`.
+    pub fn header_continue(self, bytes: &[u8]) -> Result<(Self, usize)> {
+        self.header_continue_raw(bytes)
+    }
+
     pub fn finish(mut self) -> Result<Request<'a, ENDED, (), (), ()>> {
         trace!("Trailer finish");
+        self.state.check_header_done()?;
 
         let mut w = self.out.writer();
         write!(w, "\r\n").or(OVERFLOW)?;
@@ -495,6 +1070,23 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    pub fn test_header_value_injection() -> Result<()> {
+        for value in ["a\r\nx-evil: 1", "a\nx-evil: 1", "a\rb"] {
+            let mut buf = [0; 1024];
+
+            let x = Request::new(&mut buf)
+                .http_11()
+                .get("myhost.test:8080", "/path")?
+                .header("x-broken", value);
+
+            assert_eq!(x.unwrap_err(), HootError::HeaderValue);
+        }
+
+        Ok(())
+    }
+
     #[test]
     pub fn test_illegal_body_header() -> Result<()> {
         let mut buf = [0; 1024];
@@ -524,4 +1116,414 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    pub fn test_max_output_and_align_chunks() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let output = Request::new(&mut buf)
+            .max_output(64)
+            .align_chunks(4)
+            .http_11()
+            .post("myhost.test", "/path")?
+            .flush();
+        assert_eq!(output.len(), 40);
+
+        let (token, buf) = output.ready_and_buf();
+        let req = Request::resume(token, buf);
+        assert_eq!(req.capacity(), 64);
+
+        let output = req
+            .with_chunked()?
+            .write_bytes(b"abcdef")?
+            .write_bytes(b"gh")?
+            .flush();
+
+        const EXPECTED: &[u8] = b"Transfer-Encoding: chunked\r\n\r\n\
+            4\r\nabcd\r\n2\r\nef\r\n2\r\ngh\r\n";
+        assert_eq!(&*output, EXPECTED);
+
+        let (token, buf) = output.ready_and_buf();
+        let x = Request::resume(token, buf).write_bytes(&[b'x'; 64]);
+        assert_eq!(x.unwrap_err(), HootError::OutputOverflow);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_max_chunk_size() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let output = Request::new(&mut buf)
+            .max_chunk_size(3)
+            .http_11()
+            .post("myhost.test", "/path")?
+            .with_chunked()?
+            .write_bytes(b"abcdefg")?
+            .finish()?
+            .flush();
+
+        const EXPECTED: &[u8] = b"POST /path HTTP/1.1\r\nHost: myhost.test\r\n\
+            Transfer-Encoding: chunked\r\n\r\n\
+            3\r\nabc\r\n3\r\ndef\r\n1\r\ng\r\n0\r\n\r\n";
+        assert_eq!(&*output, EXPECTED);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_min_output_required() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let req = Request::new(&mut buf)
+            .http_11()
+            .get("myhost.test", "/path")?;
+        assert_eq!(req.min_output_required(), 2);
+        assert_eq!(req.min_output_required_for_header("x-foo", b"bar"), 12);
+
+        let mut buf = [0; 1024];
+
+        let req = Request::new(&mut buf)
+            .http_11()
+            .post("myhost.test", "/path")?;
+        assert_eq!(req.min_output_required(), 40);
+
+        let req = req.with_body(2)?;
+        assert_eq!(req.min_output_required(), 1);
+        let req = req.write_bytes(b"ok")?;
+        assert_eq!(req.min_output_required(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_header_streamed() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let req = Request::new(&mut buf)
+            .http_11()
+            .get("myhost.test", "/path")?
+            .max_output(50);
+
+        let value = b"abcdefghijklmnopqrstuvwxyz";
+
+        let (req, n) = req.header_streamed("cookie", value)?;
+        assert_eq!(n, 3);
+        assert_eq!(req.header_left(), Some(23));
+
+        // Can't do anything else while a header is in progress.
+        let err = req.header("x-foo", "bar").err();
+        assert_eq!(err, Some(HootError::StreamedHeader));
+
+        let mut buf = [0; 1024];
+
+        let req = Request::new(&mut buf)
+            .http_11()
+            .get("myhost.test", "/path")?
+            .max_output(50);
+
+        let (req, _) = req.header_streamed("cookie", value)?;
+        let (token, buf) = req.flush().ready_and_buf();
+        let req = Request::resume(token, buf);
+
+        let (req, n) = req.header_continue(&value[3..])?;
+        assert_eq!(n, 23);
+        assert_eq!(req.header_left(), None);
+
+        let req = req.send()?;
+        let output = req.flush();
+        assert_eq!(&output[..], b"defghijklmnopqrstuvwxyz\r\n\r\n");
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_header_streamed_invalid() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let req = Request::new(&mut buf)
+            .http_11()
+            .get("myhost.test", "/path")?;
+
+        let err = req.header_streamed("cookie", b"a\nb").err();
+        assert_eq!(err, Some(HootError::HeaderValue));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_with_empty_body() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let req = Request::new(&mut buf)
+            .http_11()
+            .post("myhost.test", "/path")?
+            .with_empty_body()?;
+
+        let output = req.flush();
+        assert!(output.ends_with(b"Host: myhost.test\r\nContent-Length: 0\r\n\r\n"));
+
+        let _response = output.into_response();
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_with_chunked_length() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let req = Request::new(&mut buf)
+            .http_11()
+            .post("myhost.test", "/path")?
+            .with_chunked_length(3)?;
+
+        let req = req.write_bytes(b"ab")?;
+        let err = req.write_bytes(b"cd").err();
+        assert_eq!(err, Some(HootError::SentMoreThanContentLength(3, 4)));
+
+        let mut buf = [0; 1024];
+
+        let req = Request::new(&mut buf)
+            .http_11()
+            .post("myhost.test", "/path")?
+            .with_chunked_length(3)?
+            .write_bytes(b"ab")?;
+
+        let err = req.with_trailer().err();
+        assert_eq!(err, Some(HootError::SentLessThanContentLength(3, 2)));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_with_chunked_trailers() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let req = Request::new(&mut buf)
+            .http_11()
+            .post("myhost.test", "/path")?
+            .with_chunked_trailers(&["x-checksum", "x-count"])?
+            .write_bytes(b"ok")?
+            .with_trailer()?
+            .trailer("x-checksum", "abc")?
+            .trailer("x-count", "2")?
+            .finish()?;
+
+        let output = req.flush();
+        assert!(output.ends_with(
+            b"Trailer: x-checksum, x-count\r\n\
+            Transfer-Encoding: chunked\r\n\r\n\
+            2\r\nok\r\n0\r\n\
+            x-checksum: abc\r\nx-count: 2\r\n\r\n"
+        ));
+
+        let mut buf = [0; 1024];
+
+        let err = Request::new(&mut buf)
+            .http_11()
+            .post("myhost.test", "/path")?
+            .with_chunked_trailers(&["content-length"])
+            .err();
+        assert_eq!(err, Some(HootError::ForbiddenTrailer));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_header_casing() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let req = Request::new(&mut buf)
+            .http_11()
+            .header_casing(HeaderCasing::Lowercase)
+            .post("myhost.test", "/path")?
+            .header("X-Request-ID", "1")?
+            .with_chunked_trailers(&["X-Checksum"])?
+            .with_trailer()?
+            .trailer("X-Checksum", "abc")?
+            .finish()?;
+
+        let output = req.flush();
+        assert_eq!(
+            &*output,
+            b"POST /path HTTP/1.1\r\nhost: myhost.test\r\nx-request-id: 1\r\n\
+            trailer: x-checksum\r\ntransfer-encoding: chunked\r\n\r\n\
+            0\r\nx-checksum: abc\r\n\r\n"
+        );
+
+        let mut buf = [0; 1024];
+
+        let req = Request::new(&mut buf)
+            .http_11()
+            .header_casing(HeaderCasing::TitleCase)
+            .put("myhost.test", "/path")?
+            .header("x-request-id", "1")?
+            .with_body(0)?;
+
+        let output = req.flush();
+        assert_eq!(
+            &*output,
+            b"PUT /path HTTP/1.1\r\nHost: myhost.test\r\nX-Request-Id: 1\r\n\
+            Content-Length: 0\r\n\r\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "http_crate")]
+    pub fn test_http_version_from_http_crate() -> Result<()> {
+        let req = http::Request::builder()
+            .version(http::Version::HTTP_10)
+            .body(())
+            .unwrap();
+
+        let mut buf = [0; 1024];
+        let r = match Request::new(&mut buf).http_version(req.version().into()) {
+            VersionedRequest::Http10(r) => r,
+            VersionedRequest::Http11(_) => panic!("Expected HTTP/1.0"),
+        };
+
+        let err = r
+            .post("/upload")?
+            .header("transfer-encoding", "chunked")
+            .err();
+        assert_eq!(err, Some(HootError::ForbiddenBodyHeader));
+
+        let mut buf = [0; 1024];
+        let output = Request::new(&mut buf).http_10().get("/")?.send()?.flush();
+        assert_eq!(&*output, b"GET / HTTP/1.0\r\n\r\n");
+
+        // Without content-length, the response body is delimited by closing.
+        let mut res = output.ready().into_response();
+        let mut buf = [0; 1024];
+        res.try_read_response(b"HTTP/1.0 200 OK\r\n\r\n", &mut buf)?;
+        assert_eq!(res.body_mode(), Some(crate::BodyMode::CloseDelimited));
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_host_default_port() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let mut host = |host: &str, port: u16| -> Result<Vec<u8>> {
+            let output = Request::new(&mut buf)
+                .http_11()
+                .default_port(port)
+                .get(host, "/")?
+                .flush();
+            Ok(output.to_vec())
+        };
+
+        assert_eq!(
+            host("a.test:443", 443)?,
+            b"GET / HTTP/1.1\r\nHost: a.test\r\n"
+        );
+        assert_eq!(
+            host("a.test:8443", 443)?,
+            b"GET / HTTP/1.1\r\nHost: a.test:8443\r\n"
+        );
+        assert_eq!(host("[::1]:80", 80)?, b"GET / HTTP/1.1\r\nHost: [::1]\r\n");
+        assert_eq!(host("::1", 1)?, b"GET / HTTP/1.1\r\nHost: ::1\r\n");
+
+        let output = Request::new(&mut buf)
+            .http_11()
+            .without_host()
+            .get("a.test", "/")?
+            .flush();
+        assert_eq!(&*output, b"GET / HTTP/1.1\r\n");
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_bytes_sent() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let req = Request::new(&mut buf)
+            .http_11()
+            .post("myhost.test", "/path")?
+            .with_chunked()?;
+
+        // The head is done when the body starts.
+        let head = req.bytes_sent().head;
+        assert_eq!(head as usize, req.flush().len());
+
+        let mut buf = [0; 1024];
+        let (token, _) = Request::new(&mut buf)
+            .http_11()
+            .post("myhost.test", "/path")?
+            .with_chunked()?
+            .flush()
+            .ready_and_buf();
+
+        let mut buf = [0; 1024];
+        let req = Request::resume(token, &mut buf)
+            .write_bytes(b"hello")?
+            .finish()?;
+
+        let count = req.bytes_sent();
+        assert_eq!(count.head, head);
+        assert_eq!(count.payload, 5);
+        // 5\r\n \r\n 0\r\n\r\n
+        assert_eq!(count.framing, 3 + 2 + 5);
+
+        let res = req.flush().into_response();
+        assert_eq!(res.bytes_sent(), count);
+        assert_eq!(res.bytes_received().total(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_write_body_iter() -> Result<()> {
+        use crate::chunk::Dechunker;
+
+        let long = [b'x'; 100];
+        let body: &[&[u8]] = &[b"hello", b"", &long];
+
+        let mut buf = [0; 128];
+        let mut sent = vec![];
+        let mut flushes = 0;
+
+        let token = Request::new(&mut buf)
+            .http_11()
+            .post("myhost.test", "/path")?
+            .with_chunked()?
+            .write_body_iter(body.iter().copied(), |b| {
+                flushes += 1;
+                sent.extend_from_slice(b);
+                Ok::<_, HootError>(())
+            })?;
+
+        assert!(flushes > 1);
+
+        let head_end = sent.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let mut d = Dechunker::new();
+        let mut dechunked = [0; 128];
+        let (used, len) = d.parse_input(&sent[head_end..], &mut dechunked)?;
+        // The dechunker leaves the empty line ending the trailers.
+        assert_eq!(&sent[head_end + used..], b"\r\n");
+        assert!(d.is_ended());
+        assert_eq!(&dechunked[..len], [&b"hello"[..], &long].concat());
+
+        let res = token.into_response();
+        assert_eq!(res.bytes_sent().total(), sent.len() as u64);
+
+        let mut buf = [0; 64];
+        let req = Request::new(&mut buf).http_10().post("/")?.with_body(105)?;
+        let (token, _) = req.flush().ready_and_buf();
+
+        let mut buf = [0; 8];
+        let mut sent = vec![];
+        Request::resume(token, &mut buf).write_body_iter(body.iter().copied(), |b| {
+            assert!(b.len() <= 8);
+            sent.extend_from_slice(b);
+            Ok::<_, HootError>(())
+        })?;
+        assert_eq!(sent, [&b"hello"[..], &long].concat());
+
+        Ok(())
+    }
 }
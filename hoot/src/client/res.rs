@@ -2,16 +2,23 @@ use core::fmt;
 use core::marker::PhantomData;
 use core::str;
 
-use crate::body::{do_read_body, RecvBodyMode};
-use crate::header::transmute_headers;
+use crate::ascii::eq_ignore_ascii;
+use crate::body::{do_body_size_hint, do_drain_body, do_read_body, do_read_body_borrowed};
+use crate::body::{do_try_read_trailers, response_has_no_body, Framing, RecvBodyMode};
+use crate::coding::{add_content_coding, content_coding, ContentCoding};
+#[cfg(feature = "http_crate")]
+use crate::header::fill_header_map;
+use crate::header::{check_received_headers, connection_has, transmute_headers};
+use crate::parse_impl;
+use crate::parser::{check_strict, find_crlf, head_error, parse_header_line, parse_status_line};
 use crate::types::state::*;
 use crate::types::*;
-use crate::util::{cast_buf_for_headers, compare_lowercase_ascii, LengthChecker};
-use crate::BodyPart;
-use crate::{CallState, Result};
-use crate::{Header, HootError, HttpVersion};
+use crate::util::{cast_buf_for_headers, LengthChecker};
+use crate::{BodyMode, BodyPart, TrailerAttempt};
+use crate::{ByteCount, CallState, Result};
+use crate::{Header, HootError, HttpVersion, ParseMode, TransferCoding};
 
-use super::ResumeToken;
+use super::{Request, ResumeToken};
 
 pub struct Response<S: State> {
     _typ: PhantomData<S>,
@@ -48,11 +55,51 @@ impl<S: State> Response<S> {
         }
     }
 
+    pub(crate) fn method(&self) -> crate::Method {
+        // unwrap is ok, the method is always set when receiving a response.
+        self.state.method.unwrap()
+    }
+
+    /// Bytes sent for the request this is a response to.
+    pub fn bytes_sent(&self) -> ByteCount {
+        self.state.bytes_sent(0)
+    }
+
+    /// Bytes received so far.
+    pub fn bytes_received(&self) -> ByteCount {
+        self.state.recv_bytes
+    }
+
+    /// Whether any input was given for the response, even a part of the head.
+    pub(crate) fn received_any(&self) -> bool {
+        self.state.recv_any || self.state.recv_bytes.total() > 0
+    }
+
+    /// How the response body is delimited, following RFC 9112 section 6.3.
+    ///
+    /// `None` until the status and headers are read. Responses to `HEAD`, 1xx, 204
+    /// and 304 responses, and successful responses to `CONNECT` have no body, even
+    /// with a `Content-Length`.
+    pub fn body_mode(&self) -> Option<BodyMode> {
+        let mode = self.state.recv_body_mode?;
+
+        if self.state.recv_no_body {
+            return Some(BodyMode::NoBody);
+        }
+
+        Some(match mode {
+            RecvBodyMode::LengthDelimited(n) => BodyMode::LengthDelimited(n),
+            RecvBodyMode::Chunked => BodyMode::Chunked,
+            RecvBodyMode::CloseDelimited => BodyMode::CloseDelimited,
+        })
+    }
+
     fn do_try_read_response<'a, 'b>(
         &mut self,
         input: &'a [u8],
         buf: &'b mut [u8],
     ) -> Result<ResponseAttempt<'a, 'b>> {
+        self.state.recv_any |= !input.is_empty();
         let already_read_response = self.state.recv_body_mode.is_some();
 
         // Status/header reads only work once.
@@ -60,14 +107,28 @@ impl<S: State> Response<S> {
             return Ok(ResponseAttempt::empty());
         }
 
+        let limits = self.state.head_limits;
         let headers = cast_buf_for_headers(buf);
-        let mut r = httparse::Response::new(headers);
+        let capacity = headers.len();
+        let mut r = parse_impl::Response::new(limits.limit_headers(headers));
 
-        let n = match r.parse(input)? {
-            httparse::Status::Complete(v) => v,
-            httparse::Status::Partial => return Ok(ResponseAttempt::empty()),
+        let parsed = r.parse(limits.limit_input(input));
+        let parsed = parsed.map_err(|e| limits.check_err(capacity, head_error(input, e)));
+
+        let n = match parsed? {
+            parse_impl::Status::Complete(v) => v,
+            parse_impl::Status::Partial => {
+                limits.check(input, None)?;
+                return Ok(ResponseAttempt::empty());
+            }
         };
 
+        limits.check(input, Some(n))?;
+
+        if self.state.parse_mode == ParseMode::Strict {
+            check_strict(&input[..n], true)?;
+        }
+
         let ver = match r.version.unwrap() {
             0 => HttpVersion::Http10,
             1 => HttpVersion::Http11,
@@ -76,35 +137,209 @@ impl<S: State> Response<S> {
 
         let status = Status(ver, r.code.unwrap(), r.reason.unwrap_or(""));
 
+        if status.is_interim() {
+            return Ok(self.interim(n, status, Some(transmute_headers(r.headers))));
+        }
+
         // Derive body mode from knowledge this far.
         let http10 = ver == HttpVersion::Http10;
         let method = self.state.method.unwrap(); // Ok for same reason as above.
         let headers = transmute_headers(r.headers);
+        check_received_headers(headers, !self.state.reject_obs_text)?;
 
         let lookup = |name: &str| {
             for header in &*headers {
-                if compare_lowercase_ascii(header.name(), name) {
+                if eq_ignore_ascii(header.name(), name) {
                     return Some(header.value());
                 }
             }
             None
         };
 
-        let mode = RecvBodyMode::for_response(http10, method, status.1, &lookup)?;
+        let framing = Framing::from_headers(headers)?;
+        let mode = RecvBodyMode::for_response_framing(http10, method, status.1, &framing);
+        self.set_body_mode(mode, status.1);
+        self.state.transfer_coding = framing.coding();
+        self.state.content_coding = content_coding(headers);
+
+        let connection = lookup("connection").unwrap_or("");
+        self.set_connection(&status, connection, n);
+        self.state.recv_bytes.head += n as u64;
+
+        Ok(ResponseAttempt {
+            input_used: n,
+            status: Some(status),
+            headers: Some(headers),
+        })
+    }
+
+    /// Decide whether the connection can be reused after this response, or
+    /// has become a tunnel.
+    ///
+    /// Must be called after the body mode is set.
+    fn set_connection(&mut self, status: &Status<'_>, connection: &str, input_used: usize) {
+        let state = &self.state;
+
+        // HTTP/1.0 connections close unless keep-alive is asked for.
+        let req_keep_alive = state.version != Some(HttpVersion::Http10) || state.conn_keep_alive;
+        let res_keep_alive =
+            status.version() != HttpVersion::Http10 || connection_has(connection, "keep-alive");
+
+        // After switching protocols, or a successful CONNECT, the connection isn't HTTP anymore.
+        let is_connect = state.method == Some(crate::Method::CONNECT);
+        let taken_over = status.code() == 101 || is_connect && (200..300).contains(&status.code());
+
+        // A body that won't be read is left on the connection.
+        let body_unread = state.headers_only && !self.has_empty_body();
+
+        let reuse = !state.conn_close
+            && !connection_has(connection, "close")
+            && req_keep_alive
+            && res_keep_alive
+            && !taken_over
+            && !body_unread
+            && state.recv_body_mode != Some(RecvBodyMode::CloseDelimited);
+
+        trace!("Connection reusable: {}", reuse);
+        self.state.conn_close = !reuse;
+
+        if taken_over {
+            trace!("Connection taken over");
+            self.state.taken_over = Some(input_used);
+        }
+    }
+
+    /// Take note of an interim response, which is followed by another response.
+    fn interim<'a, 'b>(
+        &mut self,
+        input_used: usize,
+        status: Status<'a>,
+        headers: Option<&'b [Header<'a>]>,
+    ) -> ResponseAttempt<'a, 'b> {
+        trace!("Interim response: {}", status.code());
+        self.state.recv_bytes.head += input_used as u64;
+
+        ResponseAttempt {
+            input_used,
+            status: Some(status),
+            headers,
+        }
+    }
+
+    fn set_body_mode(&mut self, mode: RecvBodyMode, status_code: u16) {
         self.state.recv_body_mode = Some(mode);
 
+        // unwrap is ok, the method is always set when receiving a response.
+        let method = self.state.method.unwrap();
+        self.state.recv_no_body = response_has_no_body(method, status_code);
+
+        if self.state.headers_only {
+            trace!("Headers only, no body checks");
+            return;
+        }
+
         // If we are awaiting a length, put a length checker in place
         if let RecvBodyMode::LengthDelimited(len) = mode {
             if len > 0 {
                 self.state.recv_checker = Some(LengthChecker::new(len));
             }
         }
+    }
 
-        Ok(ResponseAttempt {
-            input_used: n,
-            status: Some(status),
-            headers: Some(headers),
-        })
+    /// Whether the response has no body, or a body of length 0.
+    fn has_empty_body(&self) -> bool {
+        self.state.recv_no_body
+            || self.state.recv_body_mode == Some(RecvBodyMode::LengthDelimited(0))
+    }
+
+    /// Read status and headers line by line, handing each header to `on_header`.
+    ///
+    /// Nothing is handed over unless the input holds the complete status and headers.
+    fn do_try_read_response_lines<'a>(
+        &mut self,
+        input: &'a [u8],
+        on_header: &mut dyn FnMut(Header<'a>) -> Result<()>,
+    ) -> Result<Option<(usize, Status<'a>)>> {
+        self.state.recv_any |= !input.is_empty();
+        let already_read_response = self.state.recv_body_mode.is_some();
+
+        // Status/header reads only work once.
+        if already_read_response {
+            return Ok(None);
+        }
+
+        let limits = self.state.head_limits;
+        let limited = limits.limit_input(input);
+
+        // Find the end of the headers before handing anything over.
+        let mut end = 0;
+        let mut count = 0;
+        loop {
+            let i = match find_crlf(&limited[end..]) {
+                Some(v) => v,
+                None => {
+                    limits.check(input, None)?;
+                    return Ok(None);
+                }
+            };
+            end += i + 2;
+            // The empty line ends the headers, but the status line is never empty.
+            if i == 0 && end > 2 {
+                break;
+            }
+            count += 1;
+        }
+
+        limits.check(input, Some(end))?;
+        // Lines counted include the status line.
+        limits.check_count(count - 1)?;
+
+        if self.state.parse_mode == ParseMode::Strict {
+            check_strict(&input[..end], true)?;
+        }
+
+        let mut lines = input[..end].split_inclusive(|c| *c == b'\n');
+
+        // unwrap is ok since we found at least one line above.
+        let status = parse_status_line(lines.next().unwrap())?;
+
+        let mut framing = Framing::default();
+        let mut coding = None;
+        let mut connection = "";
+
+        for line in lines {
+            if line == b"\r\n" {
+                break;
+            }
+
+            let header = parse_header_line(line)?;
+            check_received_headers(&[header], !self.state.reject_obs_text)?;
+            framing.header(header.name(), header.try_value().unwrap_or(""))?;
+            add_content_coding(&mut coding, header.name(), header.try_value().unwrap_or(""));
+
+            if eq_ignore_ascii(header.name(), "connection") {
+                connection = str::from_utf8(header.into_inner().value).unwrap_or("");
+            }
+
+            on_header(header)?;
+        }
+
+        if status.is_interim() {
+            trace!("Interim response: {}", status.code());
+            self.state.recv_bytes.head += end as u64;
+            return Ok(Some((end, status)));
+        }
+
+        let http10 = status.version() == HttpVersion::Http10;
+        let method = self.state.method.unwrap(); // Always set when receiving a response.
+        let mode = RecvBodyMode::for_response_framing(http10, method, status.code(), &framing);
+        self.set_body_mode(mode, status.code());
+        self.state.transfer_coding = framing.coding();
+        self.state.content_coding = coding;
+        self.set_connection(&status, connection, end);
+        self.state.recv_bytes.head += end as u64;
+
+        Ok(Some((end, status)))
     }
 }
 
@@ -115,7 +350,7 @@ pub struct ResponseAttempt<'a, 'b> {
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
-pub struct Status<'a>(HttpVersion, u16, &'a str);
+pub struct Status<'a>(pub(crate) HttpVersion, pub(crate) u16, pub(crate) &'a str);
 
 impl Status<'_> {
     pub fn version(&self) -> HttpVersion {
@@ -129,6 +364,13 @@ impl Status<'_> {
     pub fn text(&self) -> &str {
         self.2
     }
+
+    /// Whether this is an interim 1xx status, such as `100 Continue` or `103 Early Hints`.
+    ///
+    /// `101 Switching Protocols` is not interim, but the final response.
+    pub fn is_interim(&self) -> bool {
+        (100..200).contains(&self.1) && self.1 != 101
+    }
 }
 
 impl<'a, 'b> ResponseAttempt<'a, 'b> {
@@ -148,16 +390,75 @@ impl<'a, 'b> ResponseAttempt<'a, 'b> {
         self.input_used
     }
 
+    /// The response read is an interim 1xx response.
+    ///
+    /// The final response follows. Read it with another attempt, on the input after
+    /// what this attempt used.
+    pub fn is_interim(&self) -> bool {
+        self.status.map(|s| s.is_interim()).unwrap_or(false)
+    }
+
     pub fn status(&self) -> Option<&Status<'a>> {
         self.status.as_ref()
     }
 
+    /// The headers of the response.
+    ///
+    /// This is `None` for an unsuccessful attempt, and for attempts made with
+    /// [`Response::try_read_response_with()`], since the headers are not kept.
     pub fn headers(&self) -> Option<&'b [Header<'a>]> {
         self.headers
     }
 }
 
 impl Response<RECV_RESPONSE> {
+    /// Reject received header values with obs-text, i.e. bytes 0x80-0xff.
+    ///
+    /// Other invalid bytes (C0 controls except HTAB, and DEL) are always rejected. To
+    /// be lenient with those, run the input through [`sanitize_field_values()`][crate::sanitize_field_values]
+    /// before reading.
+    pub fn reject_obs_text(&mut self) {
+        self.state.reject_obs_text = true;
+    }
+
+    /// Declare that the body will not be read, such as for link checkers probing with `HEAD`.
+    ///
+    /// Reading the response then stops at the headers. Nothing is set up for reading a
+    /// body, and the connection is not reusable unless the response has no body. Finish
+    /// the response with [`skip_body()`][Self::skip_body].
+    pub fn headers_only(&mut self) {
+        self.state.headers_only = true;
+    }
+
+    /// Set how forgiving reading the response is, [`ParseMode::Lenient`] by default.
+    ///
+    /// A gateway that must not read messages differently than the servers behind it,
+    /// would use [`ParseMode::Strict`].
+    pub fn parse_mode(&mut self, mode: ParseMode) {
+        self.state.parse_mode = mode;
+    }
+
+    /// Fail with [`HootError::HeaderOverflow`] for a response head, the status line and headers,
+    /// larger than `max` bytes.
+    ///
+    /// Without this, the head is only limited by the input the caller buffers.
+    pub fn max_header_bytes(&mut self, max: usize) {
+        self.state.head_limits.header_bytes = Some(max);
+    }
+
+    /// Fail with [`HootError::HeaderOverflow`] for more than `max` headers.
+    ///
+    /// Without this, the headers are only limited by the buffer given for them, which
+    /// fails with [`HootError::TooManyHeaders`].
+    pub fn max_headers(&mut self, max: usize) {
+        self.state.head_limits.headers = Some(max);
+    }
+
+    /// Fail with [`HootError::HeaderOverflow`] for a status line longer than `max` bytes.
+    pub fn max_status_line_len(&mut self, max: usize) {
+        self.state.head_limits.line_len = Some(max);
+    }
+
     pub fn try_read_response<'a, 'b>(
         &mut self,
         input: &'a [u8],
@@ -166,13 +467,169 @@ impl Response<RECV_RESPONSE> {
         self.do_try_read_response(input, buf)
     }
 
+    /// Like [`try_read_response()`][Self::try_read_response], but hands each header to
+    /// `on_header` instead of keeping them.
+    ///
+    /// This needs no header buffer, which makes it possible to handle responses with many
+    /// headers while only caring for a few. The callback is only called once the input
+    /// holds the complete status and headers. The returned attempt has no headers.
+    pub fn try_read_response_with<'a>(
+        &mut self,
+        input: &'a [u8],
+        on_header: &mut dyn FnMut(&Header<'a>),
+    ) -> Result<ResponseAttempt<'a, 'static>> {
+        let mut f = |h: Header<'a>| {
+            on_header(&h);
+            Ok(())
+        };
+
+        let (input_used, status) = match self.do_try_read_response_lines(input, &mut f)? {
+            Some(v) => v,
+            None => return Ok(ResponseAttempt::empty()),
+        };
+
+        Ok(ResponseAttempt {
+            input_used,
+            status: Some(status),
+            headers: None,
+        })
+    }
+
+    /// Like [`try_read_response()`][Self::try_read_response], but only keeps the headers
+    /// for which `retain` returns true.
+    ///
+    /// `retain` is given the header name. Dropped headers take no space in `buf`, which bounds
+    /// the memory needed for responses with many headers. The body framing is still derived
+    /// from all headers. Converting the attempt to an `http::Response` only includes the
+    /// retained headers.
+    pub fn try_read_response_retain<'a, 'b>(
+        &mut self,
+        input: &'a [u8],
+        buf: &'b mut [u8],
+        retain: &dyn Fn(&str) -> bool,
+    ) -> Result<ResponseAttempt<'a, 'b>> {
+        let hbuf = cast_buf_for_headers(buf);
+        let mut len = 0;
+
+        let mut f = |h: Header<'a>| {
+            if !retain(h.name()) {
+                return Ok(());
+            }
+            let slot = hbuf.get_mut(len).ok_or(HootError::TooManyHeaders)?;
+            *slot = h.into_inner();
+            len += 1;
+            Ok(())
+        };
+
+        let (input_used, status) = match self.do_try_read_response_lines(input, &mut f)? {
+            Some(v) => v,
+            None => return Ok(ResponseAttempt::empty()),
+        };
+
+        let hbuf: &'b [parse_impl::Header<'a>] = hbuf;
+
+        Ok(ResponseAttempt {
+            input_used,
+            status: Some(status),
+            headers: Some(transmute_headers(&hbuf[..len])),
+        })
+    }
+
+    /// End the response without reading the body.
+    ///
+    /// The connection is only reusable if there was no body to read. Errors with
+    /// [`HootError::StatusIsNotComplete`] if the response is not read yet.
+    pub fn skip_body(mut self) -> Result<Response<ENDED>> {
+        if self.state.recv_body_mode.is_none() {
+            return Err(HootError::StatusIsNotComplete);
+        }
+
+        if !self.has_empty_body() {
+            trace!("Body skipped, connection not reusable");
+            self.state.conn_close = true;
+        }
+
+        Ok(self.transition())
+    }
+
     pub fn proceed(self) -> Response<RECV_BODY> {
         self.transition()
     }
+
+    /// Turn a successful response to `CONNECT` into a tunnel.
+    ///
+    /// After a 2xx response to `CONNECT`, the connection belongs to the tunneled protocol
+    /// and there is no body to read. Errors with [`HootError::NotTunnel`] for other
+    /// responses, or if the response is not read yet.
+    pub fn into_tunnel(self) -> Result<Tunnel> {
+        let is_connect = self.state.method == Some(crate::Method::CONNECT);
+        match self.state.taken_over {
+            Some(input_used) if is_connect => Ok(Tunnel { input_used }),
+            _ => Err(HootError::NotTunnel),
+        }
+    }
+
+    /// Turn a `101 Switching Protocols` response into an upgraded connection.
+    ///
+    /// The request asks for the upgrade with the `Upgrade` and `Connection: upgrade`
+    /// headers, as for WebSocket. The response headers, such as `Sec-WebSocket-Accept`,
+    /// are in the attempt that read the response. Errors with [`HootError::NotUpgraded`]
+    /// for other responses, or if the response is not read yet.
+    pub fn into_upgraded(self) -> Result<Upgraded> {
+        let is_connect = self.state.method == Some(crate::Method::CONNECT);
+        match self.state.taken_over {
+            Some(input_used) if !is_connect => Ok(Upgraded { input_used }),
+            _ => Err(HootError::NotUpgraded),
+        }
+    }
+}
+
+/// A connection switched to another protocol by `101 Switching Protocols`.
+///
+/// This is the end of HTTP on the connection. The input and output after the response
+/// head is the upgraded protocol.
+pub struct Upgraded {
+    input_used: usize,
+}
+
+impl Upgraded {
+    /// Amount of input used for the response head.
+    pub fn input_used(&self) -> usize {
+        self.input_used
+    }
+
+    /// Input after the response head, which already belongs to the upgraded protocol.
+    ///
+    /// `input` is the input the response was read from.
+    pub fn leftover<'a>(&self, input: &'a [u8]) -> &'a [u8] {
+        &input[self.input_used.min(input.len())..]
+    }
+}
+
+/// A connection established by `CONNECT`.
+///
+/// This is the end of HTTP on the connection. The input and output after the response
+/// head is the tunneled protocol.
+pub struct Tunnel {
+    input_used: usize,
+}
+
+impl Tunnel {
+    /// Amount of input used for the response head.
+    pub fn input_used(&self) -> usize {
+        self.input_used
+    }
+
+    /// Input after the response head, which already belongs to the tunneled protocol.
+    ///
+    /// `input` is the input the response was read from.
+    pub fn leftover<'a>(&self, input: &'a [u8]) -> &'a [u8] {
+        &input[self.input_used.min(input.len())..]
+    }
 }
 
 impl Response<RECV_BODY> {
-    pub fn read_body<'a, 'b>(&mut self, src: &'a [u8], dst: &'b mut [u8]) -> Result<BodyPart<'b>> {
+    pub fn read_body<'b>(&mut self, src: &[u8], dst: &'b mut [u8]) -> Result<BodyPart<'b>> {
         let already_read_response = self.state.recv_body_mode.is_some();
 
         // It's valid to skip try_read_response() and progress straight to reading
@@ -185,11 +642,75 @@ impl Response<RECV_BODY> {
             if !r.is_success() {
                 return Ok(BodyPart::empty());
             }
+
+            // The body comes after the status and headers, or after the final response
+            // if this was an interim response.
+            return Ok(BodyPart {
+                input_used: r.input_used(),
+                ..BodyPart::empty()
+            });
         }
 
         do_read_body(&mut self.state, src, dst)
     }
 
+    /// Like [`read_body()`][Self::read_body], but the body data is borrowed from the input
+    /// instead of copied.
+    ///
+    /// The status and headers must be read before, otherwise no input is used. For chunked bodies,
+    /// each part holds data from one chunk at most, and can be empty while there is
+    /// input left, such as for the chunk framing.
+    pub fn read_body_borrowed<'a>(&mut self, src: &'a [u8]) -> Result<BodyPart<'a>> {
+        do_read_body_borrowed(&mut self.state, src)
+    }
+
+    /// Bounds of the body left to read, as `(lower, upper)`, like `Iterator::size_hint()`.
+    ///
+    /// With a content-length, this is exact. For chunked bodies, the lower bound is the
+    /// rest of the current chunk, and there is no upper bound. Nothing is known before the
+    /// status and headers is read, or for bodies delimited by closing the connection.
+    pub fn body_size_hint(&self) -> (u64, Option<u64>) {
+        do_body_size_hint(&self.state)
+    }
+
+    /// Transfer coding of the body, besides chunked, which is not undone by hoot.
+    ///
+    /// The body data read is still encoded with this coding.
+    pub fn transfer_coding(&self) -> Option<TransferCoding> {
+        self.state.transfer_coding
+    }
+
+    /// Content coding of the body, from `Content-Encoding`, which is not undone by hoot.
+    ///
+    /// The body data read is still encoded with this coding, see [`coding`][crate::coding].
+    /// A coding hoot doesn't know, or more than one, is an error.
+    pub fn content_coding(&self) -> Result<Option<ContentCoding>> {
+        self.state.content_coding.transpose()
+    }
+
+    /// Read the trailers following a chunked body.
+    ///
+    /// Use this when [`BodyPart::is_trailers_pending()`] is true. Like for the headers,
+    /// the buffer is used to parse the trailers into. Once the trailers are read, the
+    /// body is finished. For bodies that are not chunked, this never succeeds.
+    pub fn try_read_trailers<'a, 'b>(
+        &mut self,
+        input: &'a [u8],
+        buf: &'b mut [u8],
+    ) -> Result<TrailerAttempt<'a, 'b>> {
+        do_try_read_trailers(&mut self.state, input, buf)
+    }
+
+    /// Skip body input without copying it.
+    ///
+    /// This is for when the body isn't wanted, but the connection should be kept
+    /// for another request. Returns the amount of input used. Repeat with more input
+    /// until [`is_finished()`][Self::is_finished]. The status and headers must be read
+    /// before draining, otherwise no input is used.
+    pub fn drain(&mut self, src: &[u8]) -> Result<usize> {
+        do_drain_body(&mut self.state, src)
+    }
+
     pub fn is_finished(&self) -> bool {
         use RecvBodyMode::*;
 
@@ -201,10 +722,34 @@ impl Response<RECV_BODY> {
         match mode {
             LengthDelimited(n) => n == 0 || self.state.did_read_to_end,
             Chunked => self.state.did_read_to_end,
-            CloseDelimited => unreachable!("CloseDelimited is not possible for server::Request"),
+            CloseDelimited => self.state.did_read_to_end,
         }
     }
 
+    /// Signal that the remote closed the connection.
+    ///
+    /// This ends a body delimited by closing the connection, such as for HTTP/1.0
+    /// responses without `Content-Length`, after which the response can be finished.
+    /// For other bodies, the input ending before the body is finished is an error,
+    /// since the body was cut short.
+    pub fn read_body_eof(&mut self) -> Result<()> {
+        if self.state.recv_body_mode == Some(RecvBodyMode::CloseDelimited) {
+            trace!("Body ended by close");
+            self.state.did_read_to_end = true;
+            return Ok(());
+        }
+
+        if let Some(checker) = &self.state.recv_checker {
+            checker.assert_expected(HootError::RecvLessThanContentLength)?;
+        }
+
+        if !self.is_finished() {
+            return Err(HootError::BodyNotFinished);
+        }
+
+        Ok(())
+    }
+
     pub fn finish(self) -> Result<Response<ENDED>> {
         if let Some(checker) = &self.state.recv_checker {
             checker.assert_expected(HootError::RecvLessThanContentLength)?;
@@ -218,6 +763,30 @@ impl Response<RECV_BODY> {
     }
 }
 
+impl Response<ENDED> {
+    /// Whether the connection can be used for another request.
+    ///
+    /// This is false if either side sent `Connection: close`, for HTTP/1.0 without
+    /// keep-alive, for bodies delimited by closing the connection, and when the
+    /// connection was taken over by an upgrade or `CONNECT`.
+    pub fn is_reusable(&self) -> bool {
+        !self.state.conn_close
+    }
+
+    /// Start the next request on the same connection.
+    ///
+    /// Errors with [`HootError::ConnectionNotReusable`] unless [`is_reusable()`][Self::is_reusable].
+    /// hoot doesn't buffer input, which means any input beyond what was used for this
+    /// response is left with the caller.
+    pub fn into_next_request(self, buf: &mut [u8]) -> Result<Request<'_, INIT, (), (), ()>> {
+        if !self.is_reusable() {
+            return Err(HootError::ConnectionNotReusable);
+        }
+
+        Ok(Request::new(buf))
+    }
+}
+
 impl fmt::Debug for Status<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("Status")
@@ -246,6 +815,615 @@ mod test {
         assert!(a.headers().unwrap().is_empty());
         Ok(())
     }
+
+    #[test]
+    fn test_recv_reject_obs_text() -> Result<()> {
+        let input = b"HTTP/1.1 200 OK\r\nX-A: caf\xc3\xa9\r\n\r\n";
+        let mut buf = [0; 1024];
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+        assert!(a.is_success());
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        r.reject_obs_text();
+        let err = r.try_read_response(input, &mut buf).err();
+        assert_eq!(err, Some(HootError::HeaderValue));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "http_crate")]
+    fn test_into_http_recycled_header_map() -> Result<()> {
+        let input = b"HTTP/1.1 200 OK\r\nX-A: 1\r\nX-A: 2\r\n\r\n";
+        let mut buf = [0; 1024];
+
+        let mut map = http::HeaderMap::with_capacity(32);
+        map.insert("x-old", http::HeaderValue::from_static("gone"));
+        let capacity = map.capacity();
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+        let res = a.try_into_http_with(map)?;
+
+        let headers = res.headers();
+        assert!(headers.get("x-old").is_none());
+        assert_eq!(headers.get_all("x-a").iter().count(), 2);
+        assert_eq!(headers.capacity(), capacity);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_less_than_content_length() -> Result<()> {
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello";
+        let mut buf = [0; 1024];
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+
+        let mut r = r.proceed();
+        r.read_body(&input[a.input_used()..], &mut buf)?;
+
+        // Connection closed here.
+        let err = r.finish().err().unwrap();
+        assert_eq!(err, HootError::RecvLessThanContentLength(10, 5));
+        assert_eq!(
+            err.to_string(),
+            "received less than content-length (expected 10, got 5)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_drain_chunked() -> Result<()> {
+        let input = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5\r\nhello\r\n3\r\nabc\r\n0\r\n\r\n";
+        let mut buf = [0; 1024];
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+        let mut pos = a.input_used();
+
+        let mut r = r.proceed();
+
+        // Drain in small pieces.
+        while !r.is_finished() {
+            let end = (pos + 4).min(input.len());
+            pos += r.drain(&input[pos..end])?;
+        }
+
+        assert!(r.finish().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_trailers() -> Result<()> {
+        let input =
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: grpc-status\r\n\r\n\
+            5\r\nhello\r\n0\r\ngrpc-status: 0\r\n\r\n";
+        let mut buf = [0; 1024];
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+        let mut pos = a.input_used();
+
+        let mut r = r.proceed();
+
+        let mut out = [0; 1024];
+        let part = r.read_body(&input[pos..], &mut out)?;
+        assert_eq!(part.data(), b"hello");
+        assert!(part.is_trailers_pending());
+        assert!(!part.is_finished());
+        pos += part.input_used();
+
+        // Partial trailers are not read.
+        let a = r.try_read_trailers(&input[pos..(input.len() - 2)], &mut buf)?;
+        assert!(!a.is_success());
+
+        let a = r.try_read_trailers(&input[pos..], &mut buf)?;
+        assert!(a.is_success());
+        assert_eq!(a.input_used(), input.len() - pos);
+        let trailers = a.trailers().unwrap();
+        assert_eq!(trailers.len(), 1);
+        assert_eq!(trailers[0].name(), "grpc-status");
+        assert_eq!(trailers[0].value(), "0");
+
+        assert!(r.is_finished());
+        assert!(r.finish().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_body_borrowed() -> Result<()> {
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhelloextra";
+        let mut buf = [0; 1024];
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+        let pos = a.input_used();
+        let mut r = r.proceed();
+
+        let part = r.read_body_borrowed(&input[pos..])?;
+        assert_eq!(part.data(), b"hello");
+        assert_eq!(part.input_used(), 5);
+        assert!(part.is_finished());
+
+        let input = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n";
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+        let mut pos = a.input_used();
+        let mut r = r.proceed();
+
+        let mut body = vec![];
+        while !r.is_finished() {
+            let part = r.read_body_borrowed(&input[pos..])?;
+            pos += part.input_used();
+            body.extend_from_slice(part.data());
+        }
+
+        assert_eq!(body, b"abcde");
+        assert_eq!(pos, input.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_body_mode() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let mode = |method, input: &[u8], buf: &mut [u8]| -> Result<Option<BodyMode>> {
+            let mut r: Response<RECV_RESPONSE> = Response::new_test();
+            r.state.method = Some(method);
+            r.try_read_response(input, buf)?;
+            Ok(r.body_mode())
+        };
+
+        use crate::Method::{GET, HEAD};
+        let length = b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n";
+
+        assert_eq!(mode(HEAD, length, &mut buf)?, Some(BodyMode::NoBody));
+        assert_eq!(
+            mode(GET, length, &mut buf)?,
+            Some(BodyMode::LengthDelimited(10))
+        );
+        assert_eq!(
+            mode(GET, b"HTTP/1.1 304 Not Modified\r\n\r\n", &mut buf)?,
+            Some(BodyMode::NoBody)
+        );
+        assert_eq!(
+            mode(GET, b"HTTP/1.0 200 OK\r\n\r\n", &mut buf)?,
+            Some(BodyMode::CloseDelimited)
+        );
+        assert_eq!(mode(GET, b"HTTP/1.1 200 OK\r\n", &mut buf)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_body_eof() -> Result<()> {
+        let input = b"HTTP/1.0 200 OK\r\n\r\nuntil close";
+        let mut buf = [0; 1024];
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+        let pos = a.input_used();
+        let mut r = r.proceed();
+
+        let part = r.read_body(&input[pos..], &mut buf)?;
+        assert_eq!(part.data(), b"until close");
+        assert!(!r.is_finished());
+
+        r.read_body_eof()?;
+        assert!(r.is_finished());
+        let r = r.finish()?;
+        assert!(!r.is_reusable());
+
+        // A length delimited body cut short.
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nabc";
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+        let pos = a.input_used();
+        let mut r = r.proceed();
+        r.read_body(&input[pos..], &mut buf)?;
+
+        let err = r.read_body_eof().err();
+        assert_eq!(err, Some(HootError::RecvLessThanContentLength(5, 3)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_interim() -> Result<()> {
+        let input = b"HTTP/1.1 103 Early Hints\r\nLink: </a.css>; rel=preload\r\n\r\n\
+            HTTP/1.1 102 Processing\r\n\r\n\
+            HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+        let mut buf = [0; 1024];
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+
+        let a = r.try_read_response(input, &mut buf)?;
+        assert!(a.is_interim());
+        assert_eq!(a.status().unwrap().code(), 103);
+        assert_eq!(a.headers().unwrap()[0].name(), "Link");
+        let mut pos = a.input_used();
+
+        let mut f = |_: &Header<'_>| {};
+        let a = r.try_read_response_with(&input[pos..], &mut f)?;
+        assert!(a.is_interim());
+        pos += a.input_used();
+
+        let a = r.try_read_response(&input[pos..], &mut buf)?;
+        assert!(!a.is_interim());
+        assert_eq!(a.status().unwrap().code(), 200);
+        pos += a.input_used();
+
+        let mut r = r.proceed();
+        let part = r.read_body(&input[pos..], &mut buf)?;
+        assert_eq!(part.data(), b"ok");
+
+        // Going straight to the body skips interim responses.
+        let mut r = Response::new_test().proceed();
+        let mut pos = 0;
+        loop {
+            let part = r.read_body(&input[pos..], &mut buf)?;
+            pos += part.input_used();
+            if part.is_finished() {
+                assert_eq!(part.data(), b"ok");
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_body_size_hint() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+        let pos = a.input_used();
+        let mut r = r.proceed();
+        assert_eq!(r.body_size_hint(), (5, Some(5)));
+        r.read_body(&input[pos..(pos + 2)], &mut buf)?;
+        assert_eq!(r.body_size_hint(), (3, Some(3)));
+
+        let input = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\na\r\nhello";
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+        let pos = a.input_used();
+        let mut r = r.proceed();
+        assert_eq!(r.body_size_hint(), (0, None));
+        r.read_body(&input[pos..], &mut buf)?;
+        assert_eq!(r.body_size_hint(), (5, None));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_coding() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let input = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: 5\r\n\r\n";
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        r.try_read_response(input, &mut buf)?;
+        assert_eq!(r.proceed().content_coding()?, Some(ContentCoding::Gzip));
+
+        let input = b"HTTP/1.1 200 OK\r\nContent-Encoding: br, gzip\r\nContent-Length: 5\r\n\r\n";
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        r.try_read_response_with(input, &mut |_| {})?;
+        let r = r.proceed();
+        assert_eq!(r.content_coding(), Err(HootError::StackedContentCoding));
+
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n";
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        r.try_read_response_with(input, &mut |_| {})?;
+        assert_eq!(r.proceed().content_coding()?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytes_received() -> Result<()> {
+        let head = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n";
+        let body = b"5\r\nhello\r\n3\r\nabc\r\n0\r\nx: 1\r\n\r\n";
+        let mut buf = [0; 1024];
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        r.try_read_response(head, &mut buf)?;
+        let mut r = r.proceed();
+
+        let mut out = [0; 1024];
+        let part = r.read_body(&body[..10], &mut out)?;
+        let pos = part.input_used();
+        r.drain(&body[pos..])?;
+        assert!(r.is_finished());
+
+        let count = r.bytes_received();
+        assert_eq!(count.head, head.len() as u64);
+        assert_eq!(count.payload, 8);
+        assert_eq!(count.framing, body.len() as u64 - 8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_trailers_skipped() -> Result<()> {
+        let input = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5\r\nhello\r\n0\r\nx-a: 1\r\n\r\n";
+        let mut buf = [0; 1024];
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+        let mut pos = a.input_used();
+
+        let mut r = r.proceed();
+
+        // Reading body past the last chunk skips the trailers.
+        while !r.is_finished() {
+            let part = r.read_body(&input[pos..], &mut buf)?;
+            pos += part.input_used();
+        }
+
+        assert_eq!(pos, input.len());
+        assert!(r.finish().is_ok());
+
+        Ok(())
+    }
+
+    fn read_to_end(input: &[u8]) -> Result<Response<ENDED>> {
+        let mut buf = [0; 1024];
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        let a = r.try_read_response(input, &mut buf)?;
+        let mut r = r.proceed();
+        r.read_body(&input[a.input_used()..], &mut buf)?;
+        r.finish()
+    }
+
+    #[test]
+    fn test_reusable() -> Result<()> {
+        let r = read_to_end(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")?;
+        assert!(r.is_reusable());
+
+        let mut buf = [0; 1024];
+        let req = r.into_next_request(&mut buf)?;
+        let out = req.http_11().get("a.test", "/")?.send()?.flush();
+        assert_eq!(&*out, b"GET / HTTP/1.1\r\nHost: a.test\r\n\r\n");
+
+        let r = read_to_end(b"HTTP/1.1 200 OK\r\nConnection: Close\r\nContent-Length: 0\r\n\r\n")?;
+        assert!(!r.is_reusable());
+        let mut buf = [0; 1024];
+        assert_eq!(
+            r.into_next_request(&mut buf).err(),
+            Some(HootError::ConnectionNotReusable)
+        );
+
+        let r = read_to_end(b"HTTP/1.0 200 OK\r\nContent-Length: 0\r\n\r\n")?;
+        assert!(!r.is_reusable());
+
+        let r =
+            read_to_end(b"HTTP/1.0 200 OK\r\nConnection: keep-alive\r\nContent-Length: 0\r\n\r\n")?;
+        assert!(r.is_reusable());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reusable_request_close() -> Result<()> {
+        let mut buf = [0; 1024];
+        let out = Request::new(&mut buf)
+            .http_11()
+            .get("a.test", "/")?
+            .header("connection", "close")?
+            .send()?
+            .flush();
+
+        let mut r = out.into_response();
+        let input = b"HTTP/1.1 204 No Content\r\n\r\n";
+        r.try_read_response(input, &mut buf)?;
+        let r = r.proceed().finish()?;
+        assert!(!r.is_reusable());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_headers_only() -> Result<()> {
+        let mut buf = [0; 1024];
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello";
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        r.state.method = Some(crate::Method::HEAD);
+        r.headers_only();
+        r.try_read_response(input, &mut buf)?;
+        assert!(r.state.recv_checker.is_none());
+        assert!(r.skip_body()?.is_reusable());
+
+        // The body is left on the connection.
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        r.headers_only();
+        r.try_read_response(input, &mut buf)?;
+        assert!(r.state.conn_close);
+        assert!(!r.skip_body()?.is_reusable());
+
+        let r: Response<RECV_RESPONSE> = Response::new_test();
+        assert_eq!(r.skip_body().err(), Some(HootError::StatusIsNotComplete));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_head_limits() -> Result<()> {
+        let input = b"HTTP/1.1 200 OK\r\nA: 1\r\nB: 2\r\nContent-Length: 0\r\n\r\n";
+        let mut buf = [0; 1024];
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        r.max_headers(2);
+        let e = r.try_read_response(input, &mut buf).err();
+        assert_eq!(e, Some(HootError::HeaderOverflow));
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        r.max_headers(2);
+        let e = r.try_read_response_with(input, &mut |_| {}).err();
+        assert_eq!(e, Some(HootError::HeaderOverflow));
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        r.max_headers(3);
+        r.max_status_line_len(15);
+        assert!(r.try_read_response_with(input, &mut |_| {})?.is_success());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tunnel() -> Result<()> {
+        let mut buf = [0; 1024];
+        let output = Request::new(&mut buf)
+            .http_11()
+            .connect("a.test:443")?
+            .send()?
+            .flush();
+        assert_eq!(
+            &*output,
+            b"CONNECT a.test:443 HTTP/1.1\r\nHost: a.test:443\r\n\r\n"
+        );
+
+        let mut r = output.into_response();
+        let input = b"HTTP/1.1 200 Connection Established\r\n\r\n\x16\x03\x01";
+        r.try_read_response(input, &mut buf)?;
+
+        let tunnel = r.into_tunnel()?;
+        assert_eq!(tunnel.leftover(input), b"\x16\x03\x01");
+
+        // A refused CONNECT is no tunnel.
+        let output = Request::new(&mut buf)
+            .http_11()
+            .connect("a.test:443")?
+            .send()?
+            .flush();
+        let mut r = output.into_response();
+        r.try_read_response(
+            b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n",
+            &mut buf,
+        )?;
+        assert_eq!(r.into_tunnel().err(), Some(HootError::NotTunnel));
+
+        let r = Request::new(&mut buf).http_11().connect("a.test");
+        assert_eq!(r.err(), Some(HootError::ConnectTarget));
+
+        // No header injection through the target.
+        for target in [
+            "a.test\r\nX-Evil: 1\r\nY: a:443",
+            "a.test :443",
+            "user@a.test:443",
+            "a.test:+443",
+            "a.test:70000",
+            "[::1:443",
+        ] {
+            let r = Request::new(&mut buf).http_11().connect(target);
+            assert_eq!(r.err(), Some(HootError::ConnectTarget), "{:?}", target);
+        }
+
+        let r = Request::new(&mut buf).http_11().connect("[::1]:443");
+        assert!(r.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_upgraded() -> Result<()> {
+        let mut buf = [0; 1024];
+        let output = Request::new(&mut buf)
+            .http_11()
+            .get("a.test", "/chat")?
+            .header("upgrade", "websocket")?
+            .header("connection", "upgrade")?
+            .header("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ==")?
+            .send()?
+            .flush();
+
+        let mut r = output.into_response();
+        let input = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+            Connection: Upgrade\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n\x81\x00";
+        let a = r.try_read_response(input, &mut buf)?;
+        assert_eq!(a.status().unwrap().code(), 101);
+        assert_eq!(a.headers().unwrap()[2].name(), "Sec-WebSocket-Accept");
+
+        let upgraded = r.into_upgraded()?;
+        assert_eq!(upgraded.leftover(input), b"\x81\x00");
+
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+        r.try_read_response(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n", &mut buf)?;
+        assert_eq!(r.into_upgraded().err(), Some(HootError::NotUpgraded));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_headers_with_callback() -> Result<()> {
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+
+        let input = b"HTTP/1.1 200 OK\r\nX-A: 1\r\nContent-Length: 5\r\nX-B: 2\r\n\r\nhello";
+
+        let mut names = vec![];
+
+        // Partial input does not hand over any headers.
+        let a =
+            r.try_read_response_with(&input[..30], &mut |h| names.push(h.name().to_string()))?;
+        assert!(!a.is_success());
+        assert!(names.is_empty());
+
+        let a = r.try_read_response_with(input, &mut |h| names.push(h.name().to_string()))?;
+        assert!(a.is_success());
+        assert_eq!(a.input_used(), input.len() - 5);
+        assert_eq!(a.status().unwrap().code(), 200);
+        assert!(a.headers().is_none());
+        assert_eq!(names, ["X-A", "Content-Length", "X-B"]);
+
+        let mut r = r.proceed();
+        let mut buf = [0; 10];
+        let part = r.read_body(&input[a.input_used()..], &mut buf)?;
+        assert_eq!(part.data(), b"hello");
+        assert!(r.is_finished());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_headers_retain() -> Result<()> {
+        let mut r: Response<RECV_RESPONSE> = Response::new_test();
+
+        let input = b"HTTP/1.1 200 OK\r\nX-A: 1\r\nContent-Length: 5\r\nLocation: /x\r\n\r\nhello";
+
+        // Too small to hold all headers.
+        let mut buf = [0; 64];
+
+        let a =
+            r.try_read_response_retain(input, &mut buf, &|n| n.eq_ignore_ascii_case("location"))?;
+        assert!(a.is_success());
+
+        let headers = a.headers().unwrap();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].name(), "Location");
+        assert_eq!(headers[0].value(), "/x");
+
+        // Content-Length was dropped, but still decides the body.
+        let mut r = r.proceed();
+        let mut buf = [0; 10];
+        let part = r.read_body(&input[a.input_used()..], &mut buf)?;
+        assert_eq!(part.data(), b"hello");
+        assert!(r.is_finished());
+
+        Ok(())
+    }
 }
 
 /// Type encapsulating a Response status text.
@@ -270,31 +1448,38 @@ impl<'a, 'b> TryFrom<ResponseAttempt<'a, 'b>> for http::Response<()> {
     type Error = HootError;
 
     fn try_from(attempt: ResponseAttempt<'a, 'b>) -> Result<Self> {
-        if !attempt.is_success() {
+        attempt.try_into_http_with(http::HeaderMap::new())
+    }
+}
+
+#[cfg(feature = "http_crate")]
+impl<'a, 'b> ResponseAttempt<'a, 'b> {
+    /// Convert to an `http::Response`, reusing the given `HeaderMap` for the headers.
+    ///
+    /// The map is cleared before use. Passing a map from an earlier response avoids
+    /// allocating a new one for every response.
+    pub fn try_into_http_with(self, mut headers: http::HeaderMap) -> Result<http::Response<()>> {
+        if !self.is_success() {
             return Err(HootError::IncompleteResponseAttempt);
         }
 
         // unwraps ok due to is_success() check above.
-        let status = attempt.status().unwrap();
-        let headers = attempt.headers().unwrap();
+        let status = self.status().unwrap();
+        fill_header_map(&mut headers, self.headers().unwrap_or(&[]))?;
 
         let http_status = http::StatusCode::from_u16(status.code())
             .map_err(|_| HootError::HttpRefusedStatusCode)?;
 
-        let mut builder = http::Response::builder()
+        let mut res = http::Response::builder()
             .version(status.version().into())
             .status(http_status)
             // http crate eschews the status text since it's out of fashion.
-            .extension(StatusText(status.text().to_owned()));
-
-        for header in headers {
-            builder = builder.header(header.name(), header.value());
-        }
-
-        let res = builder
+            .extension(StatusText(status.text().to_owned()))
             .body(())
             .expect("Successful http::Response conversion");
 
+        *res.headers_mut() = headers;
+
         Ok(res)
     }
 }
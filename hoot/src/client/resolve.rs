@@ -0,0 +1,190 @@
+use core::time::Duration;
+
+use super::Failover;
+use crate::error::Result;
+use crate::HootError;
+
+/// A kind of address record to look up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    /// IPv4 addresses.
+    A,
+    /// IPv6 addresses.
+    Aaaa,
+}
+
+/// Instruction to resolve the host of a request, before connecting to it.
+///
+/// Like all IO, looking up hosts is left to the caller. `Resolve` tells which host and
+/// port, and which records to look up, for any resolver to carry out: the system one,
+/// DNS over HTTPS, mDNS or a static map. The addresses found are handed back with
+/// [`resolved()`][Self::resolved], to connect to with a [`Failover`].
+///
+/// ```
+/// use core::time::Duration;
+/// use hoot::client::{Lookup, Resolve};
+///
+/// let resolve = Resolve::new("example.test:8080", 80)?;
+/// assert_eq!(resolve.host(), "example.test");
+/// assert_eq!(resolve.port(), 8080);
+/// assert_eq!(resolve.lookups(), &[Lookup::Aaaa, Lookup::A]);
+///
+/// // Look up the host with any resolver, here a static map.
+/// let addrs = [("2001:db8::1", 8080), ("192.0.2.1", 8080)];
+///
+/// let failover = resolve.resolved(addrs, Duration::from_secs(30));
+/// assert_eq!(failover.next_origin(Duration::ZERO), Some((0, ("2001:db8::1", 8080))));
+///
+/// // An address needs no lookup.
+/// let resolve = Resolve::new("[::1]", 443)?;
+/// assert!(resolve.is_literal());
+/// assert_eq!(resolve.host(), "::1");
+/// assert!(resolve.lookups().is_empty());
+/// # Ok::<(), hoot::HootError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resolve<'a> {
+    host: &'a str,
+    port: u16,
+    literal: bool,
+    prefer_ipv4: bool,
+}
+
+impl<'a> Resolve<'a> {
+    /// Resolve the host of an `authority`, such as `host`, `host:port` or `[::1]:port`.
+    ///
+    /// The port is `default_port` when the authority has none.
+    pub fn new(authority: &'a str, default_port: u16) -> Result<Self> {
+        let (host, port, literal) = if let Some(rest) = authority.strip_prefix('[') {
+            // IPv6 address, possibly with port.
+            let (host, rest) = rest.split_once(']').ok_or(HootError::InvalidAuthority)?;
+            let port = match rest.strip_prefix(':') {
+                Some(port) => port.parse()?,
+                None if rest.is_empty() => default_port,
+                None => return Err(HootError::InvalidAuthority),
+            };
+            (host, port, true)
+        } else if authority.matches(':').count() > 1 {
+            // IPv6 address without brackets, and so without port.
+            (authority, default_port, true)
+        } else {
+            let (host, port) = match authority.split_once(':') {
+                Some((host, port)) => (host, port.parse()?),
+                None => (authority, default_port),
+            };
+            (host, port, is_ipv4(host))
+        };
+
+        if host.is_empty() {
+            return Err(HootError::InvalidAuthority);
+        }
+
+        Ok(Resolve {
+            host,
+            port,
+            literal,
+            prefer_ipv4: false,
+        })
+    }
+
+    /// Look up IPv4 addresses before IPv6.
+    ///
+    /// By default IPv6 goes first, as in RFC 8305.
+    pub fn prefer_ipv4(mut self) -> Self {
+        self.prefer_ipv4 = true;
+        self
+    }
+
+    /// The host to look up, without brackets for an IPv6 address.
+    pub fn host(&self) -> &'a str {
+        self.host
+    }
+
+    /// The port to connect to, `default_port` if the authority has none.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Whether the host is an IP address, which needs no lookup.
+    pub fn is_literal(&self) -> bool {
+        self.literal
+    }
+
+    /// The records to look up, most preferred first. Empty for an IP address.
+    pub fn lookups(&self) -> &'static [Lookup] {
+        if self.literal {
+            &[]
+        } else if self.prefer_ipv4 {
+            &[Lookup::A, Lookup::Aaaa]
+        } else {
+            &[Lookup::Aaaa, Lookup::A]
+        }
+    }
+
+    /// Hand back the addresses found, most preferred first, to connect to in turn.
+    ///
+    /// `cooldown` is how long an address that failed is left alone, see [`Failover`].
+    pub fn resolved<T: Copy, const N: usize>(
+        &self,
+        addrs: [T; N],
+        cooldown: Duration,
+    ) -> Failover<T, N> {
+        trace!("Resolved {}: {} addresses", self.host, N);
+        Failover::new(addrs, cooldown)
+    }
+}
+
+fn is_ipv4(host: &str) -> bool {
+    let mut parts = 0;
+    for part in host.split('.') {
+        parts += 1;
+        if part.is_empty() || part.len() > 3 || part.parse::<u8>().is_err() {
+            return false;
+        }
+    }
+    parts == 4
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve_authority() {
+        let r = Resolve::new("example.test", 80).unwrap();
+        assert_eq!(
+            (r.host(), r.port(), r.is_literal()),
+            ("example.test", 80, false)
+        );
+
+        let r = Resolve::new("10.0.0.1:8080", 80).unwrap();
+        assert_eq!(
+            (r.host(), r.port(), r.is_literal()),
+            ("10.0.0.1", 8080, true)
+        );
+        assert!(r.lookups().is_empty());
+
+        // Not quite an address.
+        let r = Resolve::new("10.0.0.256", 80).unwrap();
+        assert!(!r.is_literal());
+
+        let r = Resolve::new("[2001:db8::1]:443", 80).unwrap();
+        assert_eq!(
+            (r.host(), r.port(), r.is_literal()),
+            ("2001:db8::1", 443, true)
+        );
+
+        let r = Resolve::new("::1", 80).unwrap();
+        assert_eq!((r.host(), r.port(), r.is_literal()), ("::1", 80, true));
+
+        let r = Resolve::new("example.test", 80).unwrap().prefer_ipv4();
+        assert_eq!(r.lookups(), &[Lookup::A, Lookup::Aaaa]);
+
+        let invalid = Err(HootError::InvalidAuthority);
+        assert_eq!(Resolve::new(":80", 80), invalid);
+        assert_eq!(Resolve::new("[::1", 80), invalid);
+        assert_eq!(Resolve::new("[::1]x", 80), invalid);
+        assert_eq!(Resolve::new("[]:80", 80), invalid);
+        assert_eq!(Resolve::new("a:port", 80), Err(HootError::ParseIntError));
+    }
+}
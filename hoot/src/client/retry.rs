@@ -0,0 +1,128 @@
+use crate::types::State;
+
+use super::Response;
+
+/// Opt-in retries of idempotent requests on reused connections.
+///
+/// A server can close a kept-alive connection at the same time as a new request is sent
+/// on it. The request then fails without any response, and it's unknown whether the server
+/// saw it. Idempotent requests, such as `GET`, are safe to send again on a new connection.
+/// Retries are bounded by a budget.
+///
+/// ```
+/// use hoot::client::{Request, Retry};
+///
+/// let mut retry = Retry::new(2);
+///
+/// let mut buf = [0; 1024];
+/// let output = Request::new(&mut buf)
+///     .http_11()
+///     .get("a.test", "/")?
+///     .send()?
+///     .flush();
+///
+/// // Writing the output to a reused connection succeeded, but reading
+/// // the response failed with nothing received.
+/// let response = output.into_response();
+///
+/// assert!(retry.should_retry(&response, true));
+/// # Ok::<(), hoot::HootError>(())
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Retry {
+    budget: u32,
+    used: u32,
+}
+
+impl Retry {
+    /// Retry at most `budget` times.
+    pub fn new(budget: u32) -> Self {
+        Retry { budget, used: 0 }
+    }
+
+    /// The request of `response` failed on the transport. Returns true if it should be
+    /// sent again on a new connection, which uses one retry of the budget.
+    ///
+    /// Only requests with idempotent methods, on a `reused` connection, where no response
+    /// byte was received, are retried. A part of a response head, given to
+    /// `try_read_response()`, counts as received. A failure on a new connection is not the stale
+    /// connection race, and is not retried.
+    pub fn should_retry<S: State>(&mut self, response: &Response<S>, reused: bool) -> bool {
+        let retry = reused
+            && response.method().is_idempotent()
+            && !response.received_any()
+            && self.used < self.budget;
+
+        if retry {
+            self.used += 1;
+            trace!("Retry {} of {}", self.used, self.budget);
+        }
+
+        retry
+    }
+
+    /// Number of retries used.
+    pub fn retries(&self) -> u32 {
+        self.used
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::client::Request;
+    use crate::types::state::RECV_RESPONSE;
+    use crate::BodyWriter;
+    use crate::Result;
+
+    fn get() -> Result<Response<RECV_RESPONSE>> {
+        let mut buf = [0; 1024];
+        let output = Request::new(&mut buf)
+            .http_11()
+            .get("a.test", "/")?
+            .send()?
+            .flush();
+        Ok(output.into_response())
+    }
+
+    #[test]
+    fn test_retry() -> Result<()> {
+        let mut retry = Retry::new(1);
+
+        // Not on a new connection.
+        assert!(!retry.should_retry(&get()?, false));
+
+        // Not after a response byte.
+        let mut r = get()?;
+        let mut buf = [0; 1024];
+        r.try_read_response(b"HTTP/1.1 200 OK\r\n\r\n", &mut buf)?;
+        assert!(!retry.should_retry(&r, true));
+
+        // Not after a part of the head.
+        let mut r = get()?;
+        let mut buf = [0; 1024];
+        let attempt = r.try_read_response(b"HTTP/1.1 200", &mut buf)?;
+        assert!(!attempt.is_success());
+        assert_eq!(r.bytes_received().total(), 0);
+        assert!(!retry.should_retry(&r, true));
+
+        // Not POST.
+        let mut buf = [0; 1024];
+        let output = Request::new(&mut buf)
+            .http_11()
+            .post("a.test", "/")?
+            .with_body(1)?
+            .write_bytes(b"x")?
+            .finish()?
+            .flush();
+        assert!(!retry.should_retry(&output.into_response(), true));
+
+        assert!(retry.should_retry(&get()?, true));
+
+        // Budget spent.
+        assert!(!retry.should_retry(&get()?, true));
+        assert_eq!(retry.retries(), 1);
+
+        Ok(())
+    }
+}
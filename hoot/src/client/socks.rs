@@ -0,0 +1,318 @@
+use crate::error::Result;
+use crate::HootError;
+
+/// Where a SOCKS5 proxy is to connect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocksTarget<'a> {
+    /// A host name, looked up by the proxy. This is what `socks5h://` proxies do.
+    Domain(&'a str),
+    /// An IPv4 address, as looked up by the client for `socks5://` proxies.
+    V4([u8; 4]),
+    /// An IPv6 address, as looked up by the client for `socks5://` proxies.
+    V6([u8; 16]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Greet,
+    Method,
+    Auth,
+    AuthReply,
+    Connect,
+    Reply,
+    Connected,
+}
+
+/// SOCKS5 handshake, to connect through a proxy before the HTTP exchange.
+///
+/// After connecting to the proxy, the handshake takes turns to send [`output()`] and
+/// read [`input()`] until [`is_connected()`]. From there, the connection goes to the
+/// target, and the request is sent as if connected to it directly.
+///
+/// With a host name as target, the proxy looks it up (`socks5h://`). For `socks5://`,
+/// look up the host with [`Resolve`][super::Resolve] and use the address as target.
+///
+/// ```
+/// use hoot::client::{Socks5, SocksTarget};
+///
+/// let mut buf = [0; 600];
+/// let mut socks = Socks5::new(SocksTarget::Domain("example.test"), 443)?
+///     .auth("martin", "secret")?;
+///
+/// // Offer no authentication, and username and password.
+/// assert_eq!(socks.output(&mut buf)?, &[5, 2, 0, 2]);
+///
+/// // Nothing more to send until the proxy answers.
+/// assert!(socks.output(&mut buf)?.is_empty());
+///
+/// // The proxy picks username and password.
+/// assert_eq!(socks.input(&[5, 2])?, 2);
+/// assert_eq!(socks.output(&mut buf)?, b"\x01\x06martin\x06secret");
+/// assert_eq!(socks.input(&[1, 0])?, 2);
+///
+/// // Ask for the target.
+/// assert_eq!(socks.output(&mut buf)?, b"\x05\x01\x00\x03\x0cexample.test\x01\xbb");
+///
+/// // Incomplete input is left for later.
+/// assert_eq!(socks.input(&[5, 0, 0])?, 0);
+/// assert_eq!(socks.input(&[5, 0, 0, 1, 10, 0, 0, 1, 0x1f, 0x90])?, 10);
+/// assert!(socks.is_connected());
+/// # Ok::<(), hoot::HootError>(())
+/// ```
+///
+/// [`output()`]: Self::output
+/// [`input()`]: Self::input
+/// [`is_connected()`]: Self::is_connected
+#[derive(Debug, Clone)]
+pub struct Socks5<'a> {
+    target: SocksTarget<'a>,
+    port: u16,
+    auth: Option<(&'a str, &'a str)>,
+    step: Step,
+}
+
+impl<'a> Socks5<'a> {
+    /// Connect to `target` and `port` through the proxy.
+    ///
+    /// Errors with [`HootError::SocksTarget`] for a host name longer than 255 bytes.
+    pub fn new(target: SocksTarget<'a>, port: u16) -> Result<Self> {
+        if let SocksTarget::Domain(host) = target {
+            check_len(host)?;
+        }
+
+        Ok(Socks5 {
+            target,
+            port,
+            auth: None,
+            step: Step::Greet,
+        })
+    }
+
+    /// Authenticate with username and password, as in RFC 1929.
+    ///
+    /// Errors with [`HootError::SocksTarget`] if either is longer than 255 bytes.
+    pub fn auth(mut self, username: &'a str, password: &'a str) -> Result<Self> {
+        check_len(username)?;
+        check_len(password)?;
+        self.auth = Some((username, password));
+        Ok(self)
+    }
+
+    /// The next bytes to send to the proxy, written to `buf`.
+    ///
+    /// Empty when waiting for [`input()`][Self::input], or when connected. The longest
+    /// message is 513 bytes, for the longest username and password.
+    pub fn output<'b>(&mut self, buf: &'b mut [u8]) -> Result<&'b [u8]> {
+        let len = match self.step {
+            Step::Greet => {
+                let n = if self.auth.is_some() {
+                    write(buf, &[&[5, 2, 0, 2]])?
+                } else {
+                    write(buf, &[&[5, 1, 0]])?
+                };
+                self.step = Step::Method;
+                n
+            }
+            Step::Auth => {
+                // Only in this step when there is auth.
+                let (user, pass) = self.auth.unwrap_or_default();
+                let n = write(
+                    buf,
+                    &[
+                        &[1, user.len() as u8],
+                        user.as_bytes(),
+                        &[pass.len() as u8],
+                        pass.as_bytes(),
+                    ],
+                )?;
+                self.step = Step::AuthReply;
+                n
+            }
+            Step::Connect => {
+                let port = self.port.to_be_bytes();
+                let n = match &self.target {
+                    SocksTarget::Domain(host) => write(
+                        buf,
+                        &[&[5, 1, 0, 3, host.len() as u8], host.as_bytes(), &port],
+                    )?,
+                    SocksTarget::V4(ip) => write(buf, &[&[5, 1, 0, 1], ip, &port])?,
+                    SocksTarget::V6(ip) => write(buf, &[&[5, 1, 0, 4], ip, &port])?,
+                };
+                self.step = Step::Reply;
+                n
+            }
+            _ => 0,
+        };
+
+        Ok(&buf[..len])
+    }
+
+    /// Read the answer of the proxy.
+    ///
+    /// Returns the amount of input used, which is 0 when the input isn't a complete
+    /// answer yet. Input after the final answer belongs to the target.
+    ///
+    /// Errors with [`HootError::SocksAuth`] if the proxy refuses to authenticate, and
+    /// [`HootError::SocksRefused`] if it fails to connect to the target.
+    pub fn input(&mut self, input: &[u8]) -> Result<usize> {
+        match self.step {
+            Step::Method => {
+                if input.len() < 2 {
+                    return Ok(0);
+                }
+                if input[0] != 5 {
+                    return Err(HootError::SocksVersion);
+                }
+                self.step = match input[1] {
+                    0 => Step::Connect,
+                    2 if self.auth.is_some() => Step::Auth,
+                    _ => return Err(HootError::SocksAuth),
+                };
+                Ok(2)
+            }
+            Step::AuthReply => {
+                if input.len() < 2 {
+                    return Ok(0);
+                }
+                if input[0] != 1 {
+                    return Err(HootError::SocksVersion);
+                }
+                if input[1] != 0 {
+                    return Err(HootError::SocksAuth);
+                }
+                self.step = Step::Connect;
+                Ok(2)
+            }
+            Step::Reply => {
+                if input.len() < 4 {
+                    return Ok(0);
+                }
+                if input[0] != 5 {
+                    return Err(HootError::SocksVersion);
+                }
+                if input[1] != 0 {
+                    return Err(HootError::SocksRefused(input[1]));
+                }
+
+                // The address the proxy bound, which we skip.
+                let addr_len = match input[3] {
+                    1 => 4,
+                    4 => 16,
+                    3 if input.len() > 4 => 1 + input[4] as usize,
+                    3 => return Ok(0),
+                    _ => return Err(HootError::SocksVersion),
+                };
+
+                let len = 4 + addr_len + 2;
+                if input.len() < len {
+                    return Ok(0);
+                }
+
+                trace!("SOCKS5 connected to {:?}:{}", self.target, self.port);
+                self.step = Step::Connected;
+                Ok(len)
+            }
+            _ => Ok(0),
+        }
+    }
+
+    /// Whether the proxy is connected to the target.
+    pub fn is_connected(&self) -> bool {
+        self.step == Step::Connected
+    }
+}
+
+fn check_len(s: &str) -> Result<()> {
+    if s.len() > 255 {
+        return Err(HootError::SocksTarget);
+    }
+    Ok(())
+}
+
+fn write(buf: &mut [u8], parts: &[&[u8]]) -> Result<usize> {
+    let mut n = 0;
+    for part in parts {
+        let to = buf
+            .get_mut(n..n + part.len())
+            .ok_or(HootError::OutputOverflow)?;
+        to.copy_from_slice(part);
+        n += part.len();
+    }
+    Ok(n)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_socks5_address() -> Result<()> {
+        let mut buf = [0; 64];
+
+        let mut socks = Socks5::new(SocksTarget::V4([10, 0, 0, 1]), 80)?;
+        assert_eq!(socks.output(&mut buf)?, &[5, 1, 0]);
+        assert_eq!(socks.input(&[5])?, 0);
+        assert_eq!(socks.input(&[5, 0])?, 2);
+        assert_eq!(socks.output(&mut buf)?, &[5, 1, 0, 1, 10, 0, 0, 1, 0, 80]);
+
+        // Bound to a domain, followed by data from the target.
+        let reply = b"\x05\x00\x00\x03\x05proxy\x00\x50HTTP/1.1";
+        assert_eq!(socks.input(&reply[..9])?, 0);
+        assert_eq!(socks.input(reply)?, 12);
+        assert!(socks.is_connected());
+        assert!(socks.output(&mut buf)?.is_empty());
+
+        let mut ip = [0; 16];
+        ip[15] = 1;
+        let mut socks = Socks5::new(SocksTarget::V6(ip), 443)?;
+        socks.output(&mut buf)?;
+        socks.input(&[5, 0])?;
+        let out = socks.output(&mut buf)?;
+        assert_eq!(&out[..4], &[5, 1, 0, 4]);
+        assert_eq!(&out[4..], b"\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x01\x01\xbb");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_socks5_errors() -> Result<()> {
+        let mut buf = [0; 64];
+        let target = SocksTarget::Domain("a.test");
+
+        let long = "a".repeat(256);
+        let err = Socks5::new(SocksTarget::Domain(&long), 80).err();
+        assert_eq!(err, Some(HootError::SocksTarget));
+        let err = Socks5::new(target, 80)?.auth("user", &long).err();
+        assert_eq!(err, Some(HootError::SocksTarget));
+
+        let mut socks = Socks5::new(target, 80)?;
+        socks.output(&mut buf)?;
+        assert_eq!(socks.input(&[4, 90]), Err(HootError::SocksVersion));
+
+        // Username and password wasn't offered.
+        let mut socks = Socks5::new(target, 80)?;
+        socks.output(&mut buf)?;
+        assert_eq!(socks.input(&[5, 2]), Err(HootError::SocksAuth));
+
+        let mut socks = Socks5::new(target, 80)?.auth("user", "wrong")?;
+        socks.output(&mut buf)?;
+        assert_eq!(socks.input(&[5, 0xff]), Err(HootError::SocksAuth));
+
+        let mut socks = Socks5::new(target, 80)?.auth("user", "wrong")?;
+        socks.output(&mut buf)?;
+        socks.input(&[5, 2])?;
+        socks.output(&mut buf)?;
+        assert_eq!(socks.input(&[1, 1]), Err(HootError::SocksAuth));
+
+        let mut socks = Socks5::new(target, 80)?;
+        socks.output(&mut buf)?;
+        socks.input(&[5, 0])?;
+        assert_eq!(socks.output(&mut buf[..4]), Err(HootError::OutputOverflow));
+        socks.output(&mut buf)?;
+        // Connection refused.
+        assert_eq!(socks.input(&[5, 5, 0, 1]), Err(HootError::SocksRefused(5)));
+        assert!(!socks.is_connected());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,648 @@
+//! Content codings of bodies
+//!
+//! hoot doesn't undo the `Content-Encoding` of a body, the data read is as it was sent.
+//! The coding is found with `content_coding()` on a response, or request, once the
+//! headers are read.
+//!
+//! With the `gzip` or `brotli` features, a [`Decoder`] decodes the body data read. Like
+//! `read_body()`, it decodes from an input to an output buffer. Input it didn't use
+//! must be given again, along with more.
+//!
+//! ```
+//! # #[cfg(feature = "gzip")] {
+//! use hoot::coding::{ContentCoding, Decoder};
+//!
+//! // "hello", as gzip.
+//! let body = [
+//!     0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9,
+//!     0xc9, 0x07, 0x00, 0x86, 0xa6, 0x10, 0x36, 0x05, 0x00, 0x00, 0x00,
+//! ];
+//!
+//! let mut decoder = Decoder::new(ContentCoding::Gzip)?;
+//! let mut buf = [0; 100];
+//!
+//! let part = decoder.decode(&body, &mut buf)?;
+//! assert_eq!(part.input_used(), body.len());
+//! assert_eq!(part.data(), b"hello");
+//! assert!(part.is_finished());
+//! # }
+//! # Ok::<(), hoot::HootError>(())
+//! ```
+
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+use core::ops::Deref;
+
+use crate::ascii::eq_ignore_ascii;
+use crate::{Header, HootError, Result};
+
+/// A content coding of a body, from the `Content-Encoding` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentCoding {
+    /// `gzip` or `x-gzip`
+    Gzip,
+    /// `deflate`, which is zlib, or sometimes raw deflate
+    Deflate,
+    /// `br`
+    Brotli,
+    /// `zstd`
+    Zstd,
+    /// `compress` or `x-compress`
+    Compress,
+}
+
+impl ContentCoding {
+    /// The coding of `name`, if known.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let c = if eq_ignore_ascii(name, "gzip") || eq_ignore_ascii(name, "x-gzip") {
+            ContentCoding::Gzip
+        } else if eq_ignore_ascii(name, "deflate") {
+            ContentCoding::Deflate
+        } else if eq_ignore_ascii(name, "br") {
+            ContentCoding::Brotli
+        } else if eq_ignore_ascii(name, "zstd") {
+            ContentCoding::Zstd
+        } else if eq_ignore_ascii(name, "compress") || eq_ignore_ascii(name, "x-compress") {
+            ContentCoding::Compress
+        } else {
+            return None;
+        };
+        Some(c)
+    }
+
+    /// The name used in `Content-Encoding` and `Accept-Encoding`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate",
+            ContentCoding::Brotli => "br",
+            ContentCoding::Zstd => "zstd",
+            ContentCoding::Compress => "compress",
+        }
+    }
+}
+
+/// The content coding from the `Content-Encoding` headers, if any.
+///
+/// `identity` is no coding. A coding hoot doesn't know, and more than one, are errors.
+pub(crate) fn content_coding(headers: &[Header<'_>]) -> Option<Result<ContentCoding>> {
+    let mut coding = None;
+    for h in headers {
+        add_content_coding(&mut coding, h.name(), h.try_value().unwrap_or(""));
+    }
+    coding
+}
+
+/// Take note of a header for [`content_coding()`]. Other headers are ignored.
+pub(crate) fn add_content_coding(
+    coding: &mut Option<Result<ContentCoding>>,
+    name: &str,
+    value: &str,
+) {
+    if !eq_ignore_ascii(name, "content-encoding") {
+        return;
+    }
+
+    for name in value.split(',').map(|s| s.trim()) {
+        if name.is_empty() || eq_ignore_ascii(name, "identity") {
+            continue;
+        }
+
+        let c = ContentCoding::from_name(name).ok_or(HootError::UnknownContentCoding);
+
+        *coding = match coding {
+            None => Some(c),
+            Some(_) => Some(Err(HootError::StackedContentCoding)),
+        };
+    }
+}
+
+/// Decoder of a content coding, see the [module docs][self].
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+pub struct Decoder {
+    inner: Inner,
+    finished: bool,
+}
+
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+enum Inner {
+    #[cfg(feature = "gzip")]
+    Gzip(gzip::Gzip),
+    #[cfg(feature = "gzip")]
+    Deflate(Option<flate2::Decompress>),
+    #[cfg(feature = "brotli")]
+    Brotli(brotli::Brotli),
+}
+
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+impl Decoder {
+    /// Decoder of `coding`.
+    ///
+    /// `gzip` and `deflate` need the `gzip` feature, and `br` the `brotli` feature. Other
+    /// codings are [`HootError::UnsupportedContentCoding`].
+    pub fn new(coding: ContentCoding) -> Result<Self> {
+        let inner = match coding {
+            #[cfg(feature = "gzip")]
+            ContentCoding::Gzip => Inner::Gzip(gzip::Gzip::new()),
+            #[cfg(feature = "gzip")]
+            ContentCoding::Deflate => Inner::Deflate(None),
+            #[cfg(feature = "brotli")]
+            ContentCoding::Brotli => Inner::Brotli(brotli::Brotli::new()),
+            _ => return Err(HootError::UnsupportedContentCoding),
+        };
+
+        Ok(Decoder {
+            inner,
+            finished: false,
+        })
+    }
+
+    /// Decode from `src` to `dst`.
+    ///
+    /// Decodes until either the input is used up, the output is full, or the coded data
+    /// ends. Input following the end is not used. Some input can be held back until
+    /// there is more of it.
+    pub fn decode<'b>(&mut self, src: &[u8], dst: &'b mut [u8]) -> Result<Decoded<'b>> {
+        if self.finished {
+            return Ok(Decoded {
+                input_used: 0,
+                data: &[],
+                finished: true,
+            });
+        }
+
+        let (input_used, output, finished) = match &mut self.inner {
+            #[cfg(feature = "gzip")]
+            Inner::Gzip(v) => v.decode(src, dst)?,
+            #[cfg(feature = "gzip")]
+            Inner::Deflate(v) => gzip::deflate(v, src, dst)?,
+            #[cfg(feature = "brotli")]
+            Inner::Brotli(v) => v.decode(src, dst)?,
+        };
+
+        self.finished = finished;
+
+        Ok(Decoded {
+            input_used,
+            data: &dst[..output],
+            finished,
+        })
+    }
+
+    /// Whether the end of the coded data is reached.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// Data decoded by [`Decoder::decode()`].
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+pub struct Decoded<'b> {
+    input_used: usize,
+    data: &'b [u8],
+    finished: bool,
+}
+
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+impl Decoded<'_> {
+    pub fn input_used(&self) -> usize {
+        self.input_used
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.data
+    }
+
+    /// The end of the coded data is reached.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+impl Deref for Decoded<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+#[cfg(feature = "gzip")]
+mod gzip {
+    use flate2::{Crc, Decompress, FlushDecompress, Status};
+
+    use crate::{HootError, Result};
+
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    /// The optional fields of the header, in the order they come.
+    const FIELDS: [(u8, Part); 4] = [
+        (FEXTRA, Part::ExtraLen),
+        (FNAME, Part::Name),
+        (FCOMMENT, Part::Comment),
+        (FHCRC, Part::HeaderCrc),
+    ];
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Part {
+        Header,
+        ExtraLen,
+        Extra(usize),
+        Name,
+        Comment,
+        HeaderCrc,
+        Data,
+        Trailer,
+        Done,
+    }
+
+    /// A gzip member, as of RFC 1952.
+    pub(super) struct Gzip {
+        part: Part,
+        flags: u8,
+        inflate: Decompress,
+        crc: Crc,
+        buf: [u8; 10],
+        pos: usize,
+    }
+
+    impl Gzip {
+        pub(super) fn new() -> Self {
+            Gzip {
+                part: Part::Header,
+                flags: 0,
+                inflate: Decompress::new(false),
+                crc: Crc::new(),
+                buf: [0; 10],
+                pos: 0,
+            }
+        }
+
+        /// The first optional field present from `index` of FIELDS, or else the data.
+        fn next_field(&self, index: usize) -> Part {
+            FIELDS[index..]
+                .iter()
+                .find(|(flag, _)| self.flags & flag != 0)
+                .map(|(_, part)| *part)
+                .unwrap_or(Part::Data)
+        }
+
+        /// Collect `n` bytes in the buffer. Whether they are all there.
+        fn fill(&mut self, src: &[u8], used: &mut usize, n: usize) -> bool {
+            let take = (n - self.pos).min(src.len() - *used);
+            self.buf[self.pos..self.pos + take].copy_from_slice(&src[*used..*used + take]);
+            self.pos += take;
+            *used += take;
+
+            if self.pos < n {
+                return false;
+            }
+            self.pos = 0;
+            true
+        }
+
+        /// Skip a zero terminated field. Whether the end is found.
+        fn skip_zero_terminated(src: &[u8], used: &mut usize) -> bool {
+            match src[*used..].iter().position(|b| *b == 0) {
+                Some(i) => {
+                    *used += i + 1;
+                    true
+                }
+                None => {
+                    *used = src.len();
+                    false
+                }
+            }
+        }
+
+        pub(super) fn decode(
+            &mut self,
+            src: &[u8],
+            dst: &mut [u8],
+        ) -> Result<(usize, usize, bool)> {
+            let mut used = 0;
+            let mut written = 0;
+
+            loop {
+                match self.part {
+                    Part::Header => {
+                        if !self.fill(src, &mut used, 10) {
+                            break;
+                        }
+                        if self.buf[..3] != [0x1f, 0x8b, 8] {
+                            return Err(HootError::InvalidCodedData);
+                        }
+                        self.flags = self.buf[3];
+                        self.part = self.next_field(0);
+                    }
+                    Part::ExtraLen => {
+                        if !self.fill(src, &mut used, 2) {
+                            break;
+                        }
+                        let len = u16::from_le_bytes([self.buf[0], self.buf[1]]);
+                        self.part = Part::Extra(len as usize);
+                    }
+                    Part::Extra(left) => {
+                        let take = left.min(src.len() - used);
+                        used += take;
+                        if take < left {
+                            self.part = Part::Extra(left - take);
+                            break;
+                        }
+                        self.part = self.next_field(1);
+                    }
+                    Part::Name | Part::Comment => {
+                        if !Self::skip_zero_terminated(src, &mut used) {
+                            break;
+                        }
+                        let index = if self.part == Part::Name { 2 } else { 3 };
+                        self.part = self.next_field(index);
+                    }
+                    Part::HeaderCrc => {
+                        if !self.fill(src, &mut used, 2) {
+                            break;
+                        }
+                        self.part = Part::Data;
+                    }
+                    Part::Data => {
+                        let (i, o, end) =
+                            inflate_part(&mut self.inflate, &src[used..], &mut dst[written..])?;
+                        self.crc.update(&dst[written..written + o]);
+                        used += i;
+                        written += o;
+
+                        if end {
+                            self.part = Part::Trailer;
+                        } else if i == 0 && o == 0 {
+                            break;
+                        }
+                    }
+                    Part::Trailer => {
+                        if !self.fill(src, &mut used, 8) {
+                            break;
+                        }
+                        let b = &self.buf;
+                        let crc = u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+                        let size = u32::from_le_bytes([b[4], b[5], b[6], b[7]]);
+                        if crc != self.crc.sum() || size != self.crc.amount() {
+                            return Err(HootError::InvalidCodedData);
+                        }
+                        self.part = Part::Done;
+                    }
+                    Part::Done => break,
+                }
+            }
+
+            Ok((used, written, self.part == Part::Done))
+        }
+    }
+
+    /// Decode `deflate`, which ought to be zlib, but is raw deflate from some servers.
+    pub(super) fn deflate(
+        inflate: &mut Option<Decompress>,
+        src: &[u8],
+        dst: &mut [u8],
+    ) -> Result<(usize, usize, bool)> {
+        let inflate = match inflate {
+            Some(v) => v,
+            None => {
+                // The zlib header tells them apart.
+                if src.len() < 2 {
+                    return Ok((0, 0, false));
+                }
+                let zlib =
+                    src[0] & 0x0f == 8 && (u16::from(src[0]) << 8 | u16::from(src[1])) % 31 == 0;
+                inflate.insert(Decompress::new(zlib))
+            }
+        };
+
+        let mut used = 0;
+        let mut written = 0;
+
+        loop {
+            let (i, o, end) = inflate_part(inflate, &src[used..], &mut dst[written..])?;
+            used += i;
+            written += o;
+
+            if end {
+                return Ok((used, written, true));
+            }
+            if i == 0 && o == 0 {
+                return Ok((used, written, false));
+            }
+        }
+    }
+
+    /// One round of inflating. The input used, output written, and whether it ended.
+    fn inflate_part(
+        inflate: &mut Decompress,
+        src: &[u8],
+        dst: &mut [u8],
+    ) -> Result<(usize, usize, bool)> {
+        let before_in = inflate.total_in();
+        let before_out = inflate.total_out();
+
+        let status = inflate
+            .decompress(src, dst, FlushDecompress::None)
+            .map_err(|_| HootError::InvalidCodedData)?;
+
+        let i = (inflate.total_in() - before_in) as usize;
+        let o = (inflate.total_out() - before_out) as usize;
+
+        Ok((i, o, status == Status::StreamEnd))
+    }
+}
+
+#[cfg(feature = "brotli")]
+mod brotli {
+    use std::boxed::Box;
+
+    use brotli_decompressor::{BrotliDecompressStream, BrotliResult, BrotliState, StandardAlloc};
+
+    use crate::{HootError, Result};
+
+    type State = BrotliState<StandardAlloc, StandardAlloc, StandardAlloc>;
+
+    pub(super) struct Brotli {
+        state: Box<State>,
+        needs_input: bool,
+    }
+
+    impl Brotli {
+        pub(super) fn new() -> Self {
+            let a = StandardAlloc::default();
+            Brotli {
+                state: Box::new(BrotliState::new(a, a, a)),
+                needs_input: false,
+            }
+        }
+
+        pub(super) fn decode(
+            &mut self,
+            src: &[u8],
+            dst: &mut [u8],
+        ) -> Result<(usize, usize, bool)> {
+            // Called without input when waiting for it, the decoder loses track of the
+            // stream.
+            if src.is_empty() && self.needs_input {
+                return Ok((0, 0, false));
+            }
+
+            let mut available_in = src.len();
+            let mut input_offset = 0;
+            let mut available_out = dst.len();
+            let mut output_offset = 0;
+            let mut total_out = 0;
+
+            let result = BrotliDecompressStream(
+                &mut available_in,
+                &mut input_offset,
+                src,
+                &mut available_out,
+                &mut output_offset,
+                dst,
+                &mut total_out,
+                &mut self.state,
+            );
+
+            self.needs_input = matches!(result, BrotliResult::NeedsMoreInput);
+
+            let finished = match result {
+                BrotliResult::ResultFailure => return Err(HootError::InvalidCodedData),
+                BrotliResult::ResultSuccess => true,
+                BrotliResult::NeedsMoreInput | BrotliResult::NeedsMoreOutput => false,
+            };
+
+            Ok((input_offset, output_offset, finished))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    fn coding(values: &[&str]) -> Option<Result<ContentCoding>> {
+        let mut coding = None;
+        add_content_coding(&mut coding, "content-type", "text/plain");
+        for v in values {
+            add_content_coding(&mut coding, "Content-Encoding", v);
+        }
+        coding
+    }
+
+    #[test]
+    fn test_content_coding() {
+        assert_eq!(coding(&[]), None);
+        assert_eq!(coding(&["identity"]), None);
+        assert_eq!(coding(&["GZIP"]), Some(Ok(ContentCoding::Gzip)));
+        assert_eq!(coding(&[" br "]), Some(Ok(ContentCoding::Brotli)));
+        assert_eq!(
+            coding(&["magic"]),
+            Some(Err(HootError::UnknownContentCoding))
+        );
+        assert_eq!(
+            coding(&["gzip", "br"]),
+            Some(Err(HootError::StackedContentCoding))
+        );
+        assert_eq!(
+            coding(&["deflate, gzip"]),
+            Some(Err(HootError::StackedContentCoding))
+        );
+    }
+
+    /// Decode giving the input a byte at a time, to a small output buffer.
+    #[cfg(any(feature = "gzip", feature = "brotli"))]
+    fn decode_fragmented(coding: ContentCoding, input: &[u8]) -> Result<std::vec::Vec<u8>> {
+        let mut decoder = Decoder::new(coding)?;
+        let mut out = std::vec::Vec::new();
+        let mut buf = [0; 3];
+        let mut pos = 0;
+        let mut fed = 0;
+
+        while !decoder.is_finished() {
+            let part = decoder.decode(&input[pos..fed], &mut buf)?;
+            pos += part.input_used();
+            out.extend_from_slice(part.data());
+
+            if part.input_used() == 0 && part.data().is_empty() && !part.is_finished() {
+                assert!(fed < input.len(), "input ended before coded data");
+                fed += 1;
+            }
+        }
+
+        assert_eq!(pos, input.len());
+        Ok(out)
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_decode_gzip() -> Result<()> {
+        let hello = b"hello hello hello";
+
+        // With the file name field in the header.
+        let gzip = [
+            31, 139, 8, 8, 0, 0, 0, 0, 2, 255, 97, 46, 116, 120, 116, 0, 203, 72, 205, 201, 201,
+            87, 200, 64, 144, 0, 128, 136, 249, 229, 17, 0, 0, 0,
+        ];
+        assert_eq!(decode_fragmented(ContentCoding::Gzip, &gzip)?, hello);
+
+        let zlib = [
+            120, 156, 203, 72, 205, 201, 201, 87, 200, 64, 144, 0, 58, 46, 6, 125,
+        ];
+        assert_eq!(decode_fragmented(ContentCoding::Deflate, &zlib)?, hello);
+
+        let raw = [203, 72, 205, 201, 201, 87, 200, 64, 144, 0];
+        assert_eq!(decode_fragmented(ContentCoding::Deflate, &raw)?, hello);
+
+        let mut bad_crc = gzip;
+        bad_crc[27] ^= 1;
+        assert_eq!(
+            decode_fragmented(ContentCoding::Gzip, &bad_crc),
+            Err(HootError::InvalidCodedData)
+        );
+        assert_eq!(
+            decode_fragmented(ContentCoding::Gzip, &zlib),
+            Err(HootError::InvalidCodedData)
+        );
+        assert_eq!(
+            Decoder::new(ContentCoding::Zstd).err(),
+            Some(HootError::UnsupportedContentCoding)
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn test_decode_brotli() -> Result<()> {
+        let brotli = [0x0b, 0x02, 0x80, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x03];
+        assert_eq!(decode_fragmented(ContentCoding::Brotli, &brotli)?, b"hello");
+
+        // All input at once, with output left in the decoder.
+        let mut decoder = Decoder::new(ContentCoding::Brotli)?;
+        let mut buf = [0; 2];
+        let part = decoder.decode(&brotli, &mut buf)?;
+        assert_eq!(part.data(), b"he");
+        let used = part.input_used();
+        let part = decoder.decode(&brotli[used..], &mut buf)?;
+        assert_eq!(part.data(), b"ll");
+        let used = used + part.input_used();
+        let part = decoder.decode(&brotli[used..], &mut buf)?;
+        assert_eq!(part.data(), b"o");
+        assert!(part.is_finished());
+
+        // Input after the end is not used.
+        let mut decoder = Decoder::new(ContentCoding::Brotli)?;
+        let input = [&brotli[..], b"after"].concat();
+        let mut buf = [0; 10];
+        let part = decoder.decode(&input, &mut buf)?;
+        assert_eq!(part.input_used(), brotli.len());
+        assert!(part.is_finished());
+
+        Ok(())
+    }
+}
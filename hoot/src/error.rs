@@ -2,6 +2,8 @@ use core::fmt;
 use core::num::ParseIntError;
 use core::str::Utf8Error;
 
+use crate::parse_impl;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum HootError {
@@ -39,16 +41,24 @@ pub enum HootError {
     ForbiddenTrailer,
 
     /// Attempt to send more content than declared in the `Content-Length` header.
-    SentMoreThanContentLength,
+    ///
+    /// Holds the expected length and the length that would have been sent.
+    SentMoreThanContentLength(u64, u64),
 
     /// Attempt to send less content than declared in the `Content-Length` header.
-    SentLessThanContentLength,
+    ///
+    /// Holds the expected length and the length actually sent.
+    SentLessThanContentLength(u64, u64),
 
-    /// Attempt to send more content than declared in the `Content-Length` header.
-    RecvMoreThanContentLength,
+    /// Received more content than declared in the `Content-Length` header.
+    ///
+    /// Holds the expected length and the length that would have been received.
+    RecvMoreThanContentLength(u64, u64),
 
-    /// Attempt to send less content than declared in the `Content-Length` header.
-    RecvLessThanContentLength,
+    /// Received less content than declared in the `Content-Length` header.
+    ///
+    /// Holds the expected length and the length actually received.
+    RecvLessThanContentLength(u64, u64),
 
     /// Failed to read bytes as &str
     ConvertBytesToStr,
@@ -62,18 +72,78 @@ pub enum HootError {
     /// Failed to parse an integer. This can happen if a Content-Length header contains bogus.
     ParseIntError,
 
-    /// More than one Content-Length header in response.
+    /// More than one Content-Length header.
     DuplicateContentLength,
 
+    /// Both Content-Length and Transfer-Encoding headers, which could be read differently
+    /// by another recipient.
+    ContentLengthAndTransferEncoding,
+
+    /// A header value continued on the next line with obsolete line folding.
+    ObsFold,
+
+    /// A received message head is larger than the configured limits.
+    HeaderOverflow,
+
     /// Incoming chunked encoding is incorrect.
     IncorrectChunk,
 
+    /// Incoming `Transfer-Encoding` has a coding after `chunked`, or `chunked` twice.
+    ChunkedNotLast,
+
+    /// Incoming `Transfer-Encoding` has an unknown coding.
+    UnknownTransferCoding,
+
+    /// Incoming `Transfer-Encoding` has more than one coding besides `chunked`.
+    StackedTransferCoding,
+
+    /// Incoming request `Transfer-Encoding` without `chunked` as the last coding, or in an
+    /// HTTP/1.0 request. The body can't be framed, and a server should answer with 400 and
+    /// close the connection.
+    UnframedTransferEncoding,
+
     /// Invalid byte where token is required.
     Token,
 
     /// Invalid byte in HTTP version.
     Version,
 
+    /// HTTP/1.1 request without a `Host` header.
+    MissingHost,
+
+    /// `CONNECT` request target is not `host:port`.
+    ConnectTarget,
+
+    /// An authority, or the URL holding it, without a valid host and port.
+    InvalidAuthority,
+
+    /// The response did not establish a tunnel.
+    NotTunnel,
+
+    /// The response did not upgrade the connection.
+    NotUpgraded,
+
+    /// Status not possible for an interim response. Only 1xx, except 101, are interim, and
+    /// they can't be sent to HTTP/1.0 clients.
+    InterimStatus,
+
+    /// The pipeline already holds as many requests as it can.
+    PipelineFull,
+
+    /// Request target `*` for another method than `OPTIONS`.
+    AsteriskForm,
+
+    /// Request target longer than the configured limit. A server should answer
+    /// with 414.
+    TargetTooLong,
+
+    /// Request target not in a form allowed for the method, i.e. neither a path nor
+    /// an absolute URI. A server should answer with 400.
+    TargetForm,
+
+    /// The connection can't be reused for another request.
+    ConnectionNotReusable,
+
     /// Did not read body to finish.
     BodyNotFinished,
 
@@ -83,6 +153,27 @@ pub enum HootError {
     /// Failed to get a `TryInto<u64>`.
     NotU64,
 
+    /// A streamed header was not finished, or was continued with a different
+    /// amount of bytes than was left to write.
+    StreamedHeader,
+
+    /// The proxy did not answer with SOCKS version 5.
+    #[cfg(feature = "socks")]
+    SocksVersion,
+
+    /// The proxy took none of the authentication methods offered, or refused the
+    /// username and password.
+    #[cfg(feature = "socks")]
+    SocksAuth,
+
+    /// SOCKS5 target host, username or password longer than 255 bytes.
+    #[cfg(feature = "socks")]
+    SocksTarget,
+
+    /// The proxy failed to connect to the target. Holds the reply code.
+    #[cfg(feature = "socks")]
+    SocksRefused(u8),
+
     /// A TryFrom conversion of a [`server::RequestAttempt`] that was not complete.
     #[cfg(feature = "http_crate")]
     IncompleteRequestAttempt,
@@ -94,6 +185,24 @@ pub enum HootError {
     /// A response status code failed to convert to the http crate `StatusCode`.
     #[cfg(feature = "http_crate")]
     HttpRefusedStatusCode,
+
+    /// A `Content-Encoding` coding hoot doesn't know.
+    UnknownContentCoding,
+
+    /// More than one `Content-Encoding` coding.
+    StackedContentCoding,
+
+    /// A content coding without a decoder, or without the feature of its decoder.
+    #[cfg(any(feature = "gzip", feature = "brotli"))]
+    UnsupportedContentCoding,
+
+    /// Corrupt data of a content coding.
+    #[cfg(any(feature = "gzip", feature = "brotli"))]
+    InvalidCodedData,
+
+    /// A HAR entry without the head of the request.
+    #[cfg(feature = "har")]
+    IncompleteHarRequest,
 }
 
 pub(crate) static OVERFLOW: Result<()> = Err(HootError::OutputOverflow);
@@ -112,16 +221,16 @@ impl From<ParseIntError> for HootError {
     }
 }
 
-impl From<httparse::Error> for HootError {
-    fn from(value: httparse::Error) -> Self {
+impl From<parse_impl::Error> for HootError {
+    fn from(value: parse_impl::Error) -> Self {
         match value {
-            httparse::Error::HeaderName => HootError::HeaderName,
-            httparse::Error::HeaderValue => HootError::HeaderValue,
-            httparse::Error::NewLine => HootError::NewLine,
-            httparse::Error::Status => HootError::Status,
-            httparse::Error::Token => HootError::Token,
-            httparse::Error::TooManyHeaders => HootError::TooManyHeaders,
-            httparse::Error::Version => HootError::Version,
+            parse_impl::Error::HeaderName => HootError::HeaderName,
+            parse_impl::Error::HeaderValue => HootError::HeaderValue,
+            parse_impl::Error::NewLine => HootError::NewLine,
+            parse_impl::Error::Status => HootError::Status,
+            parse_impl::Error::Token => HootError::Token,
+            parse_impl::Error::TooManyHeaders => HootError::TooManyHeaders,
+            parse_impl::Error::Version => HootError::Version,
         }
     }
 }
@@ -141,30 +250,80 @@ impl fmt::Display for HootError {
             ForbiddenBodyHeader => "forbidden header name",
             ForbiddenHttp11Header => "forbidden header for http1.1",
             ForbiddenTrailer => "forbidden trailer",
-            SentMoreThanContentLength => "sent more than content-length",
-            SentLessThanContentLength => "sent less than content-length",
-            RecvMoreThanContentLength => "received more than content-length",
-            RecvLessThanContentLength => "received less than content-length",
+            SentMoreThanContentLength(e, a) => return length_err(f, "sent more", *e, *a),
+            SentLessThanContentLength(e, a) => return length_err(f, "sent less", *e, *a),
+            RecvMoreThanContentLength(e, a) => return length_err(f, "received more", *e, *a),
+            RecvLessThanContentLength(e, a) => return length_err(f, "received less", *e, *a),
             ConvertBytesToStr => "failed to convert &[u8] to &str",
             HttpVersionMismatch => "http version mismatch",
             StatusIsNotComplete => "called complete() before entire status read",
             ParseIntError => "failed to parse integer",
             DuplicateContentLength => "multiple content-length headers",
+            ContentLengthAndTransferEncoding => "both content-length and transfer-encoding",
+            ObsFold => "obsolete line folding in header",
+            HeaderOverflow => "message head larger than limits",
             IncorrectChunk => "incorrect incoming body chunk",
+            ChunkedNotLast => "transfer-encoding chunked is not the last coding",
+            UnknownTransferCoding => "unknown transfer-encoding coding",
+            StackedTransferCoding => "more than one transfer-encoding coding besides chunked",
+            UnframedTransferEncoding => "request transfer-encoding is not chunked",
+            MissingHost => "http1.1 request without host header",
+            ConnectTarget => "connect target is not host:port",
+            InvalidAuthority => "invalid authority in url",
+            NotTunnel => "response did not establish a tunnel",
+            NotUpgraded => "response did not upgrade the connection",
+            InterimStatus => "status not allowed for interim response",
+            PipelineFull => "pipeline is full",
+            AsteriskForm => "request target * for other method than OPTIONS",
+            TargetTooLong => "request target longer than limit",
+            TargetForm => "request target form not allowed for method",
+            ConnectionNotReusable => "connection is not reusable",
             BodyNotFinished => "called finish() before body was finished",
             UnknownMethod => "unknown incoming method",
             NotU64 => "not possible to convert to u64",
+            StreamedHeader => "streamed header not finished",
+            #[cfg(feature = "socks")]
+            SocksVersion => "proxy did not answer with socks5",
+            #[cfg(feature = "socks")]
+            SocksAuth => "socks5 proxy refused authentication",
+            #[cfg(feature = "socks")]
+            SocksTarget => "socks5 target or credentials longer than 255 bytes",
+            #[cfg(feature = "socks")]
+            SocksRefused(code) => return write!(f, "socks5 proxy refused with reply {}", code),
             #[cfg(feature = "http_crate")]
             IncompleteRequestAttempt => "not a complete request",
             #[cfg(feature = "http_crate")]
             IncompleteResponseAttempt => "not a complete response",
             #[cfg(feature = "http_crate")]
             HttpRefusedStatusCode => "response status code not possible for http crate",
+            UnknownContentCoding => "unknown content-encoding coding",
+            StackedContentCoding => "more than one content-encoding coding",
+            #[cfg(any(feature = "gzip", feature = "brotli"))]
+            UnsupportedContentCoding => "no decoder for content-encoding coding",
+            #[cfg(any(feature = "gzip", feature = "brotli"))]
+            InvalidCodedData => "invalid content-encoding data",
+            #[cfg(feature = "har")]
+            IncompleteHarRequest => "HAR entry without a complete request",
         };
 
         write!(f, "{}", s)
     }
 }
 
+fn length_err(f: &mut fmt::Formatter<'_>, what: &str, expected: u64, actual: u64) -> fmt::Result {
+    write!(
+        f,
+        "{} than content-length (expected {}, got {})",
+        what, expected, actual
+    )
+}
+
 #[cfg(feature = "std")]
 impl std::error::Error for HootError {}
+
+#[cfg(feature = "std")]
+impl From<HootError> for std::io::Error {
+    fn from(e: HootError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    }
+}
@@ -0,0 +1,442 @@
+//! Pull based event parsing of requests and responses
+//!
+//! An alternative to the type state API in [`client`][crate::client] and
+//! [`server`][crate::server] for consumers that only want to observe the
+//! message as it passes by, such as proxies and recorders. No header buffer
+//! is needed, every event borrows from the input.
+//!
+//! # Example
+//!
+//! ```
+//! use hoot::event::{Event, EventParser};
+//! use hoot::Method;
+//!
+//! const INPUT: &[u8] = b"HTTP/1.1 200 OK\r\n\
+//!     Transfer-Encoding: chunked\r\n\
+//!     \r\n\
+//!     5\r\nhello\r\n0\r\n\r\n";
+//!
+//! // Parsing a response requires knowing the request method.
+//! let mut parser = EventParser::response(Method::GET);
+//!
+//! let mut input = INPUT;
+//!
+//! loop {
+//!     let (input_used, event) = parser.next_event(input)?;
+//!
+//!     // Advance the input with the amount used.
+//!     input = &input[input_used..];
+//!
+//!     match event {
+//!         Some(Event::StatusLine(status)) => assert_eq!(status.code(), 200),
+//!         Some(Event::Header(header)) => assert_eq!(header.name(), "Transfer-Encoding"),
+//!         Some(Event::HeadersEnd) => {}
+//!         Some(Event::BodyChunk(data)) => assert_eq!(data, b"hello"),
+//!         Some(Event::End) => break,
+//!         // Input used without an event, such as chunk sizes, or more input is
+//!         // needed, which doesn't happen here since the input is complete.
+//!         None => assert!(input_used > 0),
+//!         _ => {}
+//!     }
+//! }
+//!
+//! assert!(parser.is_ended());
+//! # Ok::<(), hoot::HootError>(())
+//! ```
+
+use core::fmt;
+
+use crate::body::Framing;
+use crate::chunk::Dechunker;
+use crate::client::Status;
+use crate::parser::{find_crlf, parse_header_line, parse_request_line, parse_status_line};
+use crate::server::Line;
+use crate::util::LengthChecker;
+use crate::{Header, HootError, HttpVersion, Method, RecvBodyMode, Result};
+
+/// An event parsed from the input.
+pub enum Event<'a> {
+    /// The first line of a request.
+    RequestLine(Line<'a>),
+    /// The first line of a response.
+    StatusLine(Status<'a>),
+    /// A header.
+    Header(Header<'a>),
+    /// The empty line ending the headers.
+    HeadersEnd,
+    /// Body data. For chunked bodies this is the decoded chunk data.
+    BodyChunk(&'a [u8]),
+    /// A trailer after a chunked body.
+    Trailer(Header<'a>),
+    /// The message is complete.
+    End,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Request,
+    Response(Method),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Line,
+    Headers,
+    Body,
+    Trailers,
+    Ended,
+}
+
+/// Parser producing [`Event`] from request or response input.
+pub struct EventParser {
+    kind: Kind,
+    phase: Phase,
+    version: Option<HttpVersion>,
+    method: Option<Method>,
+    status: u16,
+    framing: Framing,
+    body_mode: Option<RecvBodyMode>,
+    checker: Option<LengthChecker>,
+    dechunker: Dechunker,
+}
+
+impl EventParser {
+    /// Create a parser for an incoming request.
+    pub fn request() -> Self {
+        Self::new(Kind::Request)
+    }
+
+    /// Create a parser for an incoming response.
+    ///
+    /// The method of the request is needed to decide whether the response has a body.
+    pub fn response(method: Method) -> Self {
+        Self::new(Kind::Response(method))
+    }
+
+    fn new(kind: Kind) -> Self {
+        EventParser {
+            kind,
+            phase: Phase::Line,
+            version: None,
+            method: None,
+            status: 0,
+            framing: Framing::default(),
+            body_mode: None,
+            checker: None,
+            dechunker: Dechunker::new(),
+        }
+    }
+
+    /// Parse the next event from the input.
+    ///
+    /// Returns the amount of input used, which the caller must discard before the
+    /// next call, and the event. The event is `None` when more input is needed.
+    /// Input can be used without producing an event, such as chunk framing.
+    ///
+    /// Close delimited bodies never produce [`Event::End`], since they are ended
+    /// by the remote closing the connection.
+    pub fn next_event<'a>(&mut self, input: &'a [u8]) -> Result<(usize, Option<Event<'a>>)> {
+        match self.phase {
+            Phase::Line => self.read_line(input),
+            Phase::Headers => self.read_header(input),
+            Phase::Body => self.read_body(input),
+            Phase::Trailers => self.read_trailer(input),
+            Phase::Ended => Ok((0, None)),
+        }
+    }
+
+    /// Whether the entire message has been parsed.
+    pub fn is_ended(&self) -> bool {
+        self.phase == Phase::Ended
+    }
+
+    /// The body mode. Available once [`Event::HeadersEnd`] has been produced.
+    pub fn body_mode(&self) -> Option<RecvBodyMode> {
+        self.body_mode
+    }
+
+    fn read_line<'a>(&mut self, input: &'a [u8]) -> Result<(usize, Option<Event<'a>>)> {
+        let line = match next_line(input) {
+            Some(v) => v,
+            None => return Ok((0, None)),
+        };
+
+        let event = match self.kind {
+            Kind::Request => {
+                let line = parse_request_line(line)?;
+                trace!("Event request line: {:?}", line);
+                self.method = Some(line.method());
+                self.version = Some(line.version());
+                Event::RequestLine(line)
+            }
+            Kind::Response(method) => {
+                let status = parse_status_line(line)?;
+                trace!("Event status line: {:?}", status);
+                self.method = Some(method);
+                self.version = Some(status.version());
+                self.status = status.code();
+                Event::StatusLine(status)
+            }
+        };
+
+        self.phase = Phase::Headers;
+
+        Ok((line.len(), Some(event)))
+    }
+
+    fn read_header<'a>(&mut self, input: &'a [u8]) -> Result<(usize, Option<Event<'a>>)> {
+        let line = match next_line(input) {
+            Some(v) => v,
+            None => return Ok((0, None)),
+        };
+
+        if line.len() == 2 {
+            self.headers_end()?;
+            return Ok((2, Some(Event::HeadersEnd)));
+        }
+
+        let header = parse_header_line(line)?;
+        trace!("Event header: {:?}", header);
+
+        if let Some(value) = header.try_value() {
+            self.framing.header(header.name(), value)?;
+        }
+
+        Ok((line.len(), Some(Event::Header(header))))
+    }
+
+    fn headers_end(&mut self) -> Result<()> {
+        // Both are set when reading the line.
+        let http10 = self.version == Some(HttpVersion::Http10);
+        let method = self.method.unwrap();
+
+        let mode = match self.kind {
+            Kind::Request => {
+                self.framing.check_request(http10)?;
+                RecvBodyMode::for_request_framing(http10, method, &self.framing)
+            }
+            Kind::Response(_) => {
+                RecvBodyMode::for_response_framing(http10, method, self.status, &self.framing)
+            }
+        };
+        trace!("Event body mode: {:?}", mode);
+
+        if let RecvBodyMode::LengthDelimited(len) = mode {
+            self.checker = Some(LengthChecker::new(len));
+        }
+
+        self.body_mode = Some(mode);
+        self.phase = Phase::Body;
+
+        Ok(())
+    }
+
+    fn read_body<'a>(&mut self, input: &'a [u8]) -> Result<(usize, Option<Event<'a>>)> {
+        // unwrap is ok because we can't be in Phase::Body without setting it.
+        match self.body_mode.unwrap() {
+            RecvBodyMode::LengthDelimited(_) => {
+                // unwrap is ok because LengthDelimited always has a checker.
+                let checker = self.checker.as_mut().unwrap();
+
+                if checker.complete() {
+                    self.phase = Phase::Ended;
+                    return Ok((0, Some(Event::End)));
+                }
+
+                let amount = checker.left_to_read().min(input.len());
+                if amount == 0 {
+                    return Ok((0, None));
+                }
+
+                checker.append(amount, HootError::RecvMoreThanContentLength)?;
+
+                Ok((amount, Some(Event::BodyChunk(&input[..amount]))))
+            }
+            RecvBodyMode::Chunked => {
+                let (input_used, data) = self.dechunker.parse_input_borrowed(input)?;
+
+                if self.dechunker.is_ended() {
+                    self.phase = Phase::Trailers;
+                }
+
+                if !data.is_empty() {
+                    return Ok((input_used, Some(Event::BodyChunk(data))));
+                }
+
+                if self.phase == Phase::Trailers {
+                    // Continue straight into the trailers with the rest of the input.
+                    let (more_used, event) = self.read_trailer(&input[input_used..])?;
+                    return Ok((input_used + more_used, event));
+                }
+
+                Ok((input_used, None))
+            }
+            RecvBodyMode::CloseDelimited => {
+                if input.is_empty() {
+                    return Ok((0, None));
+                }
+
+                Ok((input.len(), Some(Event::BodyChunk(input))))
+            }
+        }
+    }
+
+    fn read_trailer<'a>(&mut self, input: &'a [u8]) -> Result<(usize, Option<Event<'a>>)> {
+        let line = match next_line(input) {
+            Some(v) => v,
+            None => return Ok((0, None)),
+        };
+
+        if line.len() == 2 {
+            self.phase = Phase::Ended;
+            return Ok((2, Some(Event::End)));
+        }
+
+        let header = parse_header_line(line)?;
+        trace!("Event trailer: {:?}", header);
+
+        Ok((line.len(), Some(Event::Trailer(header))))
+    }
+}
+
+/// The next line including the CRLF, if there is one.
+fn next_line(input: &[u8]) -> Option<&[u8]> {
+    let i = find_crlf(input)?;
+    Some(&input[..i + 2])
+}
+
+impl fmt::Debug for Event<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RequestLine(v) => f.debug_tuple("RequestLine").field(v).finish(),
+            Self::StatusLine(v) => f.debug_tuple("StatusLine").field(v).finish(),
+            Self::Header(v) => f.debug_tuple("Header").field(v).finish(),
+            Self::HeadersEnd => write!(f, "HeadersEnd"),
+            Self::BodyChunk(v) => f.debug_tuple("BodyChunk").field(&v.len()).finish(),
+            Self::Trailer(v) => f.debug_tuple("Trailer").field(v).finish(),
+            Self::End => write!(f, "End"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_request_events() -> Result<()> {
+        let mut p = EventParser::request();
+        let input = b"POST /path HTTP/1.1\r\nHost: foo\r\nContent-Length: 3\r\n\r\nabc";
+
+        let (n, e) = p.next_event(input)?;
+        assert_eq!(n, 21);
+        assert!(matches!(e, Some(Event::RequestLine(l)) if l.path() == "/path"));
+
+        let (n, e) = p.next_event(&input[21..])?;
+        assert_eq!(n, 11);
+        assert!(matches!(e, Some(Event::Header(h)) if h.name() == "Host"));
+
+        let (n, e) = p.next_event(&input[32..])?;
+        assert_eq!(n, 19);
+        assert!(matches!(e, Some(Event::Header(h)) if h.value() == "3"));
+
+        let (n, e) = p.next_event(&input[51..])?;
+        assert_eq!(n, 2);
+        assert!(matches!(e, Some(Event::HeadersEnd)));
+        assert_eq!(p.body_mode(), Some(RecvBodyMode::LengthDelimited(3)));
+
+        let (n, e) = p.next_event(&input[53..55])?;
+        assert_eq!(n, 2);
+        assert!(matches!(e, Some(Event::BodyChunk(b"ab"))));
+
+        let (n, e) = p.next_event(&input[55..])?;
+        assert_eq!(n, 1);
+        assert!(matches!(e, Some(Event::BodyChunk(b"c"))));
+
+        let (n, e) = p.next_event(&[])?;
+        assert_eq!(n, 0);
+        assert!(matches!(e, Some(Event::End)));
+        assert!(p.is_ended());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unframed_request() {
+        let inputs: [&[u8]; 2] = [
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip\r\n\r\n",
+            b"POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n",
+        ];
+
+        for mut input in inputs {
+            let mut p = EventParser::request();
+            let err = loop {
+                match p.next_event(input) {
+                    Ok((n, _)) => input = &input[n..],
+                    Err(e) => break e,
+                }
+            };
+            assert_eq!(err, HootError::UnframedTransferEncoding);
+        }
+    }
+
+    #[test]
+    fn test_partial_line() -> Result<()> {
+        let mut p = EventParser::request();
+        assert!(matches!(p.next_event(b"GET /path HT")?, (0, None)));
+        assert!(matches!(p.next_event(b"GET /path HTTP/1.1\r")?, (0, None)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunked_response_with_trailer() -> Result<()> {
+        let mut p = EventParser::response(Method::GET);
+        let mut input: &[u8] = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            3\r\nabc\r\n2\r\nde\r\n0\r\nx-checksum: 42\r\n\r\n";
+
+        let mut body = [0; 5];
+        let mut body_len = 0;
+        let mut trailers = 0;
+
+        loop {
+            let (n, e) = p.next_event(input)?;
+            input = &input[n..];
+
+            match e.unwrap() {
+                Event::BodyChunk(data) => {
+                    body[body_len..body_len + data.len()].copy_from_slice(data);
+                    body_len += data.len();
+                }
+                Event::Trailer(h) => {
+                    assert_eq!(h.name(), "x-checksum");
+                    trailers += 1;
+                }
+                Event::End => break,
+                _ => {}
+            }
+        }
+
+        assert_eq!(&body, b"abcde");
+        assert_eq!(trailers, 1);
+        assert!(input.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_head_response_no_body() -> Result<()> {
+        let mut p = EventParser::response(Method::HEAD);
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n";
+
+        let (n1, _) = p.next_event(input)?;
+        let (n2, _) = p.next_event(&input[n1..])?;
+        let (_, e) = p.next_event(&input[n1 + n2..])?;
+        assert!(matches!(e, Some(Event::HeadersEnd)));
+        assert_eq!(p.body_mode(), Some(RecvBodyMode::LengthDelimited(0)));
+
+        let (_, e) = p.next_event(&[])?;
+        assert!(matches!(e, Some(Event::End)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,626 @@
+//! Export of requests and responses as HAR
+//!
+//! HAR (HTTP Archive) is the JSON format browsers export their network traces in. A
+//! [`HarEntry`] is a [`WireHook`] capturing one request and its response from the bytes
+//! on the wire. Once the response is read, the entry is added to a [`Har`], which is
+//! written out with [`Har::to_json()`].
+//!
+//! hoot has no clock, so the start time and [`Timings`] of an entry are given by the
+//! caller. Bodies are left out unless asked for with [`HarEntry::with_bodies()`].
+//!
+//! With the blocking client in `hoot::io`, the entry is given to `CallWriter::with_hook()`
+//! and `CallReader::with_hook()` in a closure, `|d, p, b| entry.wire(d, p, b)`.
+//!
+//! ```
+//! use std::time::Duration;
+//! use hoot::har::{Har, HarEntry};
+//! use hoot::wire::{Direction, WireHook, WirePart};
+//!
+//! let mut har = Har::new();
+//! let mut entry = HarEntry::new(1_709_164_800_000).with_bodies();
+//!
+//! // As told by the hook.
+//! let request = b"GET / HTTP/1.1\r\nHost: example.test\r\n\r\n";
+//! entry.wire(Direction::Sent, WirePart::Head, request);
+//! entry.wire(Direction::Received, WirePart::Head, b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n");
+//! entry.wire(Direction::Received, WirePart::Body, b"ok");
+//!
+//! entry.timings.wait = Duration::from_millis(20);
+//! har.push(entry)?;
+//!
+//! let json = har.to_json();
+//! assert!(json.contains(r#""url":"http://example.test/""#));
+//! assert!(json.contains(r#""text":"ok""#));
+//! # Ok::<(), hoot::HootError>(())
+//! ```
+
+use std::fmt::Write;
+use std::string::String;
+use std::time::Duration;
+use std::vec::Vec;
+
+use crate::cache::civil_from_days;
+use crate::event::{Event, EventParser};
+use crate::wire::{Direction, WireHook, WirePart};
+use crate::{HootError, HttpVersion, Method, Result};
+
+/// A HAR log, the entries of which are written out as JSON.
+#[derive(Debug, Default)]
+pub struct Har {
+    entries: Vec<String>,
+}
+
+impl Har {
+    pub fn new() -> Self {
+        Har::default()
+    }
+
+    /// Add a captured request and response.
+    ///
+    /// Fails if the captured request can't be parsed. A response that wasn't captured, or
+    /// only in part, is recorded as far as it got, without a status for no response.
+    pub fn push(&mut self, entry: HarEntry) -> Result<()> {
+        let json = entry.to_json()?;
+        self.entries.push(json);
+        Ok(())
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The log as HAR 1.2 JSON.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str(r#"{"log":{"version":"1.2","creator":{"name":"hoot","version":"#);
+        string(&mut out, env!("CARGO_PKG_VERSION"));
+        out.push_str(r#"},"entries":["#);
+        out.push_str(&self.entries.join(","));
+        out.push_str("]}}");
+
+        out
+    }
+}
+
+/// Time spent in each phase of a request, measured by the caller.
+///
+/// The phases that didn't happen, such as connecting over a reused connection, are
+/// `None`. As in HAR, `ssl` is part of `connect`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Timings {
+    /// Waiting for a connection to be available.
+    pub blocked: Option<Duration>,
+    /// Resolving the host name.
+    pub dns: Option<Duration>,
+    /// Making the connection, including any TLS handshake.
+    pub connect: Option<Duration>,
+    /// The TLS handshake.
+    pub ssl: Option<Duration>,
+    /// Sending the request.
+    pub send: Duration,
+    /// Waiting for the first byte of the response.
+    pub wait: Duration,
+    /// Reading the response.
+    pub receive: Duration,
+}
+
+impl Timings {
+    /// Time of the whole request, which HAR has as the time of the entry.
+    pub fn total(&self) -> Duration {
+        let maybe = [self.blocked, self.dns, self.connect];
+        let sum: Duration = maybe.iter().flatten().sum();
+        sum + self.send + self.wait + self.receive
+    }
+}
+
+/// One request and its response, captured as a [`WireHook`].
+///
+/// See the [module docs][self].
+#[derive(Debug)]
+pub struct HarEntry {
+    started: u64,
+    https: bool,
+    bodies: bool,
+    sent: Capture,
+    received: Capture,
+    pub timings: Timings,
+}
+
+#[derive(Debug, Default)]
+struct Capture {
+    bytes: Vec<u8>,
+    head: u64,
+    body: u64,
+}
+
+impl HarEntry {
+    /// New entry for a request started at `started`, milliseconds since the Unix epoch.
+    pub fn new(started: u64) -> Self {
+        HarEntry {
+            started,
+            https: false,
+            bodies: false,
+            sent: Capture::default(),
+            received: Capture::default(),
+            timings: Timings::default(),
+        }
+    }
+
+    /// Keep the bodies, which are otherwise only counted.
+    pub fn with_bodies(mut self) -> Self {
+        self.bodies = true;
+        self
+    }
+
+    /// The request was sent over TLS, for the `https` in the URL.
+    pub fn https(mut self) -> Self {
+        self.https = true;
+        self
+    }
+
+    fn to_json(&self) -> Result<String> {
+        let request = Message::parse(EventParser::request(), &self.sent.bytes)?;
+
+        // Without a request line, there is no request.
+        let method = request.method.ok_or(HootError::IncompleteHarRequest)?;
+
+        let mut response = Message::parse(EventParser::response(method), &self.received.bytes);
+        // Skip past any 100 Continue and other interim responses.
+        while let Ok(r) = &response {
+            if !(100..200).contains(&r.status) || r.used >= self.received.bytes.len() {
+                break;
+            }
+            let rest = &self.received.bytes[r.used..];
+            response = Message::parse(EventParser::response(method), rest);
+        }
+        let response = response.unwrap_or_default();
+
+        let mut out = String::new();
+        let o = &mut out;
+
+        o.push_str(r#"{"startedDateTime":"#);
+        string(o, &iso_date(self.started));
+        let _ = write!(o, r#","time":{}"#, millis(self.timings.total()));
+
+        // Request.
+        o.push_str(r#","request":{"method":"#);
+        string(o, &format!("{:?}", method));
+        o.push_str(r#","url":"#);
+        string(o, &self.url(&request));
+        o.push_str(r#","httpVersion":"#);
+        string(o, &request.version);
+        o.push_str(r#","cookies":[],"headers":"#);
+        headers(o, &request.headers);
+        o.push_str(r#","queryString":"#);
+        query(o, &request.target);
+        if let Some(body) = request.body.as_ref().filter(|b| !b.is_empty()) {
+            o.push_str(r#","postData":{"mimeType":"#);
+            string(o, request.header("content-type").unwrap_or(""));
+            text(o, body);
+            o.push('}');
+        }
+        let _ = write!(
+            o,
+            r#","headersSize":{},"bodySize":{}}}"#,
+            self.sent.head, self.sent.body
+        );
+
+        // Response.
+        let _ = write!(
+            o,
+            r#","response":{{"status":{},"statusText":"#,
+            response.status
+        );
+        string(o, &response.target);
+        o.push_str(r#","httpVersion":"#);
+        string(o, &response.version);
+        o.push_str(r#","cookies":[],"headers":"#);
+        headers(o, &response.headers);
+
+        let size = match &response.body {
+            Some(body) => body.len() as u64,
+            None => self.received.body,
+        };
+        let _ = write!(o, r#","content":{{"size":{},"mimeType":"#, size);
+        string(o, response.header("content-type").unwrap_or(""));
+        if let Some(body) = &response.body {
+            text(o, body);
+        }
+        o.push_str(r#"},"redirectURL":"#);
+        string(o, response.header("location").unwrap_or(""));
+        // The head of the final response, interim responses are not in the entry.
+        let _ = write!(
+            o,
+            r#","headersSize":{},"bodySize":{}}}"#,
+            response.head, self.received.body
+        );
+
+        o.push_str(r#","cache":{},"timings":{"#);
+        let t = &self.timings;
+        let _ = write!(
+            o,
+            r#""blocked":{},"dns":{},"connect":{},"ssl":{},"send":{},"wait":{},"receive":{}"#,
+            maybe_millis(t.blocked),
+            maybe_millis(t.dns),
+            maybe_millis(t.connect),
+            maybe_millis(t.ssl),
+            millis(t.send),
+            millis(t.wait),
+            millis(t.receive),
+        );
+        o.push_str("}}");
+
+        Ok(out)
+    }
+
+    fn url(&self, request: &Message) -> String {
+        // Requests to proxies have the absolute URL as target.
+        if request.target.contains("://") {
+            return request.target.clone();
+        }
+
+        let scheme = if self.https { "https" } else { "http" };
+        let host = request.header("host").unwrap_or("");
+
+        format!("{}://{}{}", scheme, host, request.target)
+    }
+}
+
+impl WireHook for HarEntry {
+    fn wire(&mut self, direction: Direction, part: WirePart, bytes: &[u8]) {
+        let capture = match direction {
+            Direction::Sent => &mut self.sent,
+            Direction::Received => &mut self.received,
+        };
+
+        match part {
+            WirePart::Head => {
+                capture.head += bytes.len() as u64;
+                capture.bytes.extend_from_slice(bytes);
+            }
+            WirePart::Body => {
+                capture.body += bytes.len() as u64;
+                if self.bodies {
+                    capture.bytes.extend_from_slice(bytes);
+                }
+            }
+            // Not part of the body size, but needed to parse to the end of the message.
+            WirePart::Trailer => {
+                if self.bodies {
+                    capture.bytes.extend_from_slice(bytes);
+                }
+            }
+        }
+    }
+}
+
+/// A request or response parsed from the captured bytes.
+#[derive(Default)]
+struct Message {
+    method: Option<Method>,
+    status: u16,
+    /// Request target, or status text.
+    target: String,
+    version: String,
+    headers: Vec<(String, String)>,
+    /// Size of the head.
+    head: usize,
+    body: Option<Vec<u8>>,
+    /// Input used, including the body.
+    used: usize,
+}
+
+impl Message {
+    fn parse(mut parser: EventParser, input: &[u8]) -> Result<Message> {
+        let mut message = Message::default();
+        let mut body = None;
+        let mut pos = 0;
+
+        loop {
+            let (used, event) = parser.next_event(&input[pos..])?;
+            pos += used;
+
+            let event = match event {
+                Some(v) => v,
+                // Need more input than was captured.
+                None => break,
+            };
+
+            match event {
+                Event::RequestLine(line) => {
+                    message.method = Some(line.method());
+                    message.target = line.path().into();
+                    message.version = version(line.version()).into();
+                }
+                Event::StatusLine(status) => {
+                    message.status = status.code();
+                    message.target = status.text().into();
+                    message.version = version(status.version()).into();
+                }
+                Event::Header(h) => {
+                    let value = String::from_utf8_lossy(h.value_raw());
+                    message.headers.push((h.name().into(), value.into()));
+                }
+                // HAR has no place for trailers.
+                Event::Trailer(_) => {}
+                Event::HeadersEnd => {
+                    message.head = pos;
+                    // The rest is body, if the body was kept.
+                    if pos < input.len() {
+                        body = Some(Vec::new());
+                    }
+                }
+                Event::BodyChunk(data) => {
+                    body.get_or_insert_with(Vec::new).extend_from_slice(data);
+                }
+                Event::End => break,
+            }
+        }
+
+        message.body = body;
+        message.used = pos;
+
+        Ok(message)
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn version(v: HttpVersion) -> &'static str {
+    match v {
+        HttpVersion::Http10 => "HTTP/1.0",
+        HttpVersion::Http11 => "HTTP/1.1",
+    }
+}
+
+fn headers(out: &mut String, headers: &[(String, String)]) {
+    out.push('[');
+    for (i, (name, value)) in headers.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(r#"{"name":"#);
+        string(out, name);
+        out.push_str(r#","value":"#);
+        string(out, value);
+        out.push('}');
+    }
+    out.push(']');
+}
+
+/// The pairs of the query of `target`, as sent.
+fn query(out: &mut String, target: &str) {
+    let query = target.split_once('?').map(|(_, q)| q).unwrap_or("");
+    let query = query.split('#').next().unwrap_or("");
+
+    let pairs: Vec<_> = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (n, v) = p.split_once('=').unwrap_or((p, ""));
+            (n.into(), v.into())
+        })
+        .collect();
+
+    headers(out, &pairs);
+}
+
+/// The `text` of a body, as base64 when it isn't UTF-8.
+fn text(out: &mut String, body: &[u8]) {
+    out.push_str(r#","text":"#);
+    match std::str::from_utf8(body) {
+        Ok(text) => string(out, text),
+        Err(_) => {
+            string(out, &base64(body));
+            out.push_str(r#","encoding":"base64""#);
+        }
+    }
+}
+
+/// A JSON string.
+fn string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_micros() as f64 / 1000.0
+}
+
+/// HAR has -1 for phases that don't apply.
+fn maybe_millis(d: Option<Duration>) -> f64 {
+    d.map(millis).unwrap_or(-1.0)
+}
+
+/// Milliseconds since the Unix epoch as ISO 8601, `2024-02-29T00:00:00.000Z`.
+fn iso_date(millis: u64) -> String {
+    let secs = millis / 1000;
+    let days = secs / 86400;
+    let rem = secs % 86400;
+
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        millis % 1000
+    )
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity((input.len() + 2) / 3 * 4);
+
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    fn entry(sent: &[(WirePart, &[u8])], received: &[(WirePart, &[u8])]) -> HarEntry {
+        let mut entry = HarEntry::new(1_709_164_800_123).with_bodies();
+        for (part, bytes) in sent {
+            entry.wire(Direction::Sent, *part, bytes);
+        }
+        for (part, bytes) in received {
+            entry.wire(Direction::Received, *part, bytes);
+        }
+        entry
+    }
+
+    #[test]
+    fn test_har_entry() {
+        use WirePart::*;
+
+        let mut entry = entry(
+            &[
+                (Head, b"POST /form?a=1&b HTTP/1.1\r\nHost: x.test\r\n"),
+                (
+                    Head,
+                    b"Content-Type: text/plain\r\nContent-Length: 3\r\n\r\n",
+                ),
+                (Body, b"a\"b"),
+            ],
+            &[
+                (Head, b"HTTP/1.1 100 Continue\r\n\r\n"),
+                (Head, b"HTTP/1.1 302 Found\r\nLocation: /done\r\n"),
+                (Head, b"Transfer-Encoding: chunked\r\n\r\n"),
+                (Body, b"2\r\n\xff\x00\r\n0\r\n"),
+                (Trailer, b"X-Sum: 1\r\n\r\n"),
+            ],
+        )
+        .https();
+        entry.timings.connect = Some(Duration::from_millis(10));
+        entry.timings.wait = Duration::from_micros(2500);
+
+        let mut har = Har::new();
+        har.push(entry).unwrap();
+        let json = har.to_json();
+
+        let expect = [
+            r#"{"log":{"version":"1.2","creator":{"name":"hoot","version":""#,
+            r#""startedDateTime":"2024-02-29T00:00:00.123Z","time":12.5,"#,
+            r#""request":{"method":"POST","url":"https://x.test/form?a=1&b""#,
+            r#""queryString":[{"name":"a","value":"1"},{"name":"b","value":""}]"#,
+            r#""postData":{"mimeType":"text/plain","text":"a\"b"}"#,
+            r#""headersSize":88,"bodySize":3}"#,
+            r#""response":{"status":302,"statusText":"Found","httpVersion":"HTTP/1.1""#,
+            r#""content":{"size":2,"mimeType":"","text":"/wA=","encoding":"base64"}"#,
+            r#""redirectURL":"/done","headersSize":67,"bodySize":10}"#,
+            r#""blocked":-1,"dns":-1,"connect":10,"ssl":-1,"send":0,"wait":2.5,"receive":0"#,
+        ];
+        for e in expect {
+            assert!(json.contains(e), "{} not in {}", e, json);
+        }
+
+        // Trailers are not headers.
+        assert!(!json.contains("X-Sum"));
+    }
+
+    #[test]
+    fn test_har_binary_post() {
+        use WirePart::*;
+
+        let entry = entry(
+            &[
+                (Head, b"PUT /f HTTP/1.1\r\nHost: x.test\r\n"),
+                (Head, b"Content-Length: 2\r\n\r\n"),
+                (Body, b"\xff\x00"),
+            ],
+            &[(Head, b"HTTP/1.1 204 No Content\r\n\r\n")],
+        );
+
+        let mut har = Har::new();
+        har.push(entry).unwrap();
+        let json = har.to_json();
+
+        let expect = r#""postData":{"mimeType":"","text":"/wA=","encoding":"base64"}"#;
+        assert!(json.contains(expect), "{} not in {}", expect, json);
+    }
+
+    #[test]
+    fn test_har_without_bodies() {
+        let mut entry = HarEntry::new(0);
+        entry.wire(
+            Direction::Sent,
+            WirePart::Head,
+            b"GET / HTTP/1.1\r\nHost: x\r\n\r\n",
+        );
+        entry.wire(Direction::Received, WirePart::Head, b"HTTP/1.1 200 OK\r\n");
+        entry.wire(
+            Direction::Received,
+            WirePart::Head,
+            b"Content-Length: 5\r\n\r\n",
+        );
+        entry.wire(Direction::Received, WirePart::Body, b"hello");
+
+        let mut har = Har::new();
+        har.push(entry).unwrap();
+        let json = har.to_json();
+
+        assert!(json.contains(r#""startedDateTime":"1970-01-01T00:00:00.000Z""#));
+        assert!(json.contains(r#""content":{"size":5,"mimeType":""}"#));
+        assert!(!json.contains("postData"));
+
+        // No request captured.
+        assert!(har.push(HarEntry::new(0)).is_err());
+        assert_eq!(har.len(), 1);
+    }
+
+    #[test]
+    fn test_base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -1,13 +1,13 @@
+use crate::parse_impl::Header as InnerHeader;
 use core::fmt;
 use core::fmt::Write;
 use core::mem;
 use core::str;
-use httparse::Header as InnerHeader;
 
+use crate::ascii::eq_ignore_ascii;
 use crate::error::{Result, OVERFLOW};
 use crate::out::Writer;
-use crate::parser::parse_headers;
-use crate::util::compare_lowercase_ascii;
+use crate::parser::{find_crlf, parse_headers};
 use crate::{HootError, HttpVersion};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -16,26 +16,32 @@ pub struct Header<'a>(InnerHeader<'a>);
 
 impl<'a> Header<'a> {
     #[inline(always)]
-    pub fn name(&self) -> &str {
+    pub fn name(&self) -> &'a str {
         self.0.name
     }
 
     #[inline(always)]
-    pub fn try_value(&self) -> Option<&str> {
+    pub fn try_value(&self) -> Option<&'a str> {
         str::from_utf8(self.0.value).ok()
     }
 
     #[inline(always)]
-    pub fn value(&self) -> &str {
+    pub fn value(&self) -> &'a str {
         self.try_value().expect("header value to be valid utf-8")
     }
 
     #[inline(always)]
-    pub fn value_raw(&self) -> &[u8] {
+    pub fn value_raw(&self) -> &'a [u8] {
         self.0.value
     }
 }
 
+impl<'a> Header<'a> {
+    pub(crate) fn into_inner(self) -> InnerHeader<'a> {
+        self.0
+    }
+}
+
 impl<'a> fmt::Debug for Header<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut f = f.debug_struct("Header");
@@ -49,39 +55,112 @@ impl<'a> fmt::Debug for Header<'a> {
     }
 }
 
+/// Whether a `Connection` header value holds the given option, such as `close`.
+pub(crate) fn connection_has(value: &str, option: &str) -> bool {
+    value.split(',').any(|v| eq_ignore_ascii(v.trim(), option))
+}
+
+/// Fill a `http::HeaderMap` with headers.
+///
+/// The map is cleared, but keeps its allocation, which means a map can be recycled
+/// between calls. Capacity is reserved up front to avoid rehashing.
+#[cfg(feature = "http_crate")]
+pub(crate) fn fill_header_map(map: &mut http::HeaderMap, headers: &[Header<'_>]) -> Result<()> {
+    use http::header::{HeaderName, HeaderValue};
+
+    map.clear();
+    map.reserve(headers.len());
+
+    for header in headers {
+        let name =
+            HeaderName::from_bytes(header.name().as_bytes()).map_err(|_| HootError::HeaderName)?;
+        let value =
+            HeaderValue::from_bytes(header.value_raw()).map_err(|_| HootError::HeaderValue)?;
+        map.append(name, value);
+    }
+
+    Ok(())
+}
+
 pub(crate) fn transmute_headers<'a, 'b>(headers: &'b [InnerHeader<'a>]) -> &'b [Header<'a>] {
     // SAFETY: Our goal is to have hoot::Header be structurally the same
-    // as httparse::Header. This is guarenteed by #[repr(transparent)].
+    // as parse_impl::Header. This is guarenteed by #[repr(transparent)].
     unsafe { mem::transmute(headers) }
 }
 
+/// How header names are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderCasing {
+    /// Header names as given, and headers written by hoot capitalized, like `Content-Length`.
+    AsGiven,
+    /// Each dash separated part capitalized, like `Content-Type`.
+    TitleCase,
+    /// All lowercase, like `content-type`.
+    Lowercase,
+}
+
+impl Default for HeaderCasing {
+    fn default() -> Self {
+        HeaderCasing::AsGiven
+    }
+}
+
+/// Write a header name in the casing.
+///
+/// The name is recased through a small stack buffer, to avoid allocating.
+pub(crate) fn write_name(w: &mut Writer, name: &str, casing: HeaderCasing) -> Result<()> {
+    if casing == HeaderCasing::AsGiven {
+        w.write_bytes(name.as_bytes())?;
+        return Ok(());
+    }
+
+    let mut buf = [0_u8; 32];
+    let mut word_start = true;
+
+    for part in name.as_bytes().chunks(buf.len()) {
+        for (b, c) in buf.iter_mut().zip(part) {
+            *b = if casing == HeaderCasing::TitleCase && word_start {
+                c.to_ascii_uppercase()
+            } else {
+                c.to_ascii_lowercase()
+            };
+            word_start = *c == b'-';
+        }
+        w.write_bytes(&buf[..part.len()])?;
+    }
+
+    Ok(())
+}
+
+/// Write a header hoot adds itself, like `Content-Length`.
+pub(crate) fn write_generated(
+    w: &mut Writer,
+    name: &str,
+    value: fmt::Arguments,
+    casing: HeaderCasing,
+) -> Result<()> {
+    write_name(w, name, casing)?;
+    write!(w, ": {}\r\n", value).or(OVERFLOW)
+}
+
 pub(crate) fn check_and_output_header(
     mut w: Writer,
     version: HttpVersion,
     name: &str,
     bytes: &[u8],
     trailer: bool,
+    casing: HeaderCasing,
 ) -> Result<()> {
+    validate_header_name(name)?;
+    check_header_value(bytes)?;
+
     // Writer header
-    write!(w, "{}: ", name).or(OVERFLOW)?;
+    write_name(&mut w, name, casing)?;
+    w.write_bytes(b": ")?;
     w.write_bytes(bytes)?;
     write!(w, "\r\n").or(OVERFLOW)?;
 
-    if trailer {
-        check_headers(name, HEADERS_FORBID_TRAILER, HootError::ForbiddenTrailer)?;
-    } else {
-        // These headers are forbidden because we write them with
-        check_headers(name, HEADERS_FORBID_BODY, HootError::ForbiddenBodyHeader)?;
-
-        match version {
-            HttpVersion::Http10 => {
-                // TODO: forbid specific headers for 1.0
-            }
-            HttpVersion::Http11 => {
-                check_headers(name, HEADERS_FORBID_11, HootError::ForbiddenHttp11Header)?
-            }
-        }
-    }
+    check_forbidden(version, name, trailer)?;
 
     // TODO: forbid headers that are not allowed to be repeated
 
@@ -101,7 +180,196 @@ pub(crate) fn check_and_output_header(
     Ok(())
 }
 
+/// Start writing a header where the value might not fit the output buffer.
+///
+/// The entire header is validated up front, since it can't be parsed back
+/// from the output. The name must fit in the output, the value is written as
+/// far as possible.
+///
+/// Returns the number of value bytes written and the number of value bytes left to write
+/// before the header is finished by `\r\n`. `None` means the header is finished.
+pub(crate) fn check_and_output_header_streamed(
+    mut w: Writer,
+    version: HttpVersion,
+    name: &str,
+    bytes: &[u8],
+    trailer: bool,
+    casing: HeaderCasing,
+) -> Result<(usize, Option<usize>)> {
+    validate_header_name(name)?;
+    check_header_value(bytes)?;
+    check_forbidden(version, name, trailer)?;
+
+    write_name(&mut w, name, casing)?;
+    w.write_bytes(b": ")?;
+
+    output_header_rest(w, bytes)
+}
+
+/// Continue writing a header started with [`check_and_output_header_streamed`].
+pub(crate) fn output_header_continue(
+    w: Writer,
+    bytes: &[u8],
+    left: usize,
+) -> Result<(usize, Option<usize>)> {
+    if bytes.len() != left {
+        return Err(HootError::StreamedHeader);
+    }
+
+    check_header_value(bytes)?;
+
+    output_header_rest(w, bytes)
+}
+
+fn output_header_rest(mut w: Writer, bytes: &[u8]) -> Result<(usize, Option<usize>)> {
+    let amount = bytes.len().min(w.capacity());
+    w.write_bytes(&bytes[..amount])?;
+
+    let left = bytes.len() - amount;
+
+    // The line ending goes in once all of the value is written.
+    let done = left == 0 && w.capacity() >= 2;
+
+    if done {
+        w.write_bytes(b"\r\n")?;
+    }
+
+    w.commit();
+
+    Ok((amount, if done { None } else { Some(left) }))
+}
+
+/// Check that a header name is a valid token.
+///
+/// RFC 9110 field-name is a token, which is one or more of the characters
+/// ```text
+/// ! # $ % & ' * + - . ^ _ ` | ~ DIGIT ALPHA
+/// ```
+///
+/// This is the check used for all headers and trailers hoot sends.
+pub fn validate_header_name(name: &str) -> Result<()> {
+    if name.is_empty() || !name.bytes().all(is_tchar) {
+        return Err(HootError::HeaderName);
+    }
+    Ok(())
+}
+
+fn check_header_value(bytes: &[u8]) -> Result<()> {
+    check_field_value(bytes, true)
+}
+
+/// Check a field value against RFC 9110.
+///
+/// field-vchar / SP / HTAB, where obs-text (0x80-0xff) is optional.
+pub(crate) fn check_field_value(bytes: &[u8], allow_obs_text: bool) -> Result<()> {
+    let ok = bytes.iter().all(|b| match *b {
+        b'\t' => true,
+        0x00..=0x1f | 0x7f => false,
+        0x80..=0xff => allow_obs_text,
+        _ => true,
+    });
+    if !ok {
+        return Err(HootError::HeaderValue);
+    }
+    Ok(())
+}
+
+/// Check received headers. Invalid bytes are already rejected when parsing,
+/// so this is only needed when obs-text is not allowed.
+pub(crate) fn check_received_headers(headers: &[Header<'_>], allow_obs_text: bool) -> Result<()> {
+    if allow_obs_text {
+        return Ok(());
+    }
+    for h in headers {
+        check_field_value(h.value_raw(), false)?;
+    }
+    Ok(())
+}
+
+/// Replace invalid bytes in received header values with SP.
+///
+/// This is the lenient alternative to failing on invalid field values. It is to be
+/// used on the input _before_ reading the request or response. Only complete header
+/// lines are touched. The start line and the line endings are left as is.
+///
+/// > A recipient of CR, LF, or NUL within a field value MUST either reject the message
+/// > or replace each of those characters with SP before further processing
+///
+/// Returns the number of replaced bytes.
+pub fn sanitize_field_values(input: &mut [u8]) -> usize {
+    let mut replaced = 0;
+
+    // Skip start line
+    let mut pos = match find_crlf(input) {
+        Some(v) => v + 2,
+        None => return 0,
+    };
+
+    loop {
+        let rest = &mut input[pos..];
+
+        let end = match find_crlf_loose(rest) {
+            Some(v) => v,
+            None => break,
+        };
+
+        if end == 0 {
+            // End of headers
+            break;
+        }
+
+        for b in &mut rest[..end] {
+            if matches!(*b, 0x00..=0x08 | 0x0a..=0x1f | 0x7f) {
+                *b = b' ';
+                replaced += 1;
+            }
+        }
+
+        pos += end + 2;
+    }
+
+    replaced
+}
+
+// Like find_crlf, but skipping over lone CR.
+fn find_crlf_loose(b: &[u8]) -> Option<usize> {
+    b.windows(2).position(|w| w == b"\r\n")
+}
+
+pub(crate) fn is_tchar(b: u8) -> bool {
+    // tchar = "!" / "#" / "$" / "%" / "&" / "'" / "*" / "+" / "-" / "." /
+    //         "^" / "_" / "`" / "|" / "~" / DIGIT / ALPHA
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+/// Check a name to declare in a `Trailer` header.
+pub(crate) fn check_trailer_name(name: &str) -> Result<()> {
+    validate_header_name(name)?;
+    check_headers(name, HEADERS_FORBID_TRAILER, HootError::ForbiddenTrailer)
+}
+
+fn check_forbidden(version: HttpVersion, name: &str, trailer: bool) -> Result<()> {
+    if trailer {
+        check_headers(name, HEADERS_FORBID_TRAILER, HootError::ForbiddenTrailer)?;
+    } else {
+        // These headers are forbidden because we write them with
+        check_headers(name, HEADERS_FORBID_BODY, HootError::ForbiddenBodyHeader)?;
+
+        match version {
+            HttpVersion::Http10 => {
+                // TODO: forbid specific headers for 1.0
+            }
+            HttpVersion::Http11 => {
+                check_headers(name, HEADERS_FORBID_11, HootError::ForbiddenHttp11Header)?
+            }
+        }
+    }
+
+    Ok(())
+}
+
 // Headers that are not allowed because we set them as part of making a call.
+// Compared caseless, but kept lowercase for readability.
 const HEADERS_FORBID_BODY: &[&str] = &[
     // header set by with_body()
     "content-length",
@@ -136,7 +404,7 @@ const HEADERS_FORBID_TRAILER: &[&str] = &[
 
 fn check_headers(name: &str, forbidden: &[&str], err: HootError) -> Result<()> {
     for c in forbidden {
-        if !compare_lowercase_ascii(name, c) {
+        if !eq_ignore_ascii(name, c) {
             continue;
         }
 
@@ -146,3 +414,63 @@ fn check_headers(name: &str, forbidden: &[&str], err: HootError) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_name() -> Result<()> {
+        use crate::out::Out;
+
+        let long = "x-a-rather-long-header-name-spanning-the-buffer";
+
+        let mut buf = [0; 1024];
+        let mut out = Out::wrap(&mut buf);
+
+        let mut w = out.writer();
+        for casing in [HeaderCasing::AsGiven, HeaderCasing::TitleCase] {
+            write_name(&mut w, "wWw-authENTICATE ", casing)?;
+        }
+        write_name(&mut w, long, HeaderCasing::TitleCase)?;
+        write_name(&mut w, "Content-Type", HeaderCasing::Lowercase)?;
+        w.commit();
+
+        assert_eq!(
+            out.as_bytes(),
+            b"wWw-authENTICATE Www-Authenticate \
+            X-A-Rather-Long-Header-Name-Spanning-The-Buffer\
+            content-type"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_header_name() {
+        assert!(validate_header_name("X-Foo_bar.1~").is_ok());
+        assert!(validate_header_name("").is_err());
+        assert!(validate_header_name("x foo").is_err());
+        assert!(validate_header_name("x:foo").is_err());
+        assert!(validate_header_name("x\rfoo").is_err());
+        assert!(validate_header_name("xö").is_err());
+    }
+
+    #[test]
+    fn test_check_field_value() {
+        assert!(check_field_value(b"a b\tc", false).is_ok());
+        assert!(check_field_value(b"caf\xc3\xa9", true).is_ok());
+        assert!(check_field_value(b"caf\xc3\xa9", false).is_err());
+        assert!(check_field_value(b"a\x7fb", true).is_err());
+        assert!(check_field_value(b"a\x00b", true).is_err());
+    }
+
+    #[test]
+    fn test_sanitize_field_values() {
+        let mut input = *b"HTTP/1.1 200 OK\r\nX-A: a\x00b\x7f\r\nX-B: c\rd\r\n\r\nbody\x00";
+        assert_eq!(sanitize_field_values(&mut input), 3);
+        assert_eq!(
+            &input[..],
+            b"HTTP/1.1 200 OK\r\nX-A: a b \r\nX-B: c d\r\n\r\nbody\x00"
+        );
+    }
+}
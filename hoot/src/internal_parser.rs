@@ -0,0 +1,440 @@
+//! Built-in HTTP/1.x head parser.
+//!
+//! Used instead of httparse when the `httparse` feature is disabled. It mirrors
+//! the parts of the httparse API that hoot uses, which means the rest of the
+//! crate doesn't need to know which parser is in use.
+//!
+//! The parser is strict:
+//!
+//! * Method and header names must be tokens.
+//! * Header values must be HTAB, SP, VCHAR or obs-text.
+//! * Obsolete line folding is rejected.
+//! * Lines end with CRLF, a bare LF is accepted like httparse does.
+
+use core::mem;
+use core::str;
+
+/// A parsed header. Same layout as `httparse::Header`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Header<'a> {
+    pub name: &'a str,
+    pub value: &'a [u8],
+}
+
+pub const EMPTY_HEADER: Header<'static> = Header {
+    name: "",
+    value: b"",
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Status<T> {
+    Complete(T),
+    Partial,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+    HeaderName,
+    HeaderValue,
+    NewLine,
+    Status,
+    Token,
+    TooManyHeaders,
+    Version,
+}
+
+type Result<T> = core::result::Result<Status<T>, Error>;
+
+/// Unwrap a `Status::Complete` or return `Ok(Status::Partial)`.
+macro_rules! complete {
+    ($e:expr) => {
+        match $e? {
+            Status::Complete(v) => v,
+            Status::Partial => return Ok(Status::Partial),
+        }
+    };
+}
+
+pub struct Request<'h, 'b> {
+    pub method: Option<&'b str>,
+    pub path: Option<&'b str>,
+    pub version: Option<u8>,
+    pub headers: &'h mut [Header<'b>],
+}
+
+impl<'h, 'b> Request<'h, 'b> {
+    pub fn new(headers: &'h mut [Header<'b>]) -> Self {
+        Request {
+            method: None,
+            path: None,
+            version: None,
+            headers,
+        }
+    }
+
+    /// Parse the request line and headers. Fields are filled in as far as the input goes.
+    pub fn parse(&mut self, buf: &'b [u8]) -> Result<usize> {
+        let mut c = Cursor { buf, pos: 0 };
+
+        let method = complete!(c.token(b' '));
+        if method.is_empty() {
+            return Err(Error::Token);
+        }
+        self.method = Some(method);
+
+        let path = complete!(c.path());
+        self.path = Some(path);
+
+        let version = complete!(c.version());
+        complete!(c.newline(Error::Version));
+        self.version = Some(version);
+
+        let (n, len) = complete!(parse_headers_at(&mut c, self.headers));
+        let headers = mem::take(&mut self.headers);
+        self.headers = &mut headers[..len];
+
+        Ok(Status::Complete(n))
+    }
+}
+
+pub struct Response<'h, 'b> {
+    pub version: Option<u8>,
+    pub code: Option<u16>,
+    pub reason: Option<&'b str>,
+    pub headers: &'h mut [Header<'b>],
+}
+
+impl<'h, 'b> Response<'h, 'b> {
+    pub fn new(headers: &'h mut [Header<'b>]) -> Self {
+        Response {
+            version: None,
+            code: None,
+            reason: None,
+            headers,
+        }
+    }
+
+    /// Parse the status line and headers. Fields are filled in as far as the input goes.
+    pub fn parse(&mut self, buf: &'b [u8]) -> Result<usize> {
+        let mut c = Cursor { buf, pos: 0 };
+
+        let version = complete!(c.version());
+        complete!(c.expect(b' ', Error::Version));
+        self.version = Some(version);
+
+        let code = complete!(c.code());
+        self.code = Some(code);
+
+        let reason = complete!(c.reason());
+        self.reason = Some(reason);
+
+        let (n, len) = complete!(parse_headers_at(&mut c, self.headers));
+        let headers = mem::take(&mut self.headers);
+        self.headers = &mut headers[..len];
+
+        Ok(Status::Complete(n))
+    }
+}
+
+/// Parse headers until the empty line ending them.
+///
+/// Every complete header line is written to `dst`, also when the result is `Partial`.
+pub fn parse_headers<'b, 'h>(
+    src: &'b [u8],
+    dst: &'h mut [Header<'b>],
+) -> Result<(usize, &'h [Header<'b>])> {
+    let mut c = Cursor { buf: src, pos: 0 };
+    let (n, len) = complete!(parse_headers_at(&mut c, dst));
+    Ok(Status::Complete((n, &dst[..len])))
+}
+
+fn parse_headers_at<'b>(c: &mut Cursor<'b>, dst: &mut [Header<'b>]) -> Result<(usize, usize)> {
+    let mut len = 0;
+
+    loop {
+        match c.peek() {
+            None => return Ok(Status::Partial),
+            Some(b'\r') | Some(b'\n') => {
+                complete!(c.newline(Error::NewLine));
+                return Ok(Status::Complete((c.pos, len)));
+            }
+            Some(_) => {}
+        }
+
+        let name = complete!(c.token(b':'));
+        if name.is_empty() {
+            return Err(Error::HeaderName);
+        }
+
+        let value = complete!(c.value());
+
+        let slot = dst.get_mut(len).ok_or(Error::TooManyHeaders)?;
+        *slot = Header { name, value };
+        len += 1;
+    }
+}
+
+struct Cursor<'b> {
+    buf: &'b [u8],
+    pos: usize,
+}
+
+impl<'b> Cursor<'b> {
+    fn peek(&self) -> Option<u8> {
+        self.buf.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, b: u8, err: Error) -> Result<()> {
+        match self.peek() {
+            None => Ok(Status::Partial),
+            Some(c) if c == b => {
+                self.pos += 1;
+                Ok(Status::Complete(()))
+            }
+            Some(_) => Err(err),
+        }
+    }
+
+    /// Read up to the end byte, which is consumed. Everything before it must be a token.
+    fn token(&mut self, end: u8) -> Result<&'b str> {
+        let start = self.pos;
+        loop {
+            let c = match self.peek() {
+                Some(v) => v,
+                None => return Ok(Status::Partial),
+            };
+            if c == end {
+                break;
+            }
+            if !is_tchar(c) {
+                return Err(if end == b':' {
+                    Error::HeaderName
+                } else {
+                    Error::Token
+                });
+            }
+            self.pos += 1;
+        }
+        let token = &self.buf[start..self.pos];
+        self.pos += 1;
+        // tchar are all ascii.
+        Ok(Status::Complete(str::from_utf8(token).unwrap()))
+    }
+
+    fn path(&mut self) -> Result<&'b str> {
+        let start = self.pos;
+        loop {
+            let c = match self.peek() {
+                Some(v) => v,
+                None => return Ok(Status::Partial),
+            };
+            if c == b' ' {
+                break;
+            }
+            if !(0x21..=0x7e).contains(&c) {
+                return Err(Error::Token);
+            }
+            self.pos += 1;
+        }
+        let path = &self.buf[start..self.pos];
+        if path.is_empty() {
+            return Err(Error::Token);
+        }
+        self.pos += 1;
+        // Checked to be ascii above.
+        Ok(Status::Complete(str::from_utf8(path).unwrap()))
+    }
+
+    fn version(&mut self) -> Result<u8> {
+        const PREFIX: &[u8] = b"HTTP/1.";
+
+        let rest = &self.buf[self.pos..];
+        let n = rest.len().min(PREFIX.len());
+        if rest[..n] != PREFIX[..n] {
+            return Err(Error::Version);
+        }
+        let v = match rest.get(PREFIX.len()) {
+            Some(b'0') => 0,
+            Some(b'1') => 1,
+            Some(_) => return Err(Error::Version),
+            None => return Ok(Status::Partial),
+        };
+        self.pos += PREFIX.len() + 1;
+        Ok(Status::Complete(v))
+    }
+
+    fn code(&mut self) -> Result<u16> {
+        let rest = &self.buf[self.pos..];
+        let mut code = 0;
+        for i in 0..3 {
+            match rest.get(i) {
+                Some(c) if c.is_ascii_digit() => code = code * 10 + (c - b'0') as u16,
+                Some(_) => return Err(Error::Status),
+                None => return Ok(Status::Partial),
+            }
+        }
+        self.pos += 3;
+        Ok(Status::Complete(code))
+    }
+
+    fn reason(&mut self) -> Result<&'b str> {
+        match self.peek() {
+            None => return Ok(Status::Partial),
+            Some(b' ') => self.pos += 1,
+            // No reason phrase.
+            Some(b'\r') | Some(b'\n') => {
+                complete!(self.newline(Error::Status));
+                return Ok(Status::Complete(""));
+            }
+            Some(_) => return Err(Error::Status),
+        }
+
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                None => return Ok(Status::Partial),
+                Some(b'\r') | Some(b'\n') => break,
+                Some(c) if is_field_byte(c) => self.pos += 1,
+                Some(_) => return Err(Error::Status),
+            }
+        }
+        let reason = &self.buf[start..self.pos];
+        complete!(self.newline(Error::Status));
+
+        let reason = str::from_utf8(reason).map_err(|_| Error::Status)?;
+        Ok(Status::Complete(reason))
+    }
+
+    fn value(&mut self) -> Result<&'b [u8]> {
+        // Leading OWS
+        while let Some(b' ') | Some(b'\t') = self.peek() {
+            self.pos += 1;
+        }
+
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                None => return Ok(Status::Partial),
+                Some(b'\r') | Some(b'\n') => break,
+                Some(c) if is_field_byte(c) => self.pos += 1,
+                Some(_) => return Err(Error::HeaderValue),
+            }
+        }
+        let mut value = &self.buf[start..self.pos];
+        complete!(self.newline(Error::HeaderValue));
+
+        // Trailing OWS
+        while let Some((b' ' | b'\t', rest)) = value.split_last() {
+            value = rest;
+        }
+
+        // Obsolete line folding
+        if let Some(b' ') | Some(b'\t') = self.peek() {
+            return Err(Error::HeaderName);
+        }
+
+        Ok(Status::Complete(value))
+    }
+
+    /// Consume CRLF or LF.
+    fn newline(&mut self, err: Error) -> Result<()> {
+        match self.peek() {
+            None => return Ok(Status::Partial),
+            Some(b'\r') => self.pos += 1,
+            Some(b'\n') => {}
+            Some(_) => return Err(err),
+        }
+        match self.peek() {
+            None => Ok(Status::Partial),
+            Some(b'\n') => {
+                self.pos += 1;
+                Ok(Status::Complete(()))
+            }
+            Some(_) => Err(Error::NewLine),
+        }
+    }
+}
+
+fn is_tchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+fn is_field_byte(b: u8) -> bool {
+    b == b'\t' || (b >= b' ' && b != 0x7f)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_request() {
+        let mut h = [EMPTY_HEADER; 4];
+        let mut r = Request::new(&mut h);
+        let s = r.parse(b"GET /p HTTP/1.1\r\nHost: x \r\nA:b\r\n\r\nbody");
+        assert_eq!(s, Ok(Status::Complete(34)));
+        assert_eq!(r.method, Some("GET"));
+        assert_eq!(r.path, Some("/p"));
+        assert_eq!(r.version, Some(1));
+        assert_eq!(r.headers.len(), 2);
+        assert_eq!(
+            r.headers[0],
+            Header {
+                name: "Host",
+                value: b"x"
+            }
+        );
+        assert_eq!(
+            r.headers[1],
+            Header {
+                name: "A",
+                value: b"b"
+            }
+        );
+    }
+
+    #[test]
+    fn test_request_line_partial() {
+        let mut r = Request::new(&mut []);
+        assert_eq!(r.parse(b"GET /p HTTP/1.0\r\n"), Ok(Status::Partial));
+        assert_eq!(r.method, Some("GET"));
+        assert_eq!(r.path, Some("/p"));
+        assert_eq!(r.version, Some(0));
+
+        let mut r = Request::new(&mut []);
+        assert_eq!(r.parse(b"GET /p HT"), Ok(Status::Partial));
+        assert_eq!(r.version, None);
+    }
+
+    #[test]
+    fn test_response() {
+        let mut h = [EMPTY_HEADER; 4];
+        let mut r = Response::new(&mut h);
+        let s = r.parse(b"HTTP/1.1 404 Not Found\r\nX: 1\r\n\r\n");
+        assert_eq!(s, Ok(Status::Complete(32)));
+        assert_eq!(r.code, Some(404));
+        assert_eq!(r.reason, Some("Not Found"));
+
+        let mut r = Response::new(&mut h);
+        assert_eq!(r.parse(b"HTTP/1.1 204\r\n\r\n"), Ok(Status::Complete(16)));
+        assert_eq!(r.reason, Some(""));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut h = [EMPTY_HEADER; 1];
+        let r = parse_headers(b"X Y: 1\r\n\r\n", &mut h);
+        assert_eq!(r, Err(Error::HeaderName));
+        let r = parse_headers(b"X: 1\x7f\r\n\r\n", &mut h);
+        assert_eq!(r, Err(Error::HeaderValue));
+        let r = parse_headers(b"X: 1\r\n fold\r\n\r\n", &mut h);
+        assert_eq!(r, Err(Error::HeaderName));
+        let r = parse_headers(b"X: 1\r\nY: 2\r\n\r\n", &mut h);
+        assert_eq!(r, Err(Error::TooManyHeaders));
+
+        let mut r = Response::new(&mut h);
+        assert_eq!(r.parse(b"HTTP/2.0 200 OK\r\n"), Err(Error::Version));
+        let mut r = Request::new(&mut h);
+        assert_eq!(r.parse(b"G(T / HTTP/1.1\r\n"), Err(Error::Token));
+    }
+}
@@ -0,0 +1,511 @@
+//! Async IO
+//!
+//! The adapters of [`hoot::io`][crate::io] for async transports. The transport is
+//! wrapped in [`Futures`] for `futures-io` runtimes, or [`Tokio`] for tokio. Other
+//! runtimes can implement [`PollRead`] and [`PollWrite`] directly.
+//!
+//! The request body is held in memory, since it's written while sending the request.
+//! The response body is read as it's polled.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::io;
+use std::vec;
+
+use crate::client::{Response, ResumeToken};
+use crate::types::state::*;
+
+use super::{closed_before_response, parse_head, read_body};
+use super::{CallWriter, Input, BUF_SIZE};
+
+/// Transport to read from, like `AsyncRead`.
+pub trait PollRead {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>>;
+}
+
+/// Transport to write to, like `AsyncWrite`.
+pub trait PollWrite {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>>;
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+}
+
+/// Sends a request over an async writer.
+pub struct AsyncCallWriter<W> {
+    write: W,
+}
+
+impl<W: PollWrite + Unpin> AsyncCallWriter<W> {
+    pub fn new(write: W) -> Self {
+        AsyncCallWriter { write }
+    }
+
+    /// Send an HTTP/1.1 or HTTP/1.0 request, like [`CallWriter::send()`].
+    pub async fn send<B: AsRef<[u8]>>(
+        &mut self,
+        request: http::Request<B>,
+    ) -> io::Result<ResumeToken<ENDED, (), (), ()>> {
+        let mut out = vec![];
+        let token = CallWriter::new(&mut out).send(request.map(io::Cursor::new))?;
+
+        WriteAll {
+            write: &mut self.write,
+            buf: &out,
+        }
+        .await?;
+
+        Flush {
+            write: &mut self.write,
+        }
+        .await?;
+
+        Ok(token)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.write
+    }
+}
+
+/// Reads a response over an async reader.
+pub struct AsyncCallReader<R> {
+    read: R,
+    input: Input,
+}
+
+impl<R: PollRead + Unpin> AsyncCallReader<R> {
+    pub fn new(read: R) -> Self {
+        AsyncCallReader {
+            read,
+            input: Input::new(),
+        }
+    }
+
+    /// Read the response to the request sent with `token`, like
+    /// [`CallReader::read_response()`][super::CallReader::read_response].
+    pub async fn read_response(
+        mut self,
+        token: ResumeToken<ENDED, (), (), ()>,
+    ) -> io::Result<http::Response<AsyncResponseBody<R>>> {
+        let mut res = token.into_response();
+        let mut buf = vec![0; BUF_SIZE];
+
+        loop {
+            if let Some(response) = parse_head(&mut res, &mut self.input, &mut buf, &mut ())? {
+                let body = AsyncResponseBody {
+                    res: res.proceed(),
+                    reader: self,
+                    need_more: false,
+                };
+
+                let (parts, _) = response.into_parts();
+                return Ok(http::Response::from_parts(parts, body));
+            }
+
+            let n = Read {
+                read: &mut self.read,
+                buf: self.input.space()?,
+            }
+            .await?;
+
+            if n == 0 {
+                return Err(closed_before_response());
+            }
+            self.input.filled(n);
+        }
+    }
+}
+
+/// Body of a response read by [`AsyncCallReader`].
+pub struct AsyncResponseBody<R> {
+    res: Response<RECV_BODY>,
+    reader: AsyncCallReader<R>,
+    need_more: bool,
+}
+
+impl<R: PollRead + Unpin> AsyncResponseBody<R> {
+    /// Read body into `buf`. Returns the amount read, which is 0 at the end.
+    pub async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Read { read: self, buf }.await
+    }
+
+    /// Whether the entire body is read.
+    pub fn is_finished(&self) -> bool {
+        self.res.is_finished()
+    }
+}
+
+impl<R: PollRead + Unpin> PollRead for AsyncResponseBody<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        loop {
+            let input = &mut this.reader.input;
+            let need_more = &mut this.need_more;
+            if let Some(n) = read_body(&mut this.res, input, buf, need_more, &mut (), &mut false)? {
+                return Poll::Ready(Ok(n));
+            }
+
+            let space = this.reader.input.space()?;
+            let n = match Pin::new(&mut this.reader.read).poll_read(cx, space) {
+                Poll::Ready(v) => v?,
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if n == 0 {
+                // Errors unless the body is delimited by closing the connection.
+                this.res.read_body_eof()?;
+                return Poll::Ready(Ok(0));
+            }
+
+            this.reader.input.filled(n);
+            this.need_more = false;
+        }
+    }
+}
+
+struct Read<'a, R> {
+    read: &'a mut R,
+    buf: &'a mut [u8],
+}
+
+impl<R: PollRead + Unpin> Future for Read<'_, R> {
+    type Output = io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        Pin::new(&mut *this.read).poll_read(cx, this.buf)
+    }
+}
+
+struct WriteAll<'a, W> {
+    write: &'a mut W,
+    buf: &'a [u8],
+}
+
+impl<W: PollWrite + Unpin> Future for WriteAll<'_, W> {
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        while !this.buf.is_empty() {
+            let n = match Pin::new(&mut *this.write).poll_write(cx, this.buf) {
+                Poll::Ready(v) => v?,
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+
+            this.buf = &this.buf[n..];
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+struct Flush<'a, W> {
+    write: &'a mut W,
+}
+
+impl<W: PollWrite + Unpin> Future for Flush<'_, W> {
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.get_mut().write).poll_flush(cx)
+    }
+}
+
+/// Transport implementing the `futures-io` traits.
+///
+/// ```
+/// use futures_io::{AsyncRead, AsyncWrite};
+/// use hoot::io::async_io::{AsyncCallReader, AsyncCallWriter, Futures};
+///
+/// async fn get<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S) -> std::io::Result<Vec<u8>> {
+///     let request = http::Request::get("http://example.test/").body("").unwrap();
+///
+///     let token = AsyncCallWriter::new(Futures(&mut stream)).send(request).await?;
+///     let response = AsyncCallReader::new(Futures(stream)).read_response(token).await?;
+///
+///     let mut body = response.into_body();
+///     let mut buf = [0; 1024];
+///     let mut data = vec![];
+///     loop {
+///         let n = body.read(&mut buf).await?;
+///         if n == 0 {
+///             break;
+///         }
+///         data.extend_from_slice(&buf[..n]);
+///     }
+///     Ok(data)
+/// }
+/// ```
+#[cfg(feature = "futures-io")]
+pub struct Futures<T>(pub T);
+
+#[cfg(feature = "futures-io")]
+impl<T: futures_io::AsyncRead + Unpin> PollRead for Futures<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<T: futures_io::AsyncWrite + Unpin> PollWrite for Futures<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_flush(cx)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<R: PollRead + Unpin> futures_io::AsyncRead for AsyncResponseBody<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        PollRead::poll_read(self, cx, buf)
+    }
+}
+
+/// Transport implementing the tokio traits.
+///
+/// ```
+/// use tokio::io::{AsyncRead, AsyncWrite};
+/// use hoot::io::async_io::{AsyncCallReader, AsyncCallWriter, Tokio};
+///
+/// async fn status<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S) -> std::io::Result<u16> {
+///     let request = http::Request::get("http://example.test/").body("").unwrap();
+///
+///     let token = AsyncCallWriter::new(Tokio(&mut stream)).send(request).await?;
+///     let response = AsyncCallReader::new(Tokio(stream)).read_response(token).await?;
+///
+///     Ok(response.status().as_u16())
+/// }
+/// ```
+#[cfg(feature = "tokio")]
+pub struct Tokio<T>(pub T);
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncRead + Unpin> PollRead for Tokio<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut read_buf = tokio::io::ReadBuf::new(buf);
+        match Pin::new(&mut self.get_mut().0).poll_read(cx, &mut read_buf) {
+            Poll::Ready(v) => Poll::Ready(v.map(|_| read_buf.filled().len())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncWrite + Unpin> PollWrite for Tokio<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_flush(cx)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R: PollRead + Unpin> tokio::io::AsyncRead for AsyncResponseBody<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let dst = buf.initialize_unfilled();
+        let n = match PollRead::poll_read(self, cx, dst) {
+            Poll::Ready(v) => v?,
+            Poll::Pending => return Poll::Pending,
+        };
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    use super::*;
+
+    /// Transport that is pending every other poll, and reads or writes at most `max`
+    /// bytes at a time.
+    struct Split {
+        input: Vec<u8>,
+        output: Vec<u8>,
+        max: usize,
+        pending: bool,
+    }
+
+    impl Split {
+        fn new(input: &[u8], max: usize) -> Self {
+            Split {
+                input: input.to_vec(),
+                output: vec![],
+                max,
+                pending: false,
+            }
+        }
+
+        fn pending(&mut self, cx: &mut Context<'_>) -> bool {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+            }
+            self.pending
+        }
+    }
+
+    impl PollRead for &mut Split {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let this = self.get_mut();
+            if this.pending(cx) {
+                return Poll::Pending;
+            }
+            let n = this.max.min(buf.len()).min(this.input.len());
+            buf[..n].copy_from_slice(&this.input[..n]);
+            this.input.drain(..n);
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    impl PollWrite for &mut Split {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let this = self.get_mut();
+            if this.pending(cx) {
+                return Poll::Pending;
+            }
+            let n = this.max.min(buf.len());
+            this.output.extend_from_slice(&buf[..n]);
+            Poll::Ready(Ok(n))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn block_on<F: Future>(f: F) -> F::Output {
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        let mut f = Box::pin(f);
+        loop {
+            if let Poll::Ready(v) = f.as_mut().poll(&mut cx) {
+                return v;
+            }
+        }
+    }
+
+    #[test]
+    fn test_partial_writes_and_split_reads() -> io::Result<()> {
+        let mut transport = Split::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+            3,
+        );
+
+        block_on(async {
+            let request = http::Request::post("http://a.test/")
+                .header("content-length", "2")
+                .body("hi")
+                .unwrap();
+
+            let token = AsyncCallWriter::new(&mut transport).send(request).await?;
+
+            let response = AsyncCallReader::new(&mut transport)
+                .read_response(token)
+                .await?;
+            assert_eq!(response.status(), 200);
+
+            let mut body = response.into_body();
+            let mut buf = [0; 4];
+            let mut data = vec![];
+            loop {
+                let n = body.read(&mut buf).await?;
+                if n == 0 {
+                    break;
+                }
+                data.extend_from_slice(&buf[..n]);
+            }
+
+            assert_eq!(data, b"hello world");
+            assert!(body.is_finished());
+            Ok::<_, io::Error>(())
+        })?;
+
+        assert_eq!(
+            transport.output,
+            b"POST / HTTP/1.1\r\nHost: a.test\r\nContent-Length: 2\r\n\r\nhi"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cut_response() {
+        let mut transport = Split::new(b"HTTP/1.1 200 OK\r\nContent-", 3);
+        let token = block_on(async {
+            let request = http::Request::get("http://a.test/").body("").unwrap();
+            AsyncCallWriter::new(&mut transport).send(request).await
+        })
+        .unwrap();
+
+        let r = block_on(AsyncCallReader::new(&mut transport).read_response(token));
+        let err = r.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
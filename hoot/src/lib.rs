@@ -10,6 +10,16 @@
 //! * Correct - Encourage (or force) correct HTTP 1.1 usage.
 //!
 //! The library has both a client and a server implementation.
+//!
+//! # Features
+//!
+//! * `httparse` (default) – parse with [httparse](https://crates.io/crates/httparse).
+//!   Without it, a stricter built-in parser is used.
+//! * `std` – std integration such as writing to `std::io::Write`.
+//! * `http_crate` – conversions to and from the [http](https://crates.io/crates/http) crate,
+//!   and the blocking IO adapters in [`io`].
+//! * `futures-io`, `tokio` – async IO adapters in `io::async_io`, for either runtime.
+//! * `gzip`, `brotli` – decoding of compressed bodies in [`coding`].
 
 // For tests we use std.
 #![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
@@ -18,7 +28,9 @@
 extern crate log;
 
 mod chunk;
-use chunk::Dechunker;
+
+pub mod chunked;
+use chunked::{ChunkedDecoder, ChunkedEncoder};
 
 mod out;
 
@@ -27,7 +39,23 @@ use util::LengthChecker;
 
 pub mod types;
 
+pub mod ascii;
+
+pub mod cache;
+
+pub mod audit;
+
 mod parser;
+use parser::HeadLimits;
+pub use parser::ParseMode;
+
+#[cfg(feature = "httparse")]
+use httparse as parse_impl;
+
+#[cfg(not(feature = "httparse"))]
+mod internal_parser;
+#[cfg(not(feature = "httparse"))]
+use internal_parser as parse_impl;
 
 mod error;
 pub use error::HootError;
@@ -37,11 +65,33 @@ pub mod client;
 
 pub mod server;
 
+pub mod event;
+
+pub mod wire;
+
+pub mod coding;
+
+#[cfg(feature = "std")]
+pub mod replay;
+
+#[cfg(feature = "std")]
+pub mod test;
+
+#[cfg(feature = "har")]
+pub mod har;
+
+#[cfg(feature = "http_crate")]
+pub mod io;
+
+#[cfg(feature = "http_crate")]
+pub mod curl;
+
 mod header;
-pub use header::Header;
+pub use header::{sanitize_field_values, validate_header_name, Header, HeaderCasing};
 
 mod body;
-pub use body::{BodyPart, RecvBodyMode};
+pub use body::{BodyMode, BodyPart, RecvBodyMode, TransferCoding};
+pub use chunked::TrailerAttempt;
 
 pub trait BodyWriter: Sized {
     fn write_bytes(self, bytes: &[u8]) -> Result<Self>;
@@ -81,6 +131,12 @@ impl Method {
         use Method::*;
         matches!(self, POST | PUT | PATCH)
     }
+
+    /// Whether sending the request more than once has the same effect as sending it once.
+    pub fn is_idempotent(&self) -> bool {
+        use Method::*;
+        matches!(self, OPTIONS | GET | PUT | DELETE | HEAD | TRACE)
+    }
 }
 
 impl TryFrom<&str> for Method {
@@ -102,6 +158,35 @@ impl TryFrom<&str> for Method {
     }
 }
 
+/// Classification of a request method token.
+///
+/// A server answers a request whose method no route handles depending on whether the
+/// method is known. A known method gets `405 Method Not Allowed`, since it could be
+/// allowed for other resources. An unrecognized method gets `501 Not Implemented`.
+/// Reading a request with an unrecognized method fails with [`HootError::UnknownMethod`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodClass {
+    Known(Method),
+    Unrecognized,
+}
+
+impl MethodClass {
+    pub fn of(token: &str) -> Self {
+        match Method::try_from(token) {
+            Ok(m) => MethodClass::Known(m),
+            Err(_) => MethodClass::Unrecognized,
+        }
+    }
+
+    /// The status for a request with this method, when no route handles the method.
+    pub fn unrouted_status(&self) -> u16 {
+        match self {
+            MethodClass::Known(_) => 405,
+            MethodClass::Unrecognized => 501,
+        }
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct CallState {
     pub version: Option<HttpVersion>,
@@ -109,8 +194,78 @@ pub(crate) struct CallState {
     pub send_checker: Option<LengthChecker>,
     pub recv_body_mode: Option<RecvBodyMode>,
     pub recv_checker: Option<LengthChecker>,
-    pub dechunker: Option<Dechunker>,
+    pub dechunker: Option<ChunkedDecoder>,
     pub did_read_to_end: bool,
+    pub max_output: Option<usize>,
+    pub chunked: ChunkedEncoder,
+    pub header_left: Option<usize>,
+    pub transfer_coding: Option<TransferCoding>,
+    pub content_coding: Option<Result<coding::ContentCoding>>,
+    pub reject_obs_text: bool,
+    pub allow_missing_host: bool,
+    pub conn_close: bool,
+    pub conn_keep_alive: bool,
+    pub taken_over: Option<usize>,
+    pub sent_total: u64,
+    pub sent_head: Option<u64>,
+    pub sent_payload: u64,
+    pub recv_bytes: ByteCount,
+    /// Some input was offered for the response, even if not a complete head.
+    pub recv_any: bool,
+    pub recv_no_body: bool,
+    pub default_port: Option<u16>,
+    pub omit_host: bool,
+    pub header_casing: HeaderCasing,
+    pub headers_only: bool,
+    pub parse_mode: ParseMode,
+    pub head_limits: HeadLimits,
+}
+
+impl CallState {
+    /// Errors if a streamed header has not been finished.
+    pub(crate) fn check_header_done(&self) -> Result<()> {
+        if self.header_left.is_some() {
+            return Err(HootError::StreamedHeader);
+        }
+        Ok(())
+    }
+
+    /// Bytes sent, with `unflushed` bytes in the current output.
+    pub(crate) fn bytes_sent(&self, unflushed: usize) -> ByteCount {
+        let total = self.sent_total + unflushed as u64;
+        // Until the head is done, everything sent is head.
+        let head = self.sent_head.unwrap_or(total);
+        ByteCount {
+            head,
+            framing: total - head - self.sent_payload,
+            payload: self.sent_payload,
+        }
+    }
+
+    /// Remember the end of the head, when leaving the states sending it.
+    pub(crate) fn mark_head_sent(&mut self, unflushed: usize) {
+        if self.sent_head.is_none() {
+            self.sent_head = Some(self.sent_total + unflushed as u64);
+        }
+    }
+}
+
+/// Wire bytes of a message, for metering traffic.
+///
+/// The head is the request or status line and headers. The framing is the overhead of
+/// chunked transfer coding, including trailers. The payload is the body data itself.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ByteCount {
+    pub head: u64,
+    pub framing: u64,
+    pub payload: u64,
+}
+
+impl ByteCount {
+    /// All bytes on the wire.
+    pub fn total(&self) -> u64 {
+        self.head + self.framing + self.payload
+    }
 }
 
 use core::fmt;
@@ -2,24 +2,46 @@ use core::fmt;
 
 use crate::{HootError, Result};
 
+/// Longest body framing header ending the headers: `Content-Length: <u64::MAX>\r\n\r\n`
+pub(crate) const MAX_BODY_HEADER_LEN: usize = 40;
+
+/// Ending headers without body: `\r\n`
+pub(crate) const END_HEADERS_LEN: usize = 2;
+
+/// Smallest possible chunk, one byte: `1\r\nX\r\n`. This is also larger than ending
+/// the chunked body: `0\r\n\r\n`.
+pub(crate) const MIN_CHUNK_LEN: usize = 6;
+
+/// Length of a header line: `<name>: <value>\r\n`
+pub(crate) fn header_line_len(name: &str, value: &[u8]) -> usize {
+    name.len() + 2 + value.len() + 2
+}
+
 pub(crate) struct Out<'a> {
     buf: &'a mut [u8],
     pos: usize,
+    limit: usize,
 }
 
 impl<'a> Out<'a> {
     pub fn wrap(buf: &'a mut [u8]) -> Self {
-        Out { buf, pos: 0 }
+        let limit = buf.len();
+        Out { buf, pos: 0, limit }
+    }
+
+    /// Limit the amount of the buffer used. `None` uses the entire buffer.
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        let len = self.buf.len();
+        self.limit = limit.map(|l| l.min(len)).unwrap_or(len);
     }
 
-    #[cfg(feature = "std")]
     pub fn reset_position(&mut self) {
         self.pos = 0;
     }
 
-    fn output<'b>(&mut self, bytes: &'b [u8], from: usize) -> Result<usize> {
+    fn output(&mut self, bytes: &[u8], from: usize) -> Result<usize> {
         let start = self.pos + from;
-        let remaining = self.buf.len() - start;
+        let remaining = self.limit.saturating_sub(start);
         let len = bytes.len();
 
         if len > remaining {
@@ -48,7 +70,7 @@ impl<'a> Out<'a> {
     }
 
     pub(crate) fn capacity(&self) -> usize {
-        self.buf.len() - self.pos
+        self.limit.saturating_sub(self.pos)
     }
 }
 
@@ -62,7 +84,7 @@ impl<'b, 'a> Writer<'b, 'a> {
     pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<usize> {
         let ret = self.out.output(bytes, self.inc.unwrap_or(0));
 
-        if let Err(_) = &ret {
+        if ret.is_err() {
             // Do not increase position if we encountered an error while writing.
             self.inc = None;
         } else {
@@ -102,6 +124,12 @@ impl<'b, 'a> Writer<'b, 'a> {
         (&used[s..e], rest)
     }
 
+    /// Remaining space after what is written by this writer.
+    pub fn capacity(&self) -> usize {
+        let inc = self.inc.unwrap_or(0);
+        self.out.capacity().saturating_sub(inc)
+    }
+
     pub fn commit(mut self) {
         if let Some(inc) = self.inc.take() {
             // Commit increase to borrowed Out.
@@ -123,6 +151,7 @@ mod test {
     use core::fmt::Write;
 
     #[test]
+    #[allow(clippy::write_literal)]
     pub fn write_and_commit() {
         let mut buf = [0; 1024];
         let mut out = Out::wrap(&mut buf);
@@ -137,4 +166,16 @@ mod test {
 
         assert_eq!(std::str::from_utf8(&buf[0..14]).unwrap(), "testing 123456");
     }
+
+    #[test]
+    pub fn write_limit() {
+        let mut buf = [0; 1024];
+        let mut out = Out::wrap(&mut buf);
+        out.set_limit(Some(10));
+        assert_eq!(out.capacity(), 10);
+
+        let mut w = out.writer();
+        assert!(w.write_bytes(b"0123456789").is_ok());
+        assert!(w.write_bytes(b"a").is_err());
+    }
 }
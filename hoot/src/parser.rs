@@ -1,15 +1,160 @@
+use crate::parse_impl::{self, EMPTY_HEADER};
+
+use crate::client::Status;
 use crate::header::transmute_headers;
+use crate::server::Line;
 use crate::util::cast_buf_for_headers;
-use crate::{Header, Result};
+use crate::{Header, HootError, HttpVersion, Result};
+
+/// How forgiving parsing of received messages is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Reject bare LF line endings, whitespace between header name and colon, and
+    /// status reasons with other bytes than HTAB, SP and visible ASCII.
+    Strict,
+    /// Accept what the parser accepts, which is like curl and browsers.
+    Lenient,
+}
+
+impl Default for ParseMode {
+    fn default() -> Self {
+        ParseMode::Lenient
+    }
+}
+
+/// Limits on a received message head, to bound the work spent on a peer sending
+/// huge or endless headers.
+#[derive(Default, Clone, Copy)]
+pub(crate) struct HeadLimits {
+    pub header_bytes: Option<usize>,
+    pub headers: Option<usize>,
+    pub line_len: Option<usize>,
+    pub target_len: Option<usize>,
+}
+
+impl HeadLimits {
+    /// Check the input of a head, complete with `used` bytes, or partial.
+    pub fn check(&self, input: &[u8], used: Option<usize>) -> Result<()> {
+        let head = match used {
+            Some(n) => &input[..n],
+            None => input,
+        };
+
+        if let Some(max) = self.line_len {
+            let end = head.iter().position(|c| *c == b'\n').unwrap_or(head.len());
+            let line = &head[..end];
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if line.len() > max {
+                return Err(HootError::HeaderOverflow);
+            }
+        }
+
+        if let Some(max) = self.target_len {
+            // The target is between the first and second space of the request line.
+            let end = head.iter().position(|c| *c == b'\n').unwrap_or(head.len());
+            let line = &head[..end];
+            if let Some(i) = line.iter().position(|c| *c == b' ') {
+                let rest = &line[i + 1..];
+                let len = rest.iter().position(|c| *c == b' ').unwrap_or(rest.len());
+                if len > max {
+                    return Err(HootError::TargetTooLong);
+                }
+            }
+        }
+
+        if let Some(max) = self.header_bytes {
+            // A partial head is longer than the input so far.
+            let over = match used {
+                Some(n) => n > max,
+                None => input.len() >= max,
+            };
+            if over {
+                return Err(HootError::HeaderOverflow);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Cut the input down to the max head size, to not parse further.
+    pub fn limit_input<'a>(&self, input: &'a [u8]) -> &'a [u8] {
+        let max = self.header_bytes.unwrap_or(input.len()).min(input.len());
+        &input[..max]
+    }
+
+    /// Cut the header buffer down to the max number of headers.
+    pub fn limit_headers<'a, T>(&self, headers: &'a mut [T]) -> &'a mut [T] {
+        let max = self.headers.unwrap_or(headers.len()).min(headers.len());
+        &mut headers[..max]
+    }
+
+    /// Check a parse error, where too many headers can be the max being passed.
+    ///
+    /// `capacity` is the number of headers that fit the buffer.
+    pub fn check_err(&self, capacity: usize, e: HootError) -> HootError {
+        match self.headers {
+            Some(max) if max <= capacity && e == HootError::TooManyHeaders => {
+                HootError::HeaderOverflow
+            }
+            _ => e,
+        }
+    }
+
+    /// Check the number of headers, when counting them one by one.
+    pub fn check_count(&self, count: usize) -> Result<()> {
+        match self.headers {
+            Some(max) if count > max => Err(HootError::HeaderOverflow),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Check a parsed message head, the request or status line and headers, for things
+/// [`ParseMode::Strict`] rejects.
+pub(crate) fn check_strict(head: &[u8], is_response: bool) -> Result<()> {
+    let mut lines = head.split_inclusive(|c| *c == b'\n');
+
+    for line in lines.clone() {
+        if !line.ends_with(b"\r\n") && line.ends_with(b"\n") {
+            return Err(HootError::NewLine);
+        }
+    }
+
+    // unwrap is ok, since head is a complete message head.
+    let first = lines.next().unwrap();
+
+    if is_response {
+        // HTTP-version SP status-code SP [ reason-phrase ]
+        let reason = first.splitn(3, |c| *c == b' ').nth(2).unwrap_or(b"");
+        let reason = reason.strip_suffix(b"\r\n").unwrap_or(reason);
+        let valid = |c: &u8| *c == b'\t' || *c == b' ' || c.is_ascii_graphic();
+        if !reason.iter().all(valid) {
+            return Err(HootError::Status);
+        }
+    }
+
+    for line in lines {
+        if line == b"\r\n" {
+            break;
+        }
+
+        let colon = line.iter().position(|c| *c == b':').unwrap_or(0);
+        if let Some(b' ') | Some(b'\t') = line[..colon].last() {
+            return Err(HootError::HeaderName);
+        }
+    }
+
+    Ok(())
+}
 
 pub(crate) fn parse_headers<'a, 'b>(src: &'a [u8], dst: &'b mut [u8]) -> Result<&'b [Header<'a>]> {
     let hbuf = cast_buf_for_headers(dst);
 
     // This parses into hbuf even if it fails due to an unfinished header line.
-    httparse::parse_headers(src, hbuf)?;
+    parse_impl::parse_headers(src, hbuf)?;
 
     // cast_buf_for_headers fills the array with EMPTY_HEADER where name=""
-    // httparse::parse_headers still writes all found headers.
+    // parse_impl::parse_headers still writes all found headers.
     // This behavior is asserted in a test below.
     let count = hbuf.iter().take_while(|h| !h.name.is_empty()).count();
 
@@ -19,6 +164,83 @@ pub(crate) fn parse_headers<'a, 'b>(src: &'a [u8], dst: &'b mut [u8]) -> Result<
     Ok(output)
 }
 
+/// Parse a single request line. The line must include the ending CRLF.
+pub(crate) fn parse_request_line(line: &[u8]) -> Result<Line<'_>> {
+    let mut r = parse_impl::Request::new(&mut []);
+
+    // Without any following headers, the result is always Partial. httparse
+    // does however fill in the fields for the complete request line.
+    r.parse(line)?;
+
+    let method = r.method.ok_or(HootError::Token)?.try_into()?;
+    let path = r.path.ok_or(HootError::Token)?;
+    let version = parse_version(r.version)?;
+
+    Ok(Line(method, path, version))
+}
+
+/// Parse a single status line. The line must include the ending CRLF.
+pub(crate) fn parse_status_line(line: &[u8]) -> Result<Status<'_>> {
+    let mut r = parse_impl::Response::new(&mut []);
+
+    // See parse_request_line()
+    r.parse(line)?;
+
+    let version = parse_version(r.version)?;
+    let code = r.code.ok_or(HootError::Status)?;
+    let text = r.reason.unwrap_or("");
+
+    Ok(Status(version, code, text))
+}
+
+/// Parse a single header line. The line must include the ending CRLF.
+pub(crate) fn parse_header_line(line: &[u8]) -> Result<Header<'_>> {
+    if is_obs_fold(line) {
+        return Err(HootError::ObsFold);
+    }
+
+    let mut hbuf = [EMPTY_HEADER; 1];
+
+    // See check_partial_httparse_parse_headers below.
+    parse_impl::parse_headers(line, &mut hbuf)?;
+
+    if hbuf[0].name.is_empty() {
+        return Err(HootError::HeaderName);
+    }
+
+    Ok(transmute_headers(&hbuf)[0])
+}
+
+/// Error for a failed parse of a message head.
+///
+/// The parser reports obsolete line folding as an invalid header name. This tells
+/// them apart, since a folded line can be used to smuggle headers past a proxy.
+pub(crate) fn head_error(input: &[u8], e: parse_impl::Error) -> HootError {
+    let folded = input
+        .split(|c| *c == b'\n')
+        .skip(1)
+        .take_while(|l| !l.is_empty() && *l != b"\r")
+        .any(is_obs_fold);
+
+    if folded {
+        HootError::ObsFold
+    } else {
+        e.into()
+    }
+}
+
+fn is_obs_fold(line: &[u8]) -> bool {
+    matches!(line.first(), Some(b' ') | Some(b'\t'))
+}
+
+fn parse_version(v: Option<u8>) -> Result<HttpVersion> {
+    match v {
+        Some(0) => Ok(HttpVersion::Http10),
+        Some(1) => Ok(HttpVersion::Http11),
+        _ => Err(HootError::Version),
+    }
+}
+
 pub(crate) fn find_crlf(b: &[u8]) -> Option<usize> {
     let cr = b.iter().position(|c| *c == b'\r')?;
     let maybe_lf = b.get(cr + 1)?;
@@ -43,31 +265,67 @@ mod test {
         assert_eq!(find_crlf(b" \r\n"), Some(1));
     }
 
+    #[test]
+    fn test_check_strict() {
+        let ok = b"HTTP/1.1 200 OK Fine\r\nA: 1\r\n\r\n";
+        assert!(check_strict(ok, true).is_ok());
+        assert!(check_strict(b"HTTP/1.1 204\r\n\r\n", true).is_ok());
+
+        let bare_lf = b"GET / HTTP/1.1\r\nHost: a\n\r\n";
+        assert_eq!(check_strict(bare_lf, false), Err(HootError::NewLine));
+
+        let space = b"GET / HTTP/1.1\r\nHost : a\r\n\r\n";
+        assert_eq!(check_strict(space, false), Err(HootError::HeaderName));
+
+        let reason = b"HTTP/1.1 200 \xffK\r\n\r\n";
+        assert_eq!(check_strict(reason, true), Err(HootError::Status));
+    }
+
+    #[test]
+    fn test_parse_single_lines() -> Result<()> {
+        let line = parse_request_line(b"GET /path HTTP/1.1\r\n")?;
+        assert_eq!(line.path(), "/path");
+        assert_eq!(line.version(), HttpVersion::Http11);
+
+        let status = parse_status_line(b"HTTP/1.0 404 Not Found\r\n")?;
+        assert_eq!(status, Status(HttpVersion::Http10, 404, "Not Found"));
+
+        let header = parse_header_line(b"X-Foo: bar\r\n")?;
+        assert_eq!(header.name(), "X-Foo");
+        assert_eq!(header.value(), "bar");
+
+        assert_eq!(
+            parse_header_line(b"X Foo: bar\r\n").unwrap_err(),
+            HootError::HeaderName
+        );
+        Ok(())
+    }
+
     #[test]
     fn check_partial_httparse_parse_headers() {
         const BUF_SIZE: usize = 2048;
 
         // Depending on 32- or 64-bit architecture this might differ.
-        const HEADER_SIZE: usize = mem::size_of::<httparse::Header>();
+        const HEADER_SIZE: usize = mem::size_of::<parse_impl::Header>();
         const HEADER_COUNT: usize = BUF_SIZE / HEADER_SIZE;
 
         let mut buf = [0; BUF_SIZE];
-        let mut headers = cast_buf_for_headers(&mut buf);
+        let headers = cast_buf_for_headers(&mut buf);
         assert_eq!(headers.len(), HEADER_COUNT);
 
         // All values should be "" before we try parsing.
-        assert!(headers.iter().all(|h| h.name == "" && h.value == b""));
+        assert!(headers.iter().all(|h| h.name.is_empty() && h.value == b""));
 
         // (missing last \n)
         const PARTIAL_INPUT: &[u8] = b"My-Header: 42\r\nSome-Partial: foo\r";
 
-        let r = httparse::parse_headers(PARTIAL_INPUT, &mut headers);
+        let r = parse_impl::parse_headers(PARTIAL_INPUT, headers);
 
         // the parse doesn't fail.
         assert!(r.is_ok());
 
         // but it also isn't complete.
-        assert!(matches!(r.unwrap(), httparse::Status::Partial));
+        assert!(matches!(r.unwrap(), parse_impl::Status::Partial));
 
         // Despite that, we can still detect the headers we did find by
         // checking for the first ""/""
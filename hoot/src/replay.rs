@@ -0,0 +1,287 @@
+//! Replay of captured traffic against golden files
+//!
+//! Test support for running captured byte traces through the
+//! [`EventParser`] and comparing the produced events to a previously
+//! recorded rendering (a "golden file"). This makes it possible to turn
+//! real world traffic into regression tests.
+//!
+//! Traces are text files where `\r`, `\n`, `\t`, `\\` and `\xNN` are escapes.
+//! Actual newlines in the file are ignored, which means a trace can be
+//! laid out over several lines for readability. Lines starting with `#`
+//! are comments.
+//!
+//! ```text
+//! # A response captured from example.test
+//! HTTP/1.1 200 OK\r\n
+//! Content-Length: 5\r\n
+//! \r\n
+//! hello
+//! ```
+//!
+//! The input is replayed in several fragment sizes to make sure the
+//! outcome does not depend on how the input is split up.
+//!
+//! Set the environment variable `HOOT_BLESS=1` to (re)write golden files.
+
+use std::fmt::Write;
+use std::path::Path;
+use std::string::String;
+use std::vec::Vec;
+use std::{env, fs};
+
+use crate::event::{Event, EventParser};
+use crate::Method;
+
+/// Fragment sizes each replay is run with.
+const FRAGMENT_SIZES: &[usize] = &[1, 2, 7, 64, usize::MAX];
+
+/// A captured trace to replay.
+pub struct Replay {
+    method: Option<Method>,
+    input: Vec<u8>,
+}
+
+impl Replay {
+    /// Replay a trace of a request.
+    pub fn request(trace: &str) -> Self {
+        Replay {
+            method: None,
+            input: decode_trace(trace),
+        }
+    }
+
+    /// Replay a trace of a response to a request with the given method.
+    pub fn response(method: Method, trace: &str) -> Self {
+        Replay {
+            method: Some(method),
+            input: decode_trace(trace),
+        }
+    }
+
+    /// The decoded input bytes.
+    pub fn input(&self) -> &[u8] {
+        &self.input
+    }
+
+    /// Render the events produced when feeding the input in fragments of the given size.
+    ///
+    /// Consecutive body chunks are joined, since their boundaries depend on the
+    /// fragment size. Parse errors are rendered as the last line.
+    pub fn render(&self, fragment_size: usize) -> String {
+        let mut parser = match self.method {
+            Some(m) => EventParser::response(m),
+            None => EventParser::request(),
+        };
+
+        let mut out = String::new();
+        let mut body: Vec<u8> = Vec::new();
+
+        // Bytes fed to the parser this far.
+        let mut fed = 0;
+        // Bytes consumed by the parser.
+        let mut pos = 0;
+
+        loop {
+            let input = &self.input[pos..fed];
+
+            let (used, event) = match parser.next_event(input) {
+                Ok(v) => v,
+                Err(e) => {
+                    flush_body(&mut out, &mut body);
+                    let _ = writeln!(out, "Error {:?}", e);
+                    return out;
+                }
+            };
+
+            pos += used;
+
+            let event = match event {
+                Some(v) => v,
+                None if used > 0 => continue,
+                None => {
+                    if fed == self.input.len() {
+                        break;
+                    }
+                    fed += fragment_size.min(self.input.len() - fed);
+                    continue;
+                }
+            };
+
+            if let Event::BodyChunk(data) = event {
+                body.extend_from_slice(data);
+                continue;
+            }
+
+            flush_body(&mut out, &mut body);
+            render_event(&mut out, &event);
+
+            if parser.is_ended() {
+                break;
+            }
+        }
+
+        flush_body(&mut out, &mut body);
+
+        if !parser.is_ended() {
+            let _ = writeln!(out, "Incomplete");
+        }
+
+        if pos < self.input.len() {
+            let _ = writeln!(out, "Leftover {}", self.input.len() - pos);
+        }
+
+        out
+    }
+
+    /// Assert the replay matches the golden file at `path`.
+    ///
+    /// All fragment sizes must produce the same rendering. If `HOOT_BLESS` is set,
+    /// the golden file is written instead.
+    pub fn assert_golden(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+
+        let rendered = self.render(usize::MAX);
+
+        for size in FRAGMENT_SIZES {
+            let other = self.render(*size);
+            assert_eq!(
+                rendered, other,
+                "Replay differs when fed in fragments of {}",
+                size
+            );
+        }
+
+        if env::var_os("HOOT_BLESS").is_some() {
+            fs::write(path, &rendered).expect("write golden file");
+            return;
+        }
+
+        let golden = match fs::read_to_string(path) {
+            Ok(v) => v,
+            Err(e) => panic!(
+                "Failed to read golden file {:?} ({}). Set HOOT_BLESS=1 to create it.",
+                path, e
+            ),
+        };
+
+        assert_eq!(golden, rendered, "Replay differs from {:?}", path);
+    }
+}
+
+/// Decode a text trace to bytes. See module documentation for the format.
+pub fn decode_trace(trace: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(trace.len());
+
+    for line in trace.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let mut bytes = line.bytes();
+
+        while let Some(c) = bytes.next() {
+            if c != b'\\' {
+                out.push(c);
+                continue;
+            }
+
+            match bytes.next() {
+                Some(b'r') => out.push(b'\r'),
+                Some(b'n') => out.push(b'\n'),
+                Some(b't') => out.push(b'\t'),
+                Some(b'\\') => out.push(b'\\'),
+                Some(b'x') => {
+                    let hi = bytes.next().and_then(hex_digit);
+                    let lo = bytes.next().and_then(hex_digit);
+                    match (hi, lo) {
+                        (Some(hi), Some(lo)) => out.push(hi << 4 | lo),
+                        _ => panic!("Bad \\x escape in trace"),
+                    }
+                }
+                _ => panic!("Unknown escape in trace"),
+            }
+        }
+    }
+
+    out
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
+
+fn flush_body(out: &mut String, body: &mut Vec<u8>) {
+    if body.is_empty() {
+        return;
+    }
+
+    let _ = write!(out, "Body {} \"", body.len());
+    for b in body.drain(..) {
+        let _ = write!(out, "{}", core::ascii::escape_default(b));
+    }
+    let _ = writeln!(out, "\"");
+}
+
+fn render_event(out: &mut String, event: &Event<'_>) {
+    let _ = match event {
+        Event::RequestLine(l) => writeln!(
+            out,
+            "RequestLine {:?} {} {:?}",
+            l.method(),
+            l.path(),
+            l.version()
+        ),
+        Event::StatusLine(s) => writeln!(
+            out,
+            "StatusLine {:?} {} {}",
+            s.version(),
+            s.code(),
+            s.text()
+        ),
+        Event::Header(h) => writeln!(
+            out,
+            "Header {}: {:?}",
+            h.name(),
+            String::from_utf8_lossy(h.value_raw())
+        ),
+        Event::HeadersEnd => writeln!(out, "HeadersEnd"),
+        Event::BodyChunk(_) => unreachable!("BodyChunk is joined"),
+        Event::Trailer(h) => writeln!(
+            out,
+            "Trailer {}: {:?}",
+            h.name(),
+            String::from_utf8_lossy(h.value_raw())
+        ),
+        Event::End => writeln!(out, "End"),
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_trace() {
+        let trace = "# comment\nGET / HTTP/1.1\\r\\n\n\\r\\n\\x41\\\\";
+        assert_eq!(decode_trace(trace), b"GET / HTTP/1.1\r\n\r\nA\\");
+    }
+
+    #[test]
+    fn test_render_fragments_equal() {
+        let r = Replay::response(
+            Method::GET,
+            "HTTP/1.1 200 OK\\r\\nTransfer-Encoding: chunked\\r\\n\\r\\n\
+            3\\r\\nabc\\r\\n0\\r\\n\\r\\n",
+        );
+        let whole = r.render(usize::MAX);
+        assert_eq!(
+            whole,
+            "StatusLine HTTP/1.1 200 OK\n\
+            Header Transfer-Encoding: \"chunked\"\n\
+            HeadersEnd\n\
+            Body 3 \"abc\"\n\
+            End\n"
+        );
+        assert_eq!(whole, r.render(1));
+    }
+}
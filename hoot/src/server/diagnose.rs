@@ -0,0 +1,224 @@
+use crate::header::is_tchar;
+
+/// Best-effort look at a request head that failed to read.
+///
+/// When [`Request::try_read_request()`][super::Request::try_read_request] fails, the error
+/// says what was wrong, but not where. This scans the same input again, and keeps what
+/// could be made out of it, for a `400 Bad Request` or a log line to say more than the
+/// bare error.
+///
+/// The method and path are there if the request line got that far. The offset is the
+/// first byte that doesn't belong in a request head, if there is one. An error such as
+/// [`HootError::MissingHost`][crate::HootError::MissingHost] has no such byte.
+///
+/// ```
+/// use hoot::server::diagnose_request;
+///
+/// let d = diagnose_request(b"GET /path HTTP/1.1\r\nHost: a\r\nBad Name: x\r\n\r\n");
+///
+/// assert_eq!(d.method(), Some("GET"));
+/// assert_eq!(d.path(), Some("/path"));
+/// assert_eq!(d.offset(), Some(32));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeadDiagnostics<'a> {
+    method: Option<&'a str>,
+    path: Option<&'a str>,
+    offset: Option<usize>,
+}
+
+impl<'a> HeadDiagnostics<'a> {
+    /// The method, if it is a valid token.
+    pub fn method(&self) -> Option<&'a str> {
+        self.method
+    }
+
+    /// The request target, if it was read in full.
+    pub fn path(&self) -> Option<&'a str> {
+        self.path
+    }
+
+    /// Offset in the input of the first offending byte.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+}
+
+/// Scan the input of a failed request head, see [`HeadDiagnostics`].
+pub fn diagnose_request(input: &[u8]) -> HeadDiagnostics<'_> {
+    let mut d = HeadDiagnostics::default();
+    let mut pos;
+
+    // method SP
+    let end = scan(input, 0, is_tchar);
+    match input.get(end) {
+        Some(b' ') if end > 0 => {
+            d.method = core::str::from_utf8(&input[..end]).ok();
+            pos = end + 1;
+        }
+        Some(_) => return d.at(end),
+        None => return d,
+    }
+
+    // request-target SP
+    let end = scan(input, pos, |c| c.is_ascii_graphic());
+    match input.get(end) {
+        Some(b' ') if end > pos => {
+            d.path = core::str::from_utf8(&input[pos..end]).ok();
+            pos = end + 1;
+        }
+        Some(_) => return d.at(end),
+        None => return d,
+    }
+
+    // HTTP-version CRLF
+    for (i, c) in b"HTTP/1.".iter().enumerate() {
+        match input.get(pos + i) {
+            Some(v) if v == c => {}
+            Some(_) => return d.at(pos + i),
+            None => return d,
+        }
+    }
+    pos += 7;
+    match input.get(pos) {
+        Some(b'0' | b'1') => pos += 1,
+        Some(_) => return d.at(pos),
+        None => return d,
+    }
+    pos = match newline(input, pos) {
+        Ok(Some(v)) => v,
+        Ok(None) => return d,
+        Err(at) => return d.at(at),
+    };
+
+    // *( field-name ":" OWS field-value OWS CRLF ) CRLF
+    loop {
+        match newline(input, pos) {
+            Ok(Some(_)) => return d,
+            Ok(None) if pos == input.len() => return d,
+            _ => {}
+        }
+
+        let end = scan(input, pos, is_tchar);
+        match input.get(end) {
+            Some(b':') if end > pos => pos = end + 1,
+            // Includes a line starting with whitespace, the obsolete line folding.
+            Some(_) => return d.at(end),
+            None => return d,
+        }
+
+        pos = scan(input, pos, |c| c == b'\t' || c >= b' ' && c != 0x7f);
+        pos = match newline(input, pos) {
+            Ok(Some(v)) => v,
+            Ok(None) => return d,
+            Err(at) => return d.at(at),
+        };
+    }
+}
+
+impl<'a> HeadDiagnostics<'a> {
+    fn at(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+}
+
+/// Position of the first byte from `pos` that isn't `valid`.
+fn scan(input: &[u8], pos: usize, valid: impl Fn(u8) -> bool) -> usize {
+    input[pos..]
+        .iter()
+        .position(|c| !valid(*c))
+        .map(|i| pos + i)
+        .unwrap_or(input.len())
+}
+
+/// Position after a line ending at `pos`, `None` if the input ends first. A bare `LF` is
+/// accepted like the parser does.
+fn newline(input: &[u8], pos: usize) -> Result<Option<usize>, usize> {
+    match (input.get(pos), input.get(pos + 1)) {
+        (Some(b'\n'), _) => Ok(Some(pos + 1)),
+        (Some(b'\r'), Some(b'\n')) => Ok(Some(pos + 2)),
+        (Some(b'\r'), None) | (None, _) => Ok(None),
+        (Some(_), _) => Err(pos),
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    fn diagnose(input: &str) -> (Option<&str>, Option<&str>, Option<usize>) {
+        let d = diagnose_request(input.as_bytes());
+        (d.method(), d.path(), d.offset())
+    }
+
+    /// Input, method, path and offset.
+    type Case<'a> = (&'a str, Option<&'a str>, Option<&'a str>, Option<usize>);
+
+    #[test]
+    fn test_diagnose_request() {
+        let cases: &[Case] = &[
+            (
+                "GET / HTTP/1.1\r\nHost: a\r\n\r\n",
+                Some("GET"),
+                Some("/"),
+                None,
+            ),
+            (
+                "GET / HTTP/1.1\r\nHost: a\r\n",
+                Some("GET"),
+                Some("/"),
+                None,
+            ),
+            ("GE", None, None, None),
+            ("G@T / HTTP/1.1\r\n", None, None, Some(1)),
+            (" GET / HTTP/1.1\r\n", None, None, Some(0)),
+            ("GET /a\x7fb HTTP/1.1\r\n", Some("GET"), None, Some(6)),
+            ("GET /\x01 HTTP/1.1\r\n", Some("GET"), None, Some(5)),
+            ("GET / HTTP/2.0\r\n", Some("GET"), Some("/"), Some(11)),
+            ("GET / HTTP/1.2\r\n", Some("GET"), Some("/"), Some(13)),
+            ("GET / HTTP/1.1 x\r\n", Some("GET"), Some("/"), Some(14)),
+            ("GET / HTTP/1.1\nHost: a\n\n", Some("GET"), Some("/"), None),
+            (
+                "GET / HTTP/1.1\r\nBad Name: a\r\n",
+                Some("GET"),
+                Some("/"),
+                Some(19),
+            ),
+            (
+                "GET / HTTP/1.1\r\nHost: a\r\n folded\r\n",
+                Some("GET"),
+                Some("/"),
+                Some(25),
+            ),
+            (
+                "GET / HTTP/1.1\r\n: a\r\n",
+                Some("GET"),
+                Some("/"),
+                Some(16),
+            ),
+            (
+                "GET / HTTP/1.1\r\nX: a\x00b\r\n",
+                Some("GET"),
+                Some("/"),
+                Some(20),
+            ),
+            (
+                "GET / HTTP/1.1\r\nX: caf\u{e9}\r\n\r\n",
+                Some("GET"),
+                Some("/"),
+                None,
+            ),
+            (
+                "GET / HTTP/1.1\r\nX: a\rb\r\n",
+                Some("GET"),
+                Some("/"),
+                Some(20),
+            ),
+        ];
+
+        for (input, method, path, offset) in cases {
+            assert_eq!(diagnose(input), (*method, *path, *offset), "{:?}", input);
+        }
+    }
+}
@@ -1,5 +1,17 @@
 //! Server HTTP/1.1 request and response
 //!
+//! [`Request`] parses the incoming request, and [`Response`] writes the
+//! response into a borrowed buffer without allocating. Like the client, the
+//! response is a type state machine:
+//!
+//! ```text
+//! SEND_STATUS -> SEND_HEADERS -> SEND_BODY -> (SEND_TRAILER) -> ENDED
+//! ```
+//!
+//! The method of the request decides which transitions are possible, e.g. a
+//! response to `HEAD` can't have a body. The [`ResponseVariant`] from
+//! [`Request::into_response()`] holds the token to start the response for each method.
+//!
 //! # Example
 //!
 //! ```
@@ -97,8 +109,11 @@
 //! # Ok::<(), hoot::HootError>(())
 //! ```
 
+mod diagnose;
+pub use diagnose::{diagnose_request, HeadDiagnostics};
+
 mod req;
-pub use req::{Line, Request};
+pub use req::{Line, Request, RequestAttempt, RequestHead, RequestParser};
 
 mod res;
-pub use res::{Response, ResponseVariant, ResumeToken};
+pub use res::{Response, ResponseVariant, ResumeToken, VectoredOutput};
@@ -1,13 +1,23 @@
+use core::fmt;
+use core::fmt::Write;
 use core::marker::PhantomData;
 
-use crate::body::{do_read_body, RecvBodyMode};
-use crate::error::Result;
-use crate::header::transmute_headers;
+use crate::ascii::eq_ignore_ascii;
+use crate::body::{do_body_size_hint, do_drain_body, do_read_body, do_read_body_borrowed};
+use crate::body::{do_try_read_trailers, Framing, RecvBodyMode};
+use crate::coding::{content_coding, ContentCoding};
+use crate::error::{Result, OVERFLOW};
+#[cfg(feature = "http_crate")]
+use crate::header::fill_header_map;
+use crate::header::{check_received_headers, transmute_headers};
+use crate::out::Out;
+use crate::parse_impl;
+use crate::parser::{check_strict, head_error};
 use crate::types::state::*;
 use crate::types::*;
-use crate::util::{cast_buf_for_headers, compare_lowercase_ascii, LengthChecker};
-use crate::{BodyPart, CallState};
-use crate::{Header, HootError, HttpVersion, Method};
+use crate::util::{cast_buf_for_headers, is_authority_form, LengthChecker};
+use crate::{BodyPart, ByteCount, CallState, TrailerAttempt};
+use crate::{Header, HootError, HttpVersion, Method, ParseMode, TransferCoding};
 
 use super::res::ResponseVariant;
 
@@ -33,6 +43,11 @@ impl<S: State> Request<S> {
         }
     }
 
+    /// Bytes received so far.
+    pub fn bytes_received(&self) -> ByteCount {
+        self.state.recv_bytes
+    }
+
     fn do_try_read_request<'a, 'b>(
         &mut self,
         input: &'a [u8],
@@ -47,22 +62,36 @@ impl<S: State> Request<S> {
             return Ok(RequestAttempt::empty());
         }
 
+        let limits = self.state.head_limits;
         let headers = cast_buf_for_headers(buf);
-        let mut r = httparse::Request::new(headers);
+        let capacity = headers.len();
+        let mut r = parse_impl::Request::new(limits.limit_headers(headers));
 
-        let input_used = match r.parse(input)? {
-            httparse::Status::Complete(v) => v,
-            httparse::Status::Partial => {
+        let parsed = r.parse(limits.limit_input(input));
+        let parsed = parsed.map_err(|e| limits.check_err(capacity, head_error(input, e)));
+
+        let input_used = match parsed? {
+            parse_impl::Status::Complete(v) => v,
+            parse_impl::Status::Partial => {
                 trace!("Read partial request");
+                limits.check(input, None)?;
                 return Ok(RequestAttempt::empty());
             }
         };
 
+        limits.check(input, Some(input_used))?;
+
+        if self.state.parse_mode == ParseMode::Strict {
+            check_strict(&input[..input_used], false)?;
+        }
+
         let method: Method = r.method.unwrap().try_into()?;
         self.state.method = Some(method);
 
         let path = r.path.unwrap();
 
+        check_target(method, path)?;
+
         let ver = match r.version.unwrap() {
             0 => HttpVersion::Http10,
             1 => HttpVersion::Http11,
@@ -77,19 +106,24 @@ impl<S: State> Request<S> {
         // Derive body mode from knowledge this far.
         let http10 = ver == HttpVersion::Http10;
         let headers = transmute_headers(r.headers);
+        check_received_headers(headers, !self.state.reject_obs_text)?;
         trace!("Headers: {:?}", headers);
 
-        let lookup = |name: &str| {
-            for header in &*headers {
-                if compare_lowercase_ascii(header.name(), name) {
-                    return Some(header.value());
-                }
-            }
-            None
+        // HTTP/1.1 requires a Host header. HTTP/1.0 predates it, and is
+        // treated like a tolerated missing header.
+        let host = headers.iter().find(|h| eq_ignore_ascii(h.name(), "host"));
+        let host = match host {
+            Some(v) => core::str::from_utf8(v.into_inner().value)?,
+            None if http10 || self.state.allow_missing_host => "",
+            None => return Err(HootError::MissingHost),
         };
 
-        let mode = RecvBodyMode::for_request(http10, method, &lookup)?;
+        let framing = Framing::from_headers(headers)?;
+        framing.check_request(http10)?;
+        let mode = RecvBodyMode::for_request_framing(http10, method, &framing);
         self.state.recv_body_mode = Some(mode);
+        self.state.transfer_coding = framing.coding();
+        self.state.content_coding = content_coding(headers);
         trace!("Body mode: {:?}", mode);
 
         // If we are awaiting a length, put a length checker in place
@@ -98,22 +132,73 @@ impl<S: State> Request<S> {
             self.state.recv_checker = Some(LengthChecker::new(len));
         }
 
+        self.state.recv_bytes.head += input_used as u64;
+
         Ok(RequestAttempt {
             input_used,
             line: Some(line),
+            host: Some(host),
             headers: Some(headers),
         })
     }
 }
 
+/// Check the request target is in a form for the method.
+///
+/// `CONNECT` takes the authority-form, `host:port`. Other methods take the origin-form,
+/// a path, or the absolute-form, a URI as sent to proxies. `OPTIONS` also takes `*`.
+fn check_target(method: Method, target: &str) -> Result<()> {
+    if method == Method::CONNECT {
+        if !is_authority_form(target) {
+            return Err(HootError::ConnectTarget);
+        }
+        return Ok(());
+    }
+
+    // The asterisk-form is only for server wide OPTIONS.
+    if target == "*" {
+        if method != Method::OPTIONS {
+            return Err(HootError::AsteriskForm);
+        }
+        return Ok(());
+    }
+
+    if target.starts_with('/') || is_absolute_form(target) {
+        return Ok(());
+    }
+
+    Err(HootError::TargetForm)
+}
+
+/// Whether the target starts with `scheme://`.
+fn is_absolute_form(target: &str) -> bool {
+    let scheme = match target.split_once("://") {
+        Some((v, _)) => v,
+        None => return false,
+    };
+
+    let mut chars = scheme.bytes();
+    let first_alpha = chars
+        .next()
+        .map(|c| c.is_ascii_alphabetic())
+        .unwrap_or(false);
+
+    first_alpha && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, b'+' | b'-' | b'.'))
+}
+
 pub struct RequestAttempt<'a, 'b> {
     input_used: usize,
     line: Option<Line<'a>>,
+    host: Option<&'a str>,
     headers: Option<&'b [Header<'a>]>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
-pub struct Line<'a>(Method, &'a str, HttpVersion);
+pub struct Line<'a>(
+    pub(crate) Method,
+    pub(crate) &'a str,
+    pub(crate) HttpVersion,
+);
 
 impl Line<'_> {
     pub fn method(&self) -> Method {
@@ -127,6 +212,14 @@ impl Line<'_> {
     pub fn version(&self) -> HttpVersion {
         self.2
     }
+
+    /// Whether this is an `OPTIONS *` request.
+    ///
+    /// The asterisk-form doesn't target a path, but asks for the capabilities of
+    /// the server as a whole.
+    pub fn is_asterisk(&self) -> bool {
+        self.1 == "*"
+    }
 }
 
 impl<'a, 'b> RequestAttempt<'a, 'b> {
@@ -134,6 +227,7 @@ impl<'a, 'b> RequestAttempt<'a, 'b> {
         RequestAttempt {
             input_used: 0,
             line: None,
+            host: None,
             headers: None,
         }
     }
@@ -150,12 +244,114 @@ impl<'a, 'b> RequestAttempt<'a, 'b> {
         self.line.as_ref()
     }
 
+    /// The value of the `Host` header.
+    ///
+    /// This is an empty string for requests without `Host`, which are HTTP/1.0
+    /// requests, or HTTP/1.1 requests when [`Request::allow_missing_host()`] is set.
+    pub fn host(&self) -> Option<&'a str> {
+        self.host
+    }
+
     pub fn headers(&self) -> Option<&'b [Header<'a>]> {
         self.headers
     }
+
+    /// Write the body of a response to `TRACE`, which echoes the request head.
+    ///
+    /// The body is sent with `Content-Type: message/http`. Headers with credentials,
+    /// `Authorization`, `Proxy-Authorization` and `Cookie`, are left out, since they
+    /// could leak to scripts that are otherwise not allowed to read them. Returns the
+    /// amount written to `dst`, which is 0 if the request is not read.
+    pub fn trace_body(&self, dst: &mut [u8]) -> Result<usize> {
+        let (line, headers) = match (self.line, self.headers) {
+            (Some(line), Some(headers)) => (line, headers),
+            _ => return Ok(0),
+        };
+
+        let mut out = Out::wrap(dst);
+        let mut w = out.writer();
+
+        write!(w, "{:?} {} {:?}\r\n", line.0, line.1, line.2).or(OVERFLOW)?;
+
+        for header in headers {
+            if TRACE_EXCLUDED
+                .iter()
+                .any(|h| eq_ignore_ascii(h, header.name()))
+            {
+                continue;
+            }
+            w.write_bytes(header.name().as_bytes())?;
+            w.write_bytes(b": ")?;
+            w.write_bytes(header.value_raw())?;
+            w.write_bytes(b"\r\n")?;
+        }
+
+        w.write_bytes(b"\r\n")?;
+        w.commit();
+
+        Ok(out.as_bytes().len())
+    }
 }
 
+/// Headers with credentials, that are not echoed by `TRACE`.
+const TRACE_EXCLUDED: &[&str] = &["authorization", "proxy-authorization", "cookie"];
+
 impl Request<RECV_REQUEST> {
+    /// Reject received header values with obs-text, i.e. bytes 0x80-0xff.
+    ///
+    /// Other invalid bytes (C0 controls except HTAB, and DEL) are always rejected. To
+    /// be lenient with those, run the input through [`sanitize_field_values()`][crate::sanitize_field_values]
+    /// before reading.
+    pub fn reject_obs_text(&mut self) {
+        self.state.reject_obs_text = true;
+    }
+
+    /// Accept HTTP/1.1 requests without a `Host` header.
+    ///
+    /// Such requests are invalid, and by default rejected with [`HootError::MissingHost`],
+    /// which a server should answer with 400. Some health checkers and scanners omit the
+    /// header anyway. With this set, the request is read as having an empty authority.
+    pub fn allow_missing_host(&mut self) {
+        self.state.allow_missing_host = true;
+    }
+
+    /// Set how forgiving reading the request is, [`ParseMode::Lenient`] by default.
+    ///
+    /// A gateway that must not read messages differently than the servers behind it,
+    /// would use [`ParseMode::Strict`].
+    pub fn parse_mode(&mut self, mode: ParseMode) {
+        self.state.parse_mode = mode;
+    }
+
+    /// Fail with [`HootError::HeaderOverflow`] for a request head, the request line and headers,
+    /// larger than `max` bytes.
+    ///
+    /// Without this, the head is only limited by the input the caller buffers.
+    pub fn max_header_bytes(&mut self, max: usize) {
+        self.state.head_limits.header_bytes = Some(max);
+    }
+
+    /// Fail with [`HootError::HeaderOverflow`] for more than `max` headers.
+    ///
+    /// Without this, the headers are only limited by the buffer given for them, which
+    /// fails with [`HootError::TooManyHeaders`].
+    pub fn max_headers(&mut self, max: usize) {
+        self.state.head_limits.headers = Some(max);
+    }
+
+    /// Fail with [`HootError::HeaderOverflow`] for a request line longer than `max` bytes.
+    pub fn max_request_line_len(&mut self, max: usize) {
+        self.state.head_limits.line_len = Some(max);
+    }
+
+    /// Fail with [`HootError::TargetTooLong`] for a request target longer than `max` bytes.
+    ///
+    /// Unlike [`max_request_line_len()`][Self::max_request_line_len], the error tells the
+    /// target apart, so it can be answered with `414 URI Too Long`.
+    pub fn max_target_len(&mut self, max: usize) {
+        self.state.head_limits.target_len = Some(max);
+    }
+
     pub fn try_read_request<'a, 'b>(
         &mut self,
         input: &'a [u8],
@@ -167,10 +363,103 @@ impl Request<RECV_REQUEST> {
     pub fn proceed(self) -> Request<RECV_BODY> {
         self.transition()
     }
+
+    /// Parse the request head from input arriving in pieces.
+    ///
+    /// The input is collected in `input` until the head is complete, and `headers` is
+    /// the buffer for parsing headers, like in [`try_read_request()`][Self::try_read_request].
+    pub fn parser<'a>(self, input: &'a mut [u8], headers: &'a mut [u8]) -> RequestParser<'a> {
+        RequestParser {
+            request: Some(self),
+            input,
+            headers,
+            len: 0,
+        }
+    }
+}
+
+/// Push parser for a request head, from [`Request::parser()`].
+///
+/// Each call to [`try_parse()`][Self::try_parse] adds more input, until the head is complete.
+/// The caller doesn't need to find the end of the head. The parser only takes the input up to
+/// the end of the head, and the rest, such as the start of the body, is left to the caller.
+pub struct RequestParser<'a> {
+    request: Option<Request<RECV_REQUEST>>,
+    input: &'a mut [u8],
+    headers: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> RequestParser<'a> {
+    /// Add input and try to parse the head.
+    ///
+    /// Returns `None` while the head is incomplete, in which case all of `more` was used.
+    /// Fails with [`HootError::HeaderOverflow`] if the input buffer is full before the
+    /// head is complete. Once the head is returned, the parser is done.
+    pub fn try_parse(&mut self, more: &[u8]) -> Result<Option<RequestHead<'_>>> {
+        let request = match self.request.as_mut() {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        let before = self.len;
+        let space = &mut self.input[before..];
+        let amount = more.len().min(space.len());
+        space[..amount].copy_from_slice(&more[..amount]);
+        self.len += amount;
+
+        let attempt = request.try_read_request(&self.input[..self.len], self.headers)?;
+
+        if !attempt.is_success() {
+            if amount < more.len() {
+                return Err(HootError::HeaderOverflow);
+            }
+            return Ok(None);
+        }
+
+        let consumed = attempt.input_used() - before;
+        trace!("Parsed request head, used {} of {}", consumed, more.len());
+
+        Ok(Some(RequestHead {
+            attempt,
+            request: self.request.take().unwrap(),
+            consumed,
+        }))
+    }
+}
+
+/// A complete request head, from [`RequestParser::try_parse()`].
+///
+/// Derefs to the [`RequestAttempt`] for the request line and headers.
+pub struct RequestHead<'a> {
+    attempt: RequestAttempt<'a, 'a>,
+    request: Request<RECV_REQUEST>,
+    consumed: usize,
+}
+
+impl<'a> RequestHead<'a> {
+    /// Amount of the input, given to the last `try_parse()`, that was part of the head.
+    ///
+    /// Input after this belongs to the request body, or the next request.
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    pub fn proceed(self) -> Request<RECV_BODY> {
+        self.request.proceed()
+    }
+}
+
+impl<'a> core::ops::Deref for RequestHead<'a> {
+    type Target = RequestAttempt<'a, 'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.attempt
+    }
 }
 
 impl Request<RECV_BODY> {
-    pub fn read_body<'a, 'b>(&mut self, src: &'a [u8], dst: &'b mut [u8]) -> Result<BodyPart<'b>> {
+    pub fn read_body<'b>(&mut self, src: &[u8], dst: &'b mut [u8]) -> Result<BodyPart<'b>> {
         let already_read_response = self.state.recv_body_mode.is_some();
 
         // It's valid to skip try_read_response() and progress straight to reading
@@ -188,6 +477,60 @@ impl Request<RECV_BODY> {
         do_read_body(&mut self.state, src, dst)
     }
 
+    /// Like [`read_body()`][Self::read_body], but the body data is borrowed from the input
+    /// instead of copied.
+    ///
+    /// The request line and headers must be read before, otherwise no input is used. For chunked bodies,
+    /// each part holds data from one chunk at most, and can be empty while there is
+    /// input left, such as for the chunk framing.
+    pub fn read_body_borrowed<'a>(&mut self, src: &'a [u8]) -> Result<BodyPart<'a>> {
+        do_read_body_borrowed(&mut self.state, src)
+    }
+
+    /// Bounds of the body left to read, as `(lower, upper)`, like `Iterator::size_hint()`.
+    ///
+    /// With a content-length, this is exact. For chunked bodies, the lower bound is the
+    /// rest of the current chunk, and there is no upper bound. Nothing is known before the
+    /// request line and headers is read, or for bodies delimited by closing the connection.
+    pub fn body_size_hint(&self) -> (u64, Option<u64>) {
+        do_body_size_hint(&self.state)
+    }
+
+    /// Transfer coding of the body, besides chunked, which is not undone by hoot.
+    pub fn transfer_coding(&self) -> Option<TransferCoding> {
+        self.state.transfer_coding
+    }
+
+    /// Content coding of the body, from `Content-Encoding`, which is not undone by hoot.
+    ///
+    /// A coding hoot doesn't know, or more than one, is an error.
+    pub fn content_coding(&self) -> Result<Option<ContentCoding>> {
+        self.state.content_coding.transpose()
+    }
+
+    /// Read the trailers following a chunked body.
+    ///
+    /// Use this when [`BodyPart::is_trailers_pending()`] is true. Like for the headers,
+    /// the buffer is used to parse the trailers into. Once the trailers are read, the
+    /// body is finished. For bodies that are not chunked, this never succeeds.
+    pub fn try_read_trailers<'a, 'b>(
+        &mut self,
+        input: &'a [u8],
+        buf: &'b mut [u8],
+    ) -> Result<TrailerAttempt<'a, 'b>> {
+        do_try_read_trailers(&mut self.state, input, buf)
+    }
+
+    /// Skip body input without copying it.
+    ///
+    /// This is for when the body isn't wanted, but the connection should be kept
+    /// for another request. Returns the amount of input used. Repeat with more input
+    /// until [`is_finished()`][Self::is_finished]. The status and headers must be read
+    /// before draining, otherwise no input is used.
+    pub fn drain(&mut self, src: &[u8]) -> Result<usize> {
+        do_drain_body(&mut self.state, src)
+    }
+
     pub fn is_finished(&self) -> bool {
         use RecvBodyMode::*;
 
@@ -216,32 +559,301 @@ impl Request<RECV_BODY> {
     }
 }
 
+impl fmt::Debug for Line<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Line")
+            .field(&self.0)
+            .field(&self.1)
+            .field(&self.2)
+            .finish()
+    }
+}
+
 #[cfg(feature = "http_crate")]
 impl<'a, 'b> TryFrom<RequestAttempt<'a, 'b>> for http::Request<()> {
     type Error = HootError;
 
     fn try_from(attempt: RequestAttempt<'a, 'b>) -> Result<Self> {
-        if !attempt.is_success() {
+        attempt.try_into_http_with(http::HeaderMap::new())
+    }
+}
+
+#[cfg(feature = "http_crate")]
+impl<'a, 'b> RequestAttempt<'a, 'b> {
+    /// Convert to an `http::Request`, reusing the given `HeaderMap` for the headers.
+    ///
+    /// The map is cleared before use. Passing a map from an earlier request avoids
+    /// allocating a new one for every request.
+    pub fn try_into_http_with(self, mut headers: http::HeaderMap) -> Result<http::Request<()>> {
+        if !self.is_success() {
             return Err(HootError::IncompleteRequestAttempt);
         }
 
         // unwraps ok due to is_success() check above.
-        let line = attempt.line().unwrap();
-        let headers = attempt.headers().unwrap();
+        let line = self.line().unwrap();
+        fill_header_map(&mut headers, self.headers().unwrap())?;
 
-        let mut builder = http::Request::builder()
+        let mut req = http::Request::builder()
             .version(line.version().into())
             .method(line.method())
-            .uri(line.path());
-
-        for header in headers {
-            builder = builder.header(header.name(), header.value());
-        }
-
-        let req = builder
+            .uri(line.path())
             .body(())
             .expect("Successful http::Request conversion");
 
+        *req.headers_mut() = headers;
+
         Ok(req)
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_missing_host() -> Result<()> {
+        let input = b"GET /health HTTP/1.1\r\n\r\n";
+        let mut buf = [0; 1024];
+
+        let mut r = Request::new();
+        let e = r.try_read_request(input, &mut buf).err();
+        assert_eq!(e, Some(HootError::MissingHost));
+
+        let mut r = Request::new();
+        r.allow_missing_host();
+        let a = r.try_read_request(input, &mut buf)?;
+        assert!(a.is_success());
+        assert_eq!(a.host(), Some(""));
+
+        // HTTP/1.0 has no Host requirement.
+        let mut r = Request::new();
+        let a = r.try_read_request(b"GET /health HTTP/1.0\r\n\r\n", &mut buf)?;
+        assert_eq!(a.host(), Some(""));
+
+        let mut r = Request::new();
+        let a = r.try_read_request(b"GET / HTTP/1.1\r\nhost: a.test\r\n\r\n", &mut buf)?;
+        assert_eq!(a.host(), Some("a.test"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_conflicting_framing() {
+        let mut buf = [0; 1024];
+
+        let mut read = |input: &[u8]| {
+            let mut r = Request::new();
+            r.try_read_request(input, &mut buf).err()
+        };
+
+        let e = read(
+            b"POST / HTTP/1.1\r\nHost: a.test\r\nContent-Length: 5\r\n\
+            Transfer-Encoding: chunked\r\n\r\n",
+        );
+        assert_eq!(e, Some(HootError::ContentLengthAndTransferEncoding));
+
+        let e = read(
+            b"POST / HTTP/1.1\r\nHost: a.test\r\nContent-Length: 5\r\n\
+            Content-Length: 6\r\n\r\n",
+        );
+        assert_eq!(e, Some(HootError::DuplicateContentLength));
+
+        let e = read(
+            b"POST / HTTP/1.1\r\nHost: a.test\r\nX-A: 1\r\n\
+            \tTransfer-Encoding: chunked\r\n\r\n",
+        );
+        assert_eq!(e, Some(HootError::ObsFold));
+
+        // Without chunked, the body can't be told from the next request.
+        let e = read(
+            b"POST / HTTP/1.1\r\nHost: a.test\r\nTransfer-Encoding: gzip\r\n\r\n\
+            GET /smuggled HTTP/1.1\r\nHost: a.test\r\n\r\n",
+        );
+        assert_eq!(e, Some(HootError::UnframedTransferEncoding));
+
+        let e = read(
+            b"POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5\r\nhello\r\n0\r\n\r\n",
+        );
+        assert_eq!(e, Some(HootError::UnframedTransferEncoding));
+    }
+
+    #[test]
+    fn test_parse_mode() -> Result<()> {
+        let input = b"GET / HTTP/1.1\nHost: a.test\n\n";
+        let mut buf = [0; 1024];
+
+        let mut r = Request::new();
+        assert!(r.try_read_request(input, &mut buf)?.is_success());
+
+        let mut r = Request::new();
+        r.parse_mode(ParseMode::Strict);
+        let e = r.try_read_request(input, &mut buf).err();
+        assert_eq!(e, Some(HootError::NewLine));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_head_limits() -> Result<()> {
+        let input = b"GET /path HTTP/1.1\r\nHost: a.test\r\nX-A: 1\r\n\r\n";
+        let mut buf = [0; 1024];
+
+        let mut r = Request::new();
+        r.max_header_bytes(input.len());
+        r.max_headers(2);
+        r.max_request_line_len(18);
+        assert!(r.try_read_request(input, &mut buf)?.is_success());
+
+        let mut read = |f: &dyn Fn(&mut Request<RECV_REQUEST>), input: &[u8]| {
+            let mut r = Request::new();
+            f(&mut r);
+            r.try_read_request(input, &mut buf).err()
+        };
+
+        let overflow = Some(HootError::HeaderOverflow);
+        assert_eq!(read(&|r| r.max_header_bytes(20), input), overflow);
+        assert_eq!(read(&|r| r.max_headers(1), input), overflow);
+        assert_eq!(read(&|r| r.max_request_line_len(17), input), overflow);
+
+        // Fails before the head is complete.
+        assert_eq!(read(&|r| r.max_header_bytes(20), &input[..25]), overflow);
+        assert_eq!(read(&|r| r.max_request_line_len(5), b"GET /pa"), overflow);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_asterisk_form() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let mut r = Request::new();
+        let a = r.try_read_request(b"OPTIONS * HTTP/1.1\r\nHost: a.test\r\n\r\n", &mut buf)?;
+        assert!(a.line().unwrap().is_asterisk());
+
+        let mut r = Request::new();
+        let a = r.try_read_request(b"OPTIONS /x HTTP/1.1\r\nHost: a.test\r\n\r\n", &mut buf)?;
+        assert!(!a.line().unwrap().is_asterisk());
+
+        let mut r = Request::new();
+        let e = r
+            .try_read_request(b"GET * HTTP/1.1\r\nHost: a.test\r\n\r\n", &mut buf)
+            .err();
+        assert_eq!(e, Some(HootError::AsteriskForm));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trace_body() -> Result<()> {
+        let input = b"TRACE /x HTTP/1.1\r\nHost: a.test\r\nCookie: s=1\r\n\
+            X-Via: b\r\nauthorization: Basic Zm9v\r\n\r\n";
+        let mut buf = [0; 1024];
+
+        let mut r = Request::new();
+        let a = r.try_read_request(input, &mut buf)?;
+
+        let mut dst = [0; 1024];
+        let n = a.trace_body(&mut dst)?;
+        assert_eq!(
+            &dst[..n],
+            b"TRACE /x HTTP/1.1\r\nHost: a.test\r\nX-Via: b\r\n\r\n"
+        );
+
+        let mut dst = [0; 10];
+        assert_eq!(
+            a.trace_body(&mut dst).err(),
+            Some(HootError::OutputOverflow)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_request_parser() -> Result<()> {
+        let input = b"POST /x HTTP/1.1\r\nHost: a.test\r\nContent-Length: 2\r\n\r\nokGET";
+        let head_len = input.len() - 5;
+
+        let mut buf = [0; 1024];
+        let mut headers = [0; 1024];
+
+        // Byte by byte, up to the last one of the head.
+        let mut p = Request::new().parser(&mut buf, &mut headers);
+        for i in 0..head_len - 1 {
+            assert!(p.try_parse(&input[i..i + 1])?.is_none());
+        }
+
+        let head = p.try_parse(&input[head_len - 1..])?.unwrap();
+        assert_eq!(head.consumed(), 1);
+        assert_eq!(head.line().unwrap().path(), "/x");
+        assert_eq!(head.host(), Some("a.test"));
+
+        let mut r = head.proceed();
+        let mut dst = [0; 10];
+        let part = r.read_body(&input[head_len..], &mut dst)?;
+        assert_eq!(part.input_used(), 2);
+        assert!(r.is_finished());
+
+        // All at once.
+        let mut p = Request::new().parser(&mut buf, &mut headers);
+        let head = p.try_parse(input)?.unwrap();
+        assert_eq!(head.consumed(), head_len);
+
+        // Too much head for the buffer.
+        let mut small = [0; 20];
+        let mut p = Request::new().parser(&mut small, &mut headers);
+        assert!(p.try_parse(&input[..10])?.is_none());
+        let e = p.try_parse(&input[10..]).err();
+        assert_eq!(e, Some(HootError::HeaderOverflow));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_target() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let mut read = |max: Option<usize>, line: &str| {
+            let input = format!("{}\r\nHost: a.test\r\n\r\n", line);
+
+            let mut r = Request::new();
+            if let Some(max) = max {
+                r.max_target_len(max);
+            }
+            r.try_read_request(input.as_bytes(), &mut buf)
+                .map(|a| a.is_success())
+        };
+
+        assert_eq!(read(None, "GET /x?y=1 HTTP/1.1"), Ok(true));
+        assert_eq!(read(None, "GET http://a.test/x HTTP/1.1"), Ok(true));
+        assert_eq!(read(None, "CONNECT a.test:443 HTTP/1.1"), Ok(true));
+        assert_eq!(read(None, "OPTIONS * HTTP/1.1"), Ok(true));
+
+        let form = Err(HootError::TargetForm);
+        assert_eq!(read(None, "GET x HTTP/1.1"), form);
+        assert_eq!(read(None, "GET a.test:443 HTTP/1.1"), form);
+        assert_eq!(read(None, "GET 1http://a.test/ HTTP/1.1"), form);
+        assert_eq!(
+            read(None, "CONNECT /x HTTP/1.1"),
+            Err(HootError::ConnectTarget)
+        );
+        assert_eq!(
+            read(None, "CONNECT u@a.test:443 HTTP/1.1"),
+            Err(HootError::ConnectTarget)
+        );
+
+        assert_eq!(read(Some(6), "GET /abcde HTTP/1.1"), Ok(true));
+        assert_eq!(
+            read(Some(5), "GET /abcde HTTP/1.1"),
+            Err(HootError::TargetTooLong)
+        );
+
+        // Fails before the request line is complete.
+        let mut r = Request::new();
+        r.max_target_len(5);
+        let e = r.try_read_request(b"GET /abcdef", &mut buf).err();
+        assert_eq!(e, Some(HootError::TargetTooLong));
+
+        Ok(())
+    }
+}
@@ -3,14 +3,17 @@ use core::marker::PhantomData;
 use core::ops::Deref;
 
 use crate::error::{Result, OVERFLOW};
-use crate::header::check_and_output_header;
-use crate::out::Out;
+use crate::header::{check_and_output_header, check_and_output_header_streamed};
+use crate::header::{check_trailer_name, output_header_continue};
+use crate::header::{write_generated, write_name};
+use crate::out::{header_line_len, Out};
+use crate::out::{END_HEADERS_LEN, MAX_BODY_HEADER_LEN, MIN_CHUNK_LEN};
 use crate::types::body::*;
 use crate::types::method::*;
 use crate::types::state::*;
 use crate::util::LengthChecker;
 use crate::{types::*, BodyWriter};
-use crate::{CallState, HootError, HttpVersion};
+use crate::{ByteCount, CallState, HeaderCasing, HootError, HttpVersion};
 
 pub enum ResponseVariant {
     Get(ResumeToken<SEND_STATUS, GET, ()>),
@@ -59,7 +62,7 @@ pub struct Output<'a, S: State, M: Method, B: BodyType> {
 }
 
 impl<'a, S: State, M: Method, B: BodyType> Response<'a, S, M, B> {
-    fn transition<S2: State, M2: Method, B2: BodyType>(self) -> Response<'a, S2, M2, B2> {
+    fn transition<S2: State, M2: Method, B2: BodyType>(mut self) -> Response<'a, S2, M2, B2> {
         trace!(
             "Transition: {}/{}/{} -> {}/{}/{}",
             S::state_name(),
@@ -70,6 +73,10 @@ impl<'a, S: State, M: Method, B: BodyType> Response<'a, S, M, B> {
             B2::state_name(),
         );
 
+        if S::is_sending_head() && !S2::is_sending_head() {
+            self.state.mark_head_sent(self.out.as_bytes().len());
+        }
+
         Response {
             typ: Typ(PhantomData, PhantomData, PhantomData),
             state: self.state,
@@ -78,19 +85,64 @@ impl<'a, S: State, M: Method, B: BodyType> Response<'a, S, M, B> {
     }
 
     fn header_raw(mut self, name: &str, bytes: &[u8], trailer: bool) -> Result<Self> {
+        self.state.check_header_done()?;
         let ver = self.state.version.unwrap();
+        let casing = self.state.header_casing;
         // Attempt writing the header
         let w = self.out.writer();
-        check_and_output_header(w, ver, name, bytes, trailer)?;
+        check_and_output_header(w, ver, name, bytes, trailer, casing)?;
         Ok(self)
     }
 
+    fn header_streamed_raw(
+        mut self,
+        name: &str,
+        bytes: &[u8],
+        trailer: bool,
+    ) -> Result<(Self, usize)> {
+        self.state.check_header_done()?;
+        let ver = self.state.version.unwrap();
+        let casing = self.state.header_casing;
+        let w = self.out.writer();
+        let (amount, left) =
+            check_and_output_header_streamed(w, ver, name, bytes, trailer, casing)?;
+        self.state.header_left = left;
+        Ok((self, amount))
+    }
+
+    fn header_continue_raw(mut self, bytes: &[u8]) -> Result<(Self, usize)> {
+        let left = match self.state.header_left {
+            Some(v) => v,
+            None => return Err(HootError::StreamedHeader),
+        };
+        let w = self.out.writer();
+        let (amount, left) = output_header_continue(w, bytes, left)?;
+        self.state.header_left = left;
+        Ok((self, amount))
+    }
+
+    /// Value bytes left to write for a streamed header. `None` if no streamed header is in progress.
+    pub fn header_left(&self) -> Option<usize> {
+        self.state.header_left
+    }
+
     pub fn capacity(&self) -> usize {
         self.out.capacity()
     }
 
-    pub fn flush(self) -> Output<'a, S, M, B> {
+    /// Bytes sent so far, including output not yet flushed.
+    pub fn bytes_sent(&self) -> ByteCount {
+        self.state.bytes_sent(self.out.as_bytes().len())
+    }
+
+    /// Bytes received for the request this is a response to.
+    pub fn bytes_received(&self) -> ByteCount {
+        self.state.recv_bytes
+    }
+
+    pub fn flush(mut self) -> Output<'a, S, M, B> {
         trace!("Flush");
+        self.state.sent_total += self.out.as_bytes().len() as u64;
         Output {
             token: ResumeToken {
                 typ: self.typ,
@@ -103,6 +155,7 @@ impl<'a, S: State, M: Method, B: BodyType> Response<'a, S, M, B> {
     #[cfg(feature = "std")]
     pub fn write_to(mut self, write: &mut dyn std::io::Write) -> std::io::Result<Self> {
         write.write_all(self.out.as_bytes())?;
+        self.state.sent_total += self.out.as_bytes().len() as u64;
         self.out.reset_position();
         Ok(self)
     }
@@ -119,6 +172,53 @@ impl<'a, S: State, M: Method, B: BodyType> Response<'a, S, M, B> {
             state: token.state,
             out: Out::wrap(buf),
         }
+        .with_limit()
+    }
+
+    fn with_limit(mut self) -> Self {
+        self.out.set_limit(self.state.max_output);
+        self
+    }
+
+    /// Never use more than `max` bytes of the output buffer.
+    ///
+    /// This caps the size of each flushed output, for transports that require a
+    /// maximum frame size. The limit is kept when resuming with another buffer.
+    pub fn max_output(mut self, max: usize) -> Self {
+        trace!("Max output: {}", max);
+        self.state.max_output = Some(max);
+        self.with_limit()
+    }
+
+    /// Split chunked body data so that chunk boundaries fall on multiples of `size`
+    /// in the body.
+    ///
+    /// Panics if `size` is 0.
+    pub fn align_chunks(mut self, size: usize) -> Self {
+        assert!(size > 0, "chunk alignment must be greater than 0");
+        trace!("Align chunks: {}", size);
+        self.state.chunked = self.state.chunked.align_chunks(size);
+        self
+    }
+
+    /// Never write chunks with more than `max` bytes of body data.
+    ///
+    /// Panics if `max` is 0.
+    pub fn max_chunk_size(mut self, max: usize) -> Self {
+        assert!(max > 0, "max chunk size must be greater than 0");
+        trace!("Max chunk size: {}", max);
+        self.state.chunked = self.state.chunked.max_chunk_size(max);
+        self
+    }
+
+    /// Case header names when writing them, for peers that are picky about casing.
+    ///
+    /// Applies to headers and trailers written after this, including the ones hoot adds,
+    /// such as `Host`, `Content-Length` and `Transfer-Encoding`.
+    pub fn header_casing(mut self, casing: HeaderCasing) -> Self {
+        trace!("Header casing: {:?}", casing);
+        self.state.header_casing = casing;
+        self
     }
 }
 
@@ -144,7 +244,70 @@ impl<'a, M: Method> Response<'a, SEND_STATUS, M, ()> {
     }
 }
 
+impl<'a, M: Method> Response<'a, SEND_STATUS, M, ()> {
+    /// Send an interim (1xx) response ahead of the final response.
+    ///
+    /// Used for `103 Early Hints`, to let the client preload resources while the final
+    /// response is prepared. The interim response has no body, and the state stays in
+    /// sending status, which means the final response must still follow. `101 Switching
+    /// Protocols` is not interim, and HTTP/1.0 clients don't understand interim responses,
+    /// which both error with [`HootError::InterimStatus`].
+    pub fn interim(mut self, code: u16, text: &str, headers: &[(&str, &str)]) -> Result<Self> {
+        // Unwrap is OK, because the request must have set the version.
+        let ver = self.state.version.unwrap();
+
+        if !(100..200).contains(&code) || code == 101 || ver == HttpVersion::Http10 {
+            return Err(HootError::InterimStatus);
+        }
+
+        trace!("Send interim: {} {}", code, text);
+
+        let mut w = self.out.writer();
+        write!(w, "HTTP/1.1 {} {}\r\n", code, text).or(OVERFLOW)?;
+        w.commit();
+
+        let casing = self.state.header_casing;
+        for (name, value) in headers {
+            check_and_output_header(
+                self.out.writer(),
+                ver,
+                name,
+                value.as_bytes(),
+                false,
+                casing,
+            )?;
+        }
+
+        let mut w = self.out.writer();
+        write!(w, "\r\n").or(OVERFLOW)?;
+        w.commit();
+
+        Ok(self)
+    }
+}
+
 impl<'a, M: Method> Response<'a, SEND_HEADERS, M, ()> {
+    /// The smallest output buffer size that guarantees forward progress in this state.
+    ///
+    /// For headers this is the size needed to end the headers, including the
+    /// body framing header. Compare with the buffer size (or [`max_output()`][Self::max_output])
+    /// to detect configurations that would never progress.
+    pub fn min_output_required(&self) -> usize {
+        let has_body = !M::is_head() && !M::is_connect();
+
+        if has_body {
+            MAX_BODY_HEADER_LEN
+        } else {
+            END_HEADERS_LEN
+        }
+    }
+
+    /// Like [`min_output_required()`][Self::min_output_required], but also considering
+    /// a pending header to be written.
+    pub fn min_output_required_for_header(&self, name: &str, value: &[u8]) -> usize {
+        header_line_len(name, value).max(self.min_output_required())
+    }
+
     pub fn header(self, name: &str, value: &str) -> Result<Self> {
         trace!("Set header {}: {}", name, value);
         self.header_raw(name, value.as_bytes(), false)
@@ -154,6 +317,25 @@ impl<'a, M: Method> Response<'a, SEND_HEADERS, M, ()> {
         trace!("Set header bytes {}: {:?}", name, bytes);
         self.header_raw(name, bytes, false)
     }
+
+    /// Write a header whose value might not fit in the output buffer.
+    ///
+    /// The header is validated in full before anything is written. The name must fit
+    /// the output, while the value is written as far as there is room. Returns the number
+    /// of value bytes written. If not all of the value was written, `flush()` the output and
+    /// continue the header with [`header_continue()`][Self::header_continue] using the rest.
+    pub fn header_streamed(self, name: &str, bytes: &[u8]) -> Result<(Self, usize)> {
+        trace!("Set header streamed {}: {} bytes", name, bytes.len());
+        self.header_streamed_raw(name, bytes, false)
+    }
+
+    /// Continue a header started with [`header_streamed()`][Self::header_streamed].
+    ///
+    /// `bytes` must be the remaining part of the value not yet written. Returns the number
+    /// of value bytes written. Repeat until [`header_left()`][Self::header_left] is `None`.
+    pub fn header_continue(self, bytes: &[u8]) -> Result<(Self, usize)> {
+        self.header_continue_raw(bytes)
+    }
 }
 
 impl<'a, M: MethodWithResponseBody> Response<'a, SEND_HEADERS, M, ()> {
@@ -164,9 +346,12 @@ impl<'a, M: MethodWithResponseBody> Response<'a, SEND_HEADERS, M, ()> {
         let length: u64 = length.try_into().map_err(|_| HootError::NotU64)?;
 
         trace!("Length delimited body: {}", length);
+        self.state.check_header_done()?;
 
+        let casing = self.state.header_casing;
         let mut w = self.out.writer();
-        write!(w, "Content-Length: {}\r\n\r\n", length).or(OVERFLOW)?;
+        write_generated(&mut w, "Content-Length", format_args!("{}", length), casing)?;
+        w.write_bytes(b"\r\n")?;
         w.commit();
 
         self.state.send_checker = Some(LengthChecker::new(length));
@@ -176,16 +361,83 @@ impl<'a, M: MethodWithResponseBody> Response<'a, SEND_HEADERS, M, ()> {
 
     pub fn with_chunked(mut self) -> Result<Response<'a, SEND_BODY, M, BODY_CHUNKED>> {
         trace!("Chunked body");
+        self.state.check_header_done()?;
 
+        let casing = self.state.header_casing;
         let mut w = self.out.writer();
-        write!(w, "Transfer-Encoding: chunked\r\n\r\n").or(OVERFLOW)?;
+        write_generated(&mut w, "Transfer-Encoding", format_args!("chunked"), casing)?;
+        w.write_bytes(b"\r\n")?;
         w.commit();
 
         Ok(self.transition())
     }
 
+    /// Chunked body followed by the declared trailers.
+    ///
+    /// This writes a `Trailer` header with the names, which tells the receiver what
+    /// trailers to expect. The names are checked against the trailers that are not allowed.
+    /// The trailers are written after the body using `with_trailer()`.
+    pub fn with_chunked_trailers(
+        mut self,
+        names: &[&str],
+    ) -> Result<Response<'a, SEND_BODY, M, BODY_CHUNKED>> {
+        trace!("Chunked body with trailers: {:?}", names);
+        self.state.check_header_done()?;
+
+        for name in names {
+            check_trailer_name(name)?;
+        }
+
+        let casing = self.state.header_casing;
+        let mut w = self.out.writer();
+
+        if !names.is_empty() {
+            write_name(&mut w, "Trailer", casing)?;
+            w.write_bytes(b": ")?;
+            for (i, name) in names.iter().enumerate() {
+                if i > 0 {
+                    w.write_bytes(b", ")?;
+                }
+                write_name(&mut w, name, casing)?;
+            }
+            w.write_bytes(b"\r\n")?;
+        }
+
+        write_generated(&mut w, "Transfer-Encoding", format_args!("chunked"), casing)?;
+        w.write_bytes(b"\r\n")?;
+        w.commit();
+
+        Ok(self.transition())
+    }
+
+    /// Chunked body where the total length is known in advance.
+    ///
+    /// The body is sent chunked, but the amount written is checked against `length`
+    /// like with [`with_body()`][Self::with_body]. This is useful when the length is
+    /// known, but chunked framing is wanted anyway, such as to send trailers.
+    pub fn with_chunked_length(
+        mut self,
+        length: impl TryInto<u64>,
+    ) -> Result<Response<'a, SEND_BODY, M, BODY_CHUNKED>> {
+        let length: u64 = length.try_into().map_err(|_| HootError::NotU64)?;
+
+        trace!("Chunked body with length: {}", length);
+        self.state.check_header_done()?;
+
+        let casing = self.state.header_casing;
+        let mut w = self.out.writer();
+        write_generated(&mut w, "Transfer-Encoding", format_args!("chunked"), casing)?;
+        w.write_bytes(b"\r\n")?;
+        w.commit();
+
+        self.state.send_checker = Some(LengthChecker::new(length));
+
+        Ok(self.transition())
+    }
+
     pub fn without_body(mut self) -> Result<Response<'a, RECV_RESPONSE, M, ()>> {
         trace!("Without body");
+        self.state.check_header_done()?;
 
         let mut w = self.out.writer();
         write!(w, "\r\n").or(OVERFLOW)?;
@@ -199,6 +451,7 @@ impl<'a, M: MethodWithoutResponseBody> Response<'a, SEND_HEADERS, M, ()> {
     // TODO: Can we find a trait bound that allows us to call this without_body()?
     pub fn send(mut self) -> Result<Response<'a, ENDED, (), ()>> {
         trace!("Without body");
+        self.state.check_header_done()?;
 
         let mut w = self.out.writer();
         write!(w, "\r\n").or(OVERFLOW)?;
@@ -218,6 +471,15 @@ impl<'a, M: MethodWithResponseBody> Response<'a, SEND_BODY, M, BODY_LENGTH> {
             .expect("SendByteCheck when SEND_BODY")
     }
 
+    /// The smallest output buffer size that guarantees forward progress in this state.
+    ///
+    /// This is 1 byte while there is body left to write.
+    pub fn min_output_required(&self) -> usize {
+        let checker = self.state.send_checker.as_ref();
+        let left = checker.map(|c| c.left_to_read()).unwrap_or(0);
+        left.min(1)
+    }
+
     pub fn finish(mut self) -> Result<Response<'a, ENDED, (), ()>> {
         trace!("Body finished");
 
@@ -227,6 +489,29 @@ impl<'a, M: MethodWithResponseBody> Response<'a, SEND_BODY, M, BODY_LENGTH> {
 
         Ok(self.transition())
     }
+
+    /// Flush the output, with the body data kept apart instead of copied to the buffer.
+    ///
+    /// This is for vectored writes, such as `writev`, where the buffered output and the
+    /// body are written to the transport without copying the body. The body counts as
+    /// sent, and like other body writes, it must not exceed the content-length.
+    pub fn write_vectored<'b>(
+        mut self,
+        body: &'b [u8],
+    ) -> Result<VectoredOutput<'a, 'b, SEND_BODY, M, BODY_LENGTH>> {
+        trace!("Write vectored len: {}", body.len());
+
+        self.checker()
+            .append(body.len(), HootError::SentMoreThanContentLength)?;
+
+        self.state.sent_total += body.len() as u64;
+        self.state.sent_payload += body.len() as u64;
+
+        Ok(VectoredOutput {
+            output: self.flush(),
+            body,
+        })
+    }
 }
 
 impl<'a, M: MethodWithResponseBody> BodyWriter for Response<'a, SEND_BODY, M, BODY_LENGTH> {
@@ -241,13 +526,31 @@ impl<'a, M: MethodWithResponseBody> BodyWriter for Response<'a, SEND_BODY, M, BO
         w.write_bytes(bytes)?;
         w.commit();
 
+        self.state.sent_payload += bytes.len() as u64;
+
         Ok(self)
     }
 }
 
 impl<'a, M: MethodWithResponseBody> Response<'a, SEND_BODY, M, BODY_CHUNKED> {
+    /// The smallest output buffer size that guarantees forward progress in this state.
+    ///
+    /// This is the size of a chunk with a single byte of data.
+    pub fn min_output_required(&self) -> usize {
+        MIN_CHUNK_LEN
+    }
+
+    fn check_chunked_length(&self) -> Result<()> {
+        // Only set when using with_chunked_length()
+        if let Some(checker) = &self.state.send_checker {
+            checker.assert_expected(HootError::SentLessThanContentLength)?;
+        }
+        Ok(())
+    }
+
     pub fn with_trailer(mut self) -> Result<Response<'a, SEND_TRAILER, M, BODY_CHUNKED>> {
         trace!("With trailer");
+        self.check_chunked_length()?;
 
         let mut w = self.out.writer();
         write!(w, "0\r\n").or(OVERFLOW)?;
@@ -258,6 +561,7 @@ impl<'a, M: MethodWithResponseBody> Response<'a, SEND_BODY, M, BODY_CHUNKED> {
 
     pub fn finish(mut self) -> Result<Response<'a, ENDED, (), ()>> {
         trace!("Body chunks finished");
+        self.check_chunked_length()?;
 
         let mut w = self.out.writer();
         write!(w, "0\r\n\r\n").or(OVERFLOW)?;
@@ -276,18 +580,19 @@ impl<'a, M: MethodWithResponseBody> BodyWriter for Response<'a, SEND_BODY, M, BO
             return Ok(self);
         }
 
-        let mut w = self.out.writer();
+        if let Some(checker) = &self.state.send_checker {
+            checker.check_append(bytes.len(), HootError::SentMoreThanContentLength)?;
+        }
 
-        // chunk length
-        write!(w, "{:0x?}\r\n", bytes.len()).or(OVERFLOW)?;
+        let mut w = self.out.writer();
+        self.state.chunked.write(&mut w, bytes)?;
+        w.commit();
 
-        // chunk
-        w.write_bytes(bytes)?;
+        self.state.sent_payload += bytes.len() as u64;
 
-        // chunk end
-        write!(w, "\r\n").or(OVERFLOW)?;
-
-        w.commit();
+        if let Some(checker) = &mut self.state.send_checker {
+            checker.append(bytes.len(), HootError::SentMoreThanContentLength)?;
+        }
 
         Ok(self)
     }
@@ -295,6 +600,19 @@ impl<'a, M: MethodWithResponseBody> BodyWriter for Response<'a, SEND_BODY, M, BO
 
 // TODO: ensure trailers are declared in a `Trailer: xxx` header.
 impl<'a, M: MethodWithResponseBody> Response<'a, SEND_TRAILER, M, BODY_CHUNKED> {
+    /// The smallest output buffer size that guarantees forward progress in this state.
+    ///
+    /// This is the size needed to end the trailers.
+    pub fn min_output_required(&self) -> usize {
+        END_HEADERS_LEN
+    }
+
+    /// Like [`min_output_required()`][Self::min_output_required], but also considering
+    /// a pending trailer to be written.
+    pub fn min_output_required_for_trailer(&self, name: &str, value: &[u8]) -> usize {
+        header_line_len(name, value).max(self.min_output_required())
+    }
+
     pub fn trailer(self, name: &str, value: &str) -> Result<Self> {
         trace!("Set trailer {}: {}", name, value);
 
@@ -307,8 +625,28 @@ impl<'a, M: MethodWithResponseBody> Response<'a, SEND_TRAILER, M, BODY_CHUNKED>
         self.header_raw(name, bytes, true)
     }
 
+    /// Write a trailer whose value might not fit in the output buffer.
+    ///
+    /// The trailer is validated in full before anything is written. The name must fit
+    /// the output, while the value is written as far as there is room. Returns the number
+    /// of value bytes written. If not all of the value was written, `flush()` the output and
+    /// continue the trailer with [`header_continue()`][Self::header_continue] using the rest.
+    pub fn trailer_streamed(self, name: &str, bytes: &[u8]) -> Result<(Self, usize)> {
+        trace!("Set trailer streamed {}: {} bytes", name, bytes.len());
+        self.header_streamed_raw(name, bytes, true)
+    }
+
+    /// Continue a trailer started with [`trailer_streamed()`][Self::trailer_streamed].
+    ///
+    /// `bytes` must be the remaining part of the value not yet written. Returns the number
+    /// of value bytes written. Repeat until [`header_left()`][Self::header_left] is `None`.
+    pub fn header_continue(self, bytes: &[u8]) -> Result<(Self, usize)> {
+        self.header_continue_raw(bytes)
+    }
+
     pub fn finish(mut self) -> Result<Response<'a, ENDED, (), ()>> {
         trace!("Trailer finish");
+        self.state.check_header_done()?;
 
         let mut w = self.out.writer();
         write!(w, "\r\n").or(OVERFLOW)?;
@@ -318,6 +656,18 @@ impl<'a, M: MethodWithResponseBody> Response<'a, SEND_TRAILER, M, BODY_CHUNKED>
     }
 }
 
+impl<S: State, M: Method, B: BodyType> ResumeToken<S, M, B> {
+    /// Bytes sent, up until the output this token was made from.
+    pub fn bytes_sent(&self) -> ByteCount {
+        self.state.bytes_sent(0)
+    }
+
+    /// Bytes received for the request this is a response to.
+    pub fn bytes_received(&self) -> ByteCount {
+        self.state.recv_bytes
+    }
+}
+
 impl<'a, S: State, M: Method, B: BodyType> Output<'a, S, M, B> {
     pub fn ready(self) -> ResumeToken<S, M, B> {
         self.token
@@ -340,6 +690,43 @@ impl<'a, S: State, M: Method, B: BodyType> Deref for Output<'a, S, M, B> {
     }
 }
 
+/// Output where the body is kept apart from the buffer.
+///
+/// Write [`as_bytes()`][Self::as_bytes] followed by [`body()`][Self::body] to the transport.
+pub struct VectoredOutput<'a, 'b, S: State, M: Method, B: BodyType> {
+    output: Output<'a, S, M, B>,
+    body: &'b [u8],
+}
+
+impl<'a, 'b, S: State, M: Method, B: BodyType> VectoredOutput<'a, 'b, S, M, B> {
+    pub fn ready(self) -> ResumeToken<S, M, B> {
+        self.output.ready()
+    }
+
+    pub fn ready_and_buf(self) -> (ResumeToken<S, M, B>, &'a mut [u8]) {
+        self.output.ready_and_buf()
+    }
+
+    /// The buffered output, which goes before the body.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.output.as_bytes()
+    }
+
+    /// The body data, which goes after the buffered output.
+    pub fn body(&self) -> &'b [u8] {
+        self.body
+    }
+
+    /// Both parts as slices for [`std::io::Write::write_vectored()`].
+    #[cfg(feature = "std")]
+    pub fn io_slices(&self) -> [std::io::IoSlice<'_>; 2] {
+        [
+            std::io::IoSlice::new(self.as_bytes()),
+            std::io::IoSlice::new(self.body),
+        ]
+    }
+}
+
 impl From<CallState> for ResponseVariant {
     fn from(value: CallState) -> Self {
         // If we get an error on this unwrap, we have a bug.
@@ -359,13 +746,45 @@ impl From<CallState> for ResponseVariant {
 }
 
 impl ResponseVariant {
-    #[doc(hidden)]
+    /// Start a response without reading a request first.
+    ///
+    /// Normally the variant comes from [`Request::into_response()`][crate::server::Request::into_response],
+    /// which guarantees the response matches the request. This is for writing responses
+    /// where the request is known by other means, such as when the request was parsed
+    /// by another library.
+    ///
+    /// ```
+    /// use hoot::server::{Response, ResponseVariant};
+    /// use hoot::{BodyWriter, HttpVersion, Method};
+    ///
+    /// let variant = ResponseVariant::unchecked_from_method(Method::GET, HttpVersion::Http11);
+    ///
+    /// let token = match variant {
+    ///     ResponseVariant::Get(v) => v,
+    ///     _ => unreachable!(),
+    /// };
+    ///
+    /// let mut buf = [0; 1024];
+    ///
+    /// let output = Response::resume(token, &mut buf)
+    ///     .send_status(200, "OK")?
+    ///     .with_body(2)?
+    ///     .write_bytes(b"ok")?
+    ///     .finish()?
+    ///     .flush();
+    ///
+    /// assert_eq!(&*output, b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+    /// # Ok::<(), hoot::HootError>(())
+    /// ```
     pub fn unchecked_from_method(
         method: crate::Method,
         version: crate::HttpVersion,
     ) -> ResponseVariant {
-        let mut state = CallState::default();
-        state.version = Some(version);
+        let state = CallState {
+            version: Some(version),
+            method: Some(method),
+            ..Default::default()
+        };
 
         use ResponseVariant::*;
         match method {
@@ -381,3 +800,98 @@ impl ResponseVariant {
         }
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::Method as M;
+
+    fn get(version: HttpVersion) -> ResumeToken<SEND_STATUS, GET, ()> {
+        match ResponseVariant::unchecked_from_method(M::GET, version) {
+            ResponseVariant::Get(v) => v,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_interim() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let output = Response::resume(get(HttpVersion::Http11), &mut buf)
+            .interim(103, "Early Hints", &[("link", "</style.css>; rel=preload")])?
+            .send_status(200, "OK")?
+            .with_body(2)?
+            .write_bytes(b"ok")?
+            .finish()?
+            .flush();
+
+        assert_eq!(
+            &*output,
+            b"HTTP/1.1 103 Early Hints\r\nlink: </style.css>; rel=preload\r\n\r\n\
+            HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interim_invalid() {
+        let mut buf = [0; 1024];
+
+        for code in [101, 200, 404] {
+            let r = Response::resume(get(HttpVersion::Http11), &mut buf);
+            assert_eq!(
+                r.interim(code, "No", &[]).err(),
+                Some(HootError::InterimStatus)
+            );
+        }
+
+        let r = Response::resume(get(HttpVersion::Http10), &mut buf);
+        assert_eq!(
+            r.interim(103, "Early Hints", &[]).err(),
+            Some(HootError::InterimStatus)
+        );
+
+        // Interim responses have no body.
+        let r = Response::resume(get(HttpVersion::Http11), &mut buf);
+        assert_eq!(
+            r.interim(103, "Early Hints", &[("content-length", "1")])
+                .err(),
+            Some(HootError::ForbiddenBodyHeader)
+        );
+    }
+
+    #[test]
+    fn test_write_vectored() -> Result<()> {
+        let mut buf = [0; 1024];
+
+        let output = Response::resume(get(HttpVersion::Http11), &mut buf)
+            .send_status(200, "OK")?
+            .with_body(5)?
+            .write_vectored(b"hello")?;
+
+        assert_eq!(
+            output.as_bytes(),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n"
+        );
+        assert_eq!(output.body(), b"hello");
+
+        let token = output.ready();
+        assert_eq!(token.bytes_sent().payload, 5);
+
+        let mut buf = [0; 1024];
+        let output = Response::resume(token, &mut buf).finish()?.flush();
+        assert!(output.is_empty());
+
+        // More than the content-length.
+        let mut buf = [0; 1024];
+        let err = Response::resume(get(HttpVersion::Http11), &mut buf)
+            .send_status(200, "OK")?
+            .with_body(2)?
+            .write_vectored(b"hello")
+            .err();
+        assert_eq!(err, Some(HootError::SentMoreThanContentLength(2, 5)));
+
+        Ok(())
+    }
+}
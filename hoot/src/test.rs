@@ -0,0 +1,311 @@
+//! Scripted transport for testing clients
+//!
+//! [`MockTransport`] stands in for a socket, to test a client without a server. It is
+//! scripted with the requests the client is expected to write, and the responses it is
+//! given to read. A request is checked when the client goes on to read the response,
+//! which fails the test at the first request that isn't as expected.
+//!
+//! Requests and responses are written as the traces of [`replay`][crate::replay], where
+//! `\r`, `\n`, `\t`, `\\` and `\xNN` are escapes, and newlines in the text are ignored.
+//! In an expected request, `{*}` matches anything up to the end of the line, for the
+//! parts that differ between runs, such as dates and multipart boundaries.
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use hoot::test::MockTransport;
+//!
+//! let mut transport = MockTransport::new()
+//!     .expect_request(
+//!         "GET / HTTP/1.1\\r\\n\
+//!         Host: example.test\\r\\n\
+//!         Date: {*}\\r\\n\
+//!         \\r\\n",
+//!     )
+//!     .respond("HTTP/1.1 204 No Content\\r\\n\\r\\n");
+//!
+//! transport.write_all(b"GET / HTTP/1.1\r\nHost: example.test\r\n")?;
+//! transport.write_all(b"Date: Tue, 15 Nov 1994 08:12:31 GMT\r\n\r\n")?;
+//!
+//! let mut response = String::new();
+//! transport.read_to_string(&mut response)?;
+//! assert_eq!(response, "HTTP/1.1 204 No Content\r\n\r\n");
+//!
+//! transport.assert_done();
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::replay::decode_trace;
+
+/// Wildcard in expected requests.
+const WILDCARD: &str = "{*}";
+
+/// A transport playing a script of requests and responses, see the [module docs][self].
+///
+/// Reading gives the scripted responses, in order. Once they are all read, reading
+/// gives end of file, like a closed connection.
+pub struct MockTransport {
+    script: VecDeque<Step>,
+    written: Vec<u8>,
+    reading: Vec<u8>,
+    pos: usize,
+    read_size: usize,
+}
+
+enum Step {
+    /// The trace, and its decoded parts between wildcards.
+    Request(String, Vec<Vec<u8>>),
+    Response(Vec<u8>),
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        MockTransport {
+            script: VecDeque::new(),
+            written: Vec::new(),
+            reading: Vec::new(),
+            pos: 0,
+            read_size: usize::MAX,
+        }
+    }
+
+    /// Expect the client to write this request next.
+    pub fn expect_request(mut self, trace: &str) -> Self {
+        let parts = trace.split(WILDCARD).map(decode_trace).collect();
+        self.script.push_back(Step::Request(trace.into(), parts));
+        self
+    }
+
+    /// Give the client this response to read next.
+    pub fn respond(mut self, trace: &str) -> Self {
+        self.script.push_back(Step::Response(decode_trace(trace)));
+        self
+    }
+
+    /// Give the client at most `max` bytes per read. 1 is the most fragmented input.
+    pub fn read_size(mut self, max: usize) -> Self {
+        assert!(max > 0, "read size must be at least 1");
+        self.read_size = max;
+        self
+    }
+
+    /// Bytes written since the last request checked.
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    /// Assert the whole script is played, and nothing else written.
+    pub fn assert_done(&mut self) {
+        self.check_request();
+
+        assert!(
+            self.script.is_empty(),
+            "Script not done, {} steps left",
+            self.script.len()
+        );
+        assert!(
+            self.pos == self.reading.len(),
+            "Response not read, {} bytes left",
+            self.reading.len() - self.pos
+        );
+        assert!(
+            self.written.is_empty(),
+            "Unexpected bytes written: \"{}\"",
+            escape(&self.written)
+        );
+    }
+
+    /// Check the written bytes if the next step is a request.
+    fn check_request(&mut self) {
+        let parts = match self.script.front() {
+            Some(Step::Request(trace, parts)) => {
+                assert!(
+                    matches(parts, &self.written),
+                    "Request differs from script\nexpected: {}\nwritten:  \"{}\"",
+                    trace,
+                    escape(&self.written)
+                );
+                parts
+            }
+            _ => return,
+        };
+
+        trace!("Mock request matched ({} parts)", parts.len());
+        self.script.pop_front();
+        self.written.clear();
+    }
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl io::Read for MockTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.reading.len() {
+            // The client is done writing the request, when it reads the response.
+            self.check_request();
+
+            match self.script.pop_front() {
+                Some(Step::Response(v)) => {
+                    self.reading = v;
+                    self.pos = 0;
+                }
+                Some(step) => {
+                    self.script.push_front(step);
+                    return Ok(0);
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let left = &self.reading[self.pos..];
+        let n = left.len().min(buf.len()).min(self.read_size);
+        buf[..n].copy_from_slice(&left[..n]);
+        self.pos += n;
+
+        Ok(n)
+    }
+}
+
+impl io::Write for MockTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Whether `input` is the `parts` with a wildcard between each, where a wildcard
+/// matches anything but a line end.
+fn matches(parts: &[Vec<u8>], input: &[u8]) -> bool {
+    let (first, rest) = match parts.split_first() {
+        Some(v) => v,
+        None => return input.is_empty(),
+    };
+
+    if !input.starts_with(first) {
+        return false;
+    }
+    let input = &input[first.len()..];
+
+    if rest.is_empty() {
+        return input.is_empty();
+    }
+
+    let line = input
+        .iter()
+        .position(|b| *b == b'\r' || *b == b'\n')
+        .unwrap_or(input.len());
+
+    (0..=line).any(|skip| matches(rest, &input[skip..]))
+}
+
+fn escape(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for b in bytes {
+        let _ = write!(out, "{}", core::ascii::escape_default(*b));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::*;
+
+    fn parts(trace: &str) -> Vec<Vec<u8>> {
+        trace.split(WILDCARD).map(decode_trace).collect()
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches(&parts("a{*}c"), b"abbbc"));
+        assert!(matches(&parts("a{*}c"), b"ac"));
+        assert!(matches(&parts("a{*}b{*}c"), b"abxbbc"));
+        assert!(matches(&parts("{*}\\r\\n"), b"anything\r\n"));
+        assert!(!matches(&parts("a{*}c"), b"ab\r\nc"));
+        assert!(!matches(&parts("a{*}c"), b"abcd"));
+        assert!(!matches(&parts("abc"), b"ab"));
+    }
+
+    #[test]
+    fn test_fragmented_reads() {
+        let mut transport = MockTransport::new()
+            .expect_request("ping")
+            .respond("pong")
+            .expect_request("bye")
+            .read_size(3);
+
+        transport.write_all(b"ping").unwrap();
+
+        let mut buf = [0; 10];
+        assert_eq!(transport.read(&mut buf).unwrap(), 3);
+        assert_eq!(transport.read(&mut buf).unwrap(), 1);
+        assert_eq!(transport.written(), b"");
+
+        transport.write_all(b"bye").unwrap();
+        assert_eq!(transport.read(&mut buf).unwrap(), 0);
+
+        transport.assert_done();
+    }
+
+    #[test]
+    #[should_panic(expected = "Request differs from script")]
+    fn test_unexpected_request() {
+        let mut transport = MockTransport::new()
+            .expect_request("GET / HTTP/1.1\\r\\n\\r\\n")
+            .respond("HTTP/1.1 200 OK\\r\\n\\r\\n");
+
+        transport.write_all(b"GET /other HTTP/1.1\r\n\r\n").unwrap();
+        let _ = transport.read(&mut [0; 10]);
+    }
+
+    #[cfg(feature = "http_crate")]
+    #[test]
+    fn test_call_over_mock() {
+        use crate::io::{CallReader, CallWriter};
+
+        let mut transport = MockTransport::new()
+            .expect_request(
+                "POST /upload HTTP/1.1\\r\\n\
+                Host: example.test\\r\\n\
+                content-type: multipart/form-data; boundary={*}\\r\\n\
+                Transfer-Encoding: chunked\\r\\n\
+                \\r\\n\
+                {*}\\r\\n\
+                --{*}\\r\\n\
+                0\\r\\n\\r\\n",
+            )
+            .respond("HTTP/1.1 200 OK\\r\\nContent-Length: 2\\r\\n\\r\\nok")
+            .read_size(1);
+
+        let request = http::Request::post("http://example.test/upload")
+            .header("content-type", "multipart/form-data; boundary=x1y2")
+            .body(&b"--x1y2"[..])
+            .unwrap();
+
+        let token = CallWriter::new(&mut transport).send(request).unwrap();
+        let response = CallReader::new(&mut transport)
+            .read_response(token)
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let mut body = String::new();
+        response.into_body().read_to_string(&mut body).unwrap();
+        assert_eq!(body, "ok");
+
+        transport.assert_done();
+    }
+}
@@ -9,7 +9,15 @@ mod private {
     }
 }
 
-pub trait State: Private {}
+pub trait State: Private {
+    #[doc(hidden)]
+    fn is_sending_head() -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
+}
 
 pub trait Version: Private {
     #[doc(hidden)]
@@ -45,7 +53,7 @@ pub trait BodyType: Private {}
 
 impl Private for () {
     fn state_name() -> &'static str {
-        return "*";
+        "*"
     }
 }
 
@@ -78,10 +86,26 @@ pub mod state {
 
     impl State for () {}
 
-    impl_private!(State, INIT);
-    impl_private!(State, SEND_LINE);
-    impl_private!(State, SEND_STATUS);
-    impl_private!(State, SEND_HEADERS);
+    // States before the body, where the output is the message head.
+    macro_rules! impl_head_state {
+        ($target:ty) => {
+            impl crate::types::Private for $target {
+                fn state_name() -> &'static str {
+                    stringify!($target)
+                }
+            }
+            impl State for $target {
+                fn is_sending_head() -> bool {
+                    true
+                }
+            }
+        };
+    }
+
+    impl_head_state!(INIT);
+    impl_head_state!(SEND_LINE);
+    impl_head_state!(SEND_STATUS);
+    impl_head_state!(SEND_HEADERS);
     impl_private!(State, SEND_BODY);
     impl_private!(State, SEND_TRAILER);
     impl_private!(State, RECV_RESPONSE);
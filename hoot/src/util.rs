@@ -1,12 +1,46 @@
+use crate::parse_impl::{Header, EMPTY_HEADER};
 use core::fmt;
 use core::mem;
-use httparse::{Header, EMPTY_HEADER};
 
 use crate::{HootError, Result};
 
 // TODO: make this configurable.
 const MAX_HEADERS: usize = 100;
 
+/// Whether the request target is authority-form, `host:port`, as used by `CONNECT`.
+///
+/// The host is a name or an IP literal in brackets, and the port is a number. Anything
+/// else, such as userinfo, whitespace or control characters, is refused.
+pub(crate) fn is_authority_form(target: &str) -> bool {
+    let (host, port) = match target.rsplit_once(':') {
+        Some(v) => v,
+        None => return false,
+    };
+
+    let port_ok =
+        !port.is_empty() && port.bytes().all(|c| c.is_ascii_digit()) && port.parse::<u16>().is_ok();
+
+    let host_ok = match host.strip_prefix('[') {
+        Some(literal) => match literal.strip_suffix(']') {
+            Some(ip) => !ip.is_empty() && ip.bytes().all(is_ip_literal_char),
+            None => false,
+        },
+        None => !host.is_empty() && host.bytes().all(is_reg_name_char),
+    };
+
+    port_ok && host_ok
+}
+
+/// Unreserved, sub-delims and `%` of a reg-name, RFC 3986 3.2.2.
+fn is_reg_name_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"-._~%!$&'()*+,;=".contains(&c)
+}
+
+/// Characters of an IPv6 address, or of an IPvFuture, in brackets.
+fn is_ip_literal_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:".contains(&c)
+}
+
 /// Use a generic byte buffer to write httparse Header.
 pub(crate) fn cast_buf_for_headers<'a, 'b>(buf: &'a mut [u8]) -> &'a mut [Header<'b>] {
     // SAFETY: align_to_mut docs say "This method is essentially a transmute with
@@ -38,24 +72,6 @@ pub(crate) fn cast_buf_for_headers<'a, 'b>(buf: &'a mut [u8]) -> &'a mut [Header
     unsafe { mem::transmute(headers) }
 }
 
-pub(crate) fn compare_lowercase_ascii(a: &str, lowercased: &str) -> bool {
-    if a.len() != lowercased.len() {
-        return false;
-    }
-
-    for (a, b) in a.chars().zip(lowercased.chars()) {
-        if !a.is_ascii() {
-            return false;
-        }
-        let norm = a.to_ascii_lowercase();
-        if norm != b {
-            return false;
-        }
-    }
-
-    true
-}
-
 pub(crate) struct LengthChecker {
     handled: u64,
     expected: u64,
@@ -69,6 +85,11 @@ impl LengthChecker {
         }
     }
 
+    /// Amount left, without capping to usize.
+    pub fn left(&self) -> u64 {
+        self.expected - self.handled
+    }
+
     pub fn left_to_read(&self) -> usize {
         let left = self.expected - self.handled;
         if left > usize::MAX as u64 {
@@ -78,18 +99,27 @@ impl LengthChecker {
         }
     }
 
-    pub fn append(&mut self, amount: usize, err: HootError) -> Result<()> {
+    /// Check that `amount` more is not more than expected, without appending it.
+    ///
+    /// `err` is given the expected and the attempted total.
+    pub fn check_append(&self, amount: usize, err: fn(u64, u64) -> HootError) -> Result<()> {
         let new_total = self.handled + amount as u64;
         if new_total > self.expected {
-            return Err(err);
+            return Err(err(self.expected, new_total));
         }
-        self.handled = new_total;
         Ok(())
     }
 
-    pub fn assert_expected(&self, err: HootError) -> Result<()> {
+    pub fn append(&mut self, amount: usize, err: fn(u64, u64) -> HootError) -> Result<()> {
+        self.check_append(amount, err)?;
+        self.handled += amount as u64;
+        Ok(())
+    }
+
+    /// `err` is given the expected and the actual total.
+    pub fn assert_expected(&self, err: fn(u64, u64) -> HootError) -> Result<()> {
         if self.handled != self.expected {
-            return Err(err);
+            return Err(err(self.expected, self.handled));
         }
         Ok(())
     }
@@ -107,3 +137,37 @@ impl fmt::Debug for LengthChecker {
             .finish()
     }
 }
+
+/// Define a set of the variants of a fieldless enum, kept as bits of a `u32`.
+///
+/// The set gets `contains()`, `is_empty()` and `iter()`, in the order the variants are
+/// listed, and a private `insert()` for the module filling it.
+macro_rules! flag_set {
+    ($(#[$meta:meta])* $set:ident($flag:ident): $($variant:ident),+ $(,)?) => {
+        $(#[$meta])*
+        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+        pub struct $set(u32);
+
+        impl $set {
+            const ALL: &'static [$flag] = &[$($flag::$variant),+];
+
+            fn insert(&mut self, f: $flag) {
+                self.0 |= 1 << f as u32;
+            }
+
+            pub fn contains(&self, f: $flag) -> bool {
+                self.0 & (1 << f as u32) != 0
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.0 == 0
+            }
+
+            pub fn iter(&self) -> impl Iterator<Item = $flag> + '_ {
+                Self::ALL.iter().copied().filter(move |f| self.contains(*f))
+            }
+        }
+    };
+}
+
+pub(crate) use flag_set;
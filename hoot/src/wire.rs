@@ -0,0 +1,158 @@
+//! Hooks seeing the bytes on the wire
+//!
+//! A [`WireHook`] is given the raw bytes of messages as they are sent and received,
+//! each slice told apart as head, body or trailer. Transparent proxies and traffic
+//! recorders can duplicate the byte stream this way, without wrapping the transport.
+//!
+//! The body is as on the wire, which for a chunked body includes the chunk framing.
+//! Together, the slices are exactly the bytes written to and consumed from the
+//! transport, in order. Input read ahead, but not yet used, is reported once used.
+//!
+//! The blocking client in `hoot::io` takes a hook, see `CallWriter::with_hook()`. When
+//! driving requests or responses over `std::io` otherwise, write the output through a
+//! [`WireWriter`]. Any closure taking the same arguments as [`WireHook::wire()`] is a
+//! hook.
+
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "std")]
+use crate::ByteCount;
+
+/// Which way bytes went over the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// Part of a message a slice of wire bytes belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WirePart {
+    /// The request or status line, and the headers.
+    Head,
+    /// The body, including chunk framing.
+    Body,
+    /// The trailers after a chunked body. Only told apart from the body when received.
+    Trailer,
+}
+
+/// Receiver of the bytes on the wire, see the [module docs][self].
+pub trait WireHook {
+    fn wire(&mut self, direction: Direction, part: WirePart, bytes: &[u8]);
+}
+
+/// No hook.
+impl WireHook for () {
+    fn wire(&mut self, _: Direction, _: WirePart, _: &[u8]) {}
+}
+
+impl<F> WireHook for F
+where
+    F: FnMut(Direction, WirePart, &[u8]),
+{
+    fn wire(&mut self, direction: Direction, part: WirePart, bytes: &[u8]) {
+        (self)(direction, part, bytes)
+    }
+}
+
+/// Writer telling a hook what is written through it.
+///
+/// Before writing the output of a request or response, give its count of bytes sent,
+/// from `bytes_sent()` before flushing, with [`sending()`][Self::sending]. What is new in
+/// the count of head bytes is head, and the rest is body. Anything written without a new
+/// count is body, such as a body delimited by closing the connection.
+#[cfg(feature = "std")]
+pub struct WireWriter<W, H> {
+    write: W,
+    hook: H,
+    count: ByteCount,
+    head_left: usize,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write, H: WireHook> WireWriter<W, H> {
+    pub fn new(write: W, hook: H) -> Self {
+        WireWriter {
+            write,
+            hook,
+            count: ByteCount::default(),
+            head_left: 0,
+        }
+    }
+
+    /// Count of bytes sent, including the output written next.
+    pub fn sending(&mut self, count: ByteCount) -> &mut Self {
+        self.head_left = count.head.saturating_sub(self.count.head) as usize;
+        self.count = count;
+        self
+    }
+
+    pub fn into_inner(self) -> (W, H) {
+        (self.write, self.hook)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write, H: WireHook> io::Write for WireWriter<W, H> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.write.write(buf)?;
+
+        let (head, body) = buf[..n].split_at(self.head_left.min(n));
+        self.head_left -= head.len();
+
+        if !head.is_empty() {
+            self.hook.wire(Direction::Sent, WirePart::Head, head);
+        }
+        if !body.is_empty() {
+            self.hook.wire(Direction::Sent, WirePart::Body, body);
+        }
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write.flush()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_wire_writer() -> io::Result<()> {
+        let mut seen = vec![];
+        let hook = |d: Direction, p: WirePart, b: &[u8]| seen.push((d, p, b.to_vec()));
+        let mut w = WireWriter::new(vec![], hook);
+
+        let count = |head, payload| ByteCount {
+            head,
+            framing: 0,
+            payload,
+        };
+
+        // Head and start of body in one output.
+        w.sending(count(4, 2)).write_all(b"headbo")?;
+        w.sending(count(4, 4)).write_all(b"dy")?;
+        w.write_all(b"!")?;
+
+        let (out, _) = w.into_inner();
+        assert_eq!(out, b"headbody!");
+
+        use Direction::Sent;
+        assert_eq!(
+            seen,
+            vec![
+                (Sent, WirePart::Head, b"head".to_vec()),
+                (Sent, WirePart::Body, b"bo".to_vec()),
+                (Sent, WirePart::Body, b"dy".to_vec()),
+                (Sent, WirePart::Body, b"!".to_vec()),
+            ]
+        );
+
+        Ok(())
+    }
+}
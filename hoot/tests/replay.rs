@@ -0,0 +1,34 @@
+#![cfg(feature = "std")]
+
+use hoot::replay::Replay;
+use hoot::Method;
+
+fn fixture(name: &str) -> (String, String) {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    let trace = std::fs::read_to_string(format!("{}/{}.trace", dir, name)).unwrap();
+    (trace, format!("{}/{}.golden", dir, name))
+}
+
+#[test]
+fn replay_post_request() {
+    let (trace, golden) = fixture("post_request");
+    Replay::request(&trace).assert_golden(golden);
+}
+
+#[test]
+fn replay_chunked_response() {
+    let (trace, golden) = fixture("chunked_response");
+    Replay::response(Method::GET, &trace).assert_golden(golden);
+}
+
+#[test]
+fn replay_head_response() {
+    let (trace, golden) = fixture("head_response");
+    Replay::response(Method::HEAD, &trace).assert_golden(golden);
+}
+
+#[test]
+fn replay_bad_chunk() {
+    let (trace, golden) = fixture("bad_chunk");
+    Replay::response(Method::GET, &trace).assert_golden(golden);
+}
@@ -26,7 +26,7 @@ pub enum Error {
 impl From<Error> for io::Error {
     fn from(value: Error) -> Self {
         if let Error::Io(e) = value {
-            return e;
+            e
         } else {
             let s = value.to_string();
             io::Error::new(io::ErrorKind::Other, s)
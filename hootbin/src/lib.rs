@@ -9,7 +9,6 @@ use hoot::{server::*, Header, Method};
 use hooturl::Url;
 use std::collections::HashMap;
 use std::iter::repeat_with;
-use std::mem;
 use std::str;
 use std::time::Duration;
 use std::{io, thread};
@@ -99,7 +98,7 @@ pub fn serve_single(i: impl io::Read, mut o: impl io::Write, base_url: &str) ->
         input.fill_more()?;
 
         // Try to read all headers etc from the current input.
-        let attempt = req.try_read_request(&*input, &mut buf)?;
+        let attempt = req.try_read_request(&input, &mut buf)?;
 
         if !attempt.is_success() {
             // Input might be ended, in which case we have a problem.
@@ -142,18 +141,18 @@ pub fn serve_single(i: impl io::Read, mut o: impl io::Write, base_url: &str) ->
             Mode::Put
         } else if path.starts_with("/headers") {
             Mode::Headers
-        } else if path.starts_with("/status/") {
-            match path[8..].parse() {
+        } else if let Some(status) = path.strip_prefix("/status/") {
+            match status.parse() {
                 Ok(v) => Mode::Status(v),
                 Err(_) => send_400(&mut answer),
             }
-        } else if path.starts_with("/bytes/") {
-            match path[7..].parse() {
+        } else if let Some(bytes) = path.strip_prefix("/bytes/") {
+            match bytes.parse() {
                 Ok(v) => Mode::Bytes(v),
                 Err(_) => send_400(&mut answer),
             }
-        } else if path.starts_with("/delay/") {
-            match path[7..].parse() {
+        } else if let Some(delay) = path.strip_prefix("/delay/") {
+            match delay.parse() {
                 Ok(v) => Mode::Delay(v),
                 Err(_) => send_400(&mut answer),
             }
@@ -189,11 +188,11 @@ pub fn serve_single(i: impl io::Read, mut o: impl io::Write, base_url: &str) ->
         // For request methods that has no body (like GET), this will
         // error if the client sent any data. That means we don't
         // need to check it further down.
-        let body_part = req.read_body(&*input, &mut buf)?;
+        let body_part = req.read_body(&input, &mut buf)?;
 
         // This is ok also for methods that don't have bodies since
         // body_part would be empty.
-        answer.append_body_data(&*body_part);
+        answer.append_body_data(&body_part);
 
         // Mark used body input as consumed.
         input.consume(body_part.input_used());
@@ -239,7 +238,7 @@ pub fn serve_single(i: impl io::Read, mut o: impl io::Write, base_url: &str) ->
             o.write_all(&output)?;
             Ok(())
         }
-        _ => return Err(Error::UnhandledMethod),
+        _ => Err(Error::UnhandledMethod),
     }
 }
 
@@ -346,7 +345,7 @@ impl Answer {
         };
 
         // Take the data since we will do our best to not allocate more than we need to.
-        let data = mem::replace(&mut self.request_body, vec![]);
+        let data = std::mem::take(&mut self.request_body);
 
         // Attempt interpret the body as a string.
         let string = match String::from_utf8(data) {
@@ -398,11 +397,11 @@ impl Body {
         let mut buf_url = [0_u8; 1024];
 
         let base_url_bytes = base.as_bytes();
-        (&mut buf_url[..base_url_bytes.len()]).copy_from_slice(base_url_bytes);
+        buf_url[..base_url_bytes.len()].copy_from_slice(base_url_bytes);
 
         let path_bytes = line.as_bytes();
         let full_url_len = base_url_bytes.len() + path_bytes.len();
-        (&mut buf_url[base_url_bytes.len()..full_url_len]).copy_from_slice(path_bytes);
+        buf_url[base_url_bytes.len()..full_url_len].copy_from_slice(path_bytes);
         let url = str::from_utf8(&buf_url[..full_url_len])?.to_string();
 
         self.url = url.to_string();
@@ -1,9 +1,9 @@
-//!
+//! URL parsing without allocation.
 
 use core::fmt;
 use core::ops::Deref;
 
-///
+/// Errors from parsing a [`Url`].
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum UrlError {
@@ -86,7 +86,7 @@ impl<'a> Url<'a> {
                 if username.is_empty() || password.is_empty() {
                     return Err(UrlError::TooShortUserPass);
                 }
-                if let Some(_) = password.find(":") {
+                if password.find(":").is_some() {
                     return Err(UrlError::BadPassword);
                 }
                 username.len()
@@ -136,14 +136,14 @@ impl<'a> Url<'a> {
     pub fn username(&self) -> &str {
         self.username_end
             .map(|u| &self.buffer[(self.scheme_end as usize + 3)..u as usize])
-            .unwrap_or(&"")
+            .unwrap_or("")
     }
 
     pub fn password(&self) -> &str {
         self.username_end
             .filter(|u| *u + 1 < self.host_start)
             .map(|u| &self.buffer[(u as usize + 1)..self.host_start as usize - 1])
-            .unwrap_or(&"")
+            .unwrap_or("")
     }
 
     pub fn host(&self) -> &str {
@@ -1,11 +1,14 @@
 use core::fmt;
-use std::cell::RefCell;
 use std::io::{self, Cursor, Read};
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
 use hoot::types::state::RECV_BODY;
+use hoot::wire::{Direction, WireHook, WirePart};
 
+use crate::expect::Continue;
 use crate::fill_more::FillMoreBuffer;
+use crate::response::PayloadTooLarge;
+use crate::wire::Tap;
 use crate::Error;
 
 pub struct Body {
@@ -16,8 +19,8 @@ pub struct Body {
 enum Inner {
     Empty,
     Bytes(Cursor<Vec<u8>>),
-    Streaming(Box<dyn Read + 'static>),
-    HootBody(Rc<RefCell<HootBody>>),
+    Streaming(Box<dyn Read + Send + 'static>, Option<u64>),
+    HootBody(Arc<Mutex<HootBody>>),
 }
 
 #[derive(Clone, Copy)]
@@ -39,36 +42,86 @@ impl Body {
     }
 
     pub fn streaming(read: impl Read + Send + 'static) -> Body {
-        Inner::Streaming(Box::new(read)).into()
+        Inner::Streaming(Box::new(read), None).into()
+    }
+
+    /// Streaming body of a known length.
+    ///
+    /// A response with this body is sent with a `Content-Length` instead of
+    /// chunked. Reading stops at `len`.
+    pub fn with_length(read: impl Read + Send + 'static, len: u64) -> Body {
+        Inner::Streaming(Box::new(read.take(len)), Some(len)).into()
     }
 
     pub(crate) fn hoot(body: HootBody) -> Body {
-        Inner::HootBody(Rc::new(RefCell::new(body))).into()
+        Inner::HootBody(Arc::new(Mutex::new(body))).into()
     }
 
     pub(crate) fn hoot_clone(&self) -> Body {
         let Inner::HootBody(rc) = &self.inner else {
             unreachable!()
         };
-        let clone = Rc::clone(rc);
+        let clone = Arc::clone(rc);
         Inner::HootBody(clone).into()
     }
 
-    pub(crate) fn hoot_unwrap(self) -> HootBody {
-        let Inner::HootBody(rc) = self.inner else {
+    /// Take back the HootBody, unless something still holds a clone of it.
+    pub(crate) fn hoot_unwrap(self) -> Option<HootBody> {
+        let Inner::HootBody(arc) = self.inner else {
             unreachable!()
         };
-        let refcell = Rc::try_unwrap(rc).expect("single Rc to HootBody");
-        refcell.into_inner()
+        let mutex = Arc::try_unwrap(arc).ok()?;
+        Some(mutex.into_inner().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Limit the body to `max` bytes. Reading past it fails with [`PayloadTooLarge`].
+    pub(crate) fn set_limit(&self, max: Option<u64>) {
+        if let Inner::HootBody(v) = &self.inner {
+            lock(v).limit = max;
+        }
+    }
+
+    /// Send `100 Continue` before the body is first read.
+    pub(crate) fn set_continue(&self, expect: Continue) {
+        if let Inner::HootBody(v) = &self.inner {
+            lock(v).expect = Some(expect);
+        }
+    }
+
+    /// Whether the declared size of the body is over the limit.
+    pub(crate) fn is_over_limit(&self) -> bool {
+        let Inner::HootBody(v) = &self.inner else {
+            return false;
+        };
+        let body = lock(v);
+        match (body.limit, body.size()) {
+            (Some(limit), Some(size)) => body.delivered + size > limit,
+            _ => false,
+        }
     }
 
     pub(crate) fn size(&self) -> Option<u64> {
         match &self.inner {
             Inner::Empty => Some(0),
             Inner::Bytes(v) => Some(v.get_ref().len() as u64),
-            Inner::Streaming(_) => None,
-            Inner::HootBody(_) => None,
+            Inner::Streaming(_, size) => *size,
+            Inner::HootBody(v) => lock(v).size(),
+        }
+    }
+
+    /// Read the whole body, unless it is larger than `max`.
+    pub(crate) fn into_bytes_max(self, max: u64) -> io::Result<Option<Vec<u8>>> {
+        if self.size().map(|s| s > max).unwrap_or(false) {
+            return Ok(None);
         }
+
+        let mut buf = vec![];
+        self.take(max + 1).read_to_end(&mut buf)?;
+
+        if buf.len() as u64 > max {
+            return Ok(None);
+        }
+        Ok(Some(buf))
     }
 
     pub fn into_string(self, limit: u64) -> Result<String, Error> {
@@ -113,24 +166,48 @@ impl From<&str> for Body {
     }
 }
 
+/// Lock a shared HootBody. A panic while holding the lock leaves nothing to recover,
+/// the body is just read on.
+fn lock(v: &Mutex<HootBody>) -> std::sync::MutexGuard<'_, HootBody> {
+    v.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Input buffer of a connection.
+pub(crate) type InputBuffer = FillMoreBuffer<Box<dyn io::Read + Send + 'static>>;
+
 pub(crate) struct HootBody {
     hoot_req: Hoot,
     parse_buf: Vec<u8>,
-    buffer: FillMoreBuffer<Box<dyn io::Read + 'static>>,
+    buffer: InputBuffer,
     leftover: Vec<u8>,
+    need_more: bool,
+    consumed: u64,
+    tap: Tap,
+    trailers: bool,
+    limit: Option<u64>,
+    delivered: u64,
+    expect: Option<Continue>,
 }
 
 impl HootBody {
     pub(crate) fn new(
         hoot: impl Into<Hoot>,
         parse_buf: Vec<u8>,
-        buffer: FillMoreBuffer<Box<dyn io::Read + 'static>>,
+        buffer: InputBuffer,
+        tap: Tap,
     ) -> Self {
         HootBody {
             hoot_req: hoot.into(),
             parse_buf,
             buffer,
             leftover: vec![],
+            need_more: false,
+            consumed: 0,
+            tap,
+            trailers: false,
+            limit: None,
+            delivered: 0,
+            expect: None,
         }
     }
 }
@@ -141,17 +218,20 @@ pub(crate) enum Hoot {
 }
 
 impl Hoot {
-    fn read_body<'a, 'b>(
-        &mut self,
-        src: &'a [u8],
-        dst: &'b mut [u8],
-    ) -> io::Result<hoot::BodyPart<'b>> {
+    fn read_body<'b>(&mut self, src: &[u8], dst: &'b mut [u8]) -> io::Result<hoot::BodyPart<'b>> {
         match self {
             Hoot::Req(v) => v.read_body(src, dst),
             Hoot::Res(v) => v.read_body(src, dst),
         }
         .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
     }
+
+    fn is_finished(&self) -> bool {
+        match self {
+            Hoot::Req(v) => v.is_finished(),
+            Hoot::Res(v) => v.is_finished(),
+        }
+    }
 }
 
 impl From<hoot::server::Request<RECV_BODY>> for Hoot {
@@ -167,14 +247,67 @@ impl From<hoot::client::Response<RECV_BODY>> for Hoot {
 }
 
 impl HootBody {
-    pub(crate) fn into_buffers(self) -> (Vec<u8>, FillMoreBuffer<Box<dyn io::Read + 'static>>) {
-        assert!(self.leftover.is_empty());
-        (self.parse_buf, self.buffer)
+    /// Bytes left to read, if known exactly.
+    fn size(&self) -> Option<u64> {
+        let (lower, upper) = match &self.hoot_req {
+            Hoot::Req(v) => v.body_size_hint(),
+            Hoot::Res(v) => v.body_size_hint(),
+        };
+
+        if upper != Some(lower) {
+            return None;
+        }
+
+        Some(lower + self.leftover.len() as u64)
+    }
+
+    /// Raw input used for the body so far, including chunk framing and trailers.
+    pub(crate) fn consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Read the rest of the body, and get the buffers back to read another request.
+    ///
+    /// Whatever the handler left unread is discarded. The input buffer is left holding
+    /// exactly the input after the body, such as a pipelined request.
+    pub(crate) fn into_buffers(mut self) -> io::Result<(Vec<u8>, InputBuffer)> {
+        let unread = io::copy(&mut self, &mut io::sink())?;
+
+        if !self.hoot_req.is_finished() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed before end of body",
+            ));
+        }
+
+        trace!("Body used {} bytes, {} unread", self.consumed(), unread);
+
+        Ok((self.parse_buf, self.buffer))
     }
 }
 
 impl io::Read for HootBody {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // The client waits for this before sending the body.
+        if let Some(expect) = self.expect.take() {
+            expect.send(&mut self.tap)?;
+        }
+
+        let n = self.read_payload(buf)?;
+        self.delivered += n as u64;
+
+        // Also stops discarding the rest of the body, for the connection to be closed.
+        if self.limit.map(|l| self.delivered > l).unwrap_or(false) {
+            debug!("Request body over limit");
+            return Err(io::Error::new(io::ErrorKind::InvalidData, PayloadTooLarge));
+        }
+
+        Ok(n)
+    }
+}
+
+impl HootBody {
+    fn read_payload(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         if !self.leftover.is_empty() {
             let max = self.leftover.len().min(buf.len());
             buf[..max].copy_from_slice(&self.leftover[..max]);
@@ -182,32 +315,66 @@ impl io::Read for HootBody {
             return Ok(max);
         }
 
-        let input = self.buffer.fill_more()?;
+        loop {
+            // Never read past the end of the body, the input after it belongs to
+            // the next request.
+            if self.hoot_req.is_finished() || buf.is_empty() {
+                return Ok(0);
+            }
 
-        if input.is_empty() {
-            return Ok(0);
-        }
+            let buffered = self.buffer.buffer().len();
+
+            // The entire body might already be buffered, and reading more would wait
+            // for input the client will not send before getting the response.
+            let input = if self.need_more || buffered == 0 {
+                let input = self.buffer.fill_more()?;
+                if input.len() == buffered {
+                    // Connection closed.
+                    return Ok(0);
+                }
+                input
+            } else {
+                self.buffer.buffer()
+            };
+
+            if self.parse_buf.len() < input.len() {
+                self.parse_buf.resize(input.len(), 0);
+            }
 
-        if self.parse_buf.len() < input.len() {
-            self.parse_buf.resize(input.len(), 0);
-        }
+            let part = self.hoot_req.read_body(input, &mut self.parse_buf)?;
 
-        let part = self.hoot_req.read_body(input, &mut self.parse_buf)?;
+            let input_used = part.input_used();
 
-        let input_used = part.input_used();
+            if input_used > 0 {
+                // After the last chunk, what is left is the trailers.
+                let wire = if self.trailers {
+                    WirePart::Trailer
+                } else {
+                    WirePart::Body
+                };
+                self.tap
+                    .wire(Direction::Received, wire, &input[..input_used]);
+            }
+            self.trailers |= part.is_trailers_pending();
 
-        let data = part.data();
+            let data = part.data();
 
-        let max = buf.len().min(data.len());
-        buf[..max].copy_from_slice(&data[..max]);
+            let max = buf.len().min(data.len());
+            buf[..max].copy_from_slice(&data[..max]);
 
-        if data.len() > max {
-            self.leftover.extend_from_slice(&data[max..]);
-        }
+            if data.len() > max {
+                self.leftover.extend_from_slice(&data[max..]);
+            }
 
-        self.buffer.consume(input_used);
+            // Input only holding part of the chunk framing needs more input.
+            self.need_more = input_used == 0;
+            self.consumed += input_used as u64;
+            self.buffer.consume(input_used);
 
-        Ok(max)
+            if max > 0 {
+                return Ok(max);
+            }
+        }
     }
 }
 
@@ -216,10 +383,10 @@ impl io::Read for Body {
         match &mut self.inner {
             Inner::Empty => Ok(0),
             Inner::Bytes(v) => v.read(buf),
-            Inner::Streaming(v) => v.read(buf),
+            Inner::Streaming(v, _) => v.read(buf),
             Inner::HootBody(v) => {
-                let mut borrow = v.borrow_mut();
-                borrow.read(buf)
+                let mut lock = lock(v);
+                lock.read(buf)
             }
         }
     }
@@ -232,7 +399,7 @@ impl fmt::Debug for Body {
         match &self.inner {
             Inner::Empty => write!(f, "Empty")?,
             Inner::Bytes(v) => write!(f, "Bytes({})", v.get_ref().len())?,
-            Inner::Streaming(_) => write!(f, "Streaming")?,
+            Inner::Streaming(_, _) => write!(f, "Streaming")?,
             Inner::HootBody(v) => write!(f, "{:?}", v)?,
         }
 
@@ -0,0 +1,236 @@
+use http::header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, LOCATION};
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+
+use crate::response::empty;
+use crate::{Body, IntoResponse, Response};
+
+/// Builder of a [`Response`], with helpers for the common statuses and headers.
+///
+/// The body is given last, with [`body()`][Self::body], which like returning a body from a
+/// handler sets the `Content-Length` and a `Content-Type` unless one was set here.
+///
+/// ```
+/// use usrv::{Request, ResponseBuilder, Response};
+///
+/// fn create(_req: Request) -> Response {
+///     ResponseBuilder::created()
+///         .location("/users/1")
+///         .content_type("application/json")
+///         .body(r#"{"id":1}"#)
+/// }
+/// ```
+///
+/// For less, handlers can return a tuple of a [`StatusCode`], optionally a [`HeaderMap`],
+/// and anything that is [`IntoResponse`].
+///
+/// ```
+/// use usrv::http::StatusCode;
+///
+/// fn teapot() -> (StatusCode, &'static str) {
+///     (StatusCode::IM_A_TEAPOT, "short and stout")
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ResponseBuilder {
+    status: StatusCode,
+    headers: HeaderMap,
+    invalid: bool,
+}
+
+impl ResponseBuilder {
+    pub fn new(status: StatusCode) -> Self {
+        ResponseBuilder {
+            status,
+            headers: HeaderMap::new(),
+            invalid: false,
+        }
+    }
+
+    /// 200 OK.
+    pub fn ok() -> Self {
+        Self::new(StatusCode::OK)
+    }
+
+    /// 201 Created, usually with a [`location()`][Self::location].
+    pub fn created() -> Self {
+        Self::new(StatusCode::CREATED)
+    }
+
+    /// 204 No Content.
+    pub fn no_content() -> Self {
+        Self::new(StatusCode::NO_CONTENT)
+    }
+
+    /// 303 See Other, to `location`.
+    pub fn see_other(location: &str) -> Self {
+        Self::new(StatusCode::SEE_OTHER).location(location)
+    }
+
+    /// 400 Bad Request.
+    pub fn bad_request() -> Self {
+        Self::new(StatusCode::BAD_REQUEST)
+    }
+
+    /// 404 Not Found.
+    pub fn not_found() -> Self {
+        Self::new(StatusCode::NOT_FOUND)
+    }
+
+    /// Set a header, replacing any earlier value.
+    ///
+    /// A name or value that isn't valid makes the response a `500 Internal Server Error`.
+    pub fn header<K, V>(mut self, name: K, value: V) -> Self
+    where
+        K: TryInto<HeaderName>,
+        V: TryInto<HeaderValue>,
+    {
+        match (name.try_into(), value.try_into()) {
+            (Ok(name), Ok(value)) => {
+                self.headers.insert(name, value);
+            }
+            _ => self.invalid = true,
+        }
+        self
+    }
+
+    /// Add a header, keeping earlier values, such as for many `Set-Cookie`.
+    pub fn append_header<K, V>(mut self, name: K, value: V) -> Self
+    where
+        K: TryInto<HeaderName>,
+        V: TryInto<HeaderValue>,
+    {
+        match (name.try_into(), value.try_into()) {
+            (Ok(name), Ok(value)) => {
+                self.headers.append(name, value);
+            }
+            _ => self.invalid = true,
+        }
+        self
+    }
+
+    pub fn content_type(self, value: &str) -> Self {
+        self.header(CONTENT_TYPE, value)
+    }
+
+    pub fn location(self, value: &str) -> Self {
+        self.header(LOCATION, value)
+    }
+
+    pub fn cache_control(self, value: &str) -> Self {
+        self.header(CACHE_CONTROL, value)
+    }
+
+    /// Finish with a body.
+    pub fn body(self, body: impl Into<Body>) -> Response {
+        self.finish(body.into().into_response())
+    }
+
+    /// Finish without a body.
+    pub fn empty(self) -> Response {
+        self.finish(empty(StatusCode::OK))
+    }
+
+    fn finish(self, mut response: Response) -> Response {
+        if self.invalid {
+            error!("Invalid header in response");
+            return empty(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+
+        set_status(&mut response, self.status);
+        merge_headers(&mut response, self.headers);
+        response
+    }
+}
+
+impl IntoResponse for ResponseBuilder {
+    fn into_response(self) -> Response {
+        self.empty()
+    }
+}
+
+impl IntoResponse for StatusCode {
+    fn into_response(self) -> Response {
+        let mut response = empty(StatusCode::OK);
+        set_status(&mut response, self);
+        response
+    }
+}
+
+impl<R: IntoResponse> IntoResponse for (StatusCode, R) {
+    fn into_response(self) -> Response {
+        let mut response = self.1.into_response();
+        set_status(&mut response, self.0);
+        response
+    }
+}
+
+impl<R: IntoResponse> IntoResponse for (StatusCode, HeaderMap, R) {
+    fn into_response(self) -> Response {
+        let mut response = self.2.into_response();
+        set_status(&mut response, self.0);
+        merge_headers(&mut response, self.1);
+        response
+    }
+}
+
+fn set_status(response: &mut Response, status: StatusCode) {
+    *response.status_mut() = status;
+
+    // These never have a body, and must not say they have one of no length.
+    if status.is_informational() || status == 204 || status == 304 {
+        response.headers_mut().remove(CONTENT_LENGTH);
+    }
+}
+
+/// Headers replace those of the response with the same name.
+fn merge_headers(response: &mut Response, headers: HeaderMap) {
+    response.headers_mut().extend(headers);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_response() {
+        let res = ResponseBuilder::created()
+            .location("/users/1")
+            .content_type("application/json")
+            .append_header("set-cookie", "a=1")
+            .append_header("set-cookie", "b=2")
+            .body("{}");
+
+        assert_eq!(res.status(), 201);
+        assert_eq!(res.headers()["location"], "/users/1");
+        assert_eq!(res.headers()["content-type"], "application/json");
+        assert_eq!(res.headers()["content-length"], "2");
+        assert_eq!(res.headers().get_all("set-cookie").iter().count(), 2);
+
+        let res = ResponseBuilder::see_other("/").empty();
+        assert_eq!(res.status(), 303);
+        assert_eq!(res.headers()["content-length"], "0");
+
+        let res = ResponseBuilder::ok().header("x-bad", "a\nb").body("x");
+        assert_eq!(res.status(), 500);
+    }
+
+    #[test]
+    fn tuples() {
+        let res = (StatusCode::NO_CONTENT, ()).into_response();
+        assert!(!res.headers().contains_key("content-length"));
+
+        let res = (StatusCode::ACCEPTED, "queued").into_response();
+        assert_eq!(res.status(), 202);
+        assert_eq!(res.headers()["content-length"], "6");
+
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("text/csv"));
+        let res = (StatusCode::OK, headers, "a,b").into_response();
+        assert_eq!(res.headers()["content-type"], "text/csv");
+        assert_eq!(res.headers().get_all("content-type").iter().count(), 1);
+
+        let res = StatusCode::NO_CONTENT.into_response();
+        assert_eq!(res.status(), 204);
+        assert!(!res.headers().contains_key("content-length"));
+    }
+}
@@ -0,0 +1,93 @@
+use crate::Request;
+
+/// Charsets text bodies can be decoded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Charset {
+    Utf8,
+    Ascii,
+    Latin1,
+}
+
+impl Charset {
+    /// The charset of the request content type, UTF-8 when there is none.
+    ///
+    /// `None` for a charset that isn't supported.
+    pub(crate) fn of(request: &Request) -> Option<Charset> {
+        let Some(ctype) = request.headers().get("content-type") else {
+            return Some(Charset::Utf8);
+        };
+        let ctype = ctype.to_str().ok()?;
+
+        let param = ctype.split(';').skip(1).find_map(|p| {
+            let (name, value) = p.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| value.trim().trim_matches('"'))
+        });
+
+        match param {
+            Some(name) => Charset::from_name(name),
+            None => Some(Charset::Utf8),
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Charset> {
+        const NAMES: &[(&str, Charset)] = &[
+            ("utf-8", Charset::Utf8),
+            ("utf8", Charset::Utf8),
+            ("us-ascii", Charset::Ascii),
+            ("ascii", Charset::Ascii),
+            ("iso-8859-1", Charset::Latin1),
+            ("iso8859-1", Charset::Latin1),
+            ("latin1", Charset::Latin1),
+        ];
+
+        let found = NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name));
+
+        if found.is_none() {
+            debug!("Unsupported charset: {}", name);
+        }
+
+        found.map(|(_, c)| *c)
+    }
+
+    /// Decode `bytes` to a string. `None` for bytes that aren't valid in the charset.
+    pub(crate) fn decode(self, bytes: Vec<u8>) -> Option<String> {
+        match self {
+            Charset::Utf8 => String::from_utf8(bytes).ok(),
+            Charset::Ascii if bytes.is_ascii() => String::from_utf8(bytes).ok(),
+            Charset::Ascii => None,
+            // The bytes of ISO-8859-1 are the first 256 code points.
+            Charset::Latin1 => Some(bytes.into_iter().map(char::from).collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn of(ctype: &str) -> Option<Charset> {
+        let request = http::Request::post("/")
+            .header("content-type", ctype)
+            .body(().into())
+            .unwrap();
+        Charset::of(&request)
+    }
+
+    #[test]
+    fn charset_param() {
+        assert_eq!(of("text/plain"), Some(Charset::Utf8));
+        assert_eq!(of("text/plain; charset=UTF-8"), Some(Charset::Utf8));
+        assert_eq!(
+            of("text/plain;format=flowed; Charset=\"latin1\""),
+            Some(Charset::Latin1)
+        );
+        assert_eq!(of("text/plain; charset=us-ascii"), Some(Charset::Ascii));
+        assert_eq!(of("text/plain; charset=shift_jis"), None);
+
+        assert_eq!(Charset::Latin1.decode(vec![b'f', 0xe5]).unwrap(), "få");
+        assert_eq!(Charset::Ascii.decode(vec![b'f', 0xe5]), None);
+        assert_eq!(Charset::Utf8.decode(vec![b'f', 0xe5]), None);
+    }
+}
@@ -0,0 +1,304 @@
+use flate2::read::{GzEncoder, ZlibEncoder};
+use http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG, VARY};
+use http::{HeaderMap, HeaderValue, StatusCode};
+
+use crate::{Body, Request, Response};
+
+/// Compression of response bodies, for clients accepting it.
+///
+/// Bodies are compressed as they are written, with `gzip` or `deflate`, whichever the
+/// client's `Accept-Encoding` prefers, or `gzip` when it likes both as much. The response
+/// gets a `Content-Encoding`, and is sent chunked instead of with a `Content-Length`.
+///
+/// Only textual content types are compressed, see [`content_type()`][Self::content_type].
+/// Responses that are already encoded, partial content, event streams and bodies smaller
+/// than [`min_size()`][Self::min_size] are sent as they are. Set on a service with
+/// [`Service::compression()`][crate::Service::compression].
+///
+/// ```
+/// use usrv::{Compression, MethodRouter, Router};
+///
+/// let service = Router::new()
+///     .get("/", || "hello")
+///     .finish()
+///     .compression(Compression::new().min_size(256));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Compression {
+    level: u32,
+    min_size: u64,
+    content_types: Vec<String>,
+}
+
+/// Content-Coding of a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Coding {
+    Gzip,
+    Deflate,
+}
+
+impl Compression {
+    pub fn new() -> Self {
+        Compression {
+            level: 6,
+            min_size: 1024,
+            content_types: vec![],
+        }
+    }
+
+    /// Compression level from 0, none, to 9, the smallest output. 6 by default.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    /// Don't compress bodies of a known size smaller than this, 1024 bytes by default.
+    ///
+    /// Compressing a small body saves little, or even adds to it.
+    pub fn min_size(mut self, size: u64) -> Self {
+        self.min_size = size;
+        self
+    }
+
+    /// Also compress responses of this content type, such as `application/wasm`.
+    ///
+    /// Types under `text/`, and those ending in `json` or `xml`, or containing
+    /// `javascript`, are compressed without being added.
+    pub fn content_type(mut self, ctype: impl Into<String>) -> Self {
+        self.content_types.push(ctype.into().to_ascii_lowercase());
+        self
+    }
+
+    /// The coding to answer `request` with, if the client accepts one.
+    pub(crate) fn negotiate(&self, request: &Request) -> Option<Coding> {
+        let mut gzip = None;
+        let mut deflate = None;
+        let mut any = None;
+
+        let values = request.headers().get_all(ACCEPT_ENCODING);
+        for item in values.iter().filter_map(|v| v.to_str().ok()) {
+            for entry in item.split(',') {
+                let mut params = entry.split(';');
+                let coding = params.next().unwrap_or("").trim();
+                let q = params
+                    .filter_map(|p| p.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+
+                if coding.eq_ignore_ascii_case("gzip") || coding.eq_ignore_ascii_case("x-gzip") {
+                    gzip = Some(q);
+                } else if coding.eq_ignore_ascii_case("deflate") {
+                    deflate = Some(q);
+                } else if coding == "*" {
+                    any = Some(q);
+                }
+            }
+        }
+
+        let gzip = gzip.or(any).unwrap_or(0.0);
+        let deflate = deflate.or(any).unwrap_or(0.0);
+
+        if gzip > 0.0 && gzip >= deflate {
+            Some(Coding::Gzip)
+        } else if deflate > 0.0 {
+            Some(Coding::Deflate)
+        } else {
+            None
+        }
+    }
+
+    /// Compress the body of `response` with `coding`, when it is worth it.
+    ///
+    /// A response worth compressing gets `Vary: accept-encoding` also without a coding,
+    /// since it would have been compressed for another client.
+    pub(crate) fn compress(&self, coding: Option<Coding>, response: Response) -> Response {
+        if !self.is_eligible(&response) {
+            return response;
+        }
+
+        let (mut parts, body) = response.into_parts();
+        parts
+            .headers
+            .append(VARY, HeaderValue::from_static("accept-encoding"));
+
+        let Some(coding) = coding else {
+            return Response::from_parts(parts, body);
+        };
+
+        let ctype = body.ctype;
+
+        let level = flate2::Compression::new(self.level);
+        let (mut body, name) = match coding {
+            Coding::Gzip => (Body::streaming(GzEncoder::new(body, level)), "gzip"),
+            Coding::Deflate => (Body::streaming(ZlibEncoder::new(body, level)), "deflate"),
+        };
+        body.ctype = ctype;
+
+        let headers = &mut parts.headers;
+        headers.remove(CONTENT_LENGTH);
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static(name));
+
+        // The compressed body isn't byte for byte the same as the representation.
+        if let Some(etag) = headers.get(ETAG).and_then(|v| v.to_str().ok()) {
+            if !etag.starts_with("W/") {
+                if let Ok(weak) = HeaderValue::try_from(format!("W/{}", etag)) {
+                    headers.insert(ETAG, weak);
+                }
+            }
+        }
+
+        trace!("Compress response with {}", name);
+        Response::from_parts(parts, body)
+    }
+
+    fn is_eligible(&self, response: &Response) -> bool {
+        let status = response.status();
+        if status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::PARTIAL_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+        {
+            return false;
+        }
+
+        let headers = response.headers();
+        if headers.contains_key(CONTENT_ENCODING) {
+            return false;
+        }
+
+        if !self.is_compressible(headers) {
+            return false;
+        }
+
+        match response.body().size() {
+            Some(size) => size >= self.min_size,
+            // Streamed, compress as it goes.
+            None => true,
+        }
+    }
+
+    fn is_compressible(&self, headers: &HeaderMap) -> bool {
+        let Some(ctype) = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) else {
+            return false;
+        };
+        let ctype = ctype
+            .split(';')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase();
+
+        // Events must reach the client as they are sent, which compression holds back.
+        if ctype == "text/event-stream" {
+            return false;
+        }
+
+        ctype.starts_with("text/")
+            || ctype.ends_with("json")
+            || ctype.ends_with("xml")
+            || ctype.contains("javascript")
+            || self.content_types.contains(&ctype)
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+
+    use flate2::read::{GzDecoder, ZlibDecoder};
+
+    use super::*;
+    use crate::IntoResponse;
+
+    fn request(accept: &str) -> Request {
+        http::Request::get("/")
+            .header("accept-encoding", accept)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn negotiate() {
+        let c = Compression::new();
+        assert_eq!(
+            c.negotiate(&request("gzip, deflate, br")),
+            Some(Coding::Gzip)
+        );
+        assert_eq!(c.negotiate(&request("deflate")), Some(Coding::Deflate));
+        assert_eq!(
+            c.negotiate(&request("gzip;q=0.5, deflate;q=0.8")),
+            Some(Coding::Deflate)
+        );
+        assert_eq!(
+            c.negotiate(&request("*;q=0.1, gzip;q=0")),
+            Some(Coding::Deflate)
+        );
+        assert_eq!(c.negotiate(&request("br, identity")), None);
+        assert_eq!(c.negotiate(&request("gzip;q=0")), None);
+    }
+
+    #[test]
+    fn compress() {
+        let text = "hello world ".repeat(200);
+        let c = Compression::new();
+
+        let response = c.compress(Some(Coding::Gzip), text.clone().into_response());
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+        assert_eq!(response.headers()["vary"], "accept-encoding");
+        assert!(response.headers().get("content-length").is_none());
+        assert_eq!(response.body().size(), None);
+
+        let mut out = String::new();
+        GzDecoder::new(response.into_body())
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(out, text);
+
+        let response = c.compress(Some(Coding::Deflate), text.clone().into_response());
+        let mut out = String::new();
+        ZlibDecoder::new(response.into_body())
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(out, text);
+
+        // Not accepted by the client, but could have been.
+        let response = c.compress(None, text.clone().into_response());
+        assert!(response.headers().get("content-encoding").is_none());
+        assert_eq!(response.headers()["vary"], "accept-encoding");
+
+        // Too small, and not text.
+        let response = c.compress(Some(Coding::Gzip), "hello".into_response());
+        assert!(response.headers().get("content-encoding").is_none());
+        assert!(response.headers().get("vary").is_none());
+        let response = c.compress(Some(Coding::Gzip), vec![0_u8; 2000].into_response());
+        assert!(response.headers().get("content-encoding").is_none());
+
+        let c = c.content_type("application/octet-stream");
+        let response = c.compress(Some(Coding::Gzip), vec![0_u8; 2000].into_response());
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+    }
+
+    #[test]
+    fn service_compression() {
+        use crate::{MethodRouter, Router};
+
+        let service = Router::new()
+            .get("/", || "hello")
+            .finish()
+            .compression(Compression::new().min_size(0));
+
+        let response = service.call((), request("gzip"));
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+
+        let response = service.call((), request("identity"));
+        assert!(response.headers().get("content-encoding").is_none());
+        assert_eq!(response.headers()["content-length"], "5");
+        assert_eq!(response.headers()["vary"], "accept-encoding");
+    }
+}
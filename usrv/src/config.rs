@@ -0,0 +1,345 @@
+use std::io;
+use std::net::TcpListener;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::fill_more::DEFAULT_MAX;
+use crate::server::tcp::TcpAcceptor;
+use crate::Error;
+
+/// Settings of a server.
+///
+/// Built from the defaults with the setters, and optionally overridden from environment
+/// variables with [`with_env()`][Self::with_env]. Applied to a service with
+/// [`Service::config()`][crate::Service::config].
+///
+/// ```
+/// use std::time::Duration;
+/// use usrv::{MethodRouter, Router, ServerConfig};
+///
+/// let config = ServerConfig::new()
+///     .addr("0.0.0.0:8080")
+///     .read_timeout(Duration::from_secs(30))
+///     .max_connections(512)
+///     .with_env()?;
+///
+/// let service = Router::new().get("/", || "hello").finish().config(config);
+/// # Ok::<(), usrv::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub(crate) addr: String,
+    pub(crate) max_buffer: usize,
+    pub(crate) max_in_flight: Option<usize>,
+    pub(crate) max_connections: Option<usize>,
+    pub(crate) workers: Option<usize>,
+    pub(crate) worker_queue: Option<usize>,
+    pub(crate) retry_after: u32,
+    pub(crate) read_timeout: Option<Duration>,
+    pub(crate) write_timeout: Option<Duration>,
+    pub(crate) keep_alive: bool,
+    pub(crate) max_requests: Option<usize>,
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) deadline_header: Option<String>,
+    pub(crate) body_limit: Option<u64>,
+    pub(crate) expect_continue: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServerConfig {
+    pub fn new() -> Self {
+        ServerConfig {
+            addr: "127.0.0.1:3000".to_string(),
+            max_buffer: DEFAULT_MAX,
+            max_in_flight: None,
+            max_connections: None,
+            workers: None,
+            worker_queue: None,
+            retry_after: 5,
+            read_timeout: None,
+            write_timeout: None,
+            keep_alive: true,
+            max_requests: None,
+            idle_timeout: None,
+            deadline_header: None,
+            body_limit: None,
+            expect_continue: true,
+        }
+    }
+
+    /// Address to listen on, `127.0.0.1:3000` by default. Env `USRV_ADDR`.
+    pub fn addr(mut self, addr: impl Into<String>) -> Self {
+        self.addr = addr.into();
+        self
+    }
+
+    /// Cap on the input buffered per connection, 1MB by default. Env `USRV_MAX_BUFFER`.
+    ///
+    /// The buffer grows as needed for a request head, and shrinks back between
+    /// requests. A head that doesn't fit is answered with `431 Request Header Fields Too
+    /// Large`, and the connection is closed.
+    pub fn max_buffer(mut self, max: usize) -> Self {
+        self.max_buffer = max;
+        self
+    }
+
+    /// Cap on requests handled at once, across all connections. Env `USRV_MAX_IN_FLIGHT`.
+    ///
+    /// Requests over the cap are answered with [`ServiceUnavailable`][crate::ServiceUnavailable],
+    /// without calling any handler. Unlimited by default.
+    pub fn max_in_flight(mut self, max: usize) -> Self {
+        self.max_in_flight = Some(max);
+        self
+    }
+
+    /// Cap on open connections, each served by a thread of its own. Env `USRV_MAX_CONNECTIONS`.
+    ///
+    /// A connection over the cap gets its first request answered with
+    /// [`ServiceUnavailable`][crate::ServiceUnavailable], and is then closed. Unlimited by default.
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// Serve connections on a pool of this many threads, instead of a thread per
+    /// connection. Env `USRV_WORKERS`.
+    ///
+    /// A worker serves one connection until it closes, so with keep-alive an
+    /// [`idle_timeout()`][Self::idle_timeout] keeps idle connections from holding on to
+    /// the workers. When all workers are busy, connections wait in a queue, see
+    /// [`worker_queue()`][Self::worker_queue]. A connection that doesn't fit there either
+    /// gets its first request answered with [`ServiceUnavailable`][crate::ServiceUnavailable],
+    /// and is then closed. As many are answered at a time as there are workers, and the
+    /// ones beyond that are closed right away.
+    ///
+    /// Panics for 0 workers.
+    pub fn workers(mut self, workers: usize) -> Self {
+        assert!(workers > 0, "workers must be at least 1");
+        self.workers = Some(workers);
+        self
+    }
+
+    /// Connections to queue when all [`workers()`][Self::workers] are busy. As many as
+    /// there are workers by default. Env `USRV_WORKER_QUEUE`.
+    pub fn worker_queue(mut self, len: usize) -> Self {
+        self.worker_queue = Some(len);
+        self
+    }
+
+    /// Seconds for clients to wait before retrying, in `Retry-After` when shedding load.
+    /// 5 by default. Env `USRV_RETRY_AFTER`.
+    pub fn retry_after(mut self, seconds: u32) -> Self {
+        self.retry_after = seconds;
+        self
+    }
+
+    /// Drop a connection when reading from it stalls this long. Env `USRV_READ_TIMEOUT`,
+    /// in seconds.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Drop a connection when writing to it stalls this long. Env `USRV_WRITE_TIMEOUT`,
+    /// in seconds.
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Serve more than one request per connection, `true` by default. Env `USRV_KEEP_ALIVE`.
+    ///
+    /// Without keep-alive, responses are sent with `Connection: close`. With it, connections
+    /// are still closed when the client asks to with `Connection: close`, or is HTTP/1.0
+    /// without `Connection: keep-alive`, and when a handler sets `Connection: close`.
+    pub fn keep_alive(mut self, enabled: bool) -> Self {
+        self.keep_alive = enabled;
+        self
+    }
+
+    /// Cap on requests served over one connection. Env `USRV_MAX_REQUESTS`.
+    ///
+    /// The response to the last request is sent with `Connection: close`. Unlimited by
+    /// default.
+    pub fn max_requests(mut self, max: usize) -> Self {
+        self.max_requests = Some(max);
+        self
+    }
+
+    /// Close a kept alive connection when the next request doesn't arrive within this.
+    /// Env `USRV_IDLE_TIMEOUT`, in seconds.
+    ///
+    /// Applies while reading the head of the next request. After it, the
+    /// [`read_timeout()`][Self::read_timeout] is back. Without it, the read timeout
+    /// applies between requests too.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Header with a client deadline for requests, such as `grpc-timeout`. Env
+    /// `USRV_DEADLINE_HEADER`.
+    ///
+    /// See [`Deadline`][crate::Deadline] for the format. Not read unless set.
+    pub fn deadline_header(mut self, name: impl Into<String>) -> Self {
+        self.deadline_header = Some(name.into());
+        self
+    }
+
+    /// Cap on the size of request bodies, in bytes. Env `USRV_BODY_LIMIT`.
+    ///
+    /// A request declaring a larger `Content-Length` is answered with
+    /// [`PayloadTooLarge`][crate::PayloadTooLarge], without calling the handler. A chunked
+    /// body fails to read past the limit, which extractors answer with the same. Routes can
+    /// have a limit of their own, with [`BodyLimit`][crate::BodyLimit]. Unlimited by
+    /// default.
+    pub fn body_limit(mut self, max: u64) -> Self {
+        self.body_limit = Some(max);
+        self
+    }
+
+    /// Answer `Expect: 100-continue`, `true` by default. Env `USRV_EXPECT_CONTINUE`.
+    ///
+    /// The client waits for `100 Continue` before sending the body, which is sent when the
+    /// handler first reads the body. A handler answering without reading it saves the
+    /// client the upload, and the connection is closed after the response. When turned
+    /// off, such requests are answered with
+    /// [`ExpectationFailed`][crate::ExpectationFailed], without calling the handler.
+    pub fn expect_continue(mut self, enabled: bool) -> Self {
+        self.expect_continue = enabled;
+        self
+    }
+
+    /// Override the settings from the `USRV_*` environment variables that are set.
+    ///
+    /// Fails for a variable that doesn't parse.
+    pub fn with_env(self) -> Result<Self, Error> {
+        self.with_vars(|name| std::env::var(name).ok())
+    }
+
+    fn with_vars(mut self, var: impl Fn(&str) -> Option<String>) -> Result<Self, Error> {
+        fn parse<T: FromStr>(name: &'static str, value: String) -> Result<T, Error> {
+            value.trim().parse().map_err(|_| Error::Config(name, value))
+        }
+
+        let secs = |n: u64| Duration::from_secs(n);
+
+        if let Some(v) = var("USRV_ADDR") {
+            self.addr = v;
+        }
+        if let Some(v) = var("USRV_MAX_BUFFER") {
+            self.max_buffer = parse("USRV_MAX_BUFFER", v)?;
+        }
+        if let Some(v) = var("USRV_MAX_IN_FLIGHT") {
+            self.max_in_flight = Some(parse("USRV_MAX_IN_FLIGHT", v)?);
+        }
+        if let Some(v) = var("USRV_MAX_CONNECTIONS") {
+            self.max_connections = Some(parse("USRV_MAX_CONNECTIONS", v)?);
+        }
+        if let Some(v) = var("USRV_WORKERS") {
+            let workers = parse("USRV_WORKERS", v.clone())?;
+            if workers == 0 {
+                return Err(Error::Config("USRV_WORKERS", v));
+            }
+            self.workers = Some(workers);
+        }
+        if let Some(v) = var("USRV_WORKER_QUEUE") {
+            self.worker_queue = Some(parse("USRV_WORKER_QUEUE", v)?);
+        }
+        if let Some(v) = var("USRV_RETRY_AFTER") {
+            self.retry_after = parse("USRV_RETRY_AFTER", v)?;
+        }
+        if let Some(v) = var("USRV_READ_TIMEOUT") {
+            self.read_timeout = Some(secs(parse("USRV_READ_TIMEOUT", v)?));
+        }
+        if let Some(v) = var("USRV_WRITE_TIMEOUT") {
+            self.write_timeout = Some(secs(parse("USRV_WRITE_TIMEOUT", v)?));
+        }
+        if let Some(v) = var("USRV_KEEP_ALIVE") {
+            self.keep_alive = parse("USRV_KEEP_ALIVE", v)?;
+        }
+        if let Some(v) = var("USRV_MAX_REQUESTS") {
+            self.max_requests = Some(parse("USRV_MAX_REQUESTS", v)?);
+        }
+        if let Some(v) = var("USRV_IDLE_TIMEOUT") {
+            self.idle_timeout = Some(secs(parse("USRV_IDLE_TIMEOUT", v)?));
+        }
+        if let Some(v) = var("USRV_DEADLINE_HEADER") {
+            self.deadline_header = Some(v);
+        }
+        if let Some(v) = var("USRV_BODY_LIMIT") {
+            self.body_limit = Some(parse("USRV_BODY_LIMIT", v)?);
+        }
+        if let Some(v) = var("USRV_EXPECT_CONTINUE") {
+            self.expect_continue = parse("USRV_EXPECT_CONTINUE", v)?;
+        }
+
+        Ok(self)
+    }
+
+    /// Listen on the address, for connections with the timeouts of this config.
+    pub fn bind(&self) -> io::Result<TcpAcceptor> {
+        let listener = TcpListener::bind(&self.addr)?;
+
+        Ok(TcpAcceptor::new(listener)
+            .read_timeout(self.read_timeout)
+            .write_timeout(self.write_timeout))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn env_overrides() {
+        let vars: HashMap<_, _> = [
+            ("USRV_ADDR", "0.0.0.0:80"),
+            ("USRV_MAX_CONNECTIONS", "10"),
+            ("USRV_READ_TIMEOUT", " 30 "),
+            ("USRV_KEEP_ALIVE", "false"),
+            ("USRV_BODY_LIMIT", "1024"),
+            ("USRV_EXPECT_CONTINUE", "false"),
+        ]
+        .into_iter()
+        .collect();
+
+        let config = ServerConfig::new()
+            .addr("127.0.0.1:8080")
+            .max_in_flight(4)
+            .with_vars(|name| vars.get(name).map(|v| v.to_string()))
+            .unwrap();
+
+        assert_eq!(config.addr, "0.0.0.0:80");
+        assert_eq!(config.max_connections, Some(10));
+        assert_eq!(config.max_in_flight, Some(4));
+        assert_eq!(config.read_timeout, Some(Duration::from_secs(30)));
+        assert_eq!(config.write_timeout, None);
+        assert!(!config.keep_alive);
+        assert_eq!(config.body_limit, Some(1024));
+        assert!(!config.expect_continue);
+
+        let e = ServerConfig::new()
+            .with_vars(|name| (name == "USRV_MAX_BUFFER").then(|| "lots".to_string()))
+            .unwrap_err();
+        assert_eq!(e.to_string(), "invalid USRV_MAX_BUFFER: lots");
+
+        let e = ServerConfig::new()
+            .with_vars(|name| (name == "USRV_WORKERS").then(|| "0".to_string()))
+            .unwrap_err();
+        assert_eq!(e.to_string(), "invalid USRV_WORKERS: 0");
+    }
+
+    #[test]
+    #[should_panic(expected = "workers must be at least 1")]
+    fn no_workers() {
+        ServerConfig::new().workers(0);
+    }
+}
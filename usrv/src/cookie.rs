@@ -0,0 +1,478 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use http::header::{COOKIE, SET_COOKIE};
+use http::HeaderValue;
+
+use crate::from_req::{FromRequest, FromRequestRef};
+use crate::response::IntoResponse;
+use crate::{Request, Response};
+
+/// Extractor for the cookies sent with the request, from the `Cookie` header.
+///
+/// Pairs that can't be read are skipped. For a name sent more than once, the first value
+/// is kept, which is the one with the longest path.
+///
+/// ```
+/// use usrv::Cookies;
+///
+/// fn theme(cookies: Cookies) -> String {
+///     cookies.get("theme").unwrap_or("light").to_string()
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cookies(pub HashMap<String, String>);
+
+impl Cookies {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(|v| v.as_str())
+    }
+
+    fn parse(request: &Request) -> Self {
+        let mut map = HashMap::new();
+
+        let headers = request.headers().get_all(COOKIE);
+        for header in headers.iter().filter_map(|v| v.to_str().ok()) {
+            for pair in header.split(';') {
+                let Some((name, value)) = pair.split_once('=') else {
+                    continue;
+                };
+
+                let name = name.trim();
+                if name.is_empty() {
+                    continue;
+                }
+
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+
+                map.entry(name.to_string())
+                    .or_insert_with(|| value.to_string());
+            }
+        }
+
+        Cookies(map)
+    }
+}
+
+impl<S> FromRequestRef<S> for Cookies {
+    type Rejection = Response;
+
+    fn from_request(_state: &S, request: &Request) -> Result<Self, Self::Rejection> {
+        Ok(Cookies::parse(request))
+    }
+}
+
+impl<S> FromRequest<S> for Cookies {
+    type Rejection = Response;
+
+    fn from_request(_state: &S, request: Request) -> Result<Self, Self::Rejection> {
+        Ok(Cookies::parse(&request))
+    }
+}
+
+/// A cookie to set, written as a `Set-Cookie` header.
+///
+/// The value is sent as is, and must be of the characters allowed in a cookie: no
+/// whitespace, `"`, `,`, `;` or `\`. Encode anything else before setting it.
+///
+/// ```
+/// use usrv::{Cookie, SameSite};
+///
+/// let cookie = Cookie::new("session", "abc123")
+///     .path("/")
+///     .http_only()
+///     .same_site(SameSite::Lax)
+///     .max_age(3600);
+///
+/// assert_eq!(
+///     cookie.to_string(),
+///     "session=abc123; Path=/; HttpOnly; SameSite=Lax; Max-Age=3600"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    http_only: bool,
+    secure: bool,
+    same_site: Option<SameSite>,
+    max_age: Option<u64>,
+}
+
+/// The `SameSite` attribute of a [`Cookie`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    /// Sent on requests from other sites too, which browsers only allow with
+    /// [`secure()`][Cookie::secure].
+    None,
+}
+
+impl Cookie {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Cookie {
+            name: name.into(),
+            value: value.into(),
+            path: None,
+            http_only: false,
+            secure: false,
+            same_site: None,
+            max_age: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Only send the cookie for paths under `path`.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Hide the cookie from scripts in the browser.
+    pub fn http_only(mut self) -> Self {
+        self.http_only = true;
+        self
+    }
+
+    /// Only send the cookie over HTTPS.
+    pub fn secure(mut self) -> Self {
+        self.secure = true;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Seconds until the cookie expires. Without, it lasts the browser session.
+    pub fn max_age(mut self, seconds: u64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    fn is_valid(&self) -> bool {
+        hoot::validate_header_name(&self.name).is_ok()
+            && self.value.bytes().all(is_cookie_octet)
+            && self.path.as_ref().map(|p| !p.contains(';')).unwrap_or(true)
+    }
+}
+
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+        if let Some(same_site) = self.same_site {
+            let v = match same_site {
+                SameSite::Strict => "Strict",
+                SameSite::Lax => "Lax",
+                SameSite::None => "None",
+            };
+            write!(f, "; SameSite={}", v)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_cookie_octet(b: u8) -> bool {
+    // cookie-octet = %x21 / %x23-2B / %x2D-3A / %x3C-5B / %x5D-7E
+    matches!(b, 0x21 | 0x23..=0x2b | 0x2d..=0x3a | 0x3c..=0x5b | 0x5d..=0x7e)
+}
+
+/// Extractor for the cookies of the request, which the handler can change.
+///
+/// The changes are sent as `Set-Cookie` headers when the jar is returned together with
+/// the response, as `(CookieJar, R)` for anything `R` that is [`IntoResponse`]. Cookies
+/// with a name or value that can't be sent are left out, with a warning in the log.
+///
+/// ```
+/// use usrv::{Cookie, CookieJar};
+///
+/// fn visit(mut jar: CookieJar) -> (CookieJar, String) {
+///     let visits: u32 = jar.get("visits").and_then(|v| v.parse().ok()).unwrap_or(0);
+///     jar.add(Cookie::new("visits", (visits + 1).to_string()).path("/"));
+///     (jar, format!("visit number {}", visits + 1))
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    cookies: Cookies,
+    delta: Vec<Cookie>,
+}
+
+impl CookieJar {
+    /// The value of a cookie, as changed by this jar.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        match self.delta.iter().rev().find(|c| c.name == name) {
+            Some(c) if c.max_age == Some(0) => None,
+            Some(c) => Some(&c.value),
+            None => self.cookies.get(name),
+        }
+    }
+
+    /// Set a cookie, replacing any earlier change to a cookie of the same name and path.
+    pub fn add(&mut self, cookie: Cookie) {
+        self.delta
+            .retain(|c| c.name != cookie.name || c.path != cookie.path);
+        self.delta.push(cookie);
+    }
+
+    /// Remove a cookie from the browser, by setting it empty and expired.
+    ///
+    /// The path must be the same as when the cookie was set.
+    pub fn remove(&mut self, mut cookie: Cookie) {
+        cookie.value.clear();
+        cookie.max_age = Some(0);
+        self.add(cookie);
+    }
+
+    /// The cookies to set, in the order they were changed.
+    pub fn delta(&self) -> impl Iterator<Item = &Cookie> {
+        self.delta.iter()
+    }
+}
+
+impl<S> FromRequestRef<S> for CookieJar {
+    type Rejection = Response;
+
+    fn from_request(_state: &S, request: &Request) -> Result<Self, Self::Rejection> {
+        Ok(CookieJar {
+            cookies: Cookies::parse(request),
+            delta: vec![],
+        })
+    }
+}
+
+impl<S> FromRequest<S> for CookieJar {
+    type Rejection = Response;
+
+    fn from_request(state: &S, request: Request) -> Result<Self, Self::Rejection> {
+        <Self as FromRequestRef<S>>::from_request(state, &request)
+    }
+}
+
+impl<R: IntoResponse> IntoResponse for (CookieJar, R) {
+    fn into_response(self) -> Response {
+        let (jar, response) = self;
+        let mut response = response.into_response();
+
+        for cookie in jar.delta {
+            let value = if cookie.is_valid() {
+                HeaderValue::try_from(cookie.to_string()).ok()
+            } else {
+                None
+            };
+
+            match value {
+                Some(v) => {
+                    response.headers_mut().append(SET_COOKIE, v);
+                }
+                None => warn!("Cookie that can't be sent: {}", cookie.name),
+            }
+        }
+
+        response
+    }
+}
+
+#[cfg(feature = "signed-cookies")]
+mod signed {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use super::*;
+
+    type HmacSha256 = Hmac<Sha256>;
+
+    /// Secret key for signed cookies.
+    ///
+    /// Signed cookies are readable by the client, but can't be changed without the key.
+    /// The key should be long and random, and kept the same across restarts for cookies
+    /// to stay valid.
+    #[derive(Clone)]
+    pub struct Key(Vec<u8>);
+
+    impl Key {
+        pub fn new(secret: &[u8]) -> Self {
+            Key(secret.to_vec())
+        }
+
+        fn mac(&self, name: &str, value: &str) -> HmacSha256 {
+            let mut mac =
+                HmacSha256::new_from_slice(&self.0).expect("HMAC takes keys of any length");
+            mac.update(name.as_bytes());
+            mac.update(b"=");
+            mac.update(value.as_bytes());
+            mac
+        }
+
+        fn sign(&self, name: &str, value: &str) -> String {
+            let tag = self.mac(name, value).finalize().into_bytes();
+            tag.iter().map(|b| format!("{:02x}", b)).collect()
+        }
+
+        /// Whether `tag` is the signature, compared in constant time.
+        fn verify(&self, name: &str, value: &str, tag: &str) -> bool {
+            let Some(tag) = from_hex(tag) else {
+                return false;
+            };
+            self.mac(name, value).verify_slice(&tag).is_ok()
+        }
+    }
+
+    impl fmt::Debug for Key {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("Key(..)")
+        }
+    }
+
+    impl CookieJar {
+        /// The value of a signed cookie, `None` if missing or the signature doesn't match.
+        pub fn get_signed(&self, key: &Key, name: &str) -> Option<String> {
+            let signed = self.get(name)?;
+            let (value, tag) = signed.rsplit_once('.')?;
+
+            if !key.verify(name, value, tag) {
+                debug!("Cookie with bad signature: {}", name);
+                return None;
+            }
+
+            Some(value.to_string())
+        }
+
+        /// Set a cookie, signed with `key`. The signature is appended to the value.
+        pub fn add_signed(&mut self, key: &Key, mut cookie: Cookie) {
+            let tag = key.sign(&cookie.name, &cookie.value);
+            cookie.value = format!("{}.{}", cookie.value, tag);
+            self.add(cookie);
+        }
+    }
+
+    fn from_hex(s: &str) -> Option<Vec<u8>> {
+        if s.len() % 2 != 0 {
+            return None;
+        }
+
+        let digit = |c: u8| (c as char).to_digit(16);
+
+        s.as_bytes()
+            .chunks(2)
+            .map(|c| Some((digit(c[0])? * 16 + digit(c[1])?) as u8))
+            .collect()
+    }
+}
+
+#[cfg(feature = "signed-cookies")]
+pub use signed::Key;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(cookie: &str) -> Request {
+        http::Request::builder()
+            .header("cookie", cookie)
+            .body(crate::Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_cookies() {
+        let cookies = Cookies::parse(&request(r#"a=1; b="two" ;c=; broken; a=3; =x"#));
+        assert_eq!(cookies.get("a"), Some("1"));
+        assert_eq!(cookies.get("b"), Some("two"));
+        assert_eq!(cookies.get("c"), Some(""));
+        assert_eq!(cookies.get("broken"), None);
+        assert_eq!(cookies.0.len(), 3);
+    }
+
+    #[test]
+    fn jar_delta() {
+        let mut jar =
+            <CookieJar as FromRequestRef<()>>::from_request(&(), &request("a=1; b=2")).unwrap();
+
+        jar.add(Cookie::new("a", "x"));
+        jar.add(Cookie::new("a", "y"));
+        jar.remove(Cookie::new("b", "").path("/"));
+        jar.add(Cookie::new("bad", "has space"));
+
+        assert_eq!(jar.get("a"), Some("y"));
+        assert_eq!(jar.get("b"), None);
+
+        let res = (jar, "ok").into_response();
+        let set: Vec<_> = res.headers().get_all("set-cookie").iter().collect();
+        assert_eq!(set, ["a=y", "b=; Path=/; Max-Age=0"]);
+    }
+
+    #[cfg(feature = "signed-cookies")]
+    #[test]
+    fn signed_cookies() {
+        let key = Key::new(b"0123456789abcdef0123456789abcdef");
+
+        let mut jar = CookieJar::default();
+        jar.add_signed(&key, Cookie::new("user", "42"));
+        let set = jar.delta().next().unwrap().value().to_string();
+        assert!(set.starts_with("42."));
+
+        let jar = <CookieJar as FromRequestRef<()>>::from_request(
+            &(),
+            &request(&format!("user={}; other={}", set, set)),
+        )
+        .unwrap();
+        assert_eq!(jar.get_signed(&key, "user").as_deref(), Some("42"));
+
+        // Signed for another name, or changed.
+        assert_eq!(jar.get_signed(&key, "other"), None);
+        let changed = set.replacen("42", "43", 1);
+        let jar = <CookieJar as FromRequestRef<()>>::from_request(
+            &(),
+            &request(&format!("user={}", changed)),
+        )
+        .unwrap();
+        assert_eq!(jar.get_signed(&key, "user"), None);
+
+        let other = Key::new(b"another key");
+        let jar = <CookieJar as FromRequestRef<()>>::from_request(
+            &(),
+            &request(&format!("user={}", set)),
+        )
+        .unwrap();
+        assert_eq!(jar.get_signed(&other, "user"), None);
+
+        // Signatures that aren't hex.
+        let jar = <CookieJar as FromRequestRef<()>>::from_request(
+            &(),
+            &request("a=42.+1; b=42.abc; c=42."),
+        )
+        .unwrap();
+        for name in ["a", "b", "c"] {
+            assert_eq!(jar.get_signed(&key, name), None);
+        }
+    }
+}
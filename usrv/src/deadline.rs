@@ -0,0 +1,179 @@
+use std::time::{Duration, Instant};
+
+use http::StatusCode;
+
+use crate::from_req::{FromRequest, FromRequestRef};
+use crate::response::empty;
+use crate::{Request, Response};
+
+/// Extractor for the deadline a client set for the request.
+///
+/// The deadline comes from the header set with
+/// [`ServerConfig::deadline_header()`][crate::ServerConfig::deadline_header], counting from
+/// when the request arrived. The value is a timeout in the style of `grpc-timeout`, digits
+/// followed by a unit: `H`, `M`, `S`, `m` (milliseconds), `u` (microseconds) or `n`
+/// (nanoseconds). Digits without a unit are milliseconds.
+///
+/// A request arriving with no time left is answered with `504 Gateway Timeout`, without
+/// calling any handler. Handlers can check the time left, and pass it on with
+/// [`header_value()`][Self::header_value] to outbound calls made for the request.
+///
+/// ```
+/// use usrv::{Deadline, MethodRouter, Router, ServerConfig};
+///
+/// fn slow(deadline: Deadline) -> String {
+///     // Outbound requests get the time that is left.
+///     let propagate = deadline.header_value();
+///     format!("{:?}", propagate)
+/// }
+///
+/// let config = ServerConfig::new().deadline_header("grpc-timeout");
+/// let service = Router::new().get("/", slow).finish().config(config);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Deadline(Option<Instant>);
+
+impl Deadline {
+    /// The instant the request must be done by. `None` when the client set no deadline.
+    pub fn instant(&self) -> Option<Instant> {
+        self.0
+    }
+
+    /// Time left until the deadline, zero once passed.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.0.map(|d| d.saturating_duration_since(Instant::now()))
+    }
+
+    /// Whether the deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        self.remaining() == Some(Duration::ZERO)
+    }
+
+    /// The time left as a header value for outbound requests, in milliseconds like `250m`.
+    pub fn header_value(&self) -> Option<String> {
+        // grpc-timeout allows at most 8 digits.
+        self.remaining()
+            .map(|r| format!("{}m", r.as_millis().min(99_999_999)))
+    }
+}
+
+/// Parse a timeout like `100m` or `5S`. See [`Deadline`].
+pub(crate) fn parse_timeout(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let digits = value.bytes().take_while(|b| b.is_ascii_digit()).count();
+
+    if digits == 0 {
+        return None;
+    }
+
+    let n: u64 = value[..digits].parse().ok()?;
+
+    let timeout = match &value[digits..] {
+        "H" => Duration::from_secs(n.checked_mul(3600)?),
+        "M" => Duration::from_secs(n.checked_mul(60)?),
+        "S" => Duration::from_secs(n),
+        "m" | "" => Duration::from_millis(n),
+        "u" => Duration::from_micros(n),
+        "n" => Duration::from_nanos(n),
+        _ => return None,
+    };
+
+    Some(timeout)
+}
+
+/// The deadline set by header `name`, if the request has one that parses.
+pub(crate) fn deadline(request: &Request, name: &str) -> Option<Deadline> {
+    let value = request.headers().get(name)?.to_str().ok()?;
+
+    let Some(timeout) = parse_timeout(value) else {
+        debug!("Ignoring unparseable {}: {}", name, value);
+        return None;
+    };
+
+    Some(Deadline(Instant::now().checked_add(timeout)))
+}
+
+impl<S> FromRequestRef<S> for Deadline {
+    type Rejection = Response;
+
+    fn from_request(_state: &S, request: &Request) -> Result<Self, Self::Rejection> {
+        Ok(request.extensions().get().copied().unwrap_or_default())
+    }
+}
+
+impl<S> FromRequest<S> for Deadline {
+    type Rejection = Response;
+
+    fn from_request(_state: &S, request: Request) -> Result<Self, Self::Rejection> {
+        Ok(request.extensions().get().copied().unwrap_or_default())
+    }
+}
+
+/// 504, for a request arriving past its deadline.
+pub(crate) fn expired() -> Response {
+    empty(StatusCode::GATEWAY_TIMEOUT)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Body;
+
+    #[test]
+    fn parse_timeout_units() {
+        let secs = Duration::from_secs;
+        assert_eq!(parse_timeout("2H"), Some(secs(7200)));
+        assert_eq!(parse_timeout("3M"), Some(secs(180)));
+        assert_eq!(parse_timeout(" 5S "), Some(secs(5)));
+        assert_eq!(parse_timeout("250m"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_timeout("250"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_timeout("7u"), Some(Duration::from_micros(7)));
+        assert_eq!(parse_timeout("9n"), Some(Duration::from_nanos(9)));
+
+        for value in ["", "S", "-1S", "1.5S", "1s", "10 S", "10SS"] {
+            assert_eq!(parse_timeout(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn parse_timeout_overflow() {
+        let hours = format!("{}H", u64::MAX / 3600 + 1);
+        assert_eq!(parse_timeout(&hours), None);
+        assert_eq!(parse_timeout(&format!("{}M", u64::MAX)), None);
+
+        let max = format!("{}S", u64::MAX);
+        assert_eq!(parse_timeout(&max), Some(Duration::from_secs(u64::MAX)));
+
+        // More digits than fit a u64.
+        assert_eq!(parse_timeout("99999999999999999999m"), None);
+    }
+
+    #[test]
+    fn deadline_from_header() {
+        let deadline_of = |value: &str| {
+            let request = http::Request::get("/")
+                .header("grpc-timeout", value)
+                .body(Body::empty())
+                .unwrap();
+            deadline(&request, "grpc-timeout")
+        };
+
+        let d = deadline_of("10S").unwrap();
+        assert!(d.remaining().unwrap() <= Duration::from_secs(10));
+        assert!(!d.is_expired());
+
+        assert!(deadline_of("0m").unwrap().is_expired());
+        assert!(deadline_of("soon").is_none());
+
+        // Too far off for an Instant, which is no deadline.
+        let d = deadline_of(&format!("{}S", u64::MAX)).unwrap();
+        assert_eq!(d.instant(), None);
+        assert_eq!(d.header_value(), None);
+    }
+
+    #[test]
+    fn header_value_capped() {
+        let far = Instant::now() + Duration::from_secs(1_000_000_000);
+        assert_eq!(Deadline(Some(far)).header_value().unwrap(), "99999999m");
+    }
+}
@@ -4,14 +4,22 @@ use std::string::FromUtf8Error;
 use hoot::HootError;
 use thiserror::Error;
 
+use crate::response::BadRequest;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("{0}")]
     Hoot(#[from] HootError),
 
+    #[error("bad request: {0}")]
+    BadRequest(BadRequest),
+
     #[error("{0}")]
     Io(#[from] io::Error),
 
     #[error("ut8: {0}")]
     Utf8(#[from] FromUtf8Error),
+
+    #[error("invalid {0}: {1}")]
+    Config(&'static str, String),
 }
@@ -0,0 +1,86 @@
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use hoot::wire::{Direction, WireHook, WirePart};
+use http::{HeaderMap, Version};
+
+use crate::wire::Tap;
+
+/// Interim response telling the client to go ahead and send the body.
+const CONTINUE: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n";
+
+/// Whether the client waits for `100 Continue` before sending the body.
+///
+/// HTTP/1.0 clients don't know of it, and the expectation is ignored.
+pub(crate) fn expects_continue(version: Version, headers: &HeaderMap) -> bool {
+    version != Version::HTTP_10
+        && headers
+            .get_all("expect")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .any(|v| v.trim().eq_ignore_ascii_case("100-continue"))
+}
+
+/// Tell the client to go ahead and send the body.
+pub(crate) fn write_continue(writer: &mut dyn io::Write, tap: &mut Tap) -> io::Result<()> {
+    trace!("Send 100 Continue");
+    writer.write_all(CONTINUE)?;
+    writer.flush()?;
+    tap.wire(Direction::Sent, WirePart::Head, CONTINUE);
+    Ok(())
+}
+
+/// Writer of a connection, shared with the request bodies read from it.
+#[derive(Clone)]
+pub(crate) struct SharedWriter(Arc<Mutex<dyn io::Write + Send>>);
+
+impl SharedWriter {
+    pub(crate) fn new(writer: impl io::Write + Send + 'static) -> Self {
+        SharedWriter(Arc::new(Mutex::new(writer)))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, dyn io::Write + Send + 'static> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl io::Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+}
+
+/// A `100 Continue` owed to the client, sent on the first read of the request body.
+///
+/// Clones share whether it is owed.
+#[derive(Clone)]
+pub(crate) struct Continue(Arc<Mutex<Option<SharedWriter>>>);
+
+impl Continue {
+    pub(crate) fn new(writer: SharedWriter) -> Self {
+        Continue(Arc::new(Mutex::new(Some(writer))))
+    }
+
+    fn take(&self) -> Option<SharedWriter> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    /// Send it, unless sent already, or the response has started. Whether it was sent.
+    pub(crate) fn send(&self, tap: &mut Tap) -> io::Result<bool> {
+        let Some(mut writer) = self.take() else {
+            return Ok(false);
+        };
+
+        write_continue(&mut writer, tap)?;
+        Ok(true)
+    }
+
+    /// Don't send it, since the response is about to be written. Whether it was owed.
+    pub(crate) fn cancel(&self) -> bool {
+        self.take().is_some()
+    }
+}
@@ -0,0 +1,315 @@
+use std::fs::{File, Metadata};
+use std::io::{self, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use hoot::cache::{parse_http_date, HttpDate};
+use http::{HeaderValue, Method, StatusCode};
+
+use crate::handler::Handler;
+use crate::path::PathParams;
+use crate::response::{empty, IntoResponse, NotFound};
+use crate::{Body, Request, Response};
+
+/// Handler serving the files of a directory.
+///
+/// The file is the rest of the path captured by the route, with `*name` last in the
+/// pattern. A directory is served by its `index.html`. Paths leading outside of the
+/// directory are answered with `404 Not Found`, as are files that don't exist.
+///
+/// Files are streamed as they are read, see [`ServeFile`] for the headers and the
+/// conditional and range requests.
+///
+/// ```no_run
+/// use usrv::{MethodRouter, Router, ServeDir};
+///
+/// let service = Router::new()
+///     .get("/static/*file", ServeDir::new("public"))
+///     .finish();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServeDir {
+    root: PathBuf,
+}
+
+impl ServeDir {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        ServeDir { root: root.into() }
+    }
+
+    fn resolve(&self, request: &Request) -> Option<PathBuf> {
+        // The capture of *name, or the whole path for a route without.
+        let rest = match request.extensions().get::<PathParams>() {
+            Some(params) => params.0.last()?.1.clone(),
+            None => request.uri().path().to_string(),
+        };
+
+        let mut path = self.root.clone();
+        for segment in rest.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => return None,
+                s if s.contains(['\\', ':', '\0']) => return None,
+                s => path.push(s),
+            }
+        }
+
+        if path.is_dir() {
+            path.push("index.html");
+        }
+
+        Some(path)
+    }
+}
+
+impl<S> Handler<ServeDir, S> for ServeDir {
+    fn call(self, _state: S, request: Request) -> Response {
+        match self.resolve(&request) {
+            Some(path) => serve(&path, &request),
+            None => {
+                debug!("Path outside of served directory: {}", request.uri().path());
+                NotFound.into_response()
+            }
+        }
+    }
+}
+
+/// Handler serving one file.
+///
+/// The response has a `Content-Type` from the file extension, and an `ETag` and
+/// `Last-Modified` from the size and modification time. Conditional requests with
+/// `If-None-Match` or `If-Modified-Since` are answered with `304 Not Modified` when the
+/// file is unchanged. A request for a single `Range: bytes=` gets `206 Partial Content`,
+/// or `416 Range Not Satisfiable` for a range past the end.
+///
+/// ```no_run
+/// use usrv::{MethodRouter, Router, ServeFile};
+///
+/// let service = Router::new()
+///     .get("/favicon.ico", ServeFile::new("public/favicon.ico"))
+///     .finish();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServeFile {
+    path: PathBuf,
+}
+
+impl ServeFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        ServeFile { path: path.into() }
+    }
+}
+
+impl<S> Handler<ServeFile, S> for ServeFile {
+    fn call(self, _state: S, request: Request) -> Response {
+        serve(&self.path, &request)
+    }
+}
+
+fn serve(path: &Path, request: &Request) -> Response {
+    let (mut file, meta) = match open(path) {
+        Ok(Some(v)) => v,
+        Ok(None) => return NotFound.into_response(),
+        Err(e) => {
+            error!("Failed to open {}: {}", path.display(), e);
+            return empty(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let len = meta.len();
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+
+    let etag = format!("\"{:x}-{:x}\"", len, modified.unwrap_or(0));
+
+    let mut builder = http::Response::builder()
+        .header("etag", &etag)
+        .header("accept-ranges", "bytes");
+    if let Some(modified) = modified {
+        builder = builder.header("last-modified", http_date(modified));
+    }
+
+    let is_get = request.method() == Method::GET || request.method() == Method::HEAD;
+
+    if is_get && not_modified(request, &etag, modified) {
+        return builder
+            .status(304)
+            .body(Body::empty())
+            .unwrap_or_else(|_| empty(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+
+    builder = builder.header("content-type", content_type(path));
+
+    let range = if is_get && if_range(request, &etag) {
+        request.headers().get("range").and_then(|v| v.to_str().ok())
+    } else {
+        None
+    };
+
+    let response = match range.map(|r| parse_range(r, len)) {
+        Some(Ok(Some((start, end)))) => {
+            if let Err(e) = file.seek(SeekFrom::Start(start)) {
+                error!("Failed to seek {}: {}", path.display(), e);
+                return empty(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+            let part = end - start + 1;
+            builder
+                .status(206)
+                .header("content-range", format!("bytes {}-{}/{}", start, end, len))
+                .header("content-length", part)
+                .body(Body::with_length(file, part))
+        }
+        Some(Err(())) => builder
+            .status(416)
+            .header("content-range", format!("bytes */{}", len))
+            .header("content-length", 0)
+            .body(Body::empty()),
+        // No range, or one we don't do, such as many ranges.
+        _ => builder
+            .status(200)
+            .header("content-length", len)
+            .body(Body::with_length(file, len)),
+    };
+
+    response.unwrap_or_else(|_| empty(StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// The file and its metadata, `None` if there is no such file.
+fn open(path: &Path) -> io::Result<Option<(File, Metadata)>> {
+    let file = match File::open(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let meta = file.metadata()?;
+
+    if !meta.is_file() {
+        return Ok(None);
+    }
+
+    Ok(Some((file, meta)))
+}
+
+fn not_modified(request: &Request, etag: &str, modified: Option<u64>) -> bool {
+    let headers = request.headers();
+
+    // If-None-Match wins over If-Modified-Since.
+    if let Some(v) = headers.get("if-none-match") {
+        let Ok(v) = v.to_str() else {
+            return false;
+        };
+        return v
+            .split(',')
+            .map(|t| t.trim())
+            .any(|t| t == "*" || t.trim_start_matches("W/") == etag);
+    }
+
+    let since = headers
+        .get("if-modified-since")
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_http_date);
+
+    match (since, modified) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+/// Whether a `Range` applies, which it does unless `If-Range` names another version.
+fn if_range(request: &Request, etag: &str) -> bool {
+    match request.headers().get("if-range") {
+        Some(v) => v.to_str().map(|v| v == etag).unwrap_or(false),
+        None => true,
+    }
+}
+
+/// The first and last byte of a single range. `Ok(None)` for ranges that are ignored,
+/// and `Err` for a range past the end.
+fn parse_range(value: &str, len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+
+    if spec.contains(',') {
+        return Ok(None);
+    }
+
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return Ok(None);
+    };
+
+    let range = match (start.parse::<u64>().ok(), end.parse::<u64>().ok()) {
+        // Last bytes.
+        (None, Some(suffix)) if start.is_empty() => {
+            if suffix == 0 || len == 0 {
+                return Err(());
+            }
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (Some(start), None) if end.is_empty() => (start, len.saturating_sub(1)),
+        (Some(start), Some(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+        _ => return Ok(None),
+    };
+
+    if range.0 >= len {
+        return Err(());
+    }
+
+    Ok(Some(range))
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    match ext.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "mp4" => "video/mp4",
+        "mp3" => "audio/mpeg",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Seconds since the Unix epoch as an IMF-fixdate.
+fn http_date(secs: u64) -> HeaderValue {
+    HeaderValue::try_from(HttpDate(secs).to_string()).expect("date is a header value")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse_range("bytes=0-4", 10), Ok(Some((0, 4))));
+        assert_eq!(parse_range("bytes=5-", 10), Ok(Some((5, 9))));
+        assert_eq!(parse_range("bytes=-3", 10), Ok(Some((7, 9))));
+        assert_eq!(parse_range("bytes=-30", 10), Ok(Some((0, 9))));
+        assert_eq!(parse_range("bytes=8-100", 10), Ok(Some((8, 9))));
+        assert_eq!(parse_range("bytes=10-", 10), Err(()));
+        assert_eq!(parse_range("bytes=-0", 10), Err(()));
+        assert_eq!(parse_range("bytes=0-1,4-5", 10), Ok(None));
+        assert_eq!(parse_range("bytes=5-4", 10), Ok(None));
+        assert_eq!(parse_range("lines=1-2", 10), Ok(None));
+    }
+}
@@ -1,19 +1,30 @@
 use std::io;
 
-const INCREMENT: usize = 4096;
+/// Initial size, and the size shrunk back to between requests.
+const INITIAL: usize = 4096;
 const THRESHOLD: usize = 100;
 
+/// Default cap on the buffer size.
+pub(crate) const DEFAULT_MAX: usize = 1024 * 1024;
+
 pub struct FillMoreBuffer<Read> {
     buffer: Vec<u8>,
     pos: usize,
+    max: usize,
     reader: Option<Read>,
 }
 
 impl<Read: io::Read> FillMoreBuffer<Read> {
     pub fn new(reader: Read) -> Self {
+        Self::with_max(reader, DEFAULT_MAX)
+    }
+
+    /// Buffer that never grows beyond `max` bytes.
+    pub fn with_max(reader: Read, max: usize) -> Self {
         Self {
-            buffer: vec![0; INCREMENT],
+            buffer: vec![0; INITIAL.min(max)],
             pos: 0,
+            max,
             reader: Some(reader),
         }
     }
@@ -23,8 +34,20 @@ impl<Read: io::Read> FillMoreBuffer<Read> {
             return Ok(self.buffer());
         };
 
-        if self.pos > self.buffer.len() - THRESHOLD {
-            self.buffer.resize(self.buffer.len() + INCREMENT, 0);
+        let len = self.buffer.len();
+
+        if self.pos + THRESHOLD > len && len < self.max {
+            // Double in size, up to the max.
+            let size = (len * 2).min(self.max);
+            trace!("Grow buffer: {} -> {}", len, size);
+            self.buffer.resize(size, 0);
+        }
+
+        if self.pos == self.buffer.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "input exceeds max buffer size",
+            ));
         }
 
         let n = reader.read(&mut self.buffer[self.pos..])?;
@@ -44,7 +67,61 @@ impl<Read: io::Read> FillMoreBuffer<Read> {
         self.pos -= max;
     }
 
-    fn buffer(&self) -> &[u8] {
+    /// Shrink back to the initial size, if the buffered input fits.
+    ///
+    /// This is done between requests, for keep-alive connections to not hold on to
+    /// the peak size.
+    pub fn shrink(&mut self) {
+        let initial = INITIAL.min(self.max);
+
+        if self.buffer.len() > initial && self.pos <= initial {
+            trace!("Shrink buffer: {} -> {}", self.buffer.len(), initial);
+            self.buffer.truncate(initial);
+            self.buffer.shrink_to_fit();
+        }
+    }
+
+    /// The input buffered, and the reader, unless it has ended.
+    pub(crate) fn into_parts(mut self) -> (Vec<u8>, Option<Read>) {
+        self.buffer.truncate(self.pos);
+        (self.buffer, self.reader)
+    }
+
+    /// The input buffered, but not consumed.
+    pub(crate) fn buffer(&self) -> &[u8] {
         &self.buffer[..self.pos]
     }
+
+    /// The cap on the buffer size.
+    pub(crate) fn max(&self) -> usize {
+        self.max
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn grow_and_shrink() {
+        let input = vec![b'x'; 20_000];
+        let mut b = FillMoreBuffer::with_max(&input[..], 10_000);
+
+        let mut sizes = vec![];
+        let err = loop {
+            match b.fill_more() {
+                Ok(_) => sizes.push(b.buffer.len()),
+                Err(e) => break e,
+            }
+        };
+        sizes.dedup();
+
+        assert_eq!(sizes, [4096, 8192, 10_000]);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        b.consume(9_000);
+        b.shrink();
+        assert_eq!(b.buffer.len(), 4096);
+        assert_eq!(b.buffer(), &[b'x'; 1000][..]);
+    }
 }
@@ -1,6 +1,14 @@
 use std::convert::Infallible;
 
-use crate::response::IntoResponse;
+use serde::de::value::MapDeserializer;
+use serde::de::{self, DeserializeOwned, Deserializer, IntoDeserializer, Unexpected, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::charset::Charset;
+use crate::path::decode_bytes;
+use http::StatusCode;
+
+use crate::response::{empty, IntoResponse, PayloadTooLarge};
 use crate::{Request, Response};
 
 pub trait FromRequest<S>: Sized {
@@ -20,3 +28,518 @@ pub trait FromRequestRef<S>: Sized {
     type Rejection: Into<Response>;
     fn from_request(state: &S, request: &Request) -> Result<Self, Self::Rejection>;
 }
+
+/// Extractor for the query string of the request URI, `?a=1&b=2`.
+///
+/// The pairs are percent-decoded, with `+` as space, and deserialized into `T`, usually a
+/// struct with a field per parameter. A query that doesn't deserialize to `T` rejects the
+/// request with `400 Bad Request`.
+///
+/// ```
+/// use serde::Deserialize;
+/// use usrv::{MethodRouter, Query, Router};
+///
+/// #[derive(Deserialize)]
+/// struct Search {
+///     q: String,
+///     page: Option<u32>,
+/// }
+///
+/// fn search(Query(search): Query<Search>) -> String {
+///     format!("{} page {}", search.q, search.page.unwrap_or(1))
+/// }
+///
+/// let service = Router::new().get("/search", search).finish();
+/// ```
+pub struct Query<T>(pub T);
+
+/// 400, for a query string not deserializing to the type of a [`Query`].
+pub struct InvalidQuery;
+
+impl IntoResponse for InvalidQuery {
+    fn into_response(self) -> Response {
+        empty(StatusCode::BAD_REQUEST)
+    }
+}
+
+impl From<InvalidQuery> for Response {
+    fn from(value: InvalidQuery) -> Self {
+        value.into_response()
+    }
+}
+
+fn query<T: DeserializeOwned>(request: &Request) -> Result<Query<T>, InvalidQuery> {
+    let query = request.uri().query().unwrap_or("");
+
+    match T::deserialize(QueryDeserializer(query.as_bytes(), Charset::Utf8)) {
+        Ok(v) => Ok(Query(v)),
+        Err(e) => {
+            debug!("Query doesn't fit extractor: {}", e);
+            Err(InvalidQuery)
+        }
+    }
+}
+
+impl<S, T: DeserializeOwned> FromRequestRef<S> for Query<T> {
+    type Rejection = InvalidQuery;
+
+    fn from_request(_state: &S, request: &Request) -> Result<Self, Self::Rejection> {
+        query(request)
+    }
+}
+
+impl<S, T: DeserializeOwned> FromRequest<S> for Query<T> {
+    type Rejection = InvalidQuery;
+
+    fn from_request(_state: &S, request: Request) -> Result<Self, Self::Rejection> {
+        query(&request)
+    }
+}
+
+/// Deserializes `a=1&b=2` as a map, with the decoded bytes in the charset.
+struct QueryDeserializer<'a>(&'a [u8], Charset);
+
+impl<'a> QueryDeserializer<'a> {
+    fn pairs(&self) -> Result<Vec<(String, QueryValue)>, de::value::Error> {
+        let charset = self.1;
+        let decode = |s: &[u8]| {
+            let spaced: Vec<u8> = s
+                .iter()
+                .map(|b| if *b == b'+' { b' ' } else { *b })
+                .collect();
+            charset
+                .decode(decode_bytes(&spaced))
+                .ok_or_else(|| de::Error::custom("query is not valid in charset"))
+        };
+
+        self.0
+            .split(|b| *b == b'&')
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let (k, v) = match p.iter().position(|b| *b == b'=') {
+                    Some(i) => (&p[..i], &p[i + 1..]),
+                    None => (p, &[][..]),
+                };
+                Ok((decode(k)?, QueryValue(decode(v)?)))
+            })
+            .collect()
+    }
+}
+
+impl<'de, 'a> Deserializer<'de> for QueryDeserializer<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(MapDeserializer::new(self.pairs()?.into_iter()))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// A decoded value, parsed to whatever type is asked for.
+pub(crate) struct QueryValue(pub(crate) String);
+
+impl<'de> IntoDeserializer<'de> for QueryValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident),*) => {
+        $(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            match self.0.parse() {
+                Ok(v) => visitor.$visit(v),
+                Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&self.0), &visitor)),
+            }
+        }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for QueryValue {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    deserialize_parsed!(
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char
+    );
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct newtype_struct seq
+        tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Extractor for bodies of `application/x-www-form-urlencoded`, as posted by HTML forms.
+///
+/// The request must have the form content type, and a body of at most `LIMIT` bytes,
+/// 2MB by default. The body is decoded like a [`Query`], in the charset of the content type
+/// (UTF-8 without one), and deserialized into `T`.
+///
+/// ```
+/// use serde::Deserialize;
+/// use usrv::{Form, MethodRouter, Router};
+///
+/// #[derive(Deserialize)]
+/// struct Login {
+///     user: String,
+///     password: String,
+/// }
+///
+/// // At most 4kB of body.
+/// fn login(Form(login): Form<Login, 4096>) -> String {
+///     format!("welcome {}", login.user)
+/// }
+///
+/// let service = Router::new().post("/login", login).finish();
+/// ```
+pub struct Form<T, const LIMIT: u64 = { 2 * 1024 * 1024 }>(pub T);
+
+/// Reasons to reject a request for a [`Form`] extractor.
+#[derive(Debug)]
+pub enum FormRejection {
+    /// 415, for a request without `Content-Type: application/x-www-form-urlencoded`, or with
+    /// a charset that isn't supported.
+    ContentType,
+    /// 413, for a body over the limit.
+    TooLarge,
+    /// 400, for a body that failed to read, or deserialize.
+    Invalid,
+}
+
+impl IntoResponse for FormRejection {
+    fn into_response(self) -> Response {
+        let status = match self {
+            FormRejection::ContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            FormRejection::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            FormRejection::Invalid => StatusCode::BAD_REQUEST,
+        };
+
+        empty(status)
+    }
+}
+
+impl<S, T: DeserializeOwned, const LIMIT: u64> FromRequest<S> for Form<T, LIMIT> {
+    type Rejection = FormRejection;
+
+    fn from_request(_state: &S, request: Request) -> Result<Self, Self::Rejection> {
+        if !has_mime(&request, "application/x-www-form-urlencoded") {
+            return Err(FormRejection::ContentType);
+        }
+
+        let Some(charset) = Charset::of(&request) else {
+            return Err(FormRejection::ContentType);
+        };
+
+        let bytes = match request.into_body().into_bytes_max(LIMIT) {
+            Ok(Some(v)) => v,
+            Ok(None) => return Err(FormRejection::TooLarge),
+            Err(e) if PayloadTooLarge::is(&e) => return Err(FormRejection::TooLarge),
+            Err(e) => {
+                debug!("Failed to read form body: {}", e);
+                return Err(FormRejection::Invalid);
+            }
+        };
+
+        match T::deserialize(QueryDeserializer(&bytes, charset)) {
+            Ok(v) => Ok(Form(v)),
+            Err(e) => {
+                debug!("Form body doesn't fit extractor: {}", e);
+                Err(FormRejection::Invalid)
+            }
+        }
+    }
+}
+
+/// Whether the request content type is `mime`, regardless of parameters.
+fn has_mime(request: &Request, mime: &str) -> bool {
+    let Some(ctype) = request.headers().get("content-type") else {
+        return false;
+    };
+    let Ok(ctype) = ctype.to_str() else {
+        return false;
+    };
+
+    // Parameters, such as charset, don't matter.
+    let found = ctype.split(';').next().unwrap_or("").trim();
+    found.eq_ignore_ascii_case(mime)
+}
+
+/// Extractor and response for JSON bodies.
+///
+/// As an extractor, the request must have `Content-Type: application/json`, and a body of
+/// at most 2MB, that deserializes into `T`. The body is decoded in the charset of the
+/// content type, UTF-8 without one. As a response, `T` is serialized and sent with
+/// `Content-Type: application/json`.
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use usrv::{Json, MethodRouter, Router};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// fn create(Json(user): Json<User>) -> Json<User> {
+///     Json(user)
+/// }
+///
+/// let service = Router::new().post("/users", create).finish();
+/// ```
+#[cfg(feature = "json")]
+pub struct Json<T>(pub T);
+
+/// Max size of a [`Json`] request body.
+#[cfg(feature = "json")]
+const JSON_LIMIT: u64 = 2 * 1024 * 1024;
+
+/// Reasons to reject a request for a [`Json`] extractor.
+#[cfg(feature = "json")]
+#[derive(Debug)]
+pub enum JsonRejection {
+    /// 415, for a request without `Content-Type: application/json`, or with a charset that
+    /// isn't supported.
+    ContentType,
+    /// 413, for a body over the limit.
+    TooLarge,
+    /// 400, for a body that failed to read, or deserialize.
+    Invalid,
+}
+
+#[cfg(feature = "json")]
+impl IntoResponse for JsonRejection {
+    fn into_response(self) -> Response {
+        let status = match self {
+            JsonRejection::ContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            JsonRejection::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            JsonRejection::Invalid => StatusCode::BAD_REQUEST,
+        };
+
+        empty(status)
+    }
+}
+
+#[cfg(feature = "json")]
+impl<S, T: DeserializeOwned> FromRequest<S> for Json<T> {
+    type Rejection = JsonRejection;
+
+    fn from_request(_state: &S, request: Request) -> Result<Self, Self::Rejection> {
+        if !has_mime(&request, "application/json") {
+            return Err(JsonRejection::ContentType);
+        }
+
+        let Some(charset) = Charset::of(&request) else {
+            return Err(JsonRejection::ContentType);
+        };
+
+        let bytes = match request.into_body().into_bytes_max(JSON_LIMIT) {
+            Ok(Some(v)) => v,
+            Ok(None) => return Err(JsonRejection::TooLarge),
+            Err(e) if PayloadTooLarge::is(&e) => return Err(JsonRejection::TooLarge),
+            Err(e) => {
+                debug!("Failed to read JSON body: {}", e);
+                return Err(JsonRejection::Invalid);
+            }
+        };
+
+        let Some(text) = charset.decode(bytes) else {
+            debug!("JSON body is not valid in {:?}", charset);
+            return Err(JsonRejection::Invalid);
+        };
+
+        match serde_json::from_str(&text) {
+            Ok(v) => Ok(Json(v)),
+            Err(e) => {
+                debug!("JSON body doesn't fit extractor: {}", e);
+                Err(JsonRejection::Invalid)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Order {
+        Asc,
+        Desc,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Search {
+        q: String,
+        page: Option<u32>,
+        exact: bool,
+        order: Order,
+    }
+
+    fn query<T: DeserializeOwned>(uri: &str) -> Result<T, InvalidQuery> {
+        let request = http::Request::get(uri).body(().into()).unwrap();
+        <Query<T> as FromRequestRef<()>>::from_request(&(), &request).map(|q| q.0)
+    }
+
+    #[test]
+    fn query_struct() {
+        let search: Search = query("/s?q=a+b%26c&exact=true&order=desc").ok().unwrap();
+        assert_eq!(
+            search,
+            Search {
+                q: "a b&c".into(),
+                page: None,
+                exact: true,
+                order: Order::Desc,
+            }
+        );
+
+        let search: Search = query("/s?order=asc&page=2&exact=false&q=").ok().unwrap();
+        assert_eq!(search.page, Some(2));
+        assert_eq!(search.q, "");
+
+        assert!(query::<Search>("/s?q=a&exact=yes&order=asc").is_err());
+        assert!(query::<Search>("/s?q=a&exact=true&order=up").is_err());
+        assert!(query::<Search>("/s?q=a&page=2").is_err());
+        assert!(query::<Search>("/s").is_err());
+
+        let response = query::<Search>("/s").err().unwrap().into_response();
+        assert_eq!(response.status(), 400);
+    }
+
+    #[test]
+    fn form_request() {
+        #[derive(Debug, Deserialize)]
+        struct Login {
+            user: String,
+            remember: bool,
+        }
+
+        fn form<const LIMIT: u64>(ctype: &str, body: &str) -> Result<Login, FormRejection> {
+            let request = http::Request::post("/")
+                .header("content-type", ctype)
+                .body(body.to_string().into())
+                .unwrap();
+            <Form<Login, LIMIT> as FromRequest<()>>::from_request(&(), request).map(|f| f.0)
+        }
+
+        let ctype = "application/x-www-form-urlencoded";
+
+        let login = form::<100>(ctype, "user=martin+a&remember=true").unwrap();
+        assert_eq!(login.user, "martin a");
+        assert!(login.remember);
+
+        let e = form::<100>("text/plain", "user=martin&remember=true");
+        assert_eq!(e.err().unwrap().into_response().status(), 415);
+
+        let e = form::<100>(ctype, "user=martin&remember=maybe");
+        assert_eq!(e.err().unwrap().into_response().status(), 400);
+
+        let e = form::<10>(ctype, "user=martin&remember=true");
+        assert_eq!(e.err().unwrap().into_response().status(), 413);
+
+        let latin1 = "application/x-www-form-urlencoded; charset=iso-8859-1";
+        let login = form::<100>(latin1, "user=m%E5rtin&remember=false").unwrap();
+        assert_eq!(login.user, "mårtin");
+
+        let e = form::<100>(ctype, "user=m%E5rtin&remember=false");
+        assert_eq!(e.err().unwrap().into_response().status(), 400);
+
+        let sjis = "application/x-www-form-urlencoded; charset=shift_jis";
+        let e = form::<100>(sjis, "user=martin&remember=true");
+        assert_eq!(e.err().unwrap().into_response().status(), 415);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_request() {
+        #[derive(Debug, Deserialize)]
+        struct User {
+            name: String,
+        }
+
+        let json = |ctype: &str, body: Vec<u8>| {
+            let request = http::Request::post("/")
+                .header("content-type", ctype)
+                .body(body.into())
+                .unwrap();
+            <Json<User> as FromRequest<()>>::from_request(&(), request)
+        };
+
+        let body = br#"{"name":"martin"}"#.to_vec();
+        let user = json("application/json; charset=utf-8", body.clone());
+        assert_eq!(user.unwrap().0.name, "martin");
+
+        let e = json("text/plain", body).err().unwrap();
+        assert_eq!(e.into_response().status(), 415);
+
+        let e = json("application/json", br#"{"nom":1}"#.to_vec())
+            .err()
+            .unwrap();
+        assert_eq!(e.into_response().status(), 400);
+
+        let big = vec![b' '; JSON_LIMIT as usize + 1];
+        let e = json("application/json", big).err().unwrap();
+        assert_eq!(e.into_response().status(), 413);
+
+        let response = Json(vec!["a", "b"]).into_response();
+        assert_eq!(response.headers()["content-type"], "application/json");
+        assert_eq!(
+            response.into_body().into_string(100).unwrap(),
+            r#"["a","b"]"#
+        );
+    }
+
+    #[test]
+    fn query_map() {
+        use std::collections::HashMap;
+
+        let map: HashMap<String, String> = query("/s?a=1&b&&c=%C3%A5").ok().unwrap();
+        assert_eq!(map["a"], "1");
+        assert_eq!(map["b"], "");
+        assert_eq!(map["c"], "å");
+
+        let map: HashMap<String, u32> = query("/s").ok().unwrap();
+        assert!(map.is_empty());
+    }
+}
@@ -6,13 +6,19 @@ use crate::{Request, Response};
 
 pub struct U<T>(PhantomData<T>);
 
-// SAFETY: Without U<T>, the compiler thinks a concrete Handler<((), Request<Body>), AppState>
-// to be !Send, since Body is (deliberately) !Send. This is the compiler being overly cautious,
-// since we do not send Request<Body> across threads.
+// SAFETY: U<T> holds no T, it only tells the handler impls apart. Without this, a handler
+// taking an extractor that is !Send would be !Send, even though no T is sent anywhere.
 unsafe impl<T> Send for U<T> {}
 
 pub trait Handler<T, S>: Clone + Send + Sized + 'static {
     fn call(self, state: S, request: Request) -> Response;
+
+    /// Limit on the request body, instead of the one of the service. See [`BodyLimit`].
+    ///
+    /// [`BodyLimit`]: crate::BodyLimit
+    fn body_limit(&self) -> Option<u64> {
+        None
+    }
 }
 
 impl<S, F, Ret> Handler<(), S> for F
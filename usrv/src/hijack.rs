@@ -0,0 +1,154 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use crate::from_req::{FromRequest, FromRequestRef};
+use http::StatusCode;
+
+use crate::response::{empty, IntoResponse};
+use crate::server::Breaker;
+use crate::{Request, Response};
+
+type Callback = Box<dyn FnOnce(Connection<'_>) + Send + 'static>;
+
+/// Extractor to take over the connection after the response.
+///
+/// This is for custom protocols, such as after `101 Switching Protocols`, or tunnels
+/// for `CONNECT`. Only the status and headers of the response are sent. After that,
+/// the server stops handling the connection, and hands it to the function given to
+/// [`take_over()`][Self::take_over].
+///
+/// ```
+/// use std::io::{Read, Write};
+/// use usrv::{Hijack, Response};
+///
+/// fn upgrade(hijack: Hijack) -> Response {
+///     hijack.take_over(|mut conn| {
+///         // Echo whatever the client sends.
+///         let _ = conn.writer.write_all(&conn.buffered);
+///         let _ = std::io::copy(&mut conn.reader, conn.writer);
+///     });
+///
+///     http::Response::builder()
+///         .status(101)
+///         .header("upgrade", "echo")
+///         .header("connection", "upgrade")
+///         .body(().into())
+///         .unwrap()
+/// }
+/// ```
+///
+/// Handlers with this extractor can only be served over a connection. Otherwise, the
+/// extractor rejects the request with `500 Internal Server Error`.
+pub struct Hijack {
+    slot: HijackSlot,
+}
+
+/// A connection taken over from the server.
+pub struct Connection<'a> {
+    /// Input after the request, already read from the connection.
+    pub buffered: Vec<u8>,
+    /// The rest of the input.
+    pub reader: Box<dyn io::Read + Send + 'static>,
+    pub writer: &'a mut dyn io::Write,
+    pub(crate) breaker: &'a dyn Breaker,
+}
+
+impl Connection<'_> {
+    /// Stop reading from the client, for a read of [`reader`][Self::reader] blocked on
+    /// another thread to return end of file. An error where the transport can't.
+    pub fn shutdown_read(&self) -> io::Result<()> {
+        self.breaker.shutdown_read()
+    }
+}
+
+impl Hijack {
+    /// Take over the connection, once the response status and headers are sent.
+    pub fn take_over(self, f: impl FnOnce(Connection<'_>) + Send + 'static) {
+        *self.slot.lock() = Some(Box::new(f));
+    }
+}
+
+/// Where the server finds the function taking over the connection.
+#[derive(Clone, Default)]
+pub(crate) struct HijackSlot(Arc<Mutex<Option<Callback>>>);
+
+impl HijackSlot {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Callback>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn take(&self) -> Option<Callback> {
+        self.lock().take()
+    }
+}
+
+/// 500, for a [`Hijack`] outside of a connection.
+pub struct NotConnected;
+
+impl IntoResponse for NotConnected {
+    fn into_response(self) -> Response {
+        empty(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+impl From<NotConnected> for Response {
+    fn from(value: NotConnected) -> Self {
+        value.into_response()
+    }
+}
+
+pub(crate) fn hijack(request: &Request) -> Result<Hijack, NotConnected> {
+    match request.extensions().get::<HijackSlot>() {
+        Some(slot) => Ok(Hijack { slot: slot.clone() }),
+        None => {
+            debug!("Hijack outside of a connection");
+            Err(NotConnected)
+        }
+    }
+}
+
+impl<S> FromRequestRef<S> for Hijack {
+    type Rejection = NotConnected;
+
+    fn from_request(_state: &S, request: &Request) -> Result<Self, Self::Rejection> {
+        hijack(request)
+    }
+}
+
+impl<S> FromRequest<S> for Hijack {
+    type Rejection = NotConnected;
+
+    fn from_request(_state: &S, request: Request) -> Result<Self, Self::Rejection> {
+        hijack(&request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Body;
+
+    #[test]
+    fn hijack_slot() {
+        let mut request = http::Request::get("/").body(Body::empty()).unwrap();
+        let Err(rejection) = hijack(&request) else {
+            panic!("hijack outside of a connection");
+        };
+        assert_eq!(
+            rejection.into_response().status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        let slot = HijackSlot::default();
+        request.extensions_mut().insert(slot.clone());
+        let Ok(hijack) = hijack(&request) else {
+            panic!("no hijack with a slot");
+        };
+
+        // Nothing to take until a function is given.
+        assert!(slot.take().is_none());
+        hijack.take_over(|_| {});
+        assert!(slot.take().is_some());
+        assert!(slot.take().is_none());
+    }
+}
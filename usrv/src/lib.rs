@@ -3,6 +3,9 @@ extern crate log;
 
 pub use http;
 
+mod config;
+pub use config::ServerConfig;
+
 mod error;
 pub use error::Error;
 
@@ -12,24 +15,91 @@ pub use body::Body;
 mod handler;
 pub use handler::Handler;
 
+mod builder;
+pub use builder::ResponseBuilder;
+
+#[cfg(feature = "compression")]
+mod compress;
+#[cfg(feature = "compression")]
+pub use compress::Compression;
+
+mod cookie;
+#[cfg(feature = "signed-cookies")]
+pub use cookie::Key;
+pub use cookie::{Cookie, CookieJar, Cookies, SameSite};
+
+mod deadline;
+pub use deadline::Deadline;
+
+mod files;
+pub use files::{ServeDir, ServeFile};
+
 mod from_req;
-pub use from_req::{FromRequest, FromRequestRef};
+pub use from_req::{Form, FormRejection, FromRequest, FromRequestRef, InvalidQuery, Query};
+#[cfg(feature = "json")]
+pub use from_req::{Json, JsonRejection};
+
+mod hijack;
+pub use hijack::{Connection, Hijack, NotConnected};
+
+mod limit;
+pub use limit::BodyLimit;
+
+mod multipart;
+pub use multipart::{Multipart, MultipartRejection, Part};
+
+#[cfg(feature = "openapi")]
+mod openapi;
+#[cfg(feature = "openapi")]
+pub use openapi::{OpenApi, RouteDoc};
+
+mod path;
+pub use path::{InvalidPath, Path};
+
+mod proxy;
+pub use proxy::{ConnectProxy, ProxyAuthRequired};
 
 mod response;
-pub use response::{IntoResponse, NotFound};
+pub use response::{
+    BadRequest, ExpectationFailed, IntoResponse, MethodNotAllowed, NotFound, NotImplemented,
+    PayloadTooLarge, ServiceUnavailable,
+};
 
 mod router;
-pub use router::{Router, Service};
+pub use router::{connect, delete, get, head, options, patch, post, put, trace};
+pub use router::{MethodRouter, Methods, Route, Router, Service};
+
+mod sse;
+pub use sse::{Event, Sse};
+
+mod transport;
+pub use transport::{ClientCert, NoClientCert, TransportInfo};
+
+mod well_known;
+pub use well_known::WellKnown;
 
 pub type Request = http::Request<Body>;
 pub type Response = http::Response<Body>;
 
+mod charset;
+
+mod expect;
+
 mod fill_more;
 
+mod pool;
+
+mod shed;
+
+mod shutdown;
+pub use shutdown::ShutdownHandle;
+
 mod read_req;
 pub use read_req::read_request;
 
 mod write_res;
 pub use write_res::write_response;
 
+mod wire;
+
 pub mod server;
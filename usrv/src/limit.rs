@@ -0,0 +1,41 @@
+use crate::handler::Handler;
+use crate::{Request, Response};
+
+/// Handler with a limit on the request body of its own.
+///
+/// Replaces the limit of the service, see [`ServerConfig::body_limit()`], for routes taking
+/// larger bodies, such as uploads, or only small ones.
+///
+/// ```
+/// use usrv::{BodyLimit, MethodRouter, Multipart, Router};
+///
+/// fn upload(_parts: Multipart) {}
+///
+/// let service = Router::new()
+///     .post("/upload", BodyLimit::new(100 * 1024 * 1024, upload))
+///     .finish()
+///     .body_limit(64 * 1024);
+/// ```
+///
+/// [`ServerConfig::body_limit()`]: crate::ServerConfig::body_limit
+#[derive(Debug, Clone)]
+pub struct BodyLimit<H> {
+    max: u64,
+    handler: H,
+}
+
+impl<H> BodyLimit<H> {
+    pub fn new(max: u64, handler: H) -> Self {
+        BodyLimit { max, handler }
+    }
+}
+
+impl<T, S, H: Handler<T, S>> Handler<BodyLimit<T>, S> for BodyLimit<H> {
+    fn call(self, state: S, request: Request) -> Response {
+        self.handler.call(state, request)
+    }
+
+    fn body_limit(&self) -> Option<u64> {
+        Some(self.max)
+    }
+}
@@ -0,0 +1,380 @@
+use std::io::{self, Read};
+
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+
+use crate::from_req::FromRequest;
+use crate::response::{empty, IntoResponse};
+use crate::{Body, Request, Response};
+
+/// Max size of the headers of one part.
+const MAX_PART_HEAD: usize = 16 * 1024;
+
+/// Size of each read from the body.
+const READ_SIZE: usize = 8 * 1024;
+
+/// Extractor for `multipart/form-data` bodies, as posted by HTML forms with file uploads.
+///
+/// The body is parsed as it is read, one part at a time, without buffering it whole. Each
+/// [`Part`] has headers of its own, and a body read with [`Read`]. Moving on to the next
+/// part skips what is left of the current one.
+///
+/// ```
+/// use std::io::Read;
+/// use usrv::{MethodRouter, Multipart, Router};
+///
+/// fn upload(mut multipart: Multipart) -> String {
+///     let mut sizes = vec![];
+///
+///     while let Ok(Some(mut part)) = multipart.next_part() {
+///         let name = part.name().unwrap_or("").to_string();
+///         let size = std::io::copy(&mut part, &mut std::io::sink()).unwrap_or(0);
+///         sizes.push(format!("{}: {}", name, size));
+///     }
+///
+///     sizes.join("\n")
+/// }
+///
+/// let service = Router::new().post("/upload", upload).finish();
+/// ```
+///
+/// Requests without `Content-Type: multipart/form-data` are rejected with
+/// `415 Unsupported Media Type`, and those without a boundary with `400 Bad Request`.
+pub struct Multipart {
+    body: Body,
+    /// The delimiter before each part, `\r\n--` and the boundary.
+    delimiter: Vec<u8>,
+    /// Input read from the body, not yet consumed.
+    buf: Vec<u8>,
+    state: State,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Before the first delimiter, in the preamble.
+    Preamble,
+    /// Right after a delimiter, before the part headers, or the final `--`.
+    Delimiter,
+    /// In the body of a part.
+    Part,
+    /// After the final delimiter.
+    Done,
+}
+
+/// One part of a [`Multipart`] body.
+pub struct Part<'a> {
+    headers: HeaderMap,
+    multipart: &'a mut Multipart,
+}
+
+impl Multipart {
+    fn new(body: Body, boundary: &str) -> Self {
+        Multipart {
+            body,
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            // The first delimiter has no line break before it.
+            buf: b"\r\n".to_vec(),
+            state: State::Preamble,
+        }
+    }
+
+    /// The next part of the body, `None` after the last.
+    pub fn next_part(&mut self) -> io::Result<Option<Part<'_>>> {
+        loop {
+            match self.state {
+                State::Preamble | State::Part => {
+                    // Skip to the next delimiter.
+                    let mut skip = [0; READ_SIZE];
+                    while self.read_part(&mut skip)? > 0 {}
+                }
+                State::Delimiter => break,
+                State::Done => return Ok(None),
+            }
+        }
+
+        while self.buf.len() < 2 {
+            if !self.fill()? {
+                return Err(eof());
+            }
+        }
+
+        if self.buf.starts_with(b"--") {
+            self.state = State::Done;
+            return Ok(None);
+        }
+
+        let headers = self.read_headers()?;
+        self.state = State::Part;
+
+        Ok(Some(Part {
+            headers,
+            multipart: self,
+        }))
+    }
+
+    /// Read more of the body into the buffer. `false` at the end of it.
+    fn fill(&mut self) -> io::Result<bool> {
+        let len = self.buf.len();
+        self.buf.resize(len + READ_SIZE, 0);
+        match self.body.read(&mut self.buf[len..]) {
+            Ok(n) => {
+                self.buf.truncate(len + n);
+                Ok(n > 0)
+            }
+            Err(e) => {
+                self.buf.truncate(len);
+                Err(e)
+            }
+        }
+    }
+
+    /// Read the part headers, up to and including the empty line ending them.
+    fn read_headers(&mut self) -> io::Result<HeaderMap> {
+        let end = loop {
+            if let Some(i) = find(&self.buf, b"\r\n\r\n") {
+                break i;
+            }
+            if self.buf.len() > MAX_PART_HEAD {
+                return Err(invalid("multipart headers too large"));
+            }
+            if !self.fill()? {
+                return Err(eof());
+            }
+        };
+
+        let head = String::from_utf8(self.buf[..end].to_vec())
+            .map_err(|_| invalid("multipart headers not utf-8"))?;
+        self.buf.drain(..end + 4);
+
+        let mut headers = HeaderMap::new();
+
+        // The first line is the end of the delimiter line, possibly with trailing space.
+        for line in head.split("\r\n").skip(1) {
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| invalid("multipart header without colon"))?;
+            let name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| invalid("bad multipart header name"))?;
+            let value = HeaderValue::from_str(value.trim())
+                .map_err(|_| invalid("bad multipart header value"))?;
+            headers.append(name, value);
+        }
+
+        Ok(headers)
+    }
+
+    /// Read the body of the current part, 0 once at the delimiter after it.
+    fn read_part(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if !matches!(self.state, State::Preamble | State::Part) || out.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if let Some(i) = find(&self.buf, &self.delimiter) {
+                if i == 0 {
+                    self.buf.drain(..self.delimiter.len());
+                    self.state = State::Delimiter;
+                    return Ok(0);
+                }
+                return Ok(self.consume(i, out));
+            }
+
+            // The end of the buffer could be the start of a delimiter.
+            let safe = self.buf.len().saturating_sub(self.delimiter.len() - 1);
+            if safe > 0 {
+                return Ok(self.consume(safe, out));
+            }
+
+            if !self.fill()? {
+                return Err(eof());
+            }
+        }
+    }
+
+    fn consume(&mut self, available: usize, out: &mut [u8]) -> usize {
+        let n = available.min(out.len());
+        out[..n].copy_from_slice(&self.buf[..n]);
+        self.buf.drain(..n);
+        n
+    }
+}
+
+impl<'a> Part<'a> {
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The field name, from `Content-Disposition`.
+    pub fn name(&self) -> Option<&str> {
+        self.disposition_param("name")
+    }
+
+    /// The name of an uploaded file, from `Content-Disposition`.
+    pub fn file_name(&self) -> Option<&str> {
+        self.disposition_param("filename")
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get("content-type")?.to_str().ok()
+    }
+
+    fn disposition_param(&self, name: &str) -> Option<&str> {
+        let disposition = self.headers.get("content-disposition")?.to_str().ok()?;
+        param(disposition, name)
+    }
+}
+
+impl<'a> Read for Part<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.multipart.read_part(buf)
+    }
+}
+
+/// A parameter of a header value like `form-data; name="a"`.
+fn param<'a>(value: &'a str, name: &str) -> Option<&'a str> {
+    value.split(';').skip(1).find_map(|p| {
+        let (n, v) = p.split_once('=')?;
+        n.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| v.trim().trim_matches('"'))
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "multipart body ended early")
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Reasons to reject a request for a [`Multipart`] extractor.
+#[derive(Debug)]
+pub enum MultipartRejection {
+    /// 415, for a request without `Content-Type: multipart/form-data`.
+    ContentType,
+    /// 400, for a content type without a boundary.
+    NoBoundary,
+}
+
+impl IntoResponse for MultipartRejection {
+    fn into_response(self) -> Response {
+        let status = match self {
+            MultipartRejection::ContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            MultipartRejection::NoBoundary => StatusCode::BAD_REQUEST,
+        };
+
+        empty(status)
+    }
+}
+
+impl<S> FromRequest<S> for Multipart {
+    type Rejection = MultipartRejection;
+
+    fn from_request(_state: &S, request: Request) -> Result<Self, Self::Rejection> {
+        let ctype = request
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
+
+        let mime = ctype.split(';').next().unwrap_or("").trim();
+        if !mime.eq_ignore_ascii_case("multipart/form-data") {
+            return Err(MultipartRejection::ContentType);
+        }
+
+        let boundary = match param(ctype, "boundary") {
+            Some(v) if !v.is_empty() => v.to_string(),
+            _ => {
+                debug!("Multipart without boundary: {}", ctype);
+                return Err(MultipartRejection::NoBoundary);
+            }
+        };
+
+        Ok(Multipart::new(request.into_body(), &boundary))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Reads one byte at a time, to split delimiters across reads.
+    struct Trickle(io::Cursor<Vec<u8>>);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(1);
+            self.0.read(&mut buf[..n])
+        }
+    }
+
+    fn parse(ctype: &str, body: &str) -> Result<Multipart, MultipartRejection> {
+        let body = Body::streaming(Trickle(io::Cursor::new(body.as_bytes().to_vec())));
+        let request = http::Request::post("/")
+            .header("content-type", ctype)
+            .body(body)
+            .unwrap();
+        <Multipart as FromRequest<()>>::from_request(&(), request)
+    }
+
+    #[test]
+    fn multipart_parts() {
+        let body = "preamble\r\n\
+            --xyz\r\n\
+            Content-Disposition: form-data; name=\"title\"\r\n\
+            \r\n\
+            hello\r\n\
+            --xyz\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+            Content-Type: text/plain\r\n\
+            \r\n\
+            line\r\n--xy\r\nz\r\n\
+            --xyz\r\n\
+            Content-Disposition: form-data; name=\"skipped\"\r\n\
+            \r\n\
+            not read\r\n\
+            --xyz--\r\n";
+
+        let mut multipart = parse("multipart/form-data; boundary=xyz", body)
+            .ok()
+            .unwrap();
+
+        let mut part = multipart.next_part().unwrap().unwrap();
+        assert_eq!(part.name(), Some("title"));
+        assert_eq!(part.file_name(), None);
+        let mut s = String::new();
+        part.read_to_string(&mut s).unwrap();
+        assert_eq!(s, "hello");
+
+        let mut part = multipart.next_part().unwrap().unwrap();
+        assert_eq!(part.name(), Some("file"));
+        assert_eq!(part.file_name(), Some("a.txt"));
+        assert_eq!(part.content_type(), Some("text/plain"));
+        let mut s = String::new();
+        part.read_to_string(&mut s).unwrap();
+        assert_eq!(s, "line\r\n--xy\r\nz");
+
+        let part = multipart.next_part().unwrap().unwrap();
+        assert_eq!(part.name(), Some("skipped"));
+
+        assert!(multipart.next_part().unwrap().is_none());
+        assert!(multipart.next_part().unwrap().is_none());
+
+        let e = parse("text/plain", body).err().unwrap();
+        assert_eq!(e.into_response().status(), 415);
+
+        let e = parse("multipart/form-data", body).err().unwrap();
+        assert_eq!(e.into_response().status(), 400);
+
+        let mut truncated = parse("multipart/form-data; boundary=xyz", &body[..70])
+            .ok()
+            .unwrap();
+        let mut part = truncated.next_part().unwrap().unwrap();
+        let e = part.read_to_string(&mut String::new()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,678 @@
+use http::Method;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess};
+use serde::de::{IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_json::{json, Map, Value};
+
+/// Nesting of options, sequences and maps traced, which bounds recursive types.
+const MAX_DEPTH: usize = 16;
+
+/// Description of an API, served as an OpenAPI document.
+///
+/// Every route of the service is listed, with what is described for it here. Served with
+/// [`Service::openapi()`][crate::Service::openapi].
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use usrv::http::Method;
+/// use usrv::{Json, MethodRouter, OpenApi, Path, RouteDoc, Router};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct User {
+///     name: String,
+///     age: Option<u32>,
+/// }
+///
+/// fn user(Path(id): Path<u64>) -> Json<User> {
+///     Json(User { name: format!("user {}", id), age: None })
+/// }
+///
+/// let api = OpenApi::new("Users", "1.0").route(
+///     Method::GET,
+///     "/users/:id",
+///     RouteDoc::new().summary("Get a user").response::<User>(),
+/// );
+///
+/// let service = Router::new()
+///     .get("/users/:id", user)
+///     .finish()
+///     .openapi("/openapi.json", api);
+/// ```
+pub struct OpenApi {
+    title: String,
+    version: String,
+    docs: Vec<(Method, String, RouteDoc)>,
+}
+
+/// Description of one route, for an [`OpenApi`].
+///
+/// The schemas of request and response bodies are traced from the `Deserialize` of the
+/// type, which response types usually derive alongside `Serialize`. The schemas are
+/// approximate: types deserializing through `deserialize_any`, such as untagged enums,
+/// are left open, and enums are described by their first variant.
+#[derive(Debug, Clone, Default)]
+pub struct RouteDoc {
+    summary: Option<String>,
+    description: Option<String>,
+    request: Option<Value>,
+    response: Option<Value>,
+}
+
+impl OpenApi {
+    pub fn new(title: impl Into<String>, version: impl Into<String>) -> Self {
+        OpenApi {
+            title: title.into(),
+            version: version.into(),
+            docs: vec![],
+        }
+    }
+
+    /// Describe the route for `method` with the same `path` pattern as it is declared with.
+    pub fn route(mut self, method: Method, path: &str, doc: RouteDoc) -> Self {
+        self.docs.push((method, path.to_string(), doc));
+        self
+    }
+
+    /// The OpenAPI document for the routes of a service.
+    pub(crate) fn render(&self, routes: &[(Method, String)]) -> Value {
+        let mut paths = Map::new();
+
+        for (method, pattern) in routes {
+            // OpenAPI has no CONNECT, and patterns are always absolute.
+            if *method == Method::CONNECT || !pattern.starts_with('/') {
+                continue;
+            }
+
+            let (path, params) = openapi_path(pattern);
+
+            let doc = self
+                .docs
+                .iter()
+                .find(|(m, p, _)| m == method && p == pattern)
+                .map(|(_, _, d)| d);
+
+            let item = paths.entry(path).or_insert_with(|| json!({}));
+            item[method.as_str().to_ascii_lowercase()] = operation(doc, &params);
+        }
+
+        json!({
+            "openapi": "3.0.3",
+            "info": { "title": self.title, "version": self.version },
+            "paths": paths,
+        })
+    }
+}
+
+impl RouteDoc {
+    pub fn new() -> Self {
+        RouteDoc::default()
+    }
+
+    pub fn summary(mut self, summary: impl Into<String>) -> Self {
+        self.summary = Some(summary.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// The request takes a JSON body of `T`.
+    pub fn request<T: DeserializeOwned>(mut self) -> Self {
+        self.request = Some(schema::<T>());
+        self
+    }
+
+    /// The response is a JSON body of `T`.
+    pub fn response<T: DeserializeOwned>(mut self) -> Self {
+        self.response = Some(schema::<T>());
+        self
+    }
+}
+
+/// Convert a route pattern to an OpenAPI path, with the names of the captured segments.
+fn openapi_path(pattern: &str) -> (String, Vec<String>) {
+    let mut params = vec![];
+
+    let segments: Vec<_> = pattern
+        .split('/')
+        .map(
+            |s| match s.strip_prefix(':').or_else(|| s.strip_prefix('*')) {
+                Some(name) => {
+                    params.push(name.to_string());
+                    format!("{{{}}}", name)
+                }
+                None => s.to_string(),
+            },
+        )
+        .collect();
+
+    (segments.join("/"), params)
+}
+
+fn operation(doc: Option<&RouteDoc>, params: &[String]) -> Value {
+    let mut op = Map::new();
+
+    if let Some(summary) = doc.and_then(|d| d.summary.as_ref()) {
+        op.insert("summary".into(), json!(summary));
+    }
+    if let Some(description) = doc.and_then(|d| d.description.as_ref()) {
+        op.insert("description".into(), json!(description));
+    }
+
+    if !params.is_empty() {
+        let params: Vec<_> = params
+            .iter()
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                })
+            })
+            .collect();
+        op.insert("parameters".into(), json!(params));
+    }
+
+    if let Some(schema) = doc.and_then(|d| d.request.as_ref()) {
+        op.insert(
+            "requestBody".into(),
+            json!({
+                "required": true,
+                "content": { "application/json": { "schema": schema } },
+            }),
+        );
+    }
+
+    let mut ok = json!({ "description": "OK" });
+    if let Some(schema) = doc.and_then(|d| d.response.as_ref()) {
+        ok["content"] = json!({ "application/json": { "schema": schema } });
+    }
+    op.insert("responses".into(), json!({ "200": ok }));
+
+    Value::Object(op)
+}
+
+/// JSON schema of `T`, traced from its `Deserialize`.
+pub(crate) fn schema<T: DeserializeOwned>() -> Value {
+    let mut schema = json!({});
+
+    // Types may reject the placeholder values given while tracing. The schema traced up
+    // to that point is still the best there is.
+    if let Err(e) = T::deserialize(Tracer {
+        out: &mut schema,
+        depth: 0,
+    }) {
+        debug!("Schema tracing stopped early: {}", e);
+    }
+
+    schema
+}
+
+/// Deserializer recording the schema of what is asked for, and giving placeholder values.
+struct Tracer<'a> {
+    out: &'a mut Value,
+    depth: usize,
+}
+
+macro_rules! trace_primitive {
+    ($($method:ident => $ty:literal, $visit:ident($v:expr)),*) => {
+        $(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            *self.out = json!({ "type": $ty });
+            visitor.$visit($v)
+        }
+        )*
+    };
+}
+
+impl<'de, 'a> Deserializer<'de> for Tracer<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        *self.out = json!({});
+        visitor.visit_unit()
+    }
+
+    trace_primitive!(
+        deserialize_bool => "boolean", visit_bool(false),
+        deserialize_i8 => "integer", visit_i64(1),
+        deserialize_i16 => "integer", visit_i64(1),
+        deserialize_i32 => "integer", visit_i64(1),
+        deserialize_i64 => "integer", visit_i64(1),
+        deserialize_i128 => "integer", visit_i128(1),
+        deserialize_u8 => "integer", visit_u64(1),
+        deserialize_u16 => "integer", visit_u64(1),
+        deserialize_u32 => "integer", visit_u64(1),
+        deserialize_u64 => "integer", visit_u64(1),
+        deserialize_u128 => "integer", visit_u128(1),
+        deserialize_f32 => "number", visit_f64(1.0),
+        deserialize_f64 => "number", visit_f64(1.0),
+        deserialize_char => "string", visit_char('a'),
+        deserialize_str => "string", visit_str("a"),
+        deserialize_string => "string", visit_str("a"),
+        deserialize_identifier => "string", visit_str("a"),
+        deserialize_bytes => "string", visit_bytes(b"a"),
+        deserialize_byte_buf => "string", visit_bytes(b"a")
+    );
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.depth >= MAX_DEPTH {
+            *self.out = json!({ "nullable": true });
+            return visitor.visit_none();
+        }
+
+        let result = visitor.visit_some(Tracer {
+            out: &mut *self.out,
+            depth: self.depth + 1,
+        });
+
+        if let Value::Object(map) = self.out {
+            map.insert("nullable".into(), true.into());
+        }
+
+        result
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let remaining = if self.depth < MAX_DEPTH { 1 } else { 0 };
+        let mut items = vec![];
+
+        let result = visitor.visit_seq(SeqTracer {
+            items: &mut items,
+            remaining,
+            depth: self.depth + 1,
+        });
+
+        let items = items.pop().unwrap_or_else(|| json!({}));
+        *self.out = json!({ "type": "array", "items": items });
+
+        result
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let mut items = vec![];
+
+        let result = visitor.visit_seq(SeqTracer {
+            items: &mut items,
+            remaining: len,
+            depth: self.depth + 1,
+        });
+
+        // Items of one schema, unless they differ.
+        let first = items.first().cloned().unwrap_or_else(|| json!({}));
+        let items = if items.iter().all(|i| *i == first) {
+            first
+        } else {
+            json!({})
+        };
+        *self.out = json!({
+            "type": "array",
+            "items": items,
+            "minItems": len,
+            "maxItems": len,
+        });
+
+        result
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let remaining = if self.depth < MAX_DEPTH { 1 } else { 0 };
+        let mut value = json!({});
+
+        let result = visitor.visit_map(MapTracer {
+            value: &mut value,
+            remaining,
+            depth: self.depth + 1,
+        });
+
+        *self.out = json!({ "type": "object", "additionalProperties": value });
+
+        result
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let mut properties = Map::new();
+
+        let result = visitor.visit_map(StructTracer {
+            fields,
+            index: 0,
+            properties: &mut properties,
+            depth: self.depth + 1,
+        });
+
+        let required: Vec<_> = fields
+            .iter()
+            .filter(|f| {
+                let schema = properties.get(**f);
+                schema.map(|s| s.get("nullable").is_none()).unwrap_or(false)
+            })
+            .collect();
+
+        let mut schema = json!({ "type": "object", "properties": properties });
+        if !required.is_empty() {
+            schema["required"] = json!(required);
+        }
+        *self.out = schema;
+
+        result
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let Some(variant) = variants.first() else {
+            return Err(de::Error::custom("enum without variants"));
+        };
+
+        let mut unit = true;
+
+        let result = visitor.visit_enum(EnumTracer {
+            variant,
+            unit: &mut unit,
+            depth: self.depth + 1,
+        });
+
+        *self.out = if unit {
+            json!({ "type": "string", "enum": variants })
+        } else {
+            json!({})
+        };
+
+        result
+    }
+
+    forward_to_deserialize_any! {
+        unit unit_struct ignored_any
+    }
+}
+
+struct SeqTracer<'a> {
+    items: &'a mut Vec<Value>,
+    remaining: usize,
+    depth: usize,
+}
+
+impl<'de, 'a> SeqAccess<'de> for SeqTracer<'a> {
+    type Error = de::value::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        let mut item = json!({});
+        let result = seed.deserialize(Tracer {
+            out: &mut item,
+            depth: self.depth,
+        });
+        self.items.push(item);
+
+        result.map(Some)
+    }
+}
+
+struct MapTracer<'a> {
+    value: &'a mut Value,
+    remaining: usize,
+    depth: usize,
+}
+
+impl<'de, 'a> MapAccess<'de> for MapTracer<'a> {
+    type Error = de::value::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        let mut key = json!({});
+        let result = seed.deserialize(Tracer {
+            out: &mut key,
+            depth: self.depth,
+        });
+
+        result.map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.remaining -= 1;
+
+        seed.deserialize(Tracer {
+            out: &mut *self.value,
+            depth: self.depth,
+        })
+    }
+}
+
+struct StructTracer<'a> {
+    fields: &'static [&'static str],
+    index: usize,
+    properties: &'a mut Map<String, Value>,
+    depth: usize,
+}
+
+impl<'de, 'a> MapAccess<'de> for StructTracer<'a> {
+    type Error = de::value::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some(field) = self.fields.get(self.index) else {
+            return Ok(None);
+        };
+
+        seed.deserialize(IntoDeserializer::<Self::Error>::into_deserializer(*field))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let field = self.fields[self.index];
+        self.index += 1;
+
+        let mut value = json!({});
+        let result = seed.deserialize(Tracer {
+            out: &mut value,
+            depth: self.depth,
+        });
+        self.properties.insert(field.to_string(), value);
+
+        result
+    }
+}
+
+struct EnumTracer<'a> {
+    variant: &'static str,
+    unit: &'a mut bool,
+    depth: usize,
+}
+
+impl<'de, 'a> EnumAccess<'de> for EnumTracer<'a> {
+    type Error = de::value::Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant = IntoDeserializer::<Self::Error>::into_deserializer(self.variant);
+        let value = seed.deserialize(variant)?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for EnumTracer<'a> {
+    type Error = de::value::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        *self.unit = false;
+        seed.deserialize(Tracer {
+            out: &mut json!({}),
+            depth: self.depth,
+        })
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.unit = false;
+        Tracer {
+            out: &mut json!({}),
+            depth: self.depth,
+        }
+        .deserialize_tuple(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.unit = false;
+        Tracer {
+            out: &mut json!({}),
+            depth: self.depth,
+        }
+        .deserialize_struct("", fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{get, MethodRouter, Router};
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Role {
+        Admin,
+        User,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct User {
+        name: String,
+        age: Option<u32>,
+        role: Role,
+        tags: Vec<String>,
+        scores: HashMap<String, f64>,
+        friends: Vec<User>,
+    }
+
+    #[test]
+    fn user_schema() {
+        let schema = schema::<User>();
+        let props = &schema["properties"];
+
+        assert_eq!(schema["type"], "object");
+        assert_eq!(props["name"], json!({ "type": "string" }));
+        assert_eq!(props["age"], json!({ "type": "integer", "nullable": true }));
+        assert_eq!(props["role"]["enum"], json!(["admin", "user"]));
+        assert_eq!(props["tags"]["items"], json!({ "type": "string" }));
+        assert_eq!(props["scores"]["additionalProperties"]["type"], "number");
+        assert_eq!(props["friends"]["items"]["type"], "object");
+        assert_eq!(
+            schema["required"],
+            json!(["name", "role", "tags", "scores", "friends"])
+        );
+    }
+
+    #[test]
+    fn openapi_document() {
+        let api = OpenApi::new("Test", "0.1").route(
+            Method::GET,
+            "/users/:id",
+            RouteDoc::new().summary("Get a user").response::<User>(),
+        );
+
+        let service = Router::new()
+            .get("/users/:id", || "user")
+            .route("/files/*path", get(|| "file").put(|| "put"))
+            .get("/", || "root")
+            .finish()
+            .openapi("/openapi.json", api);
+
+        let request = http::Request::get("/openapi.json").body(().into()).unwrap();
+        let response = service.call((), request);
+        assert_eq!(response.headers()["content-type"], "application/json");
+
+        let body = response.into_body().into_string(100_000).unwrap();
+        let doc: Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(doc["info"]["title"], "Test");
+
+        let paths = doc["paths"].as_object().unwrap();
+        let keys: Vec<_> = paths.keys().map(|k| k.as_str()).collect();
+        assert_eq!(keys, ["/", "/files/{path}", "/users/{id}"]);
+
+        let user = &paths["/users/{id}"]["get"];
+        assert_eq!(user["summary"], "Get a user");
+        assert_eq!(user["parameters"][0]["name"], "id");
+        let schema = &user["responses"]["200"]["content"]["application/json"]["schema"];
+        assert_eq!(schema["type"], "object");
+
+        let files = &paths["/files/{path}"];
+        assert!(files["get"].is_object());
+        assert_eq!(files["put"]["responses"]["200"]["description"], "OK");
+    }
+}
@@ -0,0 +1,320 @@
+use http::StatusCode;
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::from_req::{FromRequest, FromRequestRef, QueryValue};
+use crate::response::{empty, IntoResponse};
+use crate::{Request, Response};
+
+/// Segments of the request path captured by a route, in the order of the pattern.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PathParams(pub Vec<(String, String)>);
+
+/// Match a path against a route pattern.
+///
+/// Segments of the pattern starting with `:` capture one segment of the path, and a
+/// last segment starting with `*` captures the rest of the path. Other segments must
+/// be equal. An empty pattern matches any path, without captures.
+pub(crate) fn match_path(pattern: &str, path: &str) -> Option<PathParams> {
+    let mut params = vec![];
+
+    if pattern.is_empty() {
+        return Some(PathParams(params));
+    }
+
+    let mut pattern_segs = pattern.split('/');
+    let mut path_segs = path.split('/');
+
+    loop {
+        match (pattern_segs.next(), path_segs.next()) {
+            (None, None) => break,
+            (Some(p), Some(s)) => {
+                if let Some(name) = p.strip_prefix('*') {
+                    let rest: Vec<_> = Some(s).into_iter().chain(path_segs).collect();
+                    params.push((name.to_string(), decode(&rest.join("/"))?));
+                    break;
+                } else if let Some(name) = p.strip_prefix(':') {
+                    if s.is_empty() {
+                        return None;
+                    }
+                    params.push((name.to_string(), decode(s)?));
+                } else if p != s {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    Some(PathParams(params))
+}
+
+/// Percent-decode a path segment. `None` if the result is not UTF-8.
+pub(crate) fn decode(s: &str) -> Option<String> {
+    String::from_utf8(decode_bytes(s.as_bytes())).ok()
+}
+
+/// Percent-decode to bytes, leaving invalid escapes as they are.
+pub(crate) fn decode_bytes(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = match bytes.get(i + 1..i + 3) {
+            Some(&[h, l]) => hex_digit(h).zip(hex_digit(l)).map(|(h, l)| h << 4 | l),
+            _ => None,
+        };
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+
+    out
+}
+
+fn hex_digit(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+/// Extractor for the segments captured by the route pattern, such as `/users/:id`.
+///
+/// `T` is deserialized from the segments. It is a single value for one captured segment,
+/// a tuple for several in the order of the pattern, or a struct or map with a field per
+/// segment name.
+///
+/// ```
+/// use serde::Deserialize;
+/// use usrv::{get, MethodRouter, Path, Router};
+///
+/// fn user(Path(id): Path<u64>) -> String {
+///     format!("user {}", id)
+/// }
+///
+/// fn file(Path((id, name)): Path<(u64, String)>) -> String {
+///     format!("file {} of {}", name, id)
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Post {
+///     year: u16,
+///     slug: String,
+/// }
+///
+/// fn post(Path(post): Path<Post>) -> String {
+///     format!("{} from {}", post.slug, post.year)
+/// }
+///
+/// let service = Router::new()
+///     .route("/users/:id", get(user))
+///     .get("/users/:id/files/*name", file)
+///     .get("/posts/:year/:slug", post)
+///     .finish();
+/// ```
+///
+/// Segments that don't deserialize to `T` reject the request with `400 Bad Request`.
+pub struct Path<T>(pub T);
+
+/// Deserializes the captured segments as a map by name, as a sequence in order, or as a
+/// single value when there is one segment.
+struct PathDeserializer<'a>(&'a [(String, String)]);
+
+impl<'a> PathDeserializer<'a> {
+    fn single(self) -> Result<QueryValue, de::value::Error> {
+        match self.0 {
+            [(_, v)] => Ok(QueryValue(v.clone())),
+            _ => Err(de::Error::invalid_length(self.0.len(), &"one path segment")),
+        }
+    }
+}
+
+macro_rules! deserialize_single {
+    ($($method:ident),*) => {
+        $(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            self.single()?.$method(visitor)
+        }
+        )*
+    };
+}
+
+impl<'de, 'a> Deserializer<'de> for PathDeserializer<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let pairs = self
+            .0
+            .iter()
+            .map(|(k, v)| (k.clone(), QueryValue(v.clone())));
+        visitor.visit_map(MapDeserializer::new(pairs))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let mut seq = SeqDeserializer::new(self.0.iter().map(|(_, v)| QueryValue(v.clone())));
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    deserialize_single!(
+        deserialize_bool,
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_i128,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_u128,
+        deserialize_f32,
+        deserialize_f64,
+        deserialize_char,
+        deserialize_str,
+        deserialize_string,
+        deserialize_option
+    );
+
+    forward_to_deserialize_any! {
+        bytes byte_buf unit unit_struct map struct identifier ignored_any
+    }
+}
+
+/// 400, for captured path segments not deserializing to the type of a [`Path`].
+pub struct InvalidPath;
+
+impl IntoResponse for InvalidPath {
+    fn into_response(self) -> Response {
+        empty(StatusCode::BAD_REQUEST)
+    }
+}
+
+impl From<InvalidPath> for Response {
+    fn from(value: InvalidPath) -> Self {
+        value.into_response()
+    }
+}
+
+fn path<T: DeserializeOwned>(request: &Request) -> Result<Path<T>, InvalidPath> {
+    let params = request
+        .extensions()
+        .get::<PathParams>()
+        .map(|p| &p.0[..])
+        .unwrap_or(&[]);
+
+    match T::deserialize(PathDeserializer(params)) {
+        Ok(v) => Ok(Path(v)),
+        Err(e) => {
+            debug!("Path params don't fit extractor: {:?}: {}", params, e);
+            Err(InvalidPath)
+        }
+    }
+}
+
+impl<S, T: DeserializeOwned> FromRequestRef<S> for Path<T> {
+    type Rejection = InvalidPath;
+
+    fn from_request(_state: &S, request: &Request) -> Result<Self, Self::Rejection> {
+        path(request)
+    }
+}
+
+impl<S, T: DeserializeOwned> FromRequest<S> for Path<T> {
+    type Rejection = InvalidPath;
+
+    fn from_request(_state: &S, request: Request) -> Result<Self, Self::Rejection> {
+        path(&request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn params(pattern: &str, path: &str) -> Option<Vec<(String, String)>> {
+        match_path(pattern, path).map(|p| p.0)
+    }
+
+    fn one(name: &str, value: &str) -> Option<Vec<(String, String)>> {
+        Some(vec![(name.to_string(), value.to_string())])
+    }
+
+    #[test]
+    fn match_path_segments() {
+        assert_eq!(params("/users/:id", "/users/42"), one("id", "42"));
+        assert_eq!(params("/users/:id", "/users/"), None);
+        assert_eq!(params("/users/:id", "/users/42/x"), None);
+        assert_eq!(params("/users", "/users/"), None);
+        assert_eq!(params("/users", "/Users"), None);
+        assert_eq!(params("", "/anything"), Some(vec![]));
+
+        // Captures are decoded, and must be UTF-8.
+        assert_eq!(params("/u/:name", "/u/caf%C3%A9"), one("name", "café"));
+        assert_eq!(params("/u/:name", "/u/%FF"), None);
+    }
+
+    #[test]
+    fn match_path_rest() {
+        assert_eq!(
+            params("/files/*rest", "/files/a/b%2Fc"),
+            one("rest", "a/b/c")
+        );
+        assert_eq!(params("/files/*rest", "/files/"), one("rest", ""));
+        assert_eq!(params("/files/*rest", "/files"), None);
+    }
+
+    #[test]
+    fn decode_escapes() {
+        assert_eq!(decode_bytes(b"a%20b"), b"a b");
+        assert_eq!(decode_bytes(b"%41%6a"), b"Aj");
+
+        // Invalid escapes, or cut off at the end, are kept as they are.
+        assert_eq!(decode_bytes(b"100%"), b"100%");
+        assert_eq!(decode_bytes(b"%4"), b"%4");
+        assert_eq!(decode_bytes(b"%zz"), b"%zz");
+        assert_eq!(decode_bytes(b"%%41"), b"%A");
+        assert_eq!(decode_bytes(b""), b"");
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::shed::{Gauge, GaugeGuard};
+
+/// Fixed set of threads, each handling the items sent to the pool with the same function.
+///
+/// The workers stop once the pool is dropped, after the items already queued.
+pub(crate) struct Pool<T> {
+    tx: Sender<(T, GaugeGuard)>,
+    /// Items queued or being handled.
+    pending: Gauge,
+    max: usize,
+}
+
+impl<T: Send + 'static> Pool<T> {
+    /// Start `workers` threads, with room for `queue` items waiting for a worker.
+    pub fn new<F>(workers: usize, queue: usize, f: F) -> Self
+    where
+        F: Fn(T) + Clone + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let rx = Arc::new(Mutex::new(rx));
+
+        for _ in 0..workers {
+            let rx = rx.clone();
+            let f = f.clone();
+            thread::spawn(move || {
+                while let Some((item, _guard)) = next(&rx) {
+                    f(item);
+                }
+            });
+        }
+
+        Pool {
+            tx,
+            pending: Gauge::default(),
+            max: workers + queue,
+        }
+    }
+
+    /// Hand the item to a worker, or give it back when all are busy and the queue full.
+    pub fn try_send(&self, item: T) -> Result<(), T> {
+        let Some(guard) = self.pending.try_enter(Some(self.max)) else {
+            return Err(item);
+        };
+
+        self.tx.send((item, guard)).map_err(|e| e.0 .0)
+    }
+}
+
+fn next<T>(rx: &Mutex<Receiver<T>>) -> Option<T> {
+    // The lock is only held while waiting, not while handling the item.
+    let rx = rx.lock().unwrap_or_else(|e| e.into_inner());
+    rx.recv().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn busy_pool() {
+        let (done_tx, done_rx) = mpsc::channel();
+        let (go_tx, go_rx) = mpsc::channel::<()>();
+        let go_rx = Arc::new(Mutex::new(go_rx));
+
+        let pool = Pool::new(2, 1, move |n: u32| {
+            let _ = go_rx.lock().unwrap().recv();
+            done_tx.send(n).unwrap();
+        });
+
+        assert_eq!(pool.try_send(1), Ok(()));
+        assert_eq!(pool.try_send(2), Ok(()));
+        // Both workers are busy, one item fits in the queue.
+        assert_eq!(pool.try_send(3), Ok(()));
+        assert_eq!(pool.try_send(4), Err(4));
+
+        for _ in 0..3 {
+            go_tx.send(()).unwrap();
+        }
+
+        let mut done: Vec<_> = done_rx.iter().take(3).collect();
+        done.sort();
+        assert_eq!(done, [1, 2, 3]);
+    }
+}
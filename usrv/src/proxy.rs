@@ -0,0 +1,299 @@
+use std::io::{self, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::hijack::{hijack, Connection};
+use http::header::{CONTENT_LENGTH, PROXY_AUTHENTICATE};
+use http::{HeaderValue, StatusCode};
+
+use crate::response::{empty, IntoResponse};
+use crate::{Request, Response};
+
+type Authenticate = dyn Fn(&Request) -> bool + Send + Sync + 'static;
+type Allow = dyn Fn(&str, u16) -> bool + Send + Sync + 'static;
+
+/// Built-in handling of `CONNECT`, to serve as a forward proxy.
+///
+/// For a `CONNECT` request, the proxy authenticates the request, checks the target is
+/// allowed, connects to it and answers `200`. After that, bytes are copied both ways
+/// between the client and the target, until one of them is done. Set on a service with
+/// [`Service::connect_proxy()`][crate::Service::connect_proxy].
+///
+/// No target is allowed until set with [`allow()`][Self::allow]. Otherwise clients could
+/// reach anything the server can, such as services on its own network.
+///
+/// ```
+/// use usrv::{ConnectProxy, MethodRouter, Router};
+///
+/// let proxy = ConnectProxy::new()
+///     .allow(|host, port| host.ends_with(".example.com") && port == 443)
+///     .realm("office")
+///     .authenticate(|req| {
+///         let auth = req.headers().get("proxy-authorization");
+///         auth.map(|v| v == "Basic dXNlcjpwYXNz").unwrap_or(false)
+///     });
+///
+/// let service = Router::new().finish().connect_proxy(proxy);
+/// ```
+#[derive(Clone)]
+pub struct ConnectProxy {
+    authenticate: Option<Arc<Authenticate>>,
+    allow: Option<Arc<Allow>>,
+    auth_required: ProxyAuthRequired,
+    connect_timeout: Option<Duration>,
+}
+
+impl Default for ConnectProxy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConnectProxy {
+    pub fn new() -> Self {
+        ConnectProxy {
+            authenticate: None,
+            allow: None,
+            auth_required: ProxyAuthRequired::basic("proxy"),
+            connect_timeout: None,
+        }
+    }
+
+    /// Check requests before connecting, typically the `Proxy-Authorization` header.
+    ///
+    /// Requests failing the check are answered with [`ProxyAuthRequired`]. Without this,
+    /// any client can use the proxy.
+    pub fn authenticate(mut self, f: impl Fn(&Request) -> bool + Send + Sync + 'static) -> Self {
+        self.authenticate = Some(Arc::new(f));
+        self
+    }
+
+    /// Allow tunnels to the targets `f` is true for, given the host and port.
+    ///
+    /// Other targets are answered with `403 Forbidden`. The host is checked as the client
+    /// gave it, before it is resolved, which an IP address or bracketed IPv6 address can
+    /// also be.
+    pub fn allow(mut self, f: impl Fn(&str, u16) -> bool + Send + Sync + 'static) -> Self {
+        self.allow = Some(Arc::new(f));
+        self
+    }
+
+    /// Allow tunnels to any target, including those on the server's own network.
+    pub fn allow_any(self) -> Self {
+        self.allow(|_, _| true)
+    }
+
+    /// The realm asked for when authentication fails, `"proxy"` by default.
+    ///
+    /// See [`ProxyAuthRequired::basic()`] for how the realm is sent.
+    pub fn realm(mut self, realm: &str) -> Self {
+        self.auth_required = ProxyAuthRequired::basic(realm);
+        self
+    }
+
+    /// Give up connecting to the target after `timeout`.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub(crate) fn call(&self, request: Request) -> Response {
+        let Some(authority) = request.uri().authority() else {
+            debug!("CONNECT without authority: {}", request.uri());
+            return status(StatusCode::BAD_REQUEST);
+        };
+        let target = authority.to_string();
+        let host = authority.host();
+        let port = authority.port_u16().unwrap_or(443);
+
+        if let Some(authenticate) = &self.authenticate {
+            if !authenticate(&request) {
+                debug!("CONNECT to {} not authenticated", target);
+                return self.auth_required.clone().into_response();
+            }
+        }
+
+        let allowed = self.allow.as_ref().map(|f| f(host, port)).unwrap_or(false);
+        if !allowed {
+            debug!("CONNECT to {} not allowed", target);
+            return status(StatusCode::FORBIDDEN);
+        }
+
+        let hijack = match hijack(&request) {
+            Ok(v) => v,
+            Err(e) => return e.into_response(),
+        };
+
+        let upstream = match self.connect(&target) {
+            Ok(v) => v,
+            Err(e) => {
+                debug!("CONNECT to {} failed: {}", target, e);
+                return status(StatusCode::BAD_GATEWAY);
+            }
+        };
+
+        debug!("CONNECT tunnel to {}", target);
+        hijack.take_over(move |conn| {
+            if let Err(e) = splice(conn, upstream) {
+                debug!("CONNECT tunnel to {} ended: {}", target, e);
+            }
+        });
+
+        status(StatusCode::OK)
+    }
+
+    fn connect(&self, target: &str) -> io::Result<TcpStream> {
+        let Some(timeout) = self.connect_timeout else {
+            return TcpStream::connect(target);
+        };
+
+        let mut last_err = None;
+
+        for addr in target.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(v) => return Ok(v),
+                Err(e) => last_err = Some(e),
+            }
+        }
+
+        Err(last_err
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address for target")))
+    }
+}
+
+/// Copy between the client and the target. Once the target is done, so is the client.
+fn splice(conn: Connection<'_>, mut upstream: TcpStream) -> io::Result<()> {
+    let Connection {
+        buffered,
+        mut reader,
+        writer,
+        breaker,
+    } = conn;
+
+    let mut upstream_write = upstream.try_clone()?;
+
+    // The client side is 'static, and can be copied on a thread of its own.
+    let to_upstream = thread::spawn(move || -> io::Result<()> {
+        upstream_write.write_all(&buffered)?;
+        io::copy(&mut reader, &mut upstream_write)?;
+        upstream_write.shutdown(Shutdown::Write)
+    });
+
+    let from_upstream = io::copy(&mut upstream, writer).and_then(|_| writer.flush());
+
+    // The copy to the target is blocked reading the client, which might never send more.
+    if let Err(e) = breaker.shutdown_read() {
+        // Left to end on the next read from the client, which fails to write.
+        debug!("Can't stop reading client of tunnel: {}", e);
+        let _ = upstream.shutdown(Shutdown::Both);
+        return from_upstream;
+    }
+
+    let to_upstream = to_upstream
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "tunnel thread panic")));
+
+    from_upstream.and(to_upstream)
+}
+
+fn status(status: StatusCode) -> Response {
+    let mut response = empty(status);
+
+    // A 2xx to CONNECT has no body, and must not declare one.
+    if status.is_success() {
+        response.headers_mut().remove(CONTENT_LENGTH);
+    }
+
+    response
+}
+
+/// 407, for a proxy request without valid credentials. Holds the challenge to answer.
+#[derive(Debug, Clone)]
+pub struct ProxyAuthRequired(HeaderValue);
+
+impl ProxyAuthRequired {
+    /// Ask for Basic credentials for `realm`.
+    ///
+    /// The realm is sent as a quoted string, with quotes and backslashes escaped.
+    /// Panics if it has control characters, which can't be sent in a header.
+    pub fn basic(realm: &str) -> Self {
+        let mut challenge = String::from("Basic realm=\"");
+        for c in realm.chars() {
+            if c == '"' || c == '\\' {
+                challenge.push('\\');
+            }
+            challenge.push(c);
+        }
+        challenge.push('"');
+
+        match HeaderValue::try_from(challenge) {
+            Ok(v) if !realm.contains(char::is_control) => ProxyAuthRequired(v),
+            _ => panic!("realm has control characters: {:?}", realm),
+        }
+    }
+}
+
+impl IntoResponse for ProxyAuthRequired {
+    fn into_response(self) -> Response {
+        let mut response = empty(StatusCode::PROXY_AUTHENTICATION_REQUIRED);
+        response.headers_mut().insert(PROXY_AUTHENTICATE, self.0);
+        response
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Body;
+    use http::Method;
+
+    #[test]
+    fn basic_realm() {
+        let challenge = |realm| {
+            let response = ProxyAuthRequired::basic(realm).into_response();
+            response.headers()[PROXY_AUTHENTICATE].clone()
+        };
+
+        assert_eq!(challenge("office"), "Basic realm=\"office\"");
+        assert_eq!(challenge(r#"a "b" \c"#), r#"Basic realm="a \"b\" \\c""#);
+        assert_eq!(challenge("café"), "Basic realm=\"café\"");
+    }
+
+    #[test]
+    fn connect_refused() {
+        let proxy = ConnectProxy::new()
+            .realm("office")
+            .authenticate(|req| req.headers().contains_key("proxy-authorization"))
+            .allow(|host, port| host == "example.test" && port == 443);
+
+        let call = |target: &str, authorized: bool| {
+            let mut request = http::Request::builder().method(Method::CONNECT).uri(target);
+            if authorized {
+                request = request.header("proxy-authorization", "Basic dXNlcjpwYXNz");
+            }
+            proxy.call(request.body(Body::empty()).unwrap())
+        };
+
+        let response = call("example.test:443", false);
+        assert_eq!(response.status(), StatusCode::PROXY_AUTHENTICATION_REQUIRED);
+        assert_eq!(
+            response.headers()[PROXY_AUTHENTICATE],
+            "Basic realm=\"office\""
+        );
+
+        assert_eq!(
+            call("example.test:22", true).status(),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(call("other.test:443", true).status(), StatusCode::FORBIDDEN);
+        assert_eq!(call("/", true).status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    #[should_panic = "realm has control characters"]
+    fn basic_realm_control() {
+        ProxyAuthRequired::basic("a\r\nX-Injected: 1");
+    }
+}
@@ -1,37 +1,90 @@
 use std::io;
 
+use hoot::wire::{Direction, WireHook, WirePart};
+use hoot::HootError;
+
 use crate::body::{Body, HootBody};
-use crate::fill_more::FillMoreBuffer;
+use crate::fill_more::{FillMoreBuffer, DEFAULT_MAX};
+use crate::response::BadRequest;
+use crate::wire::Tap;
 use crate::{Error, Request};
 
+/// Longest request target, answered with `414 URI Too Long` beyond it.
+const MAX_TARGET_LEN: usize = 8192;
+
 pub fn read_request<Read>(reader: Read) -> Result<Option<Request>, Error>
 where
-    Read: io::Read + 'static,
+    Read: io::Read + Send + 'static,
+{
+    read_request_max(reader, DEFAULT_MAX, Tap::default())
+}
+
+/// Read a request, buffering at most `max` bytes of input.
+pub(crate) fn read_request_max<Read>(
+    reader: Read,
+    max: usize,
+    tap: Tap,
+) -> Result<Option<Request>, Error>
+where
+    Read: io::Read + Send + 'static,
 {
     let parse_buf = vec![0_u8; 1024];
 
-    let boxed: Box<dyn io::Read + 'static> = Box::new(reader);
-    let fill_buf = FillMoreBuffer::new(boxed);
+    let boxed: Box<dyn io::Read + Send + 'static> = Box::new(reader);
+    let fill_buf = FillMoreBuffer::with_max(boxed, max);
 
-    read_from_buffers(parse_buf, fill_buf)
+    read_from_buffers(parse_buf, fill_buf, tap)
 }
 
 pub(crate) fn read_from_buffers(
     mut parse_buf: Vec<u8>,
-    mut fill_buf: FillMoreBuffer<Box<dyn io::Read + 'static>>,
+    mut fill_buf: FillMoreBuffer<Box<dyn io::Read + Send + 'static>>,
+    mut tap: Tap,
 ) -> Result<Option<Request>, Error> {
     let mut hoot_req = hoot::server::Request::new();
 
+    // A head that doesn't fit the buffer is answered with 431.
+    hoot_req.max_header_bytes(fill_buf.max());
+    hoot_req.max_target_len(MAX_TARGET_LEN);
+
+    let mut need_more = false;
+
     let attempt = loop {
-        let input = fill_buf.fill_more()?;
+        let buffered = fill_buf.buffer().len();
+
+        // Input left over from the previous request might hold all of this one.
+        let input = if need_more || buffered == 0 {
+            let input = fill_buf.fill_more()?;
+
+            if input.len() == buffered {
+                if input.is_empty() {
+                    // Connection closed between requests.
+                    return Ok(None);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed in request head",
+                )
+                .into());
+            }
+
+            input
+        } else {
+            fill_buf.buffer()
+        };
 
         if parse_buf.len() < input.len() {
             parse_buf.resize(input.len(), 0);
         }
 
-        let attempt = hoot_req.try_read_request(&input, &mut parse_buf)?;
+        let attempt = match hoot_req.try_read_request(input, &mut parse_buf) {
+            Ok(v) => v,
+            Err(HootError::UnknownMethod) => return Err(HootError::UnknownMethod.into()),
+            Err(e) => return Err(Error::BadRequest(BadRequest::new(e, input))),
+        };
 
         if !attempt.is_success() {
+            need_more = true;
             continue;
         }
 
@@ -41,12 +94,18 @@ pub(crate) fn read_from_buffers(
     // This much of the input buffer is already used up.
     let input_used = attempt.input_used();
 
-    let req: http::Request<()> = attempt.try_into()?;
+    let req: http::Request<()> = match attempt.try_into() {
+        Ok(v) => v,
+        Err(e) => return Err(Error::BadRequest(BadRequest::new(e, fill_buf.buffer()))),
+    };
+
+    let head = &fill_buf.buffer()[..input_used];
+    tap.wire(Direction::Received, WirePart::Head, head);
 
     // Remove the amount of input that was used up for the request header.
     fill_buf.consume(input_used);
 
-    let body = HootBody::new(hoot_req.proceed(), parse_buf, fill_buf);
+    let body = HootBody::new(hoot_req.proceed(), parse_buf, fill_buf, tap);
 
     let body = Body::hoot(body);
 
@@ -1,6 +1,11 @@
 use std::convert::Infallible;
+use std::fmt;
+use std::io;
 
-use http::HeaderValue;
+use hoot::server::diagnose_request;
+use hoot::HootError;
+use http::header::{ALLOW, CONNECTION, RETRY_AFTER};
+use http::{HeaderValue, Method, StatusCode};
 
 use crate::body::ContentType;
 use crate::{Body, Response};
@@ -9,6 +14,15 @@ pub trait IntoResponse {
     fn into_response(self) -> Response;
 }
 
+/// A response with `status` and an empty body, declared with `Content-Length: 0`.
+pub(crate) fn empty(status: StatusCode) -> Response {
+    http::Response::builder()
+        .status(status)
+        .header("content-length", 0)
+        .body(Body::empty())
+        .unwrap()
+}
+
 pub struct NotFound;
 
 impl IntoResponse for NotFound {
@@ -20,6 +34,187 @@ impl IntoResponse for NotFound {
     }
 }
 
+/// 400, for a request head that couldn't be read.
+///
+/// Holds the error, and what could be made out of the head for the log. The method and
+/// path are there if the request line got that far, and the offset is the first byte that
+/// doesn't belong in a request head. The response only tells the client the error and the
+/// offset. A head too large is answered with 431, and a request target too long with 414,
+/// instead of 400.
+#[derive(Debug, Clone)]
+pub struct BadRequest {
+    pub error: HootError,
+    pub method: Option<String>,
+    pub path: Option<String>,
+    pub offset: Option<usize>,
+}
+
+impl BadRequest {
+    /// Diagnose the input the request head failed to read from.
+    pub(crate) fn new(error: HootError, input: &[u8]) -> Self {
+        let d = diagnose_request(input);
+
+        BadRequest {
+            error,
+            method: d.method().map(Into::into),
+            path: d.path().map(Into::into),
+            offset: d.offset(),
+        }
+    }
+}
+
+impl fmt::Display for BadRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+        if let Some(method) = &self.method {
+            write!(f, ", in {}", method)?;
+            if let Some(path) = &self.path {
+                write!(f, " {}", path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl IntoResponse for BadRequest {
+    fn into_response(self) -> Response {
+        let mut body = format!("Bad request: {}", self.error);
+        if let Some(offset) = self.offset {
+            body.push_str(&format!(" at byte {}", offset));
+        }
+        body.push('\n');
+
+        let status = match self.error {
+            HootError::HeaderOverflow | HootError::TooManyHeaders => 431,
+            HootError::TargetTooLong => 414,
+            _ => 400,
+        };
+
+        http::Response::builder()
+            .status(status)
+            .header("content-type", "text/plain; charset=utf-8")
+            .header("content-length", body.len())
+            .body(Body::bytes(body.into_bytes()))
+            .unwrap()
+    }
+}
+
+/// 405, for a method the server doesn't handle. Holds the methods that are handled.
+pub struct MethodNotAllowed(pub Vec<Method>);
+
+impl IntoResponse for MethodNotAllowed {
+    fn into_response(self) -> Response {
+        let allow = self
+            .0
+            .iter()
+            .map(|m| m.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut response = empty(StatusCode::METHOD_NOT_ALLOWED);
+        let allow = HeaderValue::try_from(allow).expect("methods are a header value");
+        response.headers_mut().insert(ALLOW, allow);
+        response
+    }
+}
+
+/// 501, for a method the server doesn't recognize.
+pub struct NotImplemented;
+
+impl IntoResponse for NotImplemented {
+    fn into_response(self) -> Response {
+        empty(StatusCode::NOT_IMPLEMENTED)
+    }
+}
+
+/// 413, for a request body over the limit, see [`BodyLimit`][crate::BodyLimit].
+///
+/// Also the error of reading a body past the limit, for extractors to answer with. The
+/// connection is closed after the response, rather than reading the rest of the body.
+#[derive(Debug)]
+pub struct PayloadTooLarge;
+
+impl PayloadTooLarge {
+    /// Whether reading a body failed for being over the limit.
+    pub fn is(error: &io::Error) -> bool {
+        error
+            .get_ref()
+            .map(|e| e.is::<PayloadTooLarge>())
+            .unwrap_or(false)
+    }
+}
+
+impl fmt::Display for PayloadTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "request body over limit")
+    }
+}
+
+impl std::error::Error for PayloadTooLarge {}
+
+impl IntoResponse for PayloadTooLarge {
+    fn into_response(self) -> Response {
+        let mut response = empty(StatusCode::PAYLOAD_TOO_LARGE);
+        let close = HeaderValue::from_static("close");
+        response.headers_mut().insert(CONNECTION, close);
+        response
+    }
+}
+
+/// 417, for a request with an `Expect` the server won't meet.
+///
+/// Answers `Expect: 100-continue` when turned off with
+/// [`ServerConfig::expect_continue()`][crate::ServerConfig::expect_continue]. The body
+/// isn't read, so the connection is closed after the response.
+pub struct ExpectationFailed;
+
+impl IntoResponse for ExpectationFailed {
+    fn into_response(self) -> Response {
+        let mut response = empty(StatusCode::EXPECTATION_FAILED);
+        let close = HeaderValue::from_static("close");
+        response.headers_mut().insert(CONNECTION, close);
+        response
+    }
+}
+
+/// 503, for a server too busy to handle the request. Holds the seconds to wait before
+/// retrying, sent as `Retry-After`.
+pub struct ServiceUnavailable(pub u32);
+
+impl IntoResponse for ServiceUnavailable {
+    fn into_response(self) -> Response {
+        let mut response = empty(StatusCode::SERVICE_UNAVAILABLE);
+        response.headers_mut().insert(RETRY_AFTER, self.0.into());
+        response
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: serde::Serialize> IntoResponse for crate::Json<T> {
+    fn into_response(self) -> Response {
+        let json = match serde_json::to_vec(&self.0) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("Failed to serialize JSON response: {}", e);
+                return empty(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        };
+
+        let mut body = Body::bytes(json);
+        body.ctype = Some(ContentType("application/json"));
+        body.into_response()
+    }
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
 impl IntoResponse for Infallible {
     fn into_response(self) -> Response {
         panic!("IntoResponse for Infallible");
@@ -1,19 +1,46 @@
 use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
 use std::{io, thread};
 
-use http::Method;
+use hoot::wire::WireHook;
+use hoot::{HootError, MethodClass};
+use http::header::ALLOW;
+use http::{HeaderValue, Method, StatusCode};
 
+use crate::config::ServerConfig;
+use crate::deadline::{deadline, expired};
+use crate::expect::{expects_continue, write_continue, Continue, SharedWriter};
 use crate::handler::Handler;
-use crate::read_req::read_from_buffers;
-use crate::response::{IntoResponse, NotFound};
-use crate::server::Acceptor;
+use crate::hijack::{Connection, HijackSlot};
+use crate::path::match_path;
+use crate::pool::Pool;
+use crate::proxy::ConnectProxy;
+use crate::read_req::{read_from_buffers, read_request_max};
+use crate::response::{empty, IntoResponse, MethodNotAllowed, NotFound, NotImplemented};
+use crate::response::{ExpectationFailed, PayloadTooLarge, ServiceUnavailable};
+use crate::server::{Acceptor, Breaker};
+use crate::shed::{Gauge, GaugeGuard};
+use crate::shutdown::ShutdownHandle;
+use crate::well_known::WellKnown;
+use crate::wire::{MakeTap, Tap};
 use crate::write_res::write_response_with_buffer;
-use crate::{read_request, Error, Request, Response};
+use crate::write_res::{is_close_delimited, write_response, write_response_head};
+use crate::{Error, Request, Response, TransportInfo};
+
+/// How long a connection shed for busy workers gets to send its first request.
+const SHED_READ_TIMEOUT: Duration = Duration::from_secs(2);
 
 pub struct Router<S = ()> {
     _state: PhantomData<S>,
 }
 
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Router {
     pub fn new() -> Self {
         Self::with_state::<()>()
@@ -30,6 +57,11 @@ impl<S> Callable<S> for Router<S> {
     fn call(&self, state: S, request: Request) -> CallResult<S> {
         CallResult::Unhandled(state, request)
     }
+
+    fn methods(&self, _methods: &mut Vec<Method>) {}
+
+    #[cfg(feature = "openapi")]
+    fn routes(&self, _prefix: &str, _routes: &mut Vec<(Method, String)>) {}
 }
 
 #[allow(private_bounds)]
@@ -38,6 +70,17 @@ pub trait MethodRouter<S>: Sized + Callable<S> {
         Service {
             _state: PhantomData,
             parent: self,
+            config: ServerConfig::new(),
+            connect_proxy: None,
+            well_known: None,
+            shutdown: ShutdownHandle::default(),
+            #[cfg(feature = "openapi")]
+            openapi: None,
+            in_flight: Gauge::default(),
+            connections: Gauge::default(),
+            wire_hook: None,
+            #[cfg(feature = "compression")]
+            compression: None,
         }
     }
 
@@ -47,47 +90,93 @@ pub trait MethodRouter<S>: Sized + Callable<S> {
         method: Method,
         path: &str,
         handler: H,
-    ) -> MethodHandler<T, S, H, Self>;
+    ) -> MethodHandler<'_, T, S, H, Self>;
+
+    /// Route a path to handlers per method, as built by [`get()`], [`post()`] and friends.
+    ///
+    /// A request for the path with a method none of the handlers take is answered with
+    /// `405 Method Not Allowed`, and the methods of this route in `Allow`.
+    ///
+    /// Like for the single method handlers, the path is a pattern where `:name` captures a
+    /// segment, and a last `*name` captures the rest of the path, for the [`Path`] extractor.
+    ///
+    /// [`Path`]: crate::Path
+    fn route<C: Callable<S>>(self, path: &str, methods: Methods<S, C>) -> Route<'_, S, C, Self> {
+        Route {
+            _state: PhantomData,
+            parent: self,
+            path,
+            methods: methods.inner,
+        }
+    }
 
-    fn get<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<T, S, H, Self> {
+    fn get<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<'_, T, S, H, Self> {
         Self::handle(self, Method::GET, path, handler)
     }
 
-    fn post<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<T, S, H, Self> {
+    fn post<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<'_, T, S, H, Self> {
         Self::handle(self, Method::POST, path, handler)
     }
 
-    fn put<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<T, S, H, Self> {
+    fn put<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<'_, T, S, H, Self> {
         Self::handle(self, Method::PUT, path, handler)
     }
 
-    fn delete<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<T, S, H, Self> {
+    fn delete<T, H: Handler<T, S>>(
+        self,
+        path: &str,
+        handler: H,
+    ) -> MethodHandler<'_, T, S, H, Self> {
         Self::handle(self, Method::DELETE, path, handler)
     }
 
-    fn head<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<T, S, H, Self> {
+    fn head<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<'_, T, S, H, Self> {
         Self::handle(self, Method::HEAD, path, handler)
     }
 
-    fn options<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<T, S, H, Self> {
+    fn options<T, H: Handler<T, S>>(
+        self,
+        path: &str,
+        handler: H,
+    ) -> MethodHandler<'_, T, S, H, Self> {
         Self::handle(self, Method::OPTIONS, path, handler)
     }
 
-    fn connect<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<T, S, H, Self> {
+    fn connect<T, H: Handler<T, S>>(
+        self,
+        path: &str,
+        handler: H,
+    ) -> MethodHandler<'_, T, S, H, Self> {
         Self::handle(self, Method::CONNECT, path, handler)
     }
 
-    fn patch<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<T, S, H, Self> {
+    fn patch<T, H: Handler<T, S>>(
+        self,
+        path: &str,
+        handler: H,
+    ) -> MethodHandler<'_, T, S, H, Self> {
         Self::handle(self, Method::PATCH, path, handler)
     }
 
-    fn trace<T, H: Handler<T, S>>(self, path: &str, handler: H) -> MethodHandler<T, S, H, Self> {
+    fn trace<T, H: Handler<T, S>>(
+        self,
+        path: &str,
+        handler: H,
+    ) -> MethodHandler<'_, T, S, H, Self> {
         Self::handle(self, Method::TRACE, path, handler)
     }
 }
 
 trait Callable<S>: Clone {
     fn call(&self, state: S, request: Request) -> CallResult<S>;
+
+    /// Collect the methods handled, in the order they are declared.
+    fn methods(&self, methods: &mut Vec<Method>);
+
+    /// Collect the method and path pattern of each handler, in the order they are declared.
+    /// Handlers without a pattern of their own use `prefix`, the pattern of their route.
+    #[cfg(feature = "openapi")]
+    fn routes(&self, prefix: &str, routes: &mut Vec<(Method, String)>);
 }
 
 enum CallResult<S> {
@@ -98,64 +187,432 @@ enum CallResult<S> {
 pub struct Service<S, P> {
     _state: PhantomData<S>,
     parent: P,
+    config: ServerConfig,
+    connect_proxy: Option<ConnectProxy>,
+    well_known: Option<WellKnown>,
+    shutdown: ShutdownHandle,
+    #[cfg(feature = "openapi")]
+    openapi: Option<(String, serde_json::Value)>,
+    in_flight: Gauge,
+    connections: Gauge,
+    wire_hook: Option<MakeTap>,
+    #[cfg(feature = "compression")]
+    compression: Option<crate::Compression>,
 }
 
 #[allow(private_bounds)]
 impl<S, P: Callable<S>> Service<S, P> {
+    /// Use the settings of `config`.
+    pub fn config(mut self, config: ServerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// See [`ServerConfig::max_buffer()`].
+    pub fn max_buffer(mut self, max: usize) -> Self {
+        self.config = self.config.max_buffer(max);
+        self
+    }
+
+    /// See [`ServerConfig::max_in_flight()`].
+    pub fn max_in_flight(mut self, max: usize) -> Self {
+        self.config = self.config.max_in_flight(max);
+        self
+    }
+
+    /// See [`ServerConfig::max_connections()`].
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.config = self.config.max_connections(max);
+        self
+    }
+
+    /// See [`ServerConfig::workers()`].
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.config = self.config.workers(workers);
+        self
+    }
+
+    /// See [`ServerConfig::body_limit()`].
+    pub fn body_limit(mut self, max: u64) -> Self {
+        self.config = self.config.body_limit(max);
+        self
+    }
+
+    /// See [`ServerConfig::expect_continue()`].
+    pub fn expect_continue(mut self, enabled: bool) -> Self {
+        self.config = self.config.expect_continue(enabled);
+        self
+    }
+
+    /// See [`ServerConfig::retry_after()`].
+    pub fn retry_after(mut self, seconds: u32) -> Self {
+        self.config = self.config.retry_after(seconds);
+        self
+    }
+
+    /// Answer `CONNECT` requests as a forward proxy, instead of routing them.
+    pub fn connect_proxy(mut self, proxy: ConnectProxy) -> Self {
+        self.connect_proxy = Some(proxy);
+        self
+    }
+
+    /// Answer the endpoints set in `well_known`, before any route.
+    pub fn well_known(mut self, well_known: WellKnown) -> Self {
+        self.well_known = Some(well_known);
+        self
+    }
+
+    /// Give the bytes read and written on each connection to a hook made by `hook`.
+    ///
+    /// See [`hoot::wire`] for what the hook is told. Request heads that can't be read,
+    /// and the responses to them, are not.
+    pub fn wire_hook<F, H>(mut self, hook: F) -> Self
+    where
+        F: Fn(&TransportInfo) -> H + Send + Sync + 'static,
+        H: WireHook + Send + 'static,
+    {
+        self.wire_hook = Some(Arc::new(move |info| Tap::new(hook(info))));
+        self
+    }
+
+    /// Compress response bodies for clients accepting it, see [`Compression`][crate::Compression].
+    #[cfg(feature = "compression")]
+    pub fn compression(mut self, compression: crate::Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Serve the OpenAPI document of `api` at `path`, for all routes of the service.
+    #[cfg(feature = "openapi")]
+    pub fn openapi(mut self, path: impl Into<String>, api: crate::OpenApi) -> Self {
+        let mut routes = vec![];
+        self.parent.routes("", &mut routes);
+        self.openapi = Some((path.into(), api.render(&routes)));
+        self
+    }
+
+    /// Handle to stop [`run()`][Self::run] or [`serve()`][Self::serve] from another thread.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
     pub fn call(&self, state: S, request: Request) -> Response {
+        #[cfg(feature = "compression")]
+        if let Some(compression) = &self.compression {
+            let coding = compression.negotiate(&request);
+            let response = self.respond(state, request);
+            return compression.compress(coding, response);
+        }
+
+        self.respond(state, request)
+    }
+
+    fn respond(&self, state: S, mut request: Request) -> Response {
+        if let Some(name) = &self.config.deadline_header {
+            if let Some(deadline) = deadline(&request, name) {
+                if deadline.is_expired() {
+                    debug!("Request arrived past its deadline");
+                    return expired();
+                }
+                request.extensions_mut().insert(deadline);
+            }
+        }
+
+        // OPTIONS * is about the server as a whole, not any route.
+        if request.method() == Method::OPTIONS && request.uri() == "*" {
+            return self.server_options();
+        }
+
+        if let Some(response) = self.well_known.as_ref().and_then(|w| w.call(&request)) {
+            return response;
+        }
+
+        #[cfg(feature = "openapi")]
+        if let Some((path, doc)) = &self.openapi {
+            if request.method() == Method::GET && request.uri().path() == path {
+                return crate::Json(doc.clone()).into_response();
+            }
+        }
+
+        if request.method() == Method::CONNECT {
+            if let Some(proxy) = &self.connect_proxy {
+                return proxy.call(request);
+            }
+        }
+
         match self.parent.call(state, request) {
             CallResult::Handled(v) => v,
-            CallResult::Unhandled(_, _) => NotFound.into_response(),
+            CallResult::Unhandled(_, request) => self.unhandled(&request),
+        }
+    }
+
+    /// Response to a request no route handled.
+    fn unhandled(&self, request: &Request) -> Response {
+        match MethodClass::of(request.method().as_str()) {
+            MethodClass::Unrecognized => NotImplemented.into_response(),
+            MethodClass::Known(_) => {
+                let allowed = self.allowed_methods();
+                if allowed.contains(request.method()) {
+                    NotFound.into_response()
+                } else {
+                    MethodNotAllowed(allowed).into_response()
+                }
+            }
+        }
+    }
+
+    /// The methods handled by this service.
+    ///
+    /// `OPTIONS` is always included, since `OPTIONS *` is answered by the service itself.
+    /// So is `CONNECT`, with a [`connect_proxy()`][Self::connect_proxy].
+    pub fn allowed_methods(&self) -> Vec<Method> {
+        let mut methods = vec![];
+        self.parent.methods(&mut methods);
+        if !methods.contains(&Method::OPTIONS) {
+            methods.push(Method::OPTIONS);
+        }
+        if self.connect_proxy.is_some() && !methods.contains(&Method::CONNECT) {
+            methods.push(Method::CONNECT);
         }
+        methods
+    }
+
+    /// Response to `OPTIONS *`, with the server wide `Allow` header.
+    fn server_options(&self) -> Response {
+        let allow = self
+            .allowed_methods()
+            .iter()
+            .map(|m| m.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut response = empty(StatusCode::OK);
+        let allow = HeaderValue::try_from(allow).expect("methods are a header value");
+        response.headers_mut().insert(ALLOW, allow);
+        response
     }
 
     fn drive(
         &self,
         state: S,
-        reader: impl io::Read + 'static,
+        reader: impl io::Read + Send + 'static,
+        writer: &mut dyn io::Write,
+        breaker: &dyn Breaker,
+        single: bool,
+    ) -> Result<(), Error>
+    where
+        S: Clone,
+    {
+        self.drive_shared(state, reader, writer, None, breaker, single)
+    }
+
+    /// Drive a connection. With the `shared` writer, request bodies can send
+    /// `100 Continue` when first read. Without it, it is sent before calling the handler.
+    fn drive_shared(
+        &self,
+        state: S,
+        reader: impl io::Read + Send + 'static,
         writer: &mut dyn io::Write,
+        shared: Option<SharedWriter>,
+        breaker: &dyn Breaker,
         single: bool,
     ) -> Result<(), Error>
     where
         S: Clone,
     {
-        let Some(mut request) = read_request(reader)? else {
+        let transport_info = breaker.transport_info();
+
+        let tap = match &self.wire_hook {
+            Some(make) => make(&transport_info),
+            None => Tap::default(),
+        };
+
+        let first = read_request_max(reader, self.config.max_buffer, tap.clone());
+        let Some(mut request) = answer_bad_request(first, writer)? else {
             return Ok(());
         };
 
+        let mut write_buf = vec![0_u8; 1024];
+        let mut served = 0;
+
+        // One request at a time, which keeps the responses to pipelined requests in order.
         loop {
+            served += 1;
+            // Until the response is written, for shutdown to wait on.
+            let active = self.shutdown.enter();
+
             let request_method = request.method().clone();
-            let request_version = request.version().clone();
+            let request_version = request.version();
+            let client_keep_alive = wants_keep_alive(request_version, request.headers());
 
-            // This is a cheap clone using Rc. This is so we can retain the HootBody
-            // for consecutive requests. After this line we have two instances of Rc
+            // This is a cheap clone using Arc. This is so we can retain the HootBody
+            // for consecutive requests. After this line we have two instances of Arc
             // to the same HootBody.
             let body = request.body().hoot_clone();
 
-            // The call consumes the Rc instance in Request<Body>, leaving a single
-            // Rc in the "body" var. Body is deliberately !Send, which means the,
-            // Handlers nested in the call cannot retain the copy to the Rc<HootBody>.
-            let response = self.call(state.clone(), request);
+            // For handlers taking over the connection.
+            let slot = HijackSlot::default();
+            request.extensions_mut().insert(slot.clone());
+            request.extensions_mut().insert(transport_info.clone());
+            request.body().set_limit(self.config.body_limit);
+
+            // The client waits for 100 Continue before sending the body.
+            let expects = expects_continue(request_version, request.headers());
+            let owed = match &shared {
+                Some(w) if expects && self.config.expect_continue => {
+                    let owed = Continue::new(w.clone());
+                    request.body().set_continue(owed.clone());
+                    Some(owed)
+                }
+                _ => None,
+            };
+
+            // The call consumes the Arc instance in Request<Body>. The response can
+            // forward the request body.
+            let response = match self.in_flight.try_enter(self.config.max_in_flight) {
+                Some(_) if expects && !self.config.expect_continue => {
+                    debug!("Refusing Expect: 100-continue");
+                    drop(request);
+                    ExpectationFailed.into_response()
+                }
+                Some(_guard) => {
+                    if expects && shared.is_none() {
+                        write_continue(writer, &mut tap.clone())?;
+                    }
+                    self.call(state.clone(), request)
+                }
+                None => {
+                    debug!("Too many requests in flight, shedding load");
+                    // Let go of the body, for the connection to continue.
+                    drop(request);
+                    let mut response = ServiceUnavailable(self.config.retry_after).into_response();
+                    // Without 100 Continue, the client might send the body, or not.
+                    if expects {
+                        let close = HeaderValue::from_static("close");
+                        response.headers_mut().insert("connection", close);
+                    }
+                    response
+                }
+            };
+
+            // Too late for 100 Continue once the response is written. The client might
+            // still send the body, or not, so the connection can't continue.
+            let unsent = owed.map(|c| c.cancel()).unwrap_or(false);
+            if unsent {
+                debug!("Body not read, no 100 Continue sent");
+            }
+
+            if let Some(take_over) = slot.take() {
+                write_response_head(
+                    request_method,
+                    request_version,
+                    response,
+                    writer,
+                    &mut write_buf,
+                    tap.clone(),
+                )?;
+
+                let Some(hoot_body) = body.hoot_unwrap() else {
+                    debug!("request body retained by handler, closing connection");
+                    return Ok(());
+                };
+
+                let (_, fill_buf) = hoot_body.into_buffers()?;
+                let (buffered, reader) = fill_buf.into_parts();
+
+                drop(active);
+                debug!("Connection taken over by handler");
+                take_over(Connection {
+                    buffered,
+                    reader: reader.unwrap_or_else(|| Box::new(io::empty())),
+                    writer,
+                    breaker,
+                });
 
-            // This should succeed because there should be only one Rc.
-            let hoot_body = body.hoot_unwrap();
+                return Ok(());
+            }
 
-            // Get the buffers back to reuse for next request.
-            let (mut parse_buf, fill_buf) = hoot_body.into_buffers();
+            let is_connect = request_method == Method::CONNECT;
+
+            let at_max = self
+                .config
+                .max_requests
+                .map(|m| served >= m)
+                .unwrap_or(false);
+            let handler_close = has_token(response.headers(), "close");
+
+            let keep_alive = self.config.keep_alive
+                && client_keep_alive
+                && !is_close_delimited(request_version, &response)
+                && !handler_close
+                && !at_max
+                && !unsent
+                && !self.shutdown.is_shutdown();
+
+            let mut response = response;
+            if !keep_alive {
+                let close = HeaderValue::from_static("close");
+                response.headers_mut().insert("connection", close);
+            } else if request_version == http::Version::HTTP_10 {
+                // HTTP/1.0 closes unless told otherwise.
+                let keep = HeaderValue::from_static("keep-alive");
+                response.headers_mut().insert("connection", keep);
+            }
 
             write_response_with_buffer(
                 request_method,
                 request_version,
                 response,
                 writer,
-                &mut parse_buf,
+                &mut write_buf,
+                tap.clone(),
             )?;
 
-            if single {
+            drop(active);
+
+            // A client may send tunnel data right after CONNECT, without waiting for the
+            // response. When the tunnel isn't set up, that input can't be read as requests.
+            if is_connect {
+                debug!("CONNECT without tunnel, closing connection");
+                return Ok(());
+            }
+
+            // Now that the response, possibly holding the request body, is written, there
+            // should be only one Arc. If a handler kept the body, say by moving it to
+            // another thread, the connection can't continue.
+            let Some(hoot_body) = body.hoot_unwrap() else {
+                debug!("request body retained by handler, closing connection");
+                return Ok(());
+            };
+
+            if single || !keep_alive {
                 return Ok(());
             }
 
-            let Some(next_request) = read_from_buffers(parse_buf, fill_buf)? else {
+            // Get the buffers back to reuse for next request. This skips any body
+            // the handler didn't read.
+            let (parse_buf, mut fill_buf) = hoot_body.into_buffers()?;
+
+            fill_buf.shrink();
+
+            // Waiting for the next request is limited by the idle timeout.
+            if let Some(idle) = self.config.idle_timeout {
+                breaker.set_read_timeout(Some(idle))?;
+            }
+
+            let next_request = match read_from_buffers(parse_buf, fill_buf, tap.clone()) {
+                Err(Error::Io(e)) if is_timeout(&e) => {
+                    debug!("Idle connection timed out");
+                    return Ok(());
+                }
+                r => r,
+            };
+
+            if self.config.idle_timeout.is_some() {
+                breaker.set_read_timeout(self.config.read_timeout)?;
+            }
+
+            let Some(next_request) = answer_bad_request(next_request, writer)? else {
                 break;
             };
 
@@ -165,27 +622,152 @@ impl<S, P: Callable<S>> Service<S, P> {
         Ok(())
     }
 
+    /// Answer the first request with 503, for a connection over the cap.
+    fn shed(
+        &self,
+        reader: impl io::Read + Send + 'static,
+        writer: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        debug!("Too many connections, shedding load");
+
+        let request = read_request_max(reader, self.config.max_buffer, Tap::default());
+        let Some(request) = answer_bad_request(request, writer)? else {
+            return Ok(());
+        };
+
+        let mut response = ServiceUnavailable(self.config.retry_after).into_response();
+        response
+            .headers_mut()
+            .insert("connection", HeaderValue::from_static("close"));
+
+        write_response(
+            request.method().clone(),
+            request.version(),
+            response,
+            writer,
+        )
+    }
+
+    /// Listen on the address of the [`ServerConfig`], and [`run()`][Self::run].
+    pub fn serve(&self, state: S) -> Result<(), Error>
+    where
+        S: Clone + Send + 'static,
+        P: Send + 'static,
+    {
+        let acceptor = self.config.bind()?;
+        self.run(state, acceptor)
+    }
+
+    /// Serve the connections of the acceptor, each on a thread of its own, or on the
+    /// [`workers()`][ServerConfig::workers] when set.
+    ///
+    /// The requests of a connection are handled one at a time, in the order they arrive.
+    /// A pipelined request isn't read until the response to the one before it, including
+    /// a streamed body, is written. That way the responses are always in request order,
+    /// however long each handler takes.
+    ///
+    /// Returns once stopped with the [`shutdown_handle()`][Self::shutdown_handle].
     pub fn run<A>(&self, state: S, mut acceptor: A) -> Result<(), Error>
     where
         S: Clone + Send + 'static,
         P: Send + 'static,
         A: Acceptor,
     {
-        loop {
-            let (reader, mut writer, _breaker) = acceptor.accept()?;
+        self.shutdown.wake_on(acceptor.local_addr());
 
+        let pool = self.config.workers.map(|workers| {
+            let queue = self.config.worker_queue.unwrap_or(workers);
             let service = self.clone();
+            Pool::new(
+                workers,
+                queue,
+                move |(state, reader, writer, breaker, guard)| {
+                    service.connection(state, reader, writer, breaker, guard)
+                },
+            )
+        });
+
+        // Connections shed for busy workers, each on a thread of its own.
+        let shedding = Gauge::default();
+
+        loop {
+            if self.shutdown.is_shutdown() {
+                break;
+            }
+
+            let accepted = acceptor.accept();
+
+            // Connections after shutdown, including the one waking up accept, are dropped.
+            if self.shutdown.is_shutdown() {
+                break;
+            }
+
+            let (reader, writer, breaker) = accepted?;
+
             let state = state.clone();
+            let guard = self.connections.try_enter(self.config.max_connections);
 
-            thread::spawn(move || {
-                if let Err(e) = service.drive(state, reader, &mut writer, false) {
-                    match e {
-                        Error::Hoot(e) => error!("service error: {}", e),
-                        Error::Io(e) => debug!("client disconnect: {}", e),
-                        Error::Utf8(e) => debug!("{:?}", e),
-                    }
-                }
-            });
+            let Some(pool) = &pool else {
+                let service = self.clone();
+                thread::spawn(move || service.connection(state, reader, writer, breaker, guard));
+                continue;
+            };
+
+            if let Err((state, reader, writer, breaker, _)) =
+                pool.try_send((state, reader, writer, breaker, guard))
+            {
+                // No more threads shedding than there are workers.
+                let Some(guard) = shedding.try_enter(self.config.workers) else {
+                    debug!("All workers busy, closing connection");
+                    let _ = breaker.disconnect();
+                    continue;
+                };
+
+                debug!("All workers busy");
+                let _ = breaker.set_read_timeout(Some(SHED_READ_TIMEOUT));
+                let service = self.clone();
+                thread::spawn(move || {
+                    let _guard = guard;
+                    service.connection(state, reader, writer, breaker, None)
+                });
+            }
+        }
+
+        debug!("Shut down, no longer accepting connections");
+        Ok(())
+    }
+
+    /// Serve one connection, or shed it without a `guard` for the connection cap.
+    fn connection<R, W, B>(
+        &self,
+        state: S,
+        reader: R,
+        mut writer: W,
+        breaker: B,
+        guard: Option<GaugeGuard>,
+    ) where
+        S: Clone,
+        R: io::Read + Send + 'static,
+        W: io::Write + Send + 'static,
+        B: Breaker,
+    {
+        let result = match guard {
+            Some(_guard) => {
+                let shared = SharedWriter::new(writer);
+                let mut writer = shared.clone();
+                self.drive_shared(state, reader, &mut writer, Some(shared), &breaker, false)
+            }
+            None => self.shed(reader, &mut writer),
+        };
+
+        if let Err(e) = result {
+            match e {
+                Error::Hoot(e) => error!("service error: {}", e),
+                Error::BadRequest(e) => debug!("bad request: {}", e),
+                Error::Io(e) => debug!("client disconnect: {}", e),
+                Error::Utf8(e) => debug!("{:?}", e),
+                Error::Config(..) => unreachable!("config error from connection"),
+            }
         }
     }
 
@@ -195,12 +777,12 @@ impl<S, P: Callable<S>> Service<S, P> {
         P: 'static,
         A: Acceptor,
     {
-        let (reader, mut writer, _breaker) = acceptor.accept()?;
+        let (reader, mut writer, breaker) = acceptor.accept()?;
 
         let service = self.clone();
         let state = state.clone();
 
-        service.drive(state, reader, &mut writer, true)?;
+        service.drive(state, reader, &mut writer, &breaker, true)?;
 
         Ok(writer)
     }
@@ -212,7 +794,7 @@ impl<S> MethodRouter<S> for Router<S> {
         method: Method,
         path: &str,
         handler: H,
-    ) -> MethodHandler<T, S, H, Self> {
+    ) -> MethodHandler<'_, T, S, H, Self> {
         MethodHandler {
             _htype: PhantomData,
             _state: PhantomData,
@@ -241,9 +823,18 @@ impl<'a, T, S, H: Handler<T, S>, P: Callable<S>> Callable<S> for MethodHandler<'
             CallResult::Handled(r) => CallResult::Handled(r),
 
             // Parent did not handle request
-            CallResult::Unhandled(state, request) => {
+            CallResult::Unhandled(state, mut request) => {
                 // Try to match to our path
-                if request_matcher(&request, &self.method, self.path) {
+                if request_matcher(&mut request, &self.method, self.path) {
+                    if let Some(max) = self.handler.body_limit() {
+                        request.body().set_limit(Some(max));
+                    }
+
+                    if request.body().is_over_limit() {
+                        debug!("Request body declared over limit");
+                        return CallResult::Handled(PayloadTooLarge.into_response());
+                    }
+
                     // Run our handler
                     let result = self.handler.clone().call(state, request);
 
@@ -256,21 +847,145 @@ impl<'a, T, S, H: Handler<T, S>, P: Callable<S>> Callable<S> for MethodHandler<'
             }
         }
     }
+
+    fn methods(&self, methods: &mut Vec<Method>) {
+        self.parent.methods(methods);
+        if !methods.contains(&self.method) {
+            methods.push(self.method.clone());
+        }
+    }
+
+    #[cfg(feature = "openapi")]
+    fn routes(&self, prefix: &str, routes: &mut Vec<(Method, String)>) {
+        self.parent.routes(prefix, routes);
+        let path = if self.path.is_empty() {
+            prefix
+        } else {
+            self.path
+        };
+        routes.push((self.method.clone(), path.to_string()));
+    }
 }
 
-fn request_matcher(_request: &Request, _method: &Method, _path: &str) -> bool {
-    true
+/// Handlers for one path, one per method.
+///
+/// ```
+/// use usrv::{get, MethodRouter, Router};
+///
+/// let service = Router::new()
+///     .route("/item", get(|| "item").put(|| "updated").delete(|| "deleted"))
+///     .finish();
+/// ```
+pub struct Methods<S, C> {
+    _state: PhantomData<S>,
+    inner: C,
 }
 
-impl<'a, T1, S, H1: Handler<T1, S>, P1: Callable<S>> MethodRouter<S>
-    for MethodHandler<'a, T1, S, H1, P1>
-{
+macro_rules! methods {
+    ($($meth:ident, $meth_up:ident);*) => {
+        #[allow(private_bounds)]
+        impl<S, C: MethodRouter<S>> Methods<S, C> {
+            $(
+            pub fn $meth<T, H: Handler<T, S>>(
+                self,
+                handler: H,
+            ) -> Methods<S, MethodHandler<'static, T, S, H, C>> {
+                Methods {
+                    _state: PhantomData,
+                    inner: self.inner.handle(Method::$meth_up, "", handler),
+                }
+            }
+            )*
+        }
+
+        $(
+        /// Start [`Methods`] with a handler for this method.
+        pub fn $meth<T, S, H: Handler<T, S>>(
+            handler: H,
+        ) -> Methods<S, MethodHandler<'static, T, S, H, Router<S>>> {
+            let methods = Methods {
+                _state: PhantomData,
+                inner: Router::with_state::<S>(),
+            };
+            methods.$meth(handler)
+        }
+        )*
+    };
+}
+
+methods!(
+    get, GET;
+    post, POST;
+    put, PUT;
+    delete, DELETE;
+    head, HEAD;
+    options, OPTIONS;
+    connect, CONNECT;
+    patch, PATCH;
+    trace, TRACE
+);
+
+pub struct Route<'a, S, C, P> {
+    _state: PhantomData<S>,
+    parent: P,
+    path: &'a str,
+    methods: C,
+}
+
+impl<'a, S, C: Callable<S>, P: Callable<S>> Callable<S> for Route<'a, S, C, P> {
+    fn call(&self, state: S, request: Request) -> CallResult<S> {
+        let (state, mut request) = match self.parent.call(state, request) {
+            CallResult::Handled(r) => return CallResult::Handled(r),
+            CallResult::Unhandled(state, request) => (state, request),
+        };
+
+        if !path_matcher(&mut request, self.path) {
+            return CallResult::Unhandled(state, request);
+        }
+
+        match self.methods.call(state, request) {
+            CallResult::Handled(r) => CallResult::Handled(r),
+
+            // Methods not recognized at all are left for the service to answer with 501.
+            CallResult::Unhandled(state, request)
+                if MethodClass::of(request.method().as_str()) == MethodClass::Unrecognized =>
+            {
+                CallResult::Unhandled(state, request)
+            }
+
+            CallResult::Unhandled(_, _) => {
+                let mut allowed = vec![];
+                self.methods.methods(&mut allowed);
+                CallResult::Handled(MethodNotAllowed(allowed).into_response())
+            }
+        }
+    }
+
+    fn methods(&self, methods: &mut Vec<Method>) {
+        self.parent.methods(methods);
+        let mut own = vec![];
+        self.methods.methods(&mut own);
+        for m in own {
+            if !methods.contains(&m) {
+                methods.push(m);
+            }
+        }
+    }
+
+    #[cfg(feature = "openapi")]
+    fn routes(&self, prefix: &str, routes: &mut Vec<(Method, String)>) {
+        self.parent.routes(prefix, routes);
+        self.methods.routes(self.path, routes);
+    }
+}
+
+impl<'a, S, C: Callable<S>, P: Callable<S>> MethodRouter<S> for Route<'a, S, C, P> {
     fn handle<T, H: Handler<T, S>>(
         self,
         method: Method,
         path: &str,
         handler: H,
-    ) -> MethodHandler<T, S, H, Self> {
+    ) -> MethodHandler<'_, T, S, H, Self> {
         MethodHandler {
             _htype: PhantomData,
             _state: PhantomData,
@@ -282,43 +997,160 @@ impl<'a, T1, S, H1: Handler<T1, S>, P1: Callable<S>> MethodRouter<S>
     }
 }
 
-impl<S> Clone for Router<S> {
+impl<'a, S, C: Clone, P: Clone> Clone for Route<'a, S, C, P> {
     fn clone(&self) -> Self {
         Self {
             _state: PhantomData,
+            parent: self.parent.clone(),
+            path: self.path,
+            methods: self.methods.clone(),
         }
     }
 }
 
-impl<S, P: Clone> Clone for Service<S, P> {
-    fn clone(&self) -> Self {
-        Self {
-            _state: PhantomData,
-            parent: self.parent.clone(),
+/// Answer a request head that couldn't be read with 400, 414 or 431, see
+/// [`BadRequest`], or 501 for an unrecognized method, after which the connection is
+/// closed, since the rest of the request can't be read.
+fn answer_bad_request(
+    request: Result<Option<Request>, Error>,
+    writer: &mut dyn io::Write,
+) -> Result<Option<Request>, Error> {
+    let mut response = match request {
+        Err(Error::Hoot(HootError::UnknownMethod)) => {
+            debug!("Unknown method");
+            NotImplemented.into_response()
         }
-    }
+        Err(Error::BadRequest(bad)) => {
+            debug!("Bad request: {}", bad);
+            bad.into_response()
+        }
+        r => return r,
+    };
+
+    response
+        .headers_mut()
+        .insert("connection", HeaderValue::from_static("close"));
+    write_response(Method::GET, http::Version::HTTP_11, response, writer)?;
+
+    Ok(None)
 }
 
-impl<'a, T, S, H: Clone, P: Clone> Clone for MethodHandler<'a, T, S, H, P> {
-    fn clone(&self) -> Self {
-        Self {
-            _htype: PhantomData,
-            _state: PhantomData,
-            parent: self.parent.clone(),
-            method: self.method.clone(),
-            path: self.path,
-            handler: self.handler.clone(),
-        }
+/// Whether the client keeps the connection open after the response, as of RFC 9112.
+fn wants_keep_alive(version: http::Version, headers: &http::HeaderMap) -> bool {
+    if has_token(headers, "close") {
+        return false;
     }
+    version != http::Version::HTTP_10 || has_token(headers, "keep-alive")
 }
 
-#[cfg(test)]
-mod test {
-    use crate::server::test::TestAcceptor;
+/// Whether the `Connection` header holds `token`.
+fn has_token(headers: &http::HeaderMap, token: &str) -> bool {
+    headers
+        .get_all("connection")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|t| t.trim().eq_ignore_ascii_case(token))
+}
 
-    use super::*;
+fn is_timeout(e: &io::Error) -> bool {
+    // Which of the two depends on the platform.
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
 
-    #[test]
+fn request_matcher(request: &mut Request, method: &Method, path: &str) -> bool {
+    request.method() == method && path_matcher(request, path)
+}
+
+/// Match the request path to a route pattern, keeping the captured segments for [`Path`].
+///
+/// [`Path`]: crate::Path
+fn path_matcher(request: &mut Request, pattern: &str) -> bool {
+    // Handlers in Methods have no pattern of their own, the Route matched already.
+    if pattern.is_empty() {
+        return true;
+    }
+
+    let Some(params) = match_path(pattern, request.uri().path()) else {
+        return false;
+    };
+
+    request.extensions_mut().insert(params);
+    true
+}
+
+impl<'a, T1, S, H1: Handler<T1, S>, P1: Callable<S>> MethodRouter<S>
+    for MethodHandler<'a, T1, S, H1, P1>
+{
+    fn handle<T, H: Handler<T, S>>(
+        self,
+        method: Method,
+        path: &str,
+        handler: H,
+    ) -> MethodHandler<'_, T, S, H, Self> {
+        MethodHandler {
+            _htype: PhantomData,
+            _state: PhantomData,
+            parent: self,
+            method,
+            path,
+            handler,
+        }
+    }
+}
+
+impl<S> Clone for Router<S> {
+    fn clone(&self) -> Self {
+        Self {
+            _state: PhantomData,
+        }
+    }
+}
+
+impl<S, P: Clone> Clone for Service<S, P> {
+    fn clone(&self) -> Self {
+        Self {
+            _state: PhantomData,
+            parent: self.parent.clone(),
+            config: self.config.clone(),
+            connect_proxy: self.connect_proxy.clone(),
+            well_known: self.well_known.clone(),
+            shutdown: self.shutdown.clone(),
+            #[cfg(feature = "openapi")]
+            openapi: self.openapi.clone(),
+            in_flight: self.in_flight.clone(),
+            connections: self.connections.clone(),
+            wire_hook: self.wire_hook.clone(),
+            #[cfg(feature = "compression")]
+            compression: self.compression.clone(),
+        }
+    }
+}
+
+impl<'a, T, S, H: Clone, P: Clone> Clone for MethodHandler<'a, T, S, H, P> {
+    fn clone(&self) -> Self {
+        Self {
+            _htype: PhantomData,
+            _state: PhantomData,
+            parent: self.parent.clone(),
+            method: self.method.clone(),
+            path: self.path,
+            handler: self.handler.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::server::test::TestAcceptor;
+    use crate::Body;
+
+    use super::*;
+
+    #[test]
     fn make_route() {
         //
         struct AppState;
@@ -351,6 +1183,1148 @@ mod test {
         let _response = cloned.call(&mut state, request);
     }
 
+    #[test]
+    fn options_asterisk() {
+        fn handle(_req: Request) {}
+
+        let service = Router::new()
+            //
+            .get("/", handle)
+            .post("/", handle)
+            .get("/other", handle)
+            .finish();
+
+        let request = http::Request::options("*").body(().into()).unwrap();
+        let response = service.call((), request);
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["allow"], "GET, POST, OPTIONS");
+    }
+
+    #[test]
+    fn unhandled_method() {
+        fn handle(_req: Request) {}
+
+        let service = Router::new()
+            //
+            .get("/", handle)
+            .post("/", handle)
+            .finish();
+
+        let request = http::Request::delete("/").body(().into()).unwrap();
+        let response = service.call((), request);
+        assert_eq!(response.status(), 405);
+        assert_eq!(response.headers()["allow"], "GET, POST, OPTIONS");
+
+        let method = Method::from_bytes(b"PROPFIND").unwrap();
+        let request = http::Request::builder()
+            .method(method)
+            .uri("/")
+            .body(().into())
+            .unwrap();
+        let response = service.call((), request);
+        assert_eq!(response.status(), 501);
+    }
+
+    #[test]
+    fn bad_request() {
+        fn handle(_req: Request) {}
+
+        let service = Router::new().get("/", handle).finish();
+
+        let run = |input: &str| {
+            let reader = io::Cursor::new(input.as_bytes().to_vec());
+            let mut output = vec![];
+            service.drive((), reader, &mut output, &(), false).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let output = run("GET / HTTP/1.1\r\nHost: a\r\nBad Name: x\r\n\r\nGET / HTTP/1.1\r\n\r\n");
+        assert!(output.starts_with("HTTP/1.1 400"));
+        assert!(output.contains("connection: close\r\n"));
+        assert!(output.ends_with("\r\n\r\nBad request: invalid header name at byte 28\n"));
+
+        let output = run("GET / HTTP/1.1\r\n\r\n");
+        assert!(output.ends_with("Bad request: http1.1 request without host header\n"));
+
+        let long = format!("GET /{} HTTP/1.1\r\nHost: a\r\n\r\n", "x".repeat(9000));
+        assert!(run(&long).starts_with("HTTP/1.1 414"));
+
+        let service = Router::new().get("/", handle).finish().max_buffer(4096);
+        let large = format!(
+            "GET / HTTP/1.1\r\nHost: a\r\nX: {}\r\n\r\n",
+            "x".repeat(5000)
+        );
+        let reader = io::Cursor::new(large.into_bytes());
+        let mut output = vec![];
+        service.drive((), reader, &mut output, &(), false).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("HTTP/1.1 431"));
+        assert!(output.contains("connection: close\r\n"));
+
+        let bad = crate::read_request(io::Cursor::new(
+            b"GET /x HTTP/1.1\r\nX: \x01\r\n\r\n".to_vec(),
+        ));
+        let Err(Error::BadRequest(bad)) = bad else {
+            panic!("expected bad request");
+        };
+        assert_eq!(bad.method.as_deref(), Some("GET"));
+        assert_eq!(bad.path.as_deref(), Some("/x"));
+        assert_eq!(bad.offset, Some(20));
+        assert_eq!(
+            bad.to_string(),
+            "invalid header value at byte 20, in GET /x"
+        );
+    }
+
+    #[test]
+    fn forward_body() {
+        fn echo(req: Request) -> Body {
+            req.into_body()
+        }
+
+        let service = Router::new()
+            //
+            .post("/echo", echo)
+            .finish();
+
+        let request = http::Request::post("/echo").body("hello").unwrap();
+        let mut acceptor = TestAcceptor::new(request);
+
+        let writer = service.execute((), &mut acceptor).unwrap();
+        let response = writer.into_response().unwrap();
+        assert_eq!(response.headers()["content-length"], "5");
+
+        let body = response.into_body().into_string(20).unwrap();
+        assert_eq!(body, "hello");
+    }
+
+    #[test]
+    fn body_is_send() {
+        fn is_send<T: Send>() {}
+        is_send::<Body>();
+        is_send::<Request>();
+        is_send::<Response>();
+
+        // A handler can hand the body over to another thread.
+        fn spawned(req: Request) -> String {
+            let body = req.into_body();
+            thread::spawn(move || body.into_string(20).unwrap())
+                .join()
+                .unwrap()
+        }
+
+        let service = Router::new()
+            //
+            .post("/", spawned)
+            .finish();
+
+        let request = http::Request::post("/").body("hello").unwrap();
+        let mut acceptor = TestAcceptor::new(request);
+
+        let writer = service.execute((), &mut acceptor).unwrap();
+        let body = writer.into_response().unwrap().into_body();
+        assert_eq!(body.into_string(20).unwrap(), "hello");
+    }
+
+    #[test]
+    fn pipelined_after_body() {
+        fn post(req: Request) -> String {
+            if req.uri() == "/read" {
+                req.into_body().into_string(20).unwrap()
+            } else {
+                "unread".into()
+            }
+        }
+
+        let service = Router::new()
+            //
+            .post("/unread", post)
+            .post("/read", post)
+            .get("/", |_r: Request| "last")
+            .finish();
+
+        // Chunked bodies with trailers, one left unread by the handler, followed
+        // by more requests in the same input.
+        let input = "POST /unread HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n\
+            3\r\nabc\r\n0\r\nX-Sum: 1\r\n\r\n\
+            POST /read HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5\r\nhello\r\n0\r\nX-Sum: 2\r\n\r\n\
+            GET / HTTP/1.1\r\nHost: a\r\n\r\n";
+
+        let reader = io::Cursor::new(input.as_bytes().to_vec());
+        let mut output = vec![];
+        service.drive((), reader, &mut output, &(), false).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let bodies: Vec<_> = output
+            .split("HTTP/1.1 ")
+            .skip(1)
+            .map(|r| r.split("\r\n\r\n").nth(1).unwrap())
+            .collect();
+        assert_eq!(bodies, ["unread", "hello", "last"]);
+    }
+
+    #[test]
+    fn route_methods() {
+        let service = Router::new()
+            //
+            .get("/other", || "other")
+            .route("/item", get(|| "got").delete(|| "deleted"))
+            .finish();
+
+        let call = |method: Method, path: &str| {
+            let request = http::Request::builder()
+                .method(method)
+                .uri(path)
+                .body(().into())
+                .unwrap();
+            service.call((), request)
+        };
+
+        let body = call(Method::DELETE, "/item").into_body();
+        assert_eq!(body.into_string(20).unwrap(), "deleted");
+
+        let response = call(Method::PUT, "/item");
+        assert_eq!(response.status(), 405);
+        assert_eq!(response.headers()["allow"], "GET, DELETE");
+
+        let response = call(Method::from_bytes(b"PROPFIND").unwrap(), "/item");
+        assert_eq!(response.status(), 501);
+
+        let allowed = service.allowed_methods();
+        assert_eq!(allowed, [Method::GET, Method::DELETE, Method::OPTIONS]);
+    }
+
+    #[test]
+    fn path_params() {
+        use std::collections::HashMap;
+
+        use crate::Path;
+
+        fn user(Path(id): Path<u64>) -> String {
+            format!("user {}", id)
+        }
+
+        fn file(Path((id, name)): Path<(u64, String)>) -> String {
+            format!("file {} {}", id, name)
+        }
+
+        fn named(Path(map): Path<HashMap<String, String>>) -> String {
+            format!("{} {}", map["a"], map["b"])
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Post {
+            year: u16,
+            slug: String,
+        }
+
+        fn post(Path(post): Path<Post>) -> String {
+            format!("{} {}", post.year, post.slug)
+        }
+
+        let service = Router::new()
+            //
+            .route("/users/:id", get(user).delete(|| "deleted"))
+            .get("/users/:id/files/*name", file)
+            .get("/named/:a/:b", named)
+            .get("/posts/:year/:slug", post)
+            .get("/", || "root")
+            .finish();
+
+        let call = |method: Method, path: &str| {
+            let request = http::Request::builder()
+                .method(method)
+                .uri(path)
+                .body(().into())
+                .unwrap();
+            let response = service.call((), request);
+            let status = response.status().as_u16();
+            (status, response.into_body().into_string(100).unwrap())
+        };
+
+        assert_eq!(call(Method::GET, "/users/42"), (200, "user 42".into()));
+        assert_eq!(call(Method::DELETE, "/users/42").1, "deleted");
+        assert_eq!(call(Method::GET, "/users/x").0, 400);
+        assert_eq!(call(Method::GET, "/users/").0, 404);
+        assert_eq!(call(Method::GET, "/users/42/more").0, 404);
+        assert_eq!(call(Method::GET, "/").1, "root");
+        assert_eq!(call(Method::GET, "/nope").0, 404);
+
+        let (_, body) = call(Method::GET, "/users/7/files/a/b%20c.txt");
+        assert_eq!(body, "file 7 a/b c.txt");
+
+        assert_eq!(call(Method::GET, "/named/x/y").1, "x y");
+
+        assert_eq!(call(Method::GET, "/posts/2024/hi").1, "2024 hi");
+        assert_eq!(call(Method::GET, "/posts/year/hi").0, 400);
+
+        // Only hex digits are escapes.
+        let (_, body) = call(Method::GET, "/users/7/files/%+1%2b");
+        assert_eq!(body, "file 7 %+1+");
+    }
+
+    #[test]
+    fn connect_proxy() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        use crate::ConnectProxy;
+
+        // Upstream echoing everything back, until the client is done.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut input = vec![];
+            stream.read_to_end(&mut input).unwrap();
+            stream.write_all(&input).unwrap();
+        });
+
+        let proxy = ConnectProxy::new()
+            .allow(|host, _| host == "127.0.0.1")
+            .realm("test")
+            .authenticate(|req| {
+                let auth = req.headers().get("proxy-authorization");
+                auth.map(|v| v == "Basic dXNlcjpwYXNz").unwrap_or(false)
+            });
+
+        let service = Router::new().finish().connect_proxy(proxy);
+        assert!(service.allowed_methods().contains(&Method::CONNECT));
+
+        let drive = |auth: &str, target: &str| {
+            let input =
+                format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n{auth}\r\ntunneled");
+            let reader = io::Cursor::new(input.into_bytes());
+            let mut output = vec![];
+            service.drive((), reader, &mut output, &(), false).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let auth = "Proxy-Authorization: Basic dXNlcjpwYXNz\r\n";
+
+        let output = drive(auth, &target.to_string());
+        let (head, rest) = output.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200"));
+        assert!(!head.contains("content-length"));
+        assert_eq!(rest, "tunneled");
+
+        let output = drive("", &target.to_string());
+        assert!(output.starts_with("HTTP/1.1 407"));
+        assert!(output.contains("proxy-authenticate: Basic realm=\"test\""));
+
+        // Nothing listens on a port, once the listener is dropped.
+        let closed = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+        let output = drive(auth, &closed.to_string());
+        assert!(output.starts_with("HTTP/1.1 502"));
+
+        // Only allowed targets.
+        let output = drive(auth, "localhost:22");
+        assert!(output.starts_with("HTTP/1.1 403"));
+
+        let service = Router::new().finish().connect_proxy(ConnectProxy::new());
+        let input = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n\r\n");
+        let mut output = vec![];
+        let reader = io::Cursor::new(input.into_bytes());
+        service.drive((), reader, &mut output, &(), false).unwrap();
+        assert!(output.starts_with(b"HTTP/1.1 403"));
+    }
+
+    #[test]
+    fn connect_proxy_upstream_closes() {
+        use std::io::{Read, Write};
+        use std::net::{TcpListener, TcpStream};
+        use std::sync::mpsc;
+
+        use crate::server::tcp::TcpAcceptor;
+        use crate::ConnectProxy;
+
+        // Upstream saying bye, and closing without waiting for the client.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"bye").unwrap();
+        });
+
+        let proxy = ConnectProxy::new().allow_any();
+        let service = Router::new().finish().connect_proxy(proxy);
+
+        let mut acceptor = TcpAcceptor::new(TcpListener::bind("127.0.0.1:0").unwrap());
+        let mut client = TcpStream::connect(acceptor.local_addr().unwrap()).unwrap();
+        let (reader, mut writer, breaker) = acceptor.accept().unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let result = service.drive((), reader, &mut writer, &breaker, false);
+            tx.send(result.is_ok()).unwrap();
+        });
+
+        write!(
+            client,
+            "CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n\r\n"
+        )
+        .unwrap();
+
+        // The client keeps its end open, and the tunnel still ends.
+        let done = rx.recv_timeout(Duration::from_secs(5));
+        assert_eq!(done, Ok(true));
+
+        let mut output = String::new();
+        client.read_to_string(&mut output).unwrap();
+        assert!(output.starts_with("HTTP/1.1 200"));
+        assert!(output.ends_with("\r\n\r\nbye"));
+    }
+
+    #[test]
+    fn shed_load() {
+        let input = "GET / HTTP/1.1\r\nHost: a\r\n\r\nGET / HTTP/1.1\r\nHost: a\r\n\r\n";
+
+        let run = |service: &Service<(), _>| {
+            let reader = io::Cursor::new(input.as_bytes().to_vec());
+            let mut output = vec![];
+            service.drive((), reader, &mut output, &(), false).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let service = Router::new().get("/", || "ok").finish().max_in_flight(1);
+        let output = run(&service);
+        assert_eq!(output.matches("HTTP/1.1 200").count(), 2);
+
+        let service = service.max_in_flight(0).retry_after(10);
+        let output = run(&service);
+        assert_eq!(output.matches("HTTP/1.1 503").count(), 2);
+        assert_eq!(output.matches("retry-after: 10\r\n").count(), 2);
+        assert!(!output.contains("ok"));
+
+        // Over the connection cap, only the first request is answered.
+        let reader = io::Cursor::new(input.as_bytes().to_vec());
+        let mut output = vec![];
+        service.shed(reader, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("HTTP/1.1 503").count(), 1);
+        assert!(output.contains("connection: close\r\n"));
+    }
+
+    #[test]
+    fn graceful_shutdown() {
+        use std::io::{Read, Write};
+        use std::net::{TcpListener, TcpStream};
+
+        use crate::server::tcp::TcpAcceptor;
+
+        fn slow() -> &'static str {
+            thread::sleep(Duration::from_millis(200));
+            "slow"
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let service = Router::new().get("/", slow).finish();
+        let handle = service.shutdown_handle();
+        let server = thread::spawn(move || service.run((), TcpAcceptor::new(listener)));
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nHost: a\r\n\r\n")
+            .unwrap();
+
+        // Shut down while the handler is running.
+        thread::sleep(Duration::from_millis(50));
+        assert!(handle.shutdown(Duration::from_secs(5)));
+        assert!(handle.is_shutdown());
+        server.join().unwrap().unwrap();
+
+        // The request in progress is answered, and the connection closed.
+        let mut output = String::new();
+        client.read_to_string(&mut output).unwrap();
+        assert!(output.starts_with("HTTP/1.1 200"));
+        assert!(output.contains("connection: close\r\n"));
+        assert!(output.ends_with("slow"));
+    }
+
+    #[test]
+    fn worker_pool() {
+        use std::io::{Read, Write};
+        use std::net::{TcpListener, TcpStream};
+
+        use crate::server::tcp::TcpAcceptor;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let config = ServerConfig::new().workers(1).worker_queue(0);
+        let service = Router::new().get("/", || "ok").finish().config(config);
+        let handle = service.shutdown_handle();
+        let server = thread::spawn(move || service.run((), TcpAcceptor::new(listener)));
+
+        let request = b"GET / HTTP/1.1\r\nHost: a\r\n\r\n";
+
+        // The only worker holds on to the kept alive connection.
+        let mut first = TcpStream::connect(addr).unwrap();
+        first.write_all(request).unwrap();
+        let mut buf = [0; 1024];
+        let n = first.read(&mut buf).unwrap();
+        assert!(buf[..n].starts_with(b"HTTP/1.1 200"));
+
+        let mut second = TcpStream::connect(addr).unwrap();
+        second.write_all(request).unwrap();
+        let mut output = String::new();
+        second.read_to_string(&mut output).unwrap();
+        assert!(output.starts_with("HTTP/1.1 503"));
+        assert!(output.contains("connection: close\r\n"));
+
+        // With the one thread for shedding busy, the next connection is closed right away.
+        thread::sleep(Duration::from_millis(50));
+        let idle = TcpStream::connect(addr).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        let mut closed = TcpStream::connect(addr).unwrap();
+        let _ = closed.write_all(request);
+        let mut output = String::new();
+        let _ = closed.read_to_string(&mut output);
+        assert_eq!(output, "");
+        drop(idle);
+
+        // Once the first connection closes, the worker is free again.
+        drop(first);
+        thread::sleep(Duration::from_millis(50));
+        let mut third = TcpStream::connect(addr).unwrap();
+        third
+            .write_all(b"GET / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut output = String::new();
+        third.read_to_string(&mut output).unwrap();
+        assert!(output.starts_with("HTTP/1.1 200"));
+
+        assert!(handle.shutdown(Duration::from_secs(5)));
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn no_keep_alive() {
+        let input = "GET / HTTP/1.1\r\nHost: a\r\n\r\nGET / HTTP/1.1\r\nHost: a\r\n\r\n";
+
+        let config = ServerConfig::new().keep_alive(false);
+        let service = Router::new().get("/", || "ok").finish().config(config);
+
+        let reader = io::Cursor::new(input.as_bytes().to_vec());
+        let mut output = vec![];
+        service.drive((), reader, &mut output, &(), false).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("HTTP/1.1 200").count(), 1);
+        assert!(output.contains("connection: close\r\n"));
+    }
+
+    #[test]
+    fn keep_alive_requests() {
+        let get = "GET / HTTP/1.1\r\nHost: a\r\n\r\n";
+        let post = "POST / HTTP/1.1\r\nHost: a\r\ncontent-length: 5\r\n\r\nhello";
+
+        let run = |service: &Service<(), _>, input: String| {
+            let reader = io::Cursor::new(input.into_bytes());
+            let mut output = vec![];
+            service.drive((), reader, &mut output, &(), false).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let service = Router::new().get("/", || "ok").post("/", || "ok").finish();
+
+        // Unread request bodies are skipped.
+        let output = run(&service, format!("{post}{post}{get}"));
+        assert_eq!(output.matches("HTTP/1.1 200").count(), 3);
+        assert!(!output.contains("connection:"));
+
+        // The client closes.
+        let close = "GET / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n";
+        let output = run(&service, format!("{close}{get}"));
+        assert_eq!(output.matches("HTTP/1.1 200").count(), 1);
+        assert!(output.contains("connection: close\r\n"));
+
+        // HTTP/1.0 keeps the connection only when asked.
+        let keep = "GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n";
+        let old = "GET / HTTP/1.0\r\n\r\n";
+        let output = run(&service, format!("{keep}{old}{get}"));
+        assert_eq!(output.matches("HTTP/1.0 200").count(), 2);
+        assert!(output.contains("connection: keep-alive\r\n"));
+        let (_, last) = output.rsplit_once("HTTP/1.0").unwrap();
+        assert!(last.contains("connection: close\r\n"));
+
+        // Up to the max per connection.
+        let config = ServerConfig::new().max_requests(2);
+        let service = service.config(config);
+        let output = run(&service, format!("{get}{get}{get}"));
+        assert_eq!(output.matches("HTTP/1.1 200").count(), 2);
+        assert_eq!(output.matches("connection: close\r\n").count(), 1);
+    }
+
+    #[test]
+    fn streaming_response() {
+        fn stream() -> Response {
+            Response::new(Body::streaming(&b"hello"[..]))
+        }
+
+        let service = Router::new().get("/", stream).finish();
+
+        let run = |input: &str| {
+            let reader = io::Cursor::new(input.as_bytes().to_vec());
+            let mut output = vec![];
+            service.drive((), reader, &mut output, &(), false).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let get = "GET / HTTP/1.1\r\nHost: a\r\n\r\n";
+        let output = run(&format!("{get}{get}"));
+        assert_eq!(output.matches("Transfer-Encoding: chunked\r\n").count(), 2);
+        assert!(output.ends_with("5\r\nhello\r\n0\r\n\r\n"));
+
+        // Without chunked, the end of the body is the end of the connection.
+        let get = "GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n";
+        let output = run(&format!("{get}{get}"));
+        assert_eq!(output.matches("HTTP/1.0 200").count(), 1);
+        assert!(output.contains("connection: close\r\n"));
+        assert!(!output.contains("chunked"));
+        assert!(output.ends_with("\r\n\r\nhello"));
+    }
+
+    #[test]
+    fn body_limit() {
+        use std::collections::HashMap;
+
+        use crate::{BodyLimit, Form};
+
+        fn form(Form(form): Form<HashMap<String, String>>) -> String {
+            form["a"].clone()
+        }
+
+        let service = Router::new()
+            .post("/", form)
+            .post("/big", BodyLimit::new(100, form))
+            .post("/small", BodyLimit::new(4, form))
+            .finish()
+            .body_limit(8);
+
+        let run = |path: &str, framing: &str, body: &str| {
+            let input = format!(
+                "POST {path} HTTP/1.1\r\nHost: a\r\n\
+                Content-Type: application/x-www-form-urlencoded\r\n{framing}\r\n\r\n{body}"
+            );
+            let reader = io::Cursor::new(input.into_bytes());
+            let mut output = vec![];
+            service.drive((), reader, &mut output, &(), true).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let output = run("/", "Content-Length: 6", "a=1234");
+        assert!(output.starts_with("HTTP/1.1 200"));
+        assert!(output.ends_with("1234"));
+
+        // Declared over the limit, the handler isn't called.
+        let output = run("/", "Content-Length: 12", "a=1234567890");
+        assert!(output.starts_with("HTTP/1.1 413"));
+        assert!(output.contains("connection: close\r\n"));
+
+        let output = run("/big", "Content-Length: 12", "a=1234567890");
+        assert!(output.ends_with("1234567890"));
+
+        // Chunked, found out while reading.
+        let chunked = "c\r\na=1234567890\r\n0\r\n\r\n";
+        let output = run("/", "Transfer-Encoding: chunked", chunked);
+        assert!(output.starts_with("HTTP/1.1 413"));
+
+        let output = run("/big", "Transfer-Encoding: chunked", chunked);
+        assert!(output.ends_with("1234567890"));
+
+        // A route limit below the one of the service.
+        let output = run("/small", "Content-Length: 6", "a=1234");
+        assert!(output.starts_with("HTTP/1.1 413"));
+
+        let output = run(
+            "/small",
+            "Transfer-Encoding: chunked",
+            "6\r\na=1234\r\n0\r\n\r\n",
+        );
+        assert!(output.starts_with("HTTP/1.1 413"));
+
+        let output = run("/small", "Content-Length: 4", "a=12");
+        assert!(output.ends_with("\r\n\r\n12"));
+    }
+
+    #[test]
+    fn expect_continue() {
+        use std::sync::Mutex;
+
+        use crate::expect::SharedWriter;
+
+        #[derive(Clone, Default)]
+        struct Output(Arc<Mutex<Vec<u8>>>);
+
+        impl io::Write for Output {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        fn echo(req: Request) -> String {
+            req.into_body().into_string(100).unwrap()
+        }
+
+        let service = Router::new()
+            .post("/echo", echo)
+            .post("/refuse", || "no")
+            .finish();
+
+        let run = |service: &Service<(), _>, path: &str| {
+            let input = format!(
+                "POST {} HTTP/1.1\r\nHost: a\r\nExpect: 100-continue\r\n\
+                content-length: 5\r\n\r\nhello",
+                path
+            );
+            let reader = io::Cursor::new(input.into_bytes());
+            let output = Output::default();
+            let shared = SharedWriter::new(output.clone());
+            service
+                .drive_shared((), reader, &mut shared.clone(), Some(shared), &(), false)
+                .unwrap();
+            let output = output.0.lock().unwrap().clone();
+            String::from_utf8(output).unwrap()
+        };
+
+        let output = run(&service, "/echo");
+        assert!(output.starts_with("HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 "));
+        assert!(output.ends_with("hello"));
+
+        // Not reading the body, there's no 100 Continue, and no reading what the client
+        // might send after.
+        let output = run(&service, "/refuse");
+        assert!(output.starts_with("HTTP/1.1 200 "));
+        assert!(output.contains("connection: close\r\n"));
+
+        // Without a shared writer, it is sent before calling the handler.
+        let input = "POST /refuse HTTP/1.1\r\nHost: a\r\nExpect: 100-Continue\r\n\r\n";
+        let reader = io::Cursor::new(input.as_bytes().to_vec());
+        let mut output = vec![];
+        service.drive((), reader, &mut output, &(), true).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 "));
+
+        // Shedding the request, there's no telling whether the body follows.
+        let input = "POST /refuse HTTP/1.1\r\nHost: a\r\nExpect: 100-continue\r\n\
+            content-length: 5\r\n\r\nGET / HTTP/1.1\r\nHost: a\r\n\r\n";
+        let reader = io::Cursor::new(input.as_bytes().to_vec());
+        let mut output = vec![];
+        let busy = service.clone().max_in_flight(0);
+        busy.drive((), reader, &mut output, &(), false).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("HTTP/1.1 503 "));
+        assert!(output.contains("connection: close\r\n"));
+        assert_eq!(output.matches("HTTP/1.1").count(), 1);
+
+        let service = service.expect_continue(false);
+        let output = run(&service, "/echo");
+        assert!(output.starts_with("HTTP/1.1 417 "));
+        assert!(output.contains("connection: close\r\n"));
+    }
+
+    #[test]
+    fn cookies() {
+        use crate::{Cookie, CookieJar, SameSite};
+
+        fn login(mut jar: CookieJar) -> (CookieJar, String) {
+            let seen = jar.get("theme").unwrap_or("none").to_string();
+            jar.add(
+                Cookie::new("session", "s1")
+                    .http_only()
+                    .same_site(SameSite::Strict),
+            );
+            jar.remove(Cookie::new("old", "").path("/"));
+            (jar, seen)
+        }
+
+        let service = Router::new().get("/", login).finish();
+
+        let input = "GET / HTTP/1.1\r\nHost: a\r\nCookie: theme=dark; old=1\r\n\r\n";
+        let reader = io::Cursor::new(input.as_bytes().to_vec());
+        let mut output = vec![];
+        service.drive((), reader, &mut output, &(), true).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("set-cookie: session=s1; HttpOnly; SameSite=Strict\r\n"));
+        assert!(output.contains("set-cookie: old=; Path=/; Max-Age=0\r\n"));
+        assert!(output.ends_with("\r\n\r\ndark"));
+    }
+
+    #[test]
+    fn wire_hook() {
+        use hoot::wire::{Direction, WirePart};
+        use std::io::Read;
+        use std::sync::Mutex;
+
+        fn echo(req: Request) -> String {
+            let mut body = String::new();
+            req.into_body().read_to_string(&mut body).unwrap();
+            body
+        }
+
+        let seen = Arc::new(Mutex::new(vec![]));
+        let tap = seen.clone();
+
+        let service =
+            Router::new()
+                .post("/", echo)
+                .finish()
+                .wire_hook(move |_info: &TransportInfo| {
+                    let seen = tap.clone();
+                    move |d: Direction, p: WirePart, b: &[u8]| {
+                        seen.lock().unwrap().push((d, p, b.to_vec()));
+                    }
+                });
+
+        let input = "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 5\r\n\r\nhello";
+        let reader = io::Cursor::new(input.as_bytes().to_vec());
+        let mut output = vec![];
+        service.drive((), reader, &mut output, &(), true).unwrap();
+
+        let seen = seen.lock().unwrap();
+        let joined = |dir: Direction, part: WirePart| {
+            let parts = seen.iter().filter(|(d, p, _)| *d == dir && *p == part);
+            parts.flat_map(|(_, _, b)| b.clone()).collect::<Vec<_>>()
+        };
+
+        use Direction::*;
+        assert_eq!(
+            joined(Received, WirePart::Head),
+            input.trim_end_matches("hello").as_bytes()
+        );
+        assert_eq!(joined(Received, WirePart::Body), b"hello");
+
+        let head = joined(Sent, WirePart::Head);
+        let body = joined(Sent, WirePart::Body);
+        assert!(head.ends_with(b"\r\n\r\n"));
+        assert_eq!(body, b"hello");
+        assert_eq!([head, body].concat(), output);
+    }
+
+    #[test]
+    fn transport_info() {
+        use crate::server::Breaker;
+        use crate::{ClientCert, TransportInfo};
+
+        struct Tls;
+
+        impl Breaker for Tls {
+            fn disconnect(self) -> io::Result<()> {
+                Ok(())
+            }
+
+            fn transport_info(&self) -> TransportInfo {
+                let mut info = TransportInfo::new();
+                info.tls_protocol = Some("TLSv1.3".into());
+                info.client_cert = Some(vec![0x30, 0x82]);
+                info
+            }
+        }
+
+        fn protocol(info: TransportInfo) -> String {
+            info.tls_protocol.unwrap_or_else(|| "plain".into())
+        }
+
+        fn admin(cert: ClientCert) -> String {
+            format!("{:?}", cert.0)
+        }
+
+        let service = Router::new()
+            .get("/", protocol)
+            .get("/admin", admin)
+            .finish();
+
+        let run = |breaker: &dyn Breaker| {
+            let input = "GET / HTTP/1.1\r\nHost: a\r\n\r\nGET /admin HTTP/1.1\r\nHost: a\r\n\r\n";
+            let reader = io::Cursor::new(input.as_bytes().to_vec());
+            let mut output = vec![];
+            service
+                .drive((), reader, &mut output, breaker, false)
+                .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let output = run(&Tls);
+        assert!(output.contains("\r\n\r\nTLSv1.3HTTP/1.1 200"));
+        assert!(output.ends_with("[48, 130]"));
+
+        // Without TLS, there is no client certificate.
+        let output = run(&());
+        assert!(output.contains("\r\n\r\nplainHTTP/1.1 403"));
+    }
+
+    #[test]
+    fn well_known_endpoints() {
+        use crate::WellKnown;
+
+        let well_known = WellKnown::new()
+            .health()
+            .security_txt("Contact: mailto:a@example.com\n");
+        let service = Router::new()
+            .get("/robots.txt", || "from route")
+            .finish()
+            .well_known(well_known);
+
+        let call = |method: Method, uri: &str| {
+            let request = http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(().into())
+                .unwrap();
+            service.call((), request)
+        };
+
+        let response = call(Method::GET, "/.well-known/health");
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.into_body().into_string(100).unwrap(), "ok");
+
+        let response = call(Method::HEAD, "/.well-known/security.txt");
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-length"], "30");
+
+        // Not set, left to the routes.
+        let response = call(Method::GET, "/robots.txt");
+        assert_eq!(response.into_body().into_string(100).unwrap(), "from route");
+
+        assert_eq!(call(Method::POST, "/.well-known/health").status(), 405);
+    }
+
+    #[test]
+    fn request_deadline() {
+        use crate::Deadline;
+
+        fn remaining(deadline: Deadline) -> String {
+            deadline.header_value().unwrap_or_else(|| "none".into())
+        }
+
+        let config = ServerConfig::new().deadline_header("grpc-timeout");
+        let service = Router::new().get("/", remaining).finish().config(config);
+
+        let call = |timeout: Option<&str>| {
+            let mut request = http::Request::get("/");
+            if let Some(t) = timeout {
+                request = request.header("grpc-timeout", t);
+            }
+            service.call((), request.body(().into()).unwrap())
+        };
+
+        let response = call(Some("5S"));
+        assert_eq!(response.status(), 200);
+        let value = response.into_body().into_string(100).unwrap();
+        let millis: u64 = value.strip_suffix('m').unwrap().parse().unwrap();
+        assert!(millis > 4000 && millis <= 5000);
+
+        let response = call(None);
+        assert_eq!(response.into_body().into_string(100).unwrap(), "none");
+
+        // Not a timeout, ignored.
+        let response = call(Some("soon"));
+        assert_eq!(response.into_body().into_string(100).unwrap(), "none");
+
+        assert_eq!(call(Some("0m")).status(), 504);
+    }
+
+    #[test]
+    fn pipelined_in_order() {
+        use std::sync::mpsc;
+
+        fn slow() -> &'static str {
+            thread::sleep(Duration::from_millis(50));
+            "slow"
+        }
+
+        // The body is produced on another thread, and ends after the handler returns.
+        fn streamed() -> Body {
+            let (tx, rx) = mpsc::channel::<Vec<u8>>();
+            thread::spawn(move || {
+                for part in ["str", "eam", "ed"] {
+                    thread::sleep(Duration::from_millis(10));
+                    tx.send(part.into()).unwrap();
+                }
+            });
+
+            struct Recv(mpsc::Receiver<Vec<u8>>);
+            impl io::Read for Recv {
+                fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                    let Ok(part) = self.0.recv() else {
+                        return Ok(0);
+                    };
+                    buf[..part.len()].copy_from_slice(&part);
+                    Ok(part.len())
+                }
+            }
+
+            Body::streaming(Recv(rx))
+        }
+
+        let service = Router::new()
+            //
+            .get("/slow", slow)
+            .get("/fast", || "fast")
+            .get("/streamed", streamed)
+            .finish();
+
+        let input = "GET /slow HTTP/1.1\r\nHost: a\r\n\r\n\
+            GET /streamed HTTP/1.1\r\nHost: a\r\n\r\n\
+            GET /fast HTTP/1.1\r\nHost: a\r\n\r\n\
+            GET /slow HTTP/1.1\r\nHost: a\r\n\r\n\
+            GET /fast HTTP/1.1\r\nHost: a\r\n\r\n";
+
+        let reader = io::Cursor::new(input.as_bytes().to_vec());
+        let mut output = vec![];
+        service.drive((), reader, &mut output, &(), false).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let bodies: Vec<_> = output
+            .split("HTTP/1.1 ")
+            .skip(1)
+            .map(|r| r.split("\r\n\r\n").nth(1).unwrap())
+            .collect();
+
+        // The streamed body is chunked.
+        assert_eq!(bodies[1], "3\r\nstr\r\n3\r\neam\r\n2\r\ned\r\n0");
+        let bodies = [bodies[0], bodies[2], bodies[3], bodies[4]];
+        assert_eq!(bodies, ["slow", "fast", "slow", "fast"]);
+    }
+
+    #[test]
+    fn hijack_connection() {
+        use crate::Hijack;
+
+        fn upgrade(hijack: Hijack) -> Response {
+            hijack.take_over(|mut conn| {
+                let mut input = conn.buffered;
+                conn.reader.read_to_end(&mut input).unwrap();
+                conn.writer.write_all(b"echo:").unwrap();
+                conn.writer.write_all(&input).unwrap();
+            });
+
+            http::Response::builder()
+                .status(101)
+                .header("upgrade", "echo")
+                .body(Body::empty())
+                .unwrap()
+        }
+
+        let service = Router::new()
+            //
+            .get("/", upgrade)
+            .finish();
+
+        let input = "GET / HTTP/1.1\r\nHost: a\r\nUpgrade: echo\r\n\r\nraw bytes";
+
+        let reader = io::Cursor::new(input.as_bytes().to_vec());
+        let mut output = vec![];
+        service.drive((), reader, &mut output, &(), false).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let (head, rest) = output.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 101"));
+        assert!(head.contains("upgrade: echo"));
+        assert!(!head.contains("content-length"));
+        assert_eq!(rest, "echo:raw bytes");
+
+        // Outside of a connection, the extractor rejects the request.
+        let request = http::Request::get("/").body(().into()).unwrap();
+        assert_eq!(service.call((), request).status(), 500);
+    }
+
+    #[test]
+    fn serve_files() {
+        use crate::{ServeDir, ServeFile};
+
+        let root = std::env::temp_dir().join(format!("usrv-files-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("css")).unwrap();
+        std::fs::write(root.join("index.html"), "<p>hi</p>").unwrap();
+        std::fs::write(root.join("css/site.css"), "0123456789").unwrap();
+
+        let service = Router::new()
+            .get("/static/*file", ServeDir::new(&root))
+            .get("/site.css", ServeFile::new(root.join("css/site.css")))
+            .finish();
+
+        let run = |request: &str| {
+            let input = format!("{}\r\nHost: a\r\nConnection: close\r\n\r\n", request);
+            let reader = io::Cursor::new(input.into_bytes());
+            let mut output = vec![];
+            service.drive((), reader, &mut output, &(), false).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let output = run("GET /static/ HTTP/1.1");
+        assert!(output.starts_with("HTTP/1.1 200"));
+        assert!(output.contains("content-type: text/html; charset=utf-8\r\n"));
+        assert!(output.ends_with("\r\n\r\n<p>hi</p>"));
+
+        let output = run("GET /site.css HTTP/1.1");
+        assert!(output.contains("content-type: text/css; charset=utf-8\r\n"));
+        assert!(output.ends_with("0123456789"));
+
+        let etag = output
+            .lines()
+            .find_map(|l| l.strip_prefix("etag: "))
+            .unwrap()
+            .to_string();
+        let modified = output
+            .lines()
+            .find_map(|l| l.strip_prefix("last-modified: "))
+            .unwrap()
+            .to_string();
+
+        let output = run("GET /static/css/site.css HTTP/1.1\r\nRange: bytes=2-4");
+        assert!(output.starts_with("HTTP/1.1 206"));
+        assert!(output.contains("content-range: bytes 2-4/10\r\n"));
+        assert!(output.ends_with("\r\n\r\n234"));
+
+        let output = run("GET /site.css HTTP/1.1\r\nRange: bytes=-3");
+        assert!(output.ends_with("\r\n\r\n789"));
+
+        let output = run("GET /site.css HTTP/1.1\r\nRange: bytes=10-");
+        assert!(output.starts_with("HTTP/1.1 416"));
+        assert!(output.contains("content-range: bytes */10\r\n"));
+
+        // A range of another version is the whole file.
+        let output = run("GET /site.css HTTP/1.1\r\nRange: bytes=2-4\r\nIf-Range: \"x\"");
+        assert!(output.starts_with("HTTP/1.1 200"));
+
+        let output = run(&format!(
+            "GET /site.css HTTP/1.1\r\nIf-None-Match: {}",
+            etag
+        ));
+        assert!(output.starts_with("HTTP/1.1 304"));
+        assert!(output.ends_with("\r\n\r\n"));
+
+        let output = run(&format!(
+            "GET /site.css HTTP/1.1\r\nIf-Modified-Since: {}",
+            modified
+        ));
+        assert!(output.starts_with("HTTP/1.1 304"));
+
+        let output = run("GET /site.css HTTP/1.1\r\nIf-None-Match: \"x\"");
+        assert!(output.starts_with("HTTP/1.1 200"));
+
+        let output = run("GET /static/missing.txt HTTP/1.1");
+        assert!(output.starts_with("HTTP/1.1 404"));
+
+        let output = run("GET /static/../secret HTTP/1.1");
+        assert!(output.starts_with("HTTP/1.1 404"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
     #[test]
     fn run_service() {
         #[derive(Clone)]
@@ -1,4 +1,8 @@
 use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::TransportInfo;
 
 pub trait Acceptor {
     type Reader: io::Read + Send + 'static;
@@ -6,6 +10,14 @@ pub trait Acceptor {
     type Breaker: Breaker + Send + 'static;
 
     fn accept(&mut self) -> io::Result<(Self::Reader, Self::Writer, Self::Breaker)>;
+
+    /// Address accepted on, if connecting to it wakes up a blocked [`accept()`][Self::accept].
+    ///
+    /// Used to stop accepting on shutdown. Without it, the accept loop stops after the
+    /// next connection.
+    fn local_addr(&self) -> Option<SocketAddr> {
+        None
+    }
 }
 
 impl Breaker for () {
@@ -16,15 +28,62 @@ impl Breaker for () {
 
 pub trait Breaker {
     fn disconnect(self) -> io::Result<()>;
+
+    /// Change the read timeout of the connection, such as while waiting for the next
+    /// request. Ignored by default.
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let _ = timeout;
+        Ok(())
+    }
+
+    /// What the connection knows about the client, attached to each request read from it.
+    /// Nothing by default.
+    fn transport_info(&self) -> TransportInfo {
+        TransportInfo::default()
+    }
+
+    /// Stop reading the connection, for a read blocked on it to return end of file.
+    /// Unsupported by default.
+    fn shutdown_read(&self) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "shutdown_read"))
+    }
 }
 
 pub mod tcp {
     use std::io;
-    use std::net::{Shutdown, TcpListener, TcpStream};
+    use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+    use std::time::Duration;
 
     use super::{Acceptor, Breaker};
+    use crate::TransportInfo;
 
-    pub struct TcpAcceptor(pub TcpListener);
+    pub struct TcpAcceptor {
+        listener: TcpListener,
+        read_timeout: Option<Duration>,
+        write_timeout: Option<Duration>,
+    }
+
+    impl TcpAcceptor {
+        pub fn new(listener: TcpListener) -> Self {
+            TcpAcceptor {
+                listener,
+                read_timeout: None,
+                write_timeout: None,
+            }
+        }
+
+        /// Read timeout of accepted connections.
+        pub fn read_timeout(mut self, timeout: Option<Duration>) -> Self {
+            self.read_timeout = timeout;
+            self
+        }
+
+        /// Write timeout of accepted connections.
+        pub fn write_timeout(mut self, timeout: Option<Duration>) -> Self {
+            self.write_timeout = timeout;
+            self
+        }
+    }
 
     impl Acceptor for TcpAcceptor {
         type Reader = TcpStream;
@@ -32,11 +91,18 @@ pub mod tcp {
         type Breaker = TcpStreamBreaker;
 
         fn accept(&mut self) -> io::Result<(Self::Reader, Self::Writer, Self::Breaker)> {
-            let (stream1, _) = self.0.accept()?;
+            let (stream1, _) = self.listener.accept()?;
+            // The clones share the socket, and with it the timeouts.
+            stream1.set_read_timeout(self.read_timeout)?;
+            stream1.set_write_timeout(self.write_timeout)?;
             let stream2 = stream1.try_clone()?;
             let stream3 = stream1.try_clone()?;
             Ok((stream1, stream2, TcpStreamBreaker(stream3)))
         }
+
+        fn local_addr(&self) -> Option<SocketAddr> {
+            self.listener.local_addr().ok()
+        }
     }
 
     pub struct TcpStreamBreaker(TcpStream);
@@ -45,6 +111,20 @@ pub mod tcp {
         fn disconnect(self) -> io::Result<()> {
             self.0.shutdown(Shutdown::Both)
         }
+
+        fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+            self.0.set_read_timeout(timeout)
+        }
+
+        fn shutdown_read(&self) -> io::Result<()> {
+            self.0.shutdown(Shutdown::Read)
+        }
+
+        fn transport_info(&self) -> TransportInfo {
+            let mut info = TransportInfo::new();
+            info.peer_addr = self.0.peer_addr().ok();
+            info
+        }
     }
 }
 
@@ -58,6 +138,7 @@ pub mod test {
 
     use crate::body::HootBody;
     use crate::fill_more::FillMoreBuffer;
+    use crate::wire::Tap;
     use crate::{http, Body, Error, Request};
 
     use super::Acceptor;
@@ -146,7 +227,8 @@ pub mod test {
                 .write_to(write)?
                 .flush()
         } else if m == Method::CONNECT {
-            write_headers(hs, hoot_req.connect(host, path)?, write)?
+            let authority = req.uri().authority().map(|a| a.as_str()).unwrap_or(path);
+            write_headers(hs, hoot_req.connect(authority)?, write)?
                 .send()?
                 .write_to(write)?
                 .flush()
@@ -158,14 +240,14 @@ pub mod test {
         };
 
         fn write_headers<'a, 'b, M: hoot::types::Method>(
-            mut hs: impl Iterator<Item = (&'a HeaderName, &'a HeaderValue)>,
+            hs: impl Iterator<Item = (&'a HeaderName, &'a HeaderValue)>,
             mut hoot_req: hoot::client::Request<'b, SEND_HEADERS, HTTP_11, M, ()>,
             write: &mut impl Write,
         ) -> Result<hoot::client::Request<'b, SEND_HEADERS, HTTP_11, M, ()>, Error> {
             // flush out status line
             hoot_req = hoot_req.write_to(write)?;
 
-            while let Some((name, value)) = hs.next() {
+            for (name, value) in hs {
                 hoot_req = hoot_req
                     .header_bytes(name.as_str(), value.as_bytes())?
                     .write_to(write)?;
@@ -174,8 +256,8 @@ pub mod test {
             Ok(hoot_req)
         }
 
-        fn write_body<'a, 'b, M: hoot::types::MethodWithRequestBody>(
-            body: &'a mut Body,
+        fn write_body<'b, M: hoot::types::MethodWithRequestBody>(
+            body: &mut Body,
             hoot_req: hoot::client::Request<'b, SEND_HEADERS, HTTP_11, M, ()>,
             write: &mut impl Write,
         ) -> Result<hoot::client::Output<'b, ENDED, (), (), ()>, Error> {
@@ -256,13 +338,13 @@ pub mod test {
             let (parts, _) = res.into_parts();
 
             response.consume(amt);
-            let response: Box<dyn io::Read + 'static> = Box::new(response);
+            let response: Box<dyn io::Read + Send + 'static> = Box::new(response);
 
             let hoot_res = hoot_res.proceed();
 
             let buffer = FillMoreBuffer::new(response);
 
-            let hoot_body = HootBody::new(hoot_res, tmp, buffer);
+            let hoot_body = HootBody::new(hoot_res, tmp, buffer, Tap::default());
             let body = Body::hoot(hoot_body);
 
             Ok(http::Response::from_parts(parts, body))
@@ -0,0 +1,67 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Count of work in progress, checked against a limit.
+///
+/// Clones share the count.
+#[derive(Clone, Default)]
+pub(crate) struct Gauge {
+    count: Arc<AtomicUsize>,
+}
+
+impl Gauge {
+    /// Enter, unless `max` is reached. The returned guard leaves on drop.
+    pub fn try_enter(&self, max: Option<usize>) -> Option<GaugeGuard> {
+        let before = self.count.fetch_add(1, Ordering::AcqRel);
+        let guard = GaugeGuard(self.count.clone());
+
+        match max {
+            Some(max) if before >= max => None,
+            _ => Some(guard),
+        }
+    }
+
+    /// Enter, without any limit.
+    pub fn enter(&self) -> GaugeGuard {
+        self.count.fetch_add(1, Ordering::AcqRel);
+        GaugeGuard(self.count.clone())
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Acquire)
+    }
+}
+
+pub(crate) struct GaugeGuard(Arc<AtomicUsize>);
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gauge_limit() {
+        let gauge = Gauge::default();
+
+        let a = gauge.try_enter(Some(2)).unwrap();
+        let b = gauge.clone().try_enter(Some(2)).unwrap();
+        assert!(gauge.try_enter(Some(2)).is_none());
+
+        // Refused entries don't count, and enter() has no limit.
+        assert_eq!(gauge.count(), 2);
+        let _c = gauge.enter();
+        assert_eq!(gauge.count(), 3);
+
+        drop((a, b));
+        assert_eq!(gauge.count(), 1);
+        assert!(gauge.try_enter(Some(2)).is_some());
+        assert!(gauge.try_enter(Some(1)).is_none());
+        assert!(gauge.try_enter(None).is_some());
+        assert_eq!(gauge.count(), 1);
+    }
+}
@@ -0,0 +1,131 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::shed::{Gauge, GaugeGuard};
+
+/// Handle to shut down a running service, from another thread.
+///
+/// Got from [`Service::shutdown_handle()`][crate::Service::shutdown_handle]. Clones stop
+/// the same service.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use usrv::{MethodRouter, Router};
+///
+/// let service = Router::new().get("/", || "hello").finish();
+/// let handle = service.shutdown_handle();
+///
+/// let server = std::thread::spawn(move || service.serve(()));
+///
+/// // Later, say on SIGTERM.
+/// let drained = handle.shutdown(Duration::from_secs(10));
+/// server.join().unwrap()?;
+/// # Ok::<(), usrv::Error>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct ShutdownHandle {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    stopping: AtomicBool,
+    /// Address to connect to, to wake up a blocked accept.
+    wake: Mutex<Option<SocketAddr>>,
+    /// Requests read, and not yet answered.
+    active: Gauge,
+}
+
+impl ShutdownHandle {
+    /// Stop accepting connections, and wait up to `timeout` for the requests being handled.
+    ///
+    /// The responses to those requests are sent with `Connection: close`, and connections
+    /// are closed after them. Returns whether all requests were done within the timeout.
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        // No deadline for a timeout too far off.
+        let deadline = Instant::now().checked_add(timeout);
+
+        self.inner.stopping.store(true, Ordering::Release);
+
+        let wake = *self.inner.wake.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(addr) = wake {
+            // The connection itself is dropped by the accept loop.
+            let _ = TcpStream::connect_timeout(&addr, timeout);
+        }
+
+        while self.inner.active.count() > 0 {
+            if deadline.map_or(false, |d| Instant::now() >= d) {
+                debug!("Shutdown timed out with requests in progress");
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        true
+    }
+
+    /// Whether shutdown has started.
+    pub fn is_shutdown(&self) -> bool {
+        self.inner.stopping.load(Ordering::Acquire)
+    }
+
+    /// Wake up the accept loop on shutdown by connecting to `addr`.
+    pub(crate) fn wake_on(&self, addr: Option<SocketAddr>) {
+        // Listening on all interfaces is reachable on loopback.
+        let addr = addr.map(|mut a| {
+            if a.ip().is_unspecified() {
+                match a {
+                    SocketAddr::V4(_) => a.set_ip(Ipv4Addr::LOCALHOST.into()),
+                    SocketAddr::V6(_) => a.set_ip(Ipv6Addr::LOCALHOST.into()),
+                }
+            }
+            a
+        });
+
+        *self.inner.wake.lock().unwrap_or_else(|e| e.into_inner()) = addr;
+    }
+
+    /// Count a request as in progress, until the guard is dropped.
+    pub(crate) fn enter(&self) -> GaugeGuard {
+        self.inner.active.enter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shutdown_waits_for_requests() {
+        let handle = ShutdownHandle::default();
+        let guard = handle.enter();
+        assert!(!handle.is_shutdown());
+
+        assert!(!handle.shutdown(Duration::from_millis(20)));
+        assert!(handle.is_shutdown());
+
+        let waiting = handle.clone();
+        let waiter = thread::spawn(move || waiting.shutdown(Duration::MAX));
+
+        thread::sleep(Duration::from_millis(20));
+        drop(guard);
+        assert!(waiter.join().unwrap());
+    }
+
+    #[test]
+    fn wake_on_loopback() {
+        let handle = ShutdownHandle::default();
+        let wake = |addr: &str| {
+            handle.wake_on(Some(addr.parse().unwrap()));
+            let wake = *handle.inner.wake.lock().unwrap();
+            wake.unwrap().to_string()
+        };
+
+        assert_eq!(wake("0.0.0.0:8080"), "127.0.0.1:8080");
+        assert_eq!(wake("[::]:80"), "[::1]:80");
+        assert_eq!(wake("192.0.2.1:80"), "192.0.2.1:80");
+    }
+}
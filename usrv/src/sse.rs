@@ -0,0 +1,231 @@
+use std::fmt::Write as _;
+use std::io::{self, Read};
+use std::time::Duration;
+
+use http::HeaderValue;
+
+use crate::{Body, IntoResponse, Response};
+
+/// Response of Server-Sent Events, as a stream of [`Event`].
+///
+/// The events are sent as the iterator gives them, each one flushed to the client before
+/// the next is asked for. The response ends with the iterator, after which the connection
+/// can be kept alive as usual.
+///
+/// ```
+/// use std::time::Duration;
+/// use usrv::{Event, MethodRouter, Router, Sse};
+///
+/// fn ticks() -> Sse<impl Iterator<Item = Event> + Send> {
+///     Sse::new((0..3).map(|n| {
+///         std::thread::sleep(Duration::from_secs(1));
+///         Event::new(n.to_string()).event("tick")
+///     }))
+/// }
+///
+/// let service = Router::new().get("/ticks", ticks).finish();
+/// ```
+pub struct Sse<I>(I);
+
+impl<I> Sse<I>
+where
+    I: Iterator + Send + 'static,
+    I::Item: Into<Event>,
+{
+    pub fn new(events: I) -> Self {
+        Sse(events)
+    }
+}
+
+impl<I> IntoResponse for Sse<I>
+where
+    I: Iterator + Send + 'static,
+    I::Item: Into<Event>,
+{
+    fn into_response(self) -> Response {
+        let reader = EventReader {
+            events: self.0,
+            buf: vec![],
+            pos: 0,
+        };
+
+        let mut res = http::Response::new(Body::streaming(reader));
+        let headers = res.headers_mut();
+        headers.insert(
+            "content-type",
+            HeaderValue::from_static("text/event-stream"),
+        );
+        headers.insert("cache-control", HeaderValue::from_static("no-cache"));
+        res
+    }
+}
+
+/// One event of [`Sse`].
+#[derive(Debug, Clone, Default)]
+pub struct Event {
+    data: Option<String>,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// Event with `data`, which can be many lines.
+    pub fn new(data: impl Into<String>) -> Self {
+        Event {
+            data: Some(data.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Name of the event, for clients to listen to. Without it, the event is a `message`.
+    pub fn event(mut self, name: impl Into<String>) -> Self {
+        self.event = Some(name.into());
+        self
+    }
+
+    /// Id of the event, sent back by a reconnecting client in `Last-Event-ID`.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// How long a client waits before reconnecting, after losing the connection.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    fn write_to(&self, out: &mut String) {
+        // Only data can span lines, a line break anywhere else would end the field.
+        let line = |v: &str| v.replace(['\r', '\n'], " ");
+
+        if let Some(event) = &self.event {
+            let _ = writeln!(out, "event: {}", line(event));
+        }
+        if let Some(id) = &self.id {
+            let _ = writeln!(out, "id: {}", line(id));
+        }
+        if let Some(retry) = self.retry {
+            let _ = writeln!(out, "retry: {}", retry.as_millis());
+        }
+        if let Some(data) = &self.data {
+            // A line ends with any of CRLF, CR and LF.
+            for l in data.split("\r\n").flat_map(|l| l.split(['\r', '\n'])) {
+                let _ = writeln!(out, "data: {}", l);
+            }
+        }
+        out.push('\n');
+    }
+}
+
+impl From<String> for Event {
+    fn from(data: String) -> Self {
+        Event::new(data)
+    }
+}
+
+impl From<&str> for Event {
+    fn from(data: &str) -> Self {
+        Event::new(data)
+    }
+}
+
+/// Reads the events one at a time, for each read to end at most with the end of an event.
+struct EventReader<I> {
+    events: I,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<I> Read for EventReader<I>
+where
+    I: Iterator,
+    I::Item: Into<Event>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.buf.len() {
+            let Some(event) = self.events.next() else {
+                return Ok(0);
+            };
+
+            let mut s = String::new();
+            event.into().write_to(&mut s);
+            self.buf = s.into_bytes();
+            self.pos = 0;
+        }
+
+        let n = buf.len().min(self.buf.len() - self.pos);
+        buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::write_response;
+
+    /// Keeps what was written between flushes.
+    #[derive(Default)]
+    struct Flushes {
+        pending: Vec<u8>,
+        flushed: Vec<String>,
+    }
+
+    impl io::Write for Flushes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.pending.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            let s = String::from_utf8(std::mem::take(&mut self.pending)).unwrap();
+            self.flushed.push(s);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn events_flushed() {
+        let events = vec![
+            Event::new("hello"),
+            Event::new("two\nlines").event("update").id("2"),
+            Event::default().retry(Duration::from_secs(3)),
+        ];
+        let response = Sse::new(events.into_iter()).into_response();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        let mut out = Flushes::default();
+        write_response(
+            http::Method::GET,
+            http::Version::HTTP_11,
+            response,
+            &mut out,
+        )
+        .unwrap();
+
+        // The head is flushed with the first event.
+        assert_eq!(out.flushed.len(), 3);
+        assert!(out.flushed[0].ends_with("d\r\ndata: hello\n\n\r\n"));
+        assert_eq!(
+            out.flushed[1],
+            "2b\r\nevent: update\nid: 2\ndata: two\ndata: lines\n\n\r\n"
+        );
+        assert_eq!(out.flushed[2], "d\r\nretry: 3000\n\n\r\n");
+        assert_eq!(String::from_utf8(out.pending).unwrap(), "0\r\n\r\n");
+    }
+
+    #[test]
+    fn data_lines() {
+        let mut out = String::new();
+        Event::new("a\r\nb\rid: 6\nc").write_to(&mut out);
+        assert_eq!(out, "data: a\ndata: b\ndata: id: 6\ndata: c\n\n");
+
+        let mut out = String::new();
+        Event::new("x").event("a\rretry: 1").write_to(&mut out);
+        assert_eq!(out, "event: a retry: 1\ndata: x\n\n");
+    }
+}
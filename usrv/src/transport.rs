@@ -0,0 +1,148 @@
+use std::net::SocketAddr;
+
+use crate::from_req::{FromRequest, FromRequestRef};
+use http::StatusCode;
+
+use crate::response::{empty, IntoResponse};
+use crate::{Request, Response};
+
+/// What the connection a request came over knows about the client.
+///
+/// Set per connection by the IO layer, with
+/// [`Breaker::transport_info()`][crate::server::Breaker::transport_info], and attached to
+/// each request read from it. An acceptor terminating TLS fills in the TLS parts, such as
+/// the certificate of the client for mutual TLS. Plain TCP only has the peer address.
+///
+/// Handlers get it as an extractor, or from the request extensions.
+///
+/// ```
+/// use usrv::TransportInfo;
+///
+/// fn whoami(info: TransportInfo) -> String {
+///     format!("{:?} over {:?}", info.peer_addr, info.tls_protocol)
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TransportInfo {
+    pub peer_addr: Option<SocketAddr>,
+    /// TLS version, such as `TLSv1.3`. `None` without TLS.
+    pub tls_protocol: Option<String>,
+    /// TLS cipher suite, such as `TLS_AES_128_GCM_SHA256`.
+    pub cipher: Option<String>,
+    /// Protocol agreed with ALPN, such as `http/1.1`.
+    pub alpn: Option<Vec<u8>>,
+    /// Certificate the client presented, DER encoded.
+    pub client_cert: Option<Vec<u8>>,
+}
+
+impl TransportInfo {
+    pub fn new() -> Self {
+        TransportInfo::default()
+    }
+}
+
+impl<S> FromRequestRef<S> for TransportInfo {
+    type Rejection = Response;
+
+    fn from_request(_state: &S, request: &Request) -> Result<Self, Self::Rejection> {
+        Ok(request.extensions().get().cloned().unwrap_or_default())
+    }
+}
+
+impl<S> FromRequest<S> for TransportInfo {
+    type Rejection = Response;
+
+    fn from_request(_state: &S, request: Request) -> Result<Self, Self::Rejection> {
+        Ok(request.extensions().get().cloned().unwrap_or_default())
+    }
+}
+
+/// Extractor for the TLS certificate of the client, DER encoded.
+///
+/// For handlers that require a client identity from mutual TLS. Requests over connections
+/// without a client certificate are rejected with `403 Forbidden`. See [`TransportInfo`].
+///
+/// ```
+/// use usrv::ClientCert;
+///
+/// fn admin(cert: ClientCert) -> String {
+///     format!("certificate of {} bytes", cert.0.len())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCert(pub Vec<u8>);
+
+/// 403, for a [`ClientCert`] over a connection without one.
+pub struct NoClientCert;
+
+impl IntoResponse for NoClientCert {
+    fn into_response(self) -> Response {
+        empty(StatusCode::FORBIDDEN)
+    }
+}
+
+impl From<NoClientCert> for Response {
+    fn from(value: NoClientCert) -> Self {
+        value.into_response()
+    }
+}
+
+fn client_cert(request: &Request) -> Result<ClientCert, NoClientCert> {
+    let info = request.extensions().get::<TransportInfo>();
+
+    match info.and_then(|i| i.client_cert.clone()) {
+        Some(der) => Ok(ClientCert(der)),
+        None => {
+            debug!("Request without client certificate");
+            Err(NoClientCert)
+        }
+    }
+}
+
+impl<S> FromRequestRef<S> for ClientCert {
+    type Rejection = NoClientCert;
+
+    fn from_request(_state: &S, request: &Request) -> Result<Self, Self::Rejection> {
+        client_cert(request)
+    }
+}
+
+impl<S> FromRequest<S> for ClientCert {
+    type Rejection = NoClientCert;
+
+    fn from_request(_state: &S, request: Request) -> Result<Self, Self::Rejection> {
+        client_cert(&request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Body;
+
+    #[test]
+    fn client_cert_from_info() {
+        let mut request = http::Request::get("/").body(Body::empty()).unwrap();
+        let Err(rejection) = client_cert(&request) else {
+            panic!("client certificate without transport info");
+        };
+        assert_eq!(rejection.into_response().status(), StatusCode::FORBIDDEN);
+
+        let info = TransportInfo {
+            peer_addr: Some("192.0.2.1:4000".parse().unwrap()),
+            ..TransportInfo::new()
+        };
+        request.extensions_mut().insert(info.clone());
+        assert!(client_cert(&request).is_err());
+
+        let found = <TransportInfo as FromRequestRef<()>>::from_request(&(), &request);
+        assert_eq!(found.ok(), Some(info));
+
+        request.extensions_mut().insert(TransportInfo {
+            client_cert: Some(vec![1, 2, 3]),
+            ..TransportInfo::new()
+        });
+        assert_eq!(client_cert(&request).ok(), Some(ClientCert(vec![1, 2, 3])));
+    }
+}
@@ -0,0 +1,101 @@
+use http::Method;
+
+use crate::response::IntoResponse;
+use crate::{Request, Response};
+
+/// Built-in answers for the endpoints most deployments have.
+///
+/// Only the endpoints set are answered, to `GET` and `HEAD`, before any route. Set on a
+/// service with [`Service::well_known()`][crate::Service::well_known].
+///
+/// ```
+/// use usrv::{MethodRouter, Router, WellKnown};
+///
+/// let well_known = WellKnown::new()
+///     .health()
+///     .robots_txt("User-agent: *\nDisallow: /admin/\n")
+///     .security_txt("Contact: mailto:security@example.com\n");
+///
+/// let service = Router::new().get("/", || "hello").finish().well_known(well_known);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WellKnown {
+    health: bool,
+    robots_txt: Option<String>,
+    security_txt: Option<String>,
+}
+
+impl WellKnown {
+    pub fn new() -> Self {
+        WellKnown::default()
+    }
+
+    /// Answer `/.well-known/health` with `200 OK`, for load balancers to check on.
+    pub fn health(mut self) -> Self {
+        self.health = true;
+        self
+    }
+
+    /// Serve `/robots.txt`. Unlike the others, it lives at the root.
+    pub fn robots_txt(mut self, content: impl Into<String>) -> Self {
+        self.robots_txt = Some(content.into());
+        self
+    }
+
+    /// Serve `/.well-known/security.txt`, as of RFC 9116.
+    pub fn security_txt(mut self, content: impl Into<String>) -> Self {
+        self.security_txt = Some(content.into());
+        self
+    }
+
+    /// The answer to `request`, if it is for one of the endpoints set.
+    pub(crate) fn call(&self, request: &Request) -> Option<Response> {
+        if request.method() != Method::GET && request.method() != Method::HEAD {
+            return None;
+        }
+
+        let content = match request.uri().path() {
+            "/.well-known/health" if self.health => "ok",
+            "/robots.txt" => self.robots_txt.as_deref()?,
+            "/.well-known/security.txt" => self.security_txt.as_deref()?,
+            _ => return None,
+        };
+
+        Some(content.into_response())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Body;
+
+    fn call(well_known: &WellKnown, method: Method, path: &str) -> Option<String> {
+        let request = http::Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::empty())
+            .unwrap();
+        let response = well_known.call(&request)?;
+        assert_eq!(response.status(), 200);
+        Some(response.into_body().into_string(100).unwrap())
+    }
+
+    #[test]
+    fn only_endpoints_set() {
+        let well_known = WellKnown::new().health().robots_txt("User-agent: *\n");
+
+        assert_eq!(
+            call(&well_known, Method::GET, "/.well-known/health").as_deref(),
+            Some("ok")
+        );
+        assert!(call(&well_known, Method::HEAD, "/robots.txt").is_some());
+        assert!(call(&well_known, Method::GET, "/.well-known/security.txt").is_none());
+        assert!(call(&well_known, Method::GET, "/robots.txt/").is_none());
+        assert!(call(&well_known, Method::POST, "/.well-known/health").is_none());
+
+        let none = WellKnown::new();
+        assert!(call(&none, Method::GET, "/.well-known/health").is_none());
+        assert!(call(&none, Method::GET, "/robots.txt").is_none());
+    }
+}
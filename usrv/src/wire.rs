@@ -0,0 +1,32 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use hoot::wire::{Direction, WireHook, WirePart, WireWriter};
+
+use crate::TransportInfo;
+
+/// Makes the [`WireHook`] of each connection, see
+/// [`Service::wire_hook()`][crate::Service::wire_hook].
+pub(crate) type MakeTap = Arc<dyn Fn(&TransportInfo) -> Tap + Send + Sync>;
+
+/// The hook of a connection, shared with the request bodies read from it.
+#[derive(Clone, Default)]
+pub(crate) struct Tap(Option<Arc<Mutex<dyn WireHook + Send>>>);
+
+impl Tap {
+    pub(crate) fn new(hook: impl WireHook + Send + 'static) -> Self {
+        Tap(Some(Arc::new(Mutex::new(hook))))
+    }
+}
+
+impl WireHook for Tap {
+    fn wire(&mut self, direction: Direction, part: WirePart, bytes: &[u8]) {
+        if let Some(hook) = &self.0 {
+            let mut lock = hook.lock().unwrap_or_else(|e| e.into_inner());
+            lock.wire(direction, part, bytes);
+        }
+    }
+}
+
+/// Writer of a response, telling the hook what is written.
+pub(crate) type Sent<'a> = WireWriter<&'a mut dyn io::Write, Tap>;
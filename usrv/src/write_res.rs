@@ -1,12 +1,14 @@
 use std::io;
-use std::io::Read;
+use std::io::{Read, Write};
 
 use hoot::server::Response as HootResponse;
 use hoot::server::{ResponseVariant, ResumeToken};
 use hoot::types::state::{SEND_HEADERS, SEND_STATUS};
-use hoot::types::{Method, MethodWithResponseBody, MethodWithoutResponseBody};
+use hoot::types::{BodyType, Method, MethodWithResponseBody, MethodWithoutResponseBody, State};
+use hoot::wire::WireWriter;
 use hoot::{BodyWriter, RecvBodyMode};
 
+use crate::wire::{Sent, Tap};
 use crate::{Error, Response};
 
 pub fn write_response(
@@ -23,6 +25,7 @@ pub fn write_response(
         response,
         writer,
         &mut write_buf,
+        Tap::default(),
     )
 }
 
@@ -32,11 +35,13 @@ pub(crate) fn write_response_with_buffer(
     mut response: Response,
     writer: &mut dyn io::Write,
     write_buf: &mut Vec<u8>,
+    tap: Tap,
 ) -> Result<(), Error> {
+    let writer = &mut WireWriter::new(writer, tap);
     let method: hoot::Method = request_method.into();
     let version: hoot::HttpVersion = request_version.into();
     let variant = ResponseVariant::unchecked_from_method(method, version);
-    *response.version_mut() = request_version.clone();
+    *response.version_mut() = request_version;
 
     match variant {
         ResponseVariant::Get(v) => write_with_body(method, response, writer, write_buf, v),
@@ -51,10 +56,63 @@ pub(crate) fn write_response_with_buffer(
     }
 }
 
+/// Write only the status and headers, for a connection taken over after the response.
+pub(crate) fn write_response_head(
+    request_method: http::Method,
+    request_version: http::Version,
+    mut response: Response,
+    writer: &mut dyn io::Write,
+    write_buf: &mut Vec<u8>,
+    tap: Tap,
+) -> Result<(), Error> {
+    let writer = &mut WireWriter::new(writer, tap);
+    let method: hoot::Method = request_method.into();
+    let version: hoot::HttpVersion = request_version.into();
+    let variant = ResponseVariant::unchecked_from_method(method, version);
+    *response.version_mut() = request_version;
+
+    match variant {
+        ResponseVariant::Get(v) => write_head_only(response, writer, write_buf, v),
+        ResponseVariant::Head(v) => write_without_body(response, writer, write_buf, v),
+        ResponseVariant::Post(v) => write_head_only(response, writer, write_buf, v),
+        ResponseVariant::Put(v) => write_head_only(response, writer, write_buf, v),
+        ResponseVariant::Delete(v) => write_head_only(response, writer, write_buf, v),
+        ResponseVariant::Connect(v) => write_without_body(response, writer, write_buf, v),
+        ResponseVariant::Options(v) => write_head_only(response, writer, write_buf, v),
+        ResponseVariant::Trace(v) => write_head_only(response, writer, write_buf, v),
+        ResponseVariant::Patch(v) => write_head_only(response, writer, write_buf, v),
+    }
+}
+
+/// Whether the response body ends by closing the connection, for the connection not to
+/// be kept alive after it.
+pub(crate) fn is_close_delimited(request_version: http::Version, response: &Response) -> bool {
+    let headers = response.headers();
+    request_version == http::Version::HTTP_10
+        && !headers.contains_key("content-length")
+        && !headers.contains_key("transfer-encoding")
+        && response.body().size().is_none()
+}
+
+fn write_head_only<M: MethodWithResponseBody>(
+    response: Response,
+    writer: &mut Sent,
+    write_buf: &mut Vec<u8>,
+    token: ResumeToken<SEND_STATUS, M, ()>,
+) -> Result<(), Error> {
+    let token = write_header(&response, writer, write_buf, token)?;
+
+    let hoot_res = HootResponse::resume(token, write_buf);
+
+    send(hoot_res.without_body()?, writer)?;
+
+    Ok(())
+}
+
 fn write_with_body<M: MethodWithResponseBody>(
     method: hoot::Method,
     response: Response,
-    writer: &mut dyn io::Write,
+    writer: &mut Sent,
     write_buf: &mut Vec<u8>,
     token: ResumeToken<SEND_STATUS, M, ()>,
 ) -> Result<(), Error> {
@@ -70,17 +128,30 @@ fn write_with_body<M: MethodWithResponseBody>(
         None
     };
 
-    let body_mode = RecvBodyMode::for_response(http_10, method, status, &header_lookup)?;
+    let has_framing =
+        header_lookup("content-length").is_some() || header_lookup("transfer-encoding").is_some();
+
+    let body_mode = if has_framing {
+        RecvBodyMode::for_response(http_10, method, status, &header_lookup)?
+    } else {
+        // Without framing headers, the body decides. HTTP/1.0 has no chunked, and a body
+        // of unknown size ends when the connection closes.
+        match response.body().size() {
+            Some(size) => RecvBodyMode::LengthDelimited(size),
+            None if http_10 => RecvBodyMode::CloseDelimited,
+            None => RecvBodyMode::Chunked,
+        }
+    };
 
     let (_, mut body) = response.into_parts();
 
     const DEFAULT_SIZE_STREAMING_BODIES: usize = 32_768;
     const CHUNK_OVERHEAD: usize = 10;
 
-    let body_size = if let Some(size) = body.size() {
-        size as usize
-    } else {
-        DEFAULT_SIZE_STREAMING_BODIES
+    // Small bodies are written in one go, larger ones in parts.
+    let body_size = match body.size() {
+        Some(size) => (size as usize).min(DEFAULT_SIZE_STREAMING_BODIES),
+        None => DEFAULT_SIZE_STREAMING_BODIES,
     };
 
     let needed_buffer_size = body_size * 2 + CHUNK_OVERHEAD;
@@ -89,7 +160,7 @@ fn write_with_body<M: MethodWithResponseBody>(
         write_buf.resize(needed_buffer_size, 0);
     }
 
-    let (tmp, output) = write_buf.split_at_mut(needed_buffer_size / 2 - CHUNK_OVERHEAD);
+    let (tmp, output) = write_buf.split_at_mut(body_size);
 
     let hoot_res = HootResponse::resume(token, output);
 
@@ -103,10 +174,10 @@ fn write_with_body<M: MethodWithResponseBody>(
                     break;
                 }
 
-                hoot_res = hoot_res.write_bytes(&tmp[..n])?.write_to(writer)?;
+                hoot_res = write_out(hoot_res.write_bytes(&tmp[..n])?, writer)?;
             }
 
-            hoot_res.finish()?;
+            send(hoot_res.finish()?, writer)?;
         }
         RecvBodyMode::Chunked => {
             let mut hoot_res = hoot_res.with_chunked()?;
@@ -117,13 +188,25 @@ fn write_with_body<M: MethodWithResponseBody>(
                     break;
                 }
 
-                hoot_res = hoot_res.write_bytes(&tmp[..n])?.write_to(writer)?;
+                hoot_res = write_out(hoot_res.write_bytes(&tmp[..n])?, writer)?;
+                // Each chunk goes out as it is produced.
+                writer.flush()?;
             }
 
-            hoot_res.finish()?;
+            send(hoot_res.finish()?, writer)?;
         }
         RecvBodyMode::CloseDelimited => {
-            todo!()
+            send(hoot_res.without_body()?, writer)?;
+            loop {
+                let n = body.read(tmp)?;
+
+                if n == 0 {
+                    break;
+                }
+
+                writer.write_all(&tmp[..n])?;
+                writer.flush()?;
+            }
         }
     }
 
@@ -132,24 +215,23 @@ fn write_with_body<M: MethodWithResponseBody>(
 
 fn write_without_body<M: MethodWithoutResponseBody>(
     response: Response,
-    writer: &mut dyn io::Write,
-    mut write_buf: &mut Vec<u8>,
+    writer: &mut Sent,
+    write_buf: &mut Vec<u8>,
     token: ResumeToken<SEND_STATUS, M, ()>,
 ) -> Result<(), Error> {
     let token = write_header(&response, writer, write_buf, token)?;
 
-    let hoot_res = HootResponse::resume(token, &mut write_buf);
+    let hoot_res = HootResponse::resume(token, write_buf);
 
-    let out = hoot_res.send()?.flush();
-    writer.write_all(&out)?;
+    send(hoot_res.send()?, writer)?;
 
     Ok(())
 }
 
 fn write_header<M: Method>(
     response: &Response,
-    writer: &mut dyn io::Write,
-    mut write_buf: &mut Vec<u8>,
+    writer: &mut Sent,
+    write_buf: &mut Vec<u8>,
     token: ResumeToken<SEND_STATUS, M, ()>,
 ) -> Result<ResumeToken<SEND_HEADERS, M, ()>, Error> {
     const MAX_STATUS_LINE_LENGTH: usize = 256;
@@ -158,15 +240,10 @@ fn write_header<M: Method>(
         write_buf.resize(write_buf.len() + MAX_STATUS_LINE_LENGTH, 0);
     }
 
-    let hoot_res = HootResponse::resume(token, &mut write_buf);
+    let hoot_res = HootResponse::resume(token, write_buf);
 
-    let out = hoot_res
-        .send_status(response.status().as_u16(), response.status().as_str())?
-        .flush();
-
-    writer.write_all(&out)?;
-
-    let mut token = out.ready();
+    let hoot_res = hoot_res.send_status(response.status().as_u16(), response.status().as_str())?;
+    let mut token = send(hoot_res, writer)?;
 
     for header in response.headers() {
         if header.0.eq("content-length") || header.0.eq("transfer-encoding") {
@@ -177,22 +254,87 @@ fn write_header<M: Method>(
         let name = header.0.as_str();
         let bytes = header.1.as_bytes();
 
-        let needed_size = name.as_bytes().len() + bytes.len() + 10;
+        let needed_size = name.len() + bytes.len() + 10;
 
         if write_buf.len() < needed_size {
             write_buf.resize(write_buf.len() + needed_size, 0);
         }
 
-        let hoot_res = HootResponse::resume(token, &mut write_buf);
+        let hoot_res = HootResponse::resume(token, write_buf);
 
-        let out = hoot_res
-            .header_bytes(header.0.as_str(), header.1.as_bytes())?
-            .flush();
+        let hoot_res = hoot_res.header_bytes(header.0.as_str(), header.1.as_bytes())?;
+        token = send(hoot_res, writer)?;
+    }
 
-        writer.write_all(&out)?;
+    Ok(token)
+}
 
-        token = out.ready();
+/// Write the output of `res`, telling the hook which of it is head.
+fn write_out<'a, S: State, M: Method, B: BodyType>(
+    res: HootResponse<'a, S, M, B>,
+    writer: &mut Sent,
+) -> io::Result<HootResponse<'a, S, M, B>> {
+    writer.sending(res.bytes_sent());
+    res.write_to(writer)
+}
+
+/// Write the output of `res` and let go of the buffer, like [`write_out()`].
+fn send<S: State, M: Method, B: BodyType>(
+    res: HootResponse<'_, S, M, B>,
+    writer: &mut Sent,
+) -> io::Result<ResumeToken<S, M, B>> {
+    writer.sending(res.bytes_sent());
+    let out = res.flush();
+    writer.write_all(&out)?;
+    Ok(out.ready())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Body;
+
+    fn write(body: Body) -> String {
+        write_version(body, http::Version::HTTP_11)
     }
 
-    Ok(token)
+    fn write_version(body: Body, version: http::Version) -> String {
+        let response = http::Response::new(body);
+        let mut out = vec![];
+        write_response(http::Method::GET, version, response, &mut out).unwrap();
+
+        // Skip the status line.
+        let out = String::from_utf8(out).unwrap();
+        out.split_once("\r\n").unwrap().1.to_string()
+    }
+
+    #[test]
+    fn body_size_sets_framing() {
+        let out = write(Body::bytes("hello"));
+        assert_eq!(out, "Content-Length: 5\r\n\r\nhello");
+
+        let out = write(Body::with_length(&b"hello world"[..], 5));
+        assert_eq!(out, "Content-Length: 5\r\n\r\nhello");
+
+        let out = write(Body::streaming(&b"hello"[..]));
+        assert_eq!(
+            out,
+            "Transfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn streaming_http_10() {
+        let body = Body::streaming(&b"hello"[..]);
+        let response = http::Response::new(body);
+        assert!(is_close_delimited(http::Version::HTTP_10, &response));
+        assert!(!is_close_delimited(http::Version::HTTP_11, &response));
+
+        // No chunked for HTTP/1.0, the body ends with the connection.
+        let out = write_version(response.into_body(), http::Version::HTTP_10);
+        assert_eq!(out, "\r\nhello");
+
+        let out = write_version(Body::bytes("hello"), http::Version::HTTP_10);
+        assert_eq!(out, "Content-Length: 5\r\n\r\nhello");
+    }
 }